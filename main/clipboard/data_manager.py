@@ -230,12 +230,14 @@ class ClipboardManager:
         except Exception as e:
             log_error(f"设置历史限制失败: {e}", "Clipboard")
     
-    def start_monitoring(self, callback: Optional[Callable[[ClipboardItem], None]] = None):
+    def start_monitoring(self, callback: Optional[Callable[[ClipboardItem], None]] = None,
+                         on_cleared: Optional[Callable[[], None]] = None):
         """
         开始监听剪贴板变化
         
         Args:
             callback: 剪贴板变化时的回调函数
+            on_cleared: 剪贴板被清空时的回调（仅 Rust 端开启 set_notify_clear 时触发）
         """
         if not self.is_available:
             log_error("管理器不可用", "Clipboard")
@@ -243,11 +245,19 @@ class ClipboardManager:
         
         self._callback = callback
         
-        def _on_change(py_item):
+        def _on_change(event):
             """内部回调，转换类型后调用用户回调"""
+            # 清空通知携带的是不入库的占位项，不能当作新记录转发
+            if event.is_cleared:
+                log_debug("剪贴板已清空", "Clipboard")
+                if on_cleared:
+                    on_cleared()
+                return
+            py_item = event.item
             item = ClipboardItem.from_py_item(py_item)
             # 预处理显示文本：去掉换行符，避免日志行被切断
             preview = item.display_text[:50].replace('\r\n', ' ').replace('\n', ' ').replace('\r', ' ').strip()
+            label = "重复内容置顶" if event.is_duplicate else "新内容"
             # char_count 编码了格式数据字节统计：raw * 10_000_000 + compressed
             encoded = py_item.char_count if py_item.char_count is not None else 0
             if encoded > 0:
@@ -262,9 +272,9 @@ class ClipboardManager:
                     size_info = f"{_fmt(raw_bytes)} → {_fmt(comp_bytes)} ({ratio:.1f}x)"
                 else:
                     size_info = _fmt(raw_bytes)
-                log_debug(f"新内容: {item.icon} {preview}  [{size_info}]", "Clipboard")
+                log_debug(f"{label}: {item.icon} {preview}  [{size_info}]", "Clipboard")
             else:
-                log_debug(f"新内容: {item.icon} {preview}", "Clipboard")
+                log_debug(f"{label}: {item.icon} {preview}", "Clipboard")
             
             # 清理逻辑已由 Rust 后端处理，这里只需调用用户回调
             if self._callback:
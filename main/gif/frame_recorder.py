@@ -189,6 +189,7 @@ class FrameRecorder(QObject):
                 height=h,
                 fps=self._fps,
                 jpeg_quality=90,
+                max_duration_secs=self._max_duration_s or None,
             )
             self._store.set_state(gifrecorder.STATE_RECORDING)
         except Exception as e:
@@ -493,14 +493,14 @@ def stitch_images_rust(
         
         # 调用Rust智能拼接函数（多候选纠错机制）
         if debug:
-            result_bytes = longstitch.stitch_two_images_rust_smart_debug(
+            stitch_result = longstitch.stitch_two_images_rust_smart_debug(
                 buffer1.getvalue(),
                 buffer2.getvalue(),
                 ignore_right_pixels,
                 0.01  # min_overlap_ratio
             )
         else:
-            result_bytes = longstitch.stitch_two_images_rust_smart(
+            stitch_result = longstitch.stitch_two_images_rust_smart(
                 buffer1.getvalue(),
                 buffer2.getvalue(),
                 ignore_right_pixels,
@@ -509,8 +509,9 @@ def stitch_images_rust(
         
         elapsed = time.perf_counter() - start_time
         
-        if result_bytes is not None:
-            result = Image.open(io.BytesIO(result_bytes))
+        if stitch_result is not None:
+            # (png_bytes, overlap_rows, seam_y, overlap_ratio)
+            result = Image.open(io.BytesIO(stitch_result[0]))
             # 简化日志输出,只在 debug 模式下显示详细信息
             # if not debug:
             #  print(f"[OK] Rust拼接成功: {img1.size} + {img2.size} -> {result.size}, 耗时: {elapsed*1000:.2f}ms")
@@ -195,8 +195,8 @@ def _stitch_with_hash_rust(images):
                     )
                     return None
 
-                # 返回 PNG 字节，解码为 PIL Image
-                result = Image.open(io.BytesIO(stitch_result))
+                # 返回 (png_bytes, overlap_rows, seam_y, overlap_ratio)，解码为 PIL Image
+                result = Image.open(io.BytesIO(stitch_result[0]))
 
                 if config.verbose:
                     global _stitch_counter
@@ -22,6 +22,10 @@ type HBITMAP = isize;
 #[allow(non_camel_case_types)]
 type HGDIOBJ = isize;
 #[allow(non_camel_case_types)]
+type HMONITOR = isize;
+#[allow(non_camel_case_types)]
+type LPARAM = isize;
+#[allow(non_camel_case_types)]
 type BOOL = i32;
 #[allow(non_camel_case_types)]
 type LONG = i32;
@@ -33,6 +37,7 @@ type DWORD = u32;
 const SRCCOPY: DWORD = 0x00CC0020;
 const DIB_RGB_COLORS: u32 = 0;
 const BI_RGB: DWORD = 0;
+const MONITORINFOF_PRIMARY: DWORD = 1;
 
 #[repr(C)]
 #[allow(non_snake_case)]
@@ -57,7 +62,35 @@ struct BITMAPINFO {
     bmiColors: [u32; 1], // 不使用调色板
 }
 
+#[repr(C)]
+struct RECT {
+    left: LONG,
+    top: LONG,
+    right: LONG,
+    bottom: LONG,
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct MONITORINFO {
+    cbSize: DWORD,
+    rcMonitor: RECT,
+    rcWork: RECT,
+    dwFlags: DWORD,
+}
+
+#[repr(C)]
+struct POINT {
+    x: LONG,
+    y: LONG,
+}
+
+type MonitorEnumProc = unsafe extern "system" fn(HMONITOR, HDC, *mut RECT, LPARAM) -> BOOL;
+
 extern "system" {
+    fn EnumDisplayMonitors(hdc: HDC, lprcClip: *const RECT, lpfnEnum: MonitorEnumProc, dwData: LPARAM) -> BOOL;
+    fn GetMonitorInfoW(hMonitor: HMONITOR, lpmi: *mut MONITORINFO) -> BOOL;
+    fn GetCursorPos(lpPoint: *mut POINT) -> BOOL;
     fn GetDC(hWnd: HWND) -> HDC;
     fn ReleaseDC(hWnd: HWND, hDC: HDC) -> i32;
     fn CreateCompatibleDC(hdc: HDC) -> HDC;
@@ -75,6 +108,63 @@ extern "system" {
     ) -> i32;
 }
 
+// ── 显示器枚举 ──
+
+/// 一台显示器在虚拟桌面中的位置和大小
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayInfo {
+    pub left: i32,
+    pub top: i32,
+    pub width: i32,
+    pub height: i32,
+    /// 是否为主显示器（主显示器左上角恒为虚拟桌面原点）
+    pub primary: bool,
+}
+
+unsafe extern "system" fn collect_monitor(monitor: HMONITOR, _hdc: HDC, _rect: *mut RECT, data: LPARAM) -> BOOL {
+    let displays = &mut *(data as *mut Vec<DisplayInfo>);
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as DWORD,
+        rcMonitor: RECT { left: 0, top: 0, right: 0, bottom: 0 },
+        rcWork: RECT { left: 0, top: 0, right: 0, bottom: 0 },
+        dwFlags: 0,
+    };
+    if GetMonitorInfoW(monitor, &mut info) != 0 {
+        let rc = &info.rcMonitor;
+        displays.push(DisplayInfo {
+            left: rc.left,
+            top: rc.top,
+            width: rc.right - rc.left,
+            height: rc.bottom - rc.top,
+            primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+        });
+    }
+    1 // 继续枚举
+}
+
+/// 枚举所有显示器，主显示器排在第一位（下标即 RecordConfig::display_index）
+pub fn list_displays() -> Result<Vec<DisplayInfo>, String> {
+    let mut displays: Vec<DisplayInfo> = Vec::new();
+    let ok = unsafe {
+        EnumDisplayMonitors(0, std::ptr::null(), collect_monitor, &mut displays as *mut _ as LPARAM)
+    };
+    if ok == 0 {
+        return Err("EnumDisplayMonitors failed".into());
+    }
+    displays.sort_by_key(|d| !d.primary);
+    Ok(displays)
+}
+
+/// 当前鼠标位置（虚拟桌面坐标），获取失败（如安全桌面）时返回 None
+pub fn cursor_pos() -> Option<(i32, i32)> {
+    let mut point = POINT { x: 0, y: 0 };
+    if unsafe { GetCursorPos(&mut point) } != 0 {
+        Some((point.x, point.y))
+    } else {
+        None
+    }
+}
+
 // ── 截屏上下文（可复用，避免每帧重新创建 GDI 对象）──
 
 /// 屏幕截取器 — 持有 GDI 资源，可重复截屏同一区域
@@ -143,8 +233,8 @@ impl ScreenCapture {
 
     /// 截取一帧，返回 BGRA 像素切片（底部在前 → 需翻转行）
     ///
-    /// 返回的切片指向内部缓冲区，生命周期与 `&mut self` 相同。
-    pub fn grab(&mut self) -> Result<&[u8], String> {
+    /// 返回的切片指向内部缓冲区，生命周期与 `&mut self` 相同（可原地叠加光标等标记）。
+    pub fn grab(&mut self) -> Result<&mut [u8], String> {
         unsafe {
             // BitBlt: 屏幕 → 内存 DC
             let ok = BitBlt(
@@ -187,7 +277,7 @@ impl ScreenCapture {
                 return Err("GetDIBits failed".into());
             }
 
-            Ok(&self.buffer)
+            Ok(&mut self.buffer)
         }
     }
 
@@ -225,4 +315,14 @@ mod tests {
         // 像素不全是 0（屏幕上总有点东西）
         assert!(bgra.iter().any(|&b| b != 0));
     }
+
+    #[test]
+    fn list_displays_primary_first() {
+        let displays = list_displays().unwrap();
+        assert!(!displays.is_empty());
+        assert!(displays[0].primary);
+        assert_eq!((displays[0].left, displays[0].top), (0, 0));
+        assert_eq!(displays.iter().filter(|d| d.primary).count(), 1);
+        assert!(displays.iter().all(|d| d.width > 0 && d.height > 0));
+    }
 }
@@ -0,0 +1,137 @@
+//! 联系表导出 — 从录制中均匀抽取若干帧，缩小后拼成一张网格预览图
+//!
+//! 无需回放即可浏览整段录制的内容，也可作为录制文件的封面缩略图。
+//! 解码 + 缩放并行执行，复用回放 / GIF 导出的 JPEG 解码与 resize。
+
+use image::{Rgba, RgbaImage};
+use rayon::prelude::*;
+
+use crate::frame_store::FrameStore;
+use crate::jpeg;
+use crate::resize::resize_rgb;
+
+/// 缩略图之间以及四周的间距（像素）
+const SHEET_GAP: u32 = 4;
+/// 间距处的背景色
+const SHEET_BACKGROUND: Rgba<u8> = Rgba([32, 32, 32, 255]);
+/// 联系表最大像素数（宽 × 高，约 400 MB RGBA），超出时拒绝导出
+const MAX_SHEET_PIXELS: u64 = 100_000_000;
+
+/// 从 frame_count 帧中均匀抽取至多 samples 个下标（包含首尾帧）
+fn sample_indices(frame_count: usize, samples: usize) -> Vec<usize> {
+    let samples = samples.min(frame_count);
+    if samples <= 1 {
+        return vec![0; samples];
+    }
+    (0..samples)
+        .map(|k| k * (frame_count - 1) / (samples - 1))
+        .collect()
+}
+
+/// 导出联系表
+///
+/// * `columns` — 每行的缩略图数量
+/// * `thumb_width` — 缩略图宽度，高度按录制区域的宽高比计算
+/// * `samples` — 抽取的帧数（超过总帧数时取全部帧）
+///
+/// 联系表尺寸在解码之前计算，溢出或超过 MAX_SHEET_PIXELS 时返回 Err
+pub fn export_contact_sheet(
+    store: &FrameStore,
+    columns: u32,
+    thumb_width: u32,
+    samples: usize,
+) -> Result<RgbaImage, String> {
+    if columns == 0 || thumb_width == 0 || samples == 0 {
+        return Err("columns, thumb_width and samples must be > 0".into());
+    }
+    let indices = sample_indices(store.frame_count(), samples);
+    if indices.is_empty() {
+        return Err("no frames to export".into());
+    }
+
+    let (src_w, src_h) = (store.width().max(1) as u64, store.height() as u64);
+    let thumb_height = ((src_h * thumb_width as u64 + src_w / 2) / src_w).max(1);
+    let thumb_height = u32::try_from(thumb_height).map_err(|_| "contact sheet too large".to_string())?;
+
+    let count = indices.len() as u32;
+    let columns = columns.min(count);
+    let rows = count.div_ceil(columns);
+    let sheet_size = |cells: u32, cell: u32| {
+        cell.checked_add(SHEET_GAP)?
+            .checked_mul(cells)?
+            .checked_add(SHEET_GAP)
+    };
+    let (sheet_width, sheet_height) = match (sheet_size(columns, thumb_width), sheet_size(rows, thumb_height)) {
+        (Some(w), Some(h)) if w as u64 * h as u64 <= MAX_SHEET_PIXELS => (w, h),
+        _ => {
+            return Err(format!(
+                "contact sheet too large: {columns}x{rows} thumbnails of {thumb_width}x{thumb_height} exceed {MAX_SHEET_PIXELS} pixels"
+            ))
+        }
+    };
+
+    // 并行: clone_jpeg → decode → resize
+    let thumbs: Vec<Vec<u8>> = indices
+        .par_iter()
+        .map(|&idx| {
+            let (jpeg_data, _elapsed) = store
+                .clone_jpeg(idx)
+                .ok_or_else(|| format!("frame {idx} missing"))?;
+            let d = jpeg::decode_to_rgb(&jpeg_data)?;
+            resize_rgb(&d.rgb, d.width, d.height, thumb_width, thumb_height)
+        })
+        .collect::<Result<_, String>>()?;
+
+    let mut sheet = RgbaImage::from_pixel(sheet_width, sheet_height, SHEET_BACKGROUND);
+
+    for (i, rgb) in thumbs.iter().enumerate() {
+        let x0 = SHEET_GAP + (i as u32 % columns) * (thumb_width + SHEET_GAP);
+        let y0 = SHEET_GAP + (i as u32 / columns) * (thumb_height + SHEET_GAP);
+        for (p, px) in rgb.chunks_exact(3).enumerate() {
+            let (x, y) = (p as u32 % thumb_width, p as u32 / thumb_width);
+            sheet.put_pixel(x0 + x, y0 + y, Rgba([px[0], px[1], px[2], 255]));
+        }
+    }
+
+    Ok(sheet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame_store::RecordConfig;
+
+    #[test]
+    fn sample_indices_include_first_and_last() {
+        assert_eq!(sample_indices(10, 4), vec![0, 3, 6, 9]);
+        assert_eq!(sample_indices(3, 8), vec![0, 1, 2]);
+        assert_eq!(sample_indices(5, 1), vec![0]);
+        assert!(sample_indices(0, 4).is_empty());
+    }
+
+    #[test]
+    fn contact_sheet_grid() {
+        let store = FrameStore::new(64, 48, 10, RecordConfig::default());
+        for (i, gray) in [0u8, 50, 100, 150, 200].into_iter().enumerate() {
+            store.push_bgra(&vec![gray; 64 * 48 * 4], i as u32 * 100).unwrap();
+        }
+
+        // 抽取第 0、2、4 帧，排成 2 列 × 2 行，缩略图 32×24
+        let sheet = export_contact_sheet(&store, 2, 32, 3).unwrap();
+        assert_eq!(sheet.dimensions(), (2 * 36 + 4, 2 * 28 + 4));
+
+        let center = |col: u32, row: u32| sheet.get_pixel(4 + col * 36 + 16, 4 + row * 28 + 12)[0];
+        assert!(center(0, 0).abs_diff(0) <= 4);
+        assert!(center(1, 0).abs_diff(100) <= 4);
+        assert!(center(0, 1).abs_diff(200) <= 4);
+        // 最后一格没有缩略图，保留背景色
+        assert_eq!(*sheet.get_pixel(4 + 36 + 16, 4 + 28 + 12), SHEET_BACKGROUND);
+
+        assert!(export_contact_sheet(&store, 0, 32, 3).is_err());
+        // 超大参数在解码前被拒绝：u32 溢出，或超过像素上限
+        assert!(export_contact_sheet(&store, u32::MAX, u32::MAX, 5).unwrap_err().starts_with("contact sheet too large"));
+        assert!(export_contact_sheet(&store, u32::MAX, 20_000, 5).unwrap_err().starts_with("contact sheet too large"));
+        let empty = FrameStore::new(64, 48, 10, RecordConfig::default());
+        assert!(export_contact_sheet(&empty, 2, 32, 3).is_err());
+    }
+}
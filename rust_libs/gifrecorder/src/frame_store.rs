@@ -9,6 +9,9 @@
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Mutex;
 
+use image::codecs::png::PngEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+
 use crate::jpeg;
 use crate::resize::resize_rgb;
 
@@ -32,6 +35,30 @@ pub struct RecordConfig {
     pub max_frames: usize,
     /// 最大内存字节数 (0 = 不限)
     pub max_memory_bytes: usize,
+    /// 录制循环跳过与上一帧几乎相同的静止帧（上一帧的显示时间相应延长）
+    pub drop_static_frames: bool,
+    /// 最大录制时长（秒，不含暂停时间），达到后录制循环自动停止 (None = 不限)
+    pub max_duration_secs: Option<f32>,
+    /// 录制循环存入这么多帧后自动停止 (None = 不限)；
+    /// 与 max_frames 不同，后者只在超出时丢弃最旧帧、录制继续
+    pub stop_after_frames: Option<u32>,
+    /// GIF 导出的调色板颜色数（2 ~ 256，超出范围时截断），其中一个固定用作透明色
+    pub gif_colors: usize,
+    /// GIF 导出时用 Floyd–Steinberg 误差扩散抖动，减轻渐变处的色带
+    pub gif_dither: bool,
+    /// 预录时长（秒，0 = 不预录）：RecordSession 启动后先处于待命状态，
+    /// 只在环形缓冲中保留最近这段时长的帧，start_recording() 时作为开头的帧存入
+    pub pre_roll_secs: f32,
+    /// 录制的显示器下标（同 capture::list_displays 的顺序，0 = 主显示器），
+    /// RecordSession 的截取坐标相对于该显示器左上角
+    pub display_index: usize,
+    /// RecordSession 在每帧的鼠标位置画一个红色十字
+    pub overlay_cursor: bool,
+    /// 可变帧率：录制循环只保存变化像素比例超过 change_threshold_percent 的帧，
+    /// 跳过的时长由上一帧的显示时间覆盖
+    pub variable_fps: bool,
+    /// 可变帧率的变化阈值（百分比，0 ~ 100）
+    pub change_threshold_percent: f32,
 }
 
 impl Default for RecordConfig {
@@ -40,6 +67,16 @@ impl Default for RecordConfig {
             jpeg_quality: 95,
             max_frames: 0,
             max_memory_bytes: 0,
+            drop_static_frames: false,
+            max_duration_secs: None,
+            stop_after_frames: None,
+            gif_colors: 256,
+            gif_dither: true,
+            pre_roll_secs: 0.0,
+            display_index: 0,
+            overlay_cursor: false,
+            variable_fps: false,
+            change_threshold_percent: 1.0,
         }
     }
 }
@@ -111,6 +148,10 @@ impl FrameStore {
         self.fps
     }
 
+    pub fn config(&self) -> &RecordConfig {
+        &self.config
+    }
+
     pub fn frame_count(&self) -> usize {
         self.frames.lock().unwrap().len()
     }
@@ -171,6 +212,31 @@ impl FrameStore {
     ///
     /// 返回 Ok(true) 表示正常存储，Ok(false) 表示被丢弃（超限），Err 表示编码失败
     pub fn push_bgra(&self, bgra: &[u8], elapsed_ms: u32) -> Result<bool, String> {
+        let jpeg_data = self.encode_bgra(bgra)?;
+        Ok(self.push_jpeg(jpeg_data, elapsed_ms))
+    }
+
+    /// 接收一帧 RGB24 数据，压缩为 JPEG 存储
+    pub fn push_rgb(&self, rgb: &[u8], elapsed_ms: u32) -> Result<bool, String> {
+        let expected = (self.width * self.height * 3) as usize;
+        if rgb.len() != expected {
+            return Err(format!(
+                "RGB size mismatch: got {} expected {} ({}x{})",
+                rgb.len(),
+                expected,
+                self.width,
+                self.height
+            ));
+        }
+
+        let jpeg_data = jpeg::encode_rgb(rgb, self.width, self.height, self.config.jpeg_quality)?;
+        self.push_jpeg(jpeg_data, elapsed_ms);
+
+        Ok(true)
+    }
+
+    /// 按录制配置把一帧 BGRA 压缩为 JPEG（不存储）
+    pub(crate) fn encode_bgra(&self, bgra: &[u8]) -> Result<Vec<u8>, String> {
         let expected = (self.width * self.height * 4) as usize;
         if bgra.len() != expected {
             return Err(format!(
@@ -182,7 +248,13 @@ impl FrameStore {
             ));
         }
 
-        let jpeg_data = jpeg::encode_bgra(bgra, self.width, self.height, self.config.jpeg_quality)?;
+        jpeg::encode_bgra(bgra, self.width, self.height, self.config.jpeg_quality)
+    }
+
+    /// 存入一帧已压缩的 JPEG
+    ///
+    /// 返回 false 表示为腾出空间丢弃了最旧帧（超出帧数或内存上限）
+    pub(crate) fn push_jpeg(&self, jpeg_data: Vec<u8>, elapsed_ms: u32) -> bool {
         let jpeg_size = jpeg_data.len() as u64;
 
         let mut frames = self.frames.lock().unwrap();
@@ -221,56 +293,51 @@ impl FrameStore {
             elapsed_ms,
         });
 
-        Ok(!dropped)
+        !dropped
     }
 
-    /// 接收一帧 RGB24 数据，压缩为 JPEG 存储
-    pub fn push_rgb(&self, rgb: &[u8], elapsed_ms: u32) -> Result<bool, String> {
-        let expected = (self.width * self.height * 3) as usize;
-        if rgb.len() != expected {
-            return Err(format!(
-                "RGB size mismatch: got {} expected {} ({}x{})",
-                rgb.len(),
-                expected,
-                self.width,
-                self.height
-            ));
-        }
+    /// 以新的时间戳重复存储最后一帧（不重新编码）
+    ///
+    /// 录制结束时若末尾的静止帧都被跳过，用它保留最后一段静止画面的时长；
+    /// 同样受帧数 / 内存上限约束（超限时丢弃最旧帧）。
+    /// 没有帧或时间戳不晚于最后一帧时不做任何事
+    pub fn repeat_last_frame(&self, elapsed_ms: u32) {
+        let data = {
+            let frames = self.frames.lock().unwrap();
+            match frames.last() {
+                Some(last) if elapsed_ms > last.elapsed_ms => last.data.clone(),
+                _ => return,
+            }
+        };
+        self.push_jpeg(data, elapsed_ms);
+    }
 
-        let jpeg_data = jpeg::encode_rgb(rgb, self.width, self.height, self.config.jpeg_quality)?;
-        let jpeg_size = jpeg_data.len() as u64;
+    // ══════════════════════════════════════════════
+    //  剪辑
+    // ══════════════════════════════════════════════
 
+    /// 只保留下标在 [start_frame, end_frame] 内的帧（含两端），并把第一帧的时间戳平移到 0
+    ///
+    /// 返回保留的帧数；不满足 start_frame < end_frame < 帧数时返回 Err，帧数据不变
+    pub fn trim(&self, start_frame: usize, end_frame: usize) -> Result<usize, String> {
         let mut frames = self.frames.lock().unwrap();
-
-        // 同 push_bgra 的超限策略
-        if self.config.max_frames > 0 && frames.len() >= self.config.max_frames {
-            if let Some(old) = frames.first() {
-                let old_size = old.data.len() as u64;
-                self.total_bytes.fetch_sub(old_size, Ordering::Relaxed);
-            }
-            frames.remove(0);
-            self.dropped_frames.fetch_add(1, Ordering::Relaxed);
+        if start_frame >= end_frame || end_frame >= frames.len() {
+            return Err(format!(
+                "invalid trim range [{start_frame}, {end_frame}] (count={})",
+                frames.len()
+            ));
         }
 
-        if self.config.max_memory_bytes > 0 {
-            while self.total_bytes.load(Ordering::Relaxed) + jpeg_size
-                > self.config.max_memory_bytes as u64
-                && !frames.is_empty()
-            {
-                let old_size = frames[0].data.len() as u64;
-                self.total_bytes.fetch_sub(old_size, Ordering::Relaxed);
-                frames.remove(0);
-                self.dropped_frames.fetch_add(1, Ordering::Relaxed);
-            }
+        frames.truncate(end_frame + 1);
+        frames.drain(..start_frame);
+        let base = frames[0].elapsed_ms;
+        for frame in frames.iter_mut() {
+            frame.elapsed_ms -= base;
         }
+        let total: u64 = frames.iter().map(|f| f.data.len() as u64).sum();
+        self.total_bytes.store(total, Ordering::Relaxed);
 
-        self.total_bytes.fetch_add(jpeg_size, Ordering::Relaxed);
-        frames.push(JpegFrame {
-            data: jpeg_data,
-            elapsed_ms,
-        });
-
-        Ok(true)
+        Ok(frames.len())
     }
 
     // ══════════════════════════════════════════════
@@ -303,6 +370,30 @@ impl FrameStore {
         }
     }
 
+    /// 首帧缩略图：等比缩小到 max_size × max_size 以内（不放大），编码为 PNG
+    ///
+    /// 还没有帧时返回 Ok(None)
+    pub fn first_frame_thumbnail(&self, max_size: u32) -> Result<Option<Vec<u8>>, String> {
+        if max_size == 0 {
+            return Err("max_size must be > 0".into());
+        }
+        if self.frame_count() == 0 {
+            return Ok(None);
+        }
+
+        let (src_w, src_h) = (self.width.max(1) as u64, self.height.max(1) as u64);
+        let scale_base = src_w.max(src_h).max(max_size as u64);
+        let thumb_width = ((src_w * max_size as u64 + scale_base / 2) / scale_base).max(1) as u32;
+        let thumb_height = ((src_h * max_size as u64 + scale_base / 2) / scale_base).max(1) as u32;
+
+        let rgb = self.get_frame_rgb(0, thumb_width, thumb_height)?;
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png)
+            .write_image(&rgb, thumb_width, thumb_height, ExtendedColorType::Rgb8)
+            .map_err(|e| format!("PNG encode failed: {e}"))?;
+        Ok(Some(png))
+    }
+
     // ══════════════════════════════════════════════
     //  清理
     // ══════════════════════════════════════════════
@@ -410,6 +501,87 @@ mod tests {
         assert_eq!(store.get_elapsed_ms(0), Some(100)); // 最旧的是第 1 帧
     }
 
+    #[test]
+    fn repeat_last_frame_extends_duration() {
+        let store = FrameStore::new(64, 48, 15, RecordConfig::default());
+        store.repeat_last_frame(100);
+        assert_eq!(store.frame_count(), 0);
+
+        store.push_bgra(&make_bgra(64, 48), 0).unwrap();
+        let size = store.memory_usage_bytes();
+        store.repeat_last_frame(900);
+        store.repeat_last_frame(900);
+        assert_eq!(store.frame_timestamps(), vec![0, 900]);
+        assert_eq!(store.memory_usage_bytes(), size * 2);
+    }
+
+    #[test]
+    fn repeat_last_frame_respects_limits() {
+        let store = FrameStore::new(64, 48, 15, RecordConfig {
+            max_frames: 2,
+            ..Default::default()
+        });
+        store.push_bgra(&make_bgra(64, 48), 0).unwrap();
+        store.push_bgra(&make_bgra(64, 48), 100).unwrap();
+        store.repeat_last_frame(900);
+        assert_eq!(store.frame_timestamps(), vec![100, 900]);
+        assert_eq!(store.dropped_frames(), 1);
+
+        // 内存上限只够放一帧：重复帧替换掉原来的最后一帧
+        let probe = FrameStore::new(64, 48, 15, RecordConfig::default());
+        probe.push_bgra(&make_bgra(64, 48), 0).unwrap();
+        let frame_size = probe.memory_usage_bytes() as usize;
+        let store = FrameStore::new(64, 48, 15, RecordConfig {
+            max_memory_bytes: frame_size,
+            ..Default::default()
+        });
+        store.push_bgra(&make_bgra(64, 48), 0).unwrap();
+        store.repeat_last_frame(900);
+        assert_eq!(store.frame_timestamps(), vec![900]);
+        assert_eq!(store.memory_usage_bytes(), frame_size as u64);
+    }
+
+    #[test]
+    fn trim_keeps_range_and_rebases() {
+        let store = FrameStore::new(16, 12, 10, RecordConfig::default());
+        for i in 0..120 {
+            store.push_bgra(&make_bgra(16, 12), i * 100).unwrap();
+        }
+        let frame_size = store.memory_usage_bytes() / 120;
+
+        assert!(store.trim(50, 50).is_err());
+        assert!(store.trim(60, 10).is_err());
+        assert!(store.trim(10, 120).is_err());
+        assert_eq!(store.frame_count(), 120);
+
+        // [10, 110] → 101 帧，原时间戳 1000..=11000
+        assert_eq!(store.trim(10, 110).unwrap(), 101);
+        assert_eq!(store.frame_count(), 101);
+        assert_eq!(store.get_elapsed_ms(0), Some(0));
+        assert_eq!(store.total_duration_ms(), 10000);
+        assert_eq!(store.memory_usage_bytes(), frame_size * 101);
+    }
+
+    #[test]
+    fn first_frame_thumbnail_fits_max_size() {
+        let store = FrameStore::new(64, 48, 10, RecordConfig::default());
+        assert_eq!(store.first_frame_thumbnail(32).unwrap(), None);
+        assert!(store.first_frame_thumbnail(0).is_err());
+
+        store.push_bgra(&vec![100u8; 64 * 48 * 4], 0).unwrap();
+        store.push_bgra(&vec![200u8; 64 * 48 * 4], 100).unwrap();
+
+        let png = store.first_frame_thumbnail(32).unwrap().unwrap();
+        let thumb = image::load_from_memory_with_format(&png, image::ImageFormat::Png).unwrap().to_rgb8();
+        assert_eq!(thumb.dimensions(), (32, 24));
+        assert!(thumb.get_pixel(16, 12)[0].abs_diff(100) <= 4);
+
+        // 不放大
+        let png = store.first_frame_thumbnail(500).unwrap().unwrap();
+        let full = image::load_from_memory_with_format(&png, image::ImageFormat::Png).unwrap();
+        assert_eq!((full.width(), full.height()), (64, 48));
+    }
+
     #[test]
     fn state_transitions() {
         let store = FrameStore::new(64, 48, 15, RecordConfig::default());
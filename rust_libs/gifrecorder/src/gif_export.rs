@@ -8,6 +8,7 @@
 //! 帧差分：对比当前帧与上一帧的 RGBA 像素，只编码变化区域，
 //! 未变化像素标记为透明，大幅减少量化 + LZW 的工作量。
 //! 所有帧共享一个全局 palette，用于消除逐帧独立调色板造成的颜色抖动。
+//! 调色板颜色数与是否做 Floyd–Steinberg 抖动由 RecordConfig::gif_colors / gif_dither 决定。
 
 use std::fs::File;
 use std::io::BufWriter;
@@ -27,7 +28,6 @@ const BATCH_SIZE: usize = 8;
 const GLOBAL_PALETTE_MAX_SAMPLES: usize = 12;
 const GLOBAL_PALETTE_PIXEL_STEP: usize = 4;
 const GLOBAL_PALETTE_SPEED: i32 = 10;
-const GLOBAL_PALETTE_COLORS: usize = 255;
 const TRANSPARENT_INDEX: u8 = 0;
 
 struct GlobalPalette {
@@ -144,6 +144,8 @@ pub fn export_gif(
         need_resize,
     )?;
 
+    let dither = store.config().gif_dither;
+
     // ── gif crate 编码器（固定全局调色板 + 帧差分） ──
     let file = File::create(&opts.path).map_err(|e| format!("create file: {e}"))?;
     let writer = BufWriter::with_capacity(256 * 1024, file); // 256KB 写缓冲
@@ -224,7 +226,7 @@ pub fn export_gif(
                 if let Some((dx, dy, dw, dh)) = find_dirty_rect(prev, &rgba, dst_w, dst_h) {
                     // 提取脏区域，未变化像素标记为透明
                     let dirty = extract_dirty_rgba(prev, &rgba, dst_w, dx, dy, dw, dh);
-                    let indexed = rgba_to_palette_indices(&dirty, &global_palette.quantizer);
+                    let indexed = rgba_to_palette_indices(&dirty, dw, &global_palette.quantizer, dither);
                     let mut frame = GifFrame::from_indexed_pixels(
                         dw as u16, dh as u16, indexed, Some(TRANSPARENT_INDEX),
                     );
@@ -251,7 +253,7 @@ pub fn export_gif(
                 }
             } else {
                 // ── 首帧: 全帧编码 ──
-                let indexed = rgba_to_palette_indices(&rgba, &global_palette.quantizer);
+                let indexed = rgba_to_palette_indices(&rgba, dst_w, &global_palette.quantizer, dither);
                 let mut frame = GifFrame::from_indexed_pixels(
                     dst_w as u16, dst_h as u16, indexed, Some(TRANSPARENT_INDEX),
                 );
@@ -374,7 +376,7 @@ fn build_global_palette(
 
     let quantizer = NeuQuant::new(
         GLOBAL_PALETTE_SPEED,
-        GLOBAL_PALETTE_COLORS,
+        palette_color_count(store.config().gif_colors),
         &sampled_rgba,
     );
     let mut palette_rgb = Vec::with_capacity(256 * 3);
@@ -387,6 +389,11 @@ fn build_global_palette(
     })
 }
 
+/// 量化器可用的颜色数：gif_colors 截断到 2 ~ 256，再减去固定占用索引 0 的透明色
+fn palette_color_count(gif_colors: usize) -> usize {
+    gif_colors.clamp(2, 256) - 1
+}
+
 fn choose_palette_sample_indices(start: usize, end: usize, max_samples: usize) -> Vec<usize> {
     let total = end - start + 1;
     if total <= max_samples {
@@ -410,14 +417,48 @@ fn append_sampled_pixels(dst: &mut Vec<u8>, rgba: &[u8], pixel_step: usize) {
     }
 }
 
-fn rgba_to_palette_indices(rgba: &[u8], quantizer: &NeuQuant) -> Vec<u8> {
+/// RGBA（宽 width）→ 调色板索引，alpha=0 的像素映射为透明色
+///
+/// dither 为 true 时做 Floyd–Steinberg 误差扩散：量化误差按 7/16、3/16、5/16、1/16
+/// 分给右、左下、下、右下的像素；透明像素不参与（既不扩散也不使用误差）
+fn rgba_to_palette_indices(rgba: &[u8], width: u32, quantizer: &NeuQuant, dither: bool) -> Vec<u8> {
     let mut indexed = Vec::with_capacity(rgba.len() / 4);
-    for pixel in rgba.chunks_exact(4) {
-        if pixel[3] == 0 {
-            indexed.push(TRANSPARENT_INDEX);
-        } else {
-            indexed.push((quantizer.index_of(pixel) + 1) as u8);
+    if !dither {
+        for pixel in rgba.chunks_exact(4) {
+            if pixel[3] == 0 {
+                indexed.push(TRANSPARENT_INDEX);
+            } else {
+                indexed.push((quantizer.index_of(pixel) + 1) as u8);
+            }
+        }
+        return indexed;
+    }
+
+    let width = width as usize;
+    // 当前行 / 下一行累积的 RGB 误差（×16），左右各多留一格省去边界判断
+    let mut err_cur = vec![[0i32; 3]; width + 2];
+    let mut err_next = vec![[0i32; 3]; width + 2];
+    for row in rgba.chunks_exact(width * 4) {
+        for (x, pixel) in row.chunks_exact(4).enumerate() {
+            if pixel[3] == 0 {
+                indexed.push(TRANSPARENT_INDEX);
+                continue;
+            }
+            let err = err_cur[x + 1];
+            let wanted: [i32; 3] = std::array::from_fn(|c| (pixel[c] as i32 + err[c] / 16).clamp(0, 255));
+            let idx = quantizer.index_of(&[wanted[0] as u8, wanted[1] as u8, wanted[2] as u8, pixel[3]]);
+            let chosen = quantizer.lookup(idx).unwrap_or_default();
+            for (c, (&w, &q)) in wanted.iter().zip(&chosen).enumerate() {
+                let e = w - q as i32;
+                err_cur[x + 2][c] += e * 7;
+                err_next[x][c] += e * 3;
+                err_next[x + 1][c] += e * 5;
+                err_next[x + 2][c] += e;
+            }
+            indexed.push((idx + 1) as u8);
         }
+        std::mem::swap(&mut err_cur, &mut err_next);
+        err_next.fill([0; 3]);
     }
     indexed
 }
@@ -626,6 +667,42 @@ mod tests {
         assert_eq!(dst[3], 255);
     }
 
+    #[test]
+    fn test_palette_color_count_clamped() {
+        assert_eq!(palette_color_count(256), 255);
+        assert_eq!(palette_color_count(64), 63);
+        assert_eq!(palette_color_count(1000), 255);
+        assert_eq!(palette_color_count(0), 1);
+
+        // 最少只剩 1 种颜色时量化器仍可用
+        let quantizer = NeuQuant::new(GLOBAL_PALETTE_SPEED, palette_color_count(2), &[90, 90, 90, 255]);
+        assert_eq!(rgba_to_palette_indices(&[10, 200, 30, 255, 0, 0, 0, 0], 2, &quantizer, true), vec![1, 0]);
+    }
+
+    #[test]
+    fn test_dither_mixes_palette_colors() {
+        // 只有黑白两色的调色板，量化 50% 灰
+        let samples: Vec<u8> = (0..64).flat_map(|i| if i % 2 == 0 { [0, 0, 0, 255] } else { [255, 255, 255, 255] }).collect();
+        let quantizer = NeuQuant::new(1, 2, &samples);
+        let (w, h) = (16u32, 16u32);
+        let gray = [128u8, 128, 128, 255].repeat((w * h) as usize);
+
+        // 不抖动：整块映射为同一颜色
+        let flat = rgba_to_palette_indices(&gray, w, &quantizer, false);
+        assert!(flat.iter().all(|&i| i == flat[0]));
+
+        // 抖动：黑白交错，比例接近一半
+        let dithered = rgba_to_palette_indices(&gray, w, &quantizer, true);
+        assert_eq!(dithered.len(), (w * h) as usize);
+        let first = dithered.iter().filter(|&&i| i == dithered[0]).count();
+        assert!((96..=160).contains(&first), "{first}");
+
+        // 透明像素保持透明索引
+        let mut holes = gray.clone();
+        holes[3] = 0;
+        assert_eq!(rgba_to_palette_indices(&holes, w, &quantizer, true)[0], TRANSPARENT_INDEX);
+    }
+
     #[test]
     fn test_blend_sprite_negative_offset() {
         // sprite 部分超出 dst 左边界
@@ -1,16 +1,19 @@
 //! gifrecorder — Rust 实现的 GIF 录制器
 //!
 //! 替代 PyAV (67 MB) 的轻量级方案。
-//! 提供帧存储、JPEG 压缩、后台解码、GIF 导出、Win32 屏幕截取。
+//! 提供帧存储、JPEG 压缩、后台解码、GIF 导出、PNG 序列导出、联系表导出、Win32 屏幕截取。
 
 pub mod capture;
+pub mod contact_sheet;
 pub mod decoder;
 pub mod frame_store;
 pub mod gif_export;
 pub mod jpeg;
+pub mod png_sequence;
 pub mod recorder;
 pub mod resize;
 
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use pyo3::prelude::*;
@@ -19,7 +22,7 @@ use pyo3::types::{PyBytes, PyDict};
 
 use decoder::FrameDecoder;
 use frame_store::{FrameStore, RecordConfig, RecordState};
-use recorder::RecordSession;
+use recorder::{FrameCallback, RecordSession};
 
 // ═══════════════════════════════════════════════
 //  PyFrameStore — Python 包装
@@ -46,8 +49,26 @@ impl PyFrameStore {
     ///     jpeg_quality: JPEG 压缩质量 (1-100, 默认 95)
     ///     max_frames: 最大帧数 (0=不限, 默认 0)
     ///     max_memory_bytes: 最大内存字节数 (0=不限, 默认 0)
+    ///     drop_static_frames: RecordSession 录制时跳过与上一帧几乎相同的静止帧，
+    ///         上一帧的显示时间相应延长 (默认 False)
+    ///     max_duration_secs: RecordSession 录制达到该时长 (秒，不含暂停) 后自动停止，
+    ///         状态变为 Stopped (None=不限, 默认 None)
+    ///     stop_after_frames: RecordSession 存入这么多帧后自动停止，状态变为 Stopped
+    ///         (None=不限, 默认 None)；max_frames 则只丢弃最旧帧、不停止录制
+    ///     gif_colors: export_gif 的调色板颜色数 (2-256，超出范围时截断，默认 256)，
+    ///         颜色少时文件更小
+    ///     gif_dither: export_gif 使用 Floyd–Steinberg 抖动减轻色带 (默认 True)
+    ///     pre_roll_secs: 预录时长 (秒, 0=不预录, 默认 0)。大于 0 时 RecordSession 启动后先待命，
+    ///         只保留最近这段时长的画面，调用 start_recording() 时作为录制开头
+    ///     display_index: RecordSession 录制的显示器 (list_displays() 的下标, 0=主显示器, 默认 0)，
+    ///         截取坐标相对于该显示器左上角
+    ///     overlay_cursor: RecordSession 在每帧的鼠标位置画一个 5×5 红色十字 (默认 False)
+    ///     variable_fps: 可变帧率，RecordSession 只保存与上一帧相比变化像素超过
+    ///         change_threshold_percent 的帧，GIF 延迟按实际时间戳计算 (默认 False)
+    ///     change_threshold_percent: 可变帧率的变化阈值，百分比 (默认 1.0)
     #[new]
-    #[pyo3(signature = (width, height, fps, jpeg_quality=95, max_frames=0, max_memory_bytes=0))]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (width, height, fps, jpeg_quality=95, max_frames=0, max_memory_bytes=0, drop_static_frames=false, max_duration_secs=None, stop_after_frames=None, gif_colors=256, gif_dither=true, pre_roll_secs=0.0, display_index=0, overlay_cursor=false, variable_fps=false, change_threshold_percent=1.0))]
     fn new(
         width: u32,
         height: u32,
@@ -55,11 +76,31 @@ impl PyFrameStore {
         jpeg_quality: i32,
         max_frames: usize,
         max_memory_bytes: usize,
+        drop_static_frames: bool,
+        max_duration_secs: Option<f32>,
+        stop_after_frames: Option<u32>,
+        gif_colors: usize,
+        gif_dither: bool,
+        pre_roll_secs: f32,
+        display_index: usize,
+        overlay_cursor: bool,
+        variable_fps: bool,
+        change_threshold_percent: f32,
     ) -> Self {
         let config = RecordConfig {
             jpeg_quality,
             max_frames,
             max_memory_bytes,
+            drop_static_frames,
+            max_duration_secs,
+            stop_after_frames,
+            gif_colors,
+            gif_dither,
+            pre_roll_secs,
+            display_index,
+            overlay_cursor,
+            variable_fps,
+            change_threshold_percent,
         };
         Self {
             inner: Arc::new(FrameStore::new(width, height, fps, config)),
@@ -202,6 +243,22 @@ impl PyFrameStore {
             .map_err(|e| pyo3::exceptions::PyValueError::new_err(e))
     }
 
+    // ── 剪辑 ──
+
+    /// 只保留下标在 [start_frame, end_frame] 内的帧 (含两端，通常在导出前调用)，
+    /// 保留的第一帧时间戳变为 0，total_duration_ms 随之更新
+    ///
+    /// Returns:
+    ///     int — 保留的帧数 (end_frame - start_frame + 1)
+    ///
+    /// Raises:
+    ///     ValueError: 不满足 start_frame < end_frame < frame_count
+    fn trim(&self, start_frame: usize, end_frame: usize) -> PyResult<usize> {
+        self.inner
+            .trim(start_frame, end_frame)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e))
+    }
+
     // ── 回放阶段 ──
 
     /// 解码单帧为 RGB24 bytes
@@ -228,6 +285,25 @@ impl PyFrameStore {
         Ok(PyBytes::new_bound(py, &rgb))
     }
 
+    /// 首帧缩略图：等比缩小到 max_size × max_size 以内 (不放大)，编码为 PNG
+    ///
+    /// Args:
+    ///     max_size: 缩略图最长边 (默认 200)
+    ///
+    /// Returns:
+    ///     bytes | None — PNG 数据，还没有帧时返回 None
+    ///
+    /// Raises:
+    ///     ValueError: max_size 为 0，或解码 / 编码失败
+    #[pyo3(signature = (max_size=200))]
+    fn get_first_frame_thumbnail<'py>(&self, py: Python<'py>, max_size: u32) -> PyResult<Option<Bound<'py, PyBytes>>> {
+        let store = self.inner.clone();
+        let png = py
+            .allow_threads(|| store.first_frame_thumbnail(max_size))
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e))?;
+        Ok(png.map(|png| PyBytes::new_bound(py, &png)))
+    }
+
     /// 创建后台解码器 (用于流式回放)
     ///
     /// Args:
@@ -343,6 +419,57 @@ impl PyFrameStore {
         })
     }
 
+    // ── PNG 序列导出 ──
+
+    /// 导出为 PNG 序列：output_dir 下每帧一张 frame_000001.png、frame_000002.png …，
+    /// 另写 frames.txt 清单 (frame_count / fps / pattern)，可直接用于
+    /// ffmpeg -framerate <fps> -i frame_%06d.png
+    ///
+    /// Args:
+    ///     output_dir: 输出目录 (不存在时自动创建，同名文件覆盖)
+    ///
+    /// Returns:
+    ///     int — 导出的帧数
+    ///
+    /// Raises:
+    ///     ValueError: 没有帧，或写文件 / 编码失败
+    fn export_png_sequence(&self, py: Python<'_>, output_dir: PathBuf) -> PyResult<usize> {
+        let store = self.inner.clone();
+        py.allow_threads(|| png_sequence::export_png_sequence(&store, &output_dir))
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e))
+    }
+
+    // ── 联系表导出 ──
+
+    /// 导出联系表：均匀抽取若干帧（含首尾帧）缩小后拼成网格，用于快速预览整段录制
+    ///
+    /// Args:
+    ///     columns: 每行缩略图数量 (默认 4)
+    ///     thumb_width: 缩略图宽度，高度按录制区域比例计算 (默认 240)
+    ///     samples: 抽取的帧数 (默认 columns × columns，超过总帧数时取全部帧)
+    ///
+    /// Returns:
+    ///     (bytes, width, height) — RGBA 像素数据及其尺寸
+    ///
+    /// Raises:
+    ///     ValueError: 没有帧，参数为 0，或联系表尺寸过大
+    #[pyo3(signature = (columns=4, thumb_width=240, samples=None))]
+    fn export_contact_sheet<'py>(
+        &self,
+        py: Python<'py>,
+        columns: u32,
+        thumb_width: u32,
+        samples: Option<usize>,
+    ) -> PyResult<(Bound<'py, PyBytes>, u32, u32)> {
+        let samples = samples.unwrap_or((columns as usize).saturating_mul(columns as usize));
+        let store = self.inner.clone();
+        let sheet = py
+            .allow_threads(|| contact_sheet::export_contact_sheet(&store, columns, thumb_width, samples))
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e))?;
+        let (width, height) = sheet.dimensions();
+        Ok((PyBytes::new_bound(py, sheet.as_raw()), width, height))
+    }
+
     /// 取消正在进行的 GIF 导出
     fn cancel_export(&self) {
         self.inner.set_cancel(true);
@@ -505,9 +632,16 @@ impl PyFrameDecoder {
 ///     session.pause()
 ///     session.resume()
 ///     session.stop()  # 阻塞等待线程退出
+///
+/// 传入 frame_callback 可逐帧检查画面，回调返回 True 时自动停止录制。
+///
+/// FrameStore 设置了 pre_roll_secs 时会话以待命状态启动，
+/// 调用 session.start_recording() 后才开始录制。
 #[pyclass(name = "RecordSession")]
 struct PyRecordSession {
     inner: Option<RecordSession>,
+    /// 停止后 get_recording_info 仍可查询帧信息
+    store: Arc<FrameStore>,
 }
 
 #[pymethods]
@@ -516,12 +650,19 @@ impl PyRecordSession {
     ///
     /// Args:
     ///     store: FrameStore 实例
-    ///     left: 截取区域左上角 X (屏幕坐标)
-    ///     top: 截取区域左上角 Y (屏幕坐标)
+    ///     left: 截取区域左上角 X (相对于 store 的 display_index 显示器，主显示器即屏幕坐标)
+    ///     top: 截取区域左上角 Y (同上)
     ///     width: 截取区域宽度
     ///     height: 截取区域高度
     ///     fps: 目标帧率
+    ///     frame_callback: 可选逐帧回调 fn(png: bytes) -> bool，每存入一帧以该帧的 PNG 数据调用
+    ///         (在后台线程获取 GIL 后执行)。返回 True 时停止录制，状态变为 Stopped；
+    ///         回调抛出异常时打印异常并停止录制。回调或 GIL 忙时到达的帧不会传给回调
+    ///
+    /// Raises:
+    ///     RuntimeError: display_index 超出显示器数量
     #[new]
+    #[pyo3(signature = (store, left, top, width, height, fps, frame_callback=None))]
     fn new(
         store: &PyFrameStore,
         left: i32,
@@ -529,32 +670,67 @@ impl PyRecordSession {
         width: i32,
         height: i32,
         fps: u32,
+        frame_callback: Option<PyObject>,
     ) -> PyResult<Self> {
+        let frame_callback: Option<FrameCallback> = frame_callback.map(|cb| -> FrameCallback {
+            Box::new(move |png: &[u8]| {
+                Python::with_gil(|py| match cb.call1(py, (PyBytes::new_bound(py, png),)) {
+                    Ok(result) => result.is_truthy(py).unwrap_or(false),
+                    Err(e) => {
+                        e.print(py);
+                        true // 回调异常 → 停止
+                    }
+                })
+            })
+        });
         let session = RecordSession::start(
             store.inner.clone(),
             left, top, width, height, fps,
+            frame_callback,
         )
         .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e))?;
 
-        Ok(Self { inner: Some(session) })
+        Ok(Self {
+            inner: Some(session),
+            store: store.inner.clone(),
+        })
     }
 
-    /// 暂停录制
-    fn pause(&self) -> PyResult<()> {
+    /// 结束预录待命、开始录制 (Armed → Recording)
+    ///
+    /// 待命期间缓冲的最近 pre_roll_secs 秒画面作为录制开头存入 FrameStore
+    ///
+    /// Returns:
+    ///     bool — False 表示当前不在待命状态，未做任何改变
+    fn start_recording(&self) -> PyResult<bool> {
         let session = self.inner.as_ref().ok_or_else(|| {
             pyo3::exceptions::PyRuntimeError::new_err("session already stopped")
         })?;
-        session.pause();
-        Ok(())
+        Ok(session.start_recording())
     }
 
-    /// 恢复录制
-    fn resume(&self) -> PyResult<()> {
+    /// 暂停录制 (Recording → Paused)
+    ///
+    /// 暂停期间不截屏，恢复后的时间戳扣除暂停时长，输出中没有空档
+    ///
+    /// Returns:
+    ///     bool — False 表示当前不在录制状态，未做任何改变
+    fn pause(&self) -> PyResult<bool> {
         let session = self.inner.as_ref().ok_or_else(|| {
             pyo3::exceptions::PyRuntimeError::new_err("session already stopped")
         })?;
-        session.resume();
-        Ok(())
+        Ok(session.pause())
+    }
+
+    /// 恢复录制 (Paused → Recording)
+    ///
+    /// Returns:
+    ///     bool — False 表示当前不在暂停状态，未做任何改变
+    fn resume(&self) -> PyResult<bool> {
+        let session = self.inner.as_ref().ok_or_else(|| {
+            pyo3::exceptions::PyRuntimeError::new_err("session already stopped")
+        })?;
+        Ok(session.resume())
     }
 
     /// 停止录制 (阻塞等待截屏线程退出)
@@ -566,7 +742,7 @@ impl PyRecordSession {
         Ok(())
     }
 
-    /// 当前状态 (0=idle, 1=recording, 2=paused, 3=stopped)
+    /// 当前状态 (0=idle, 1=recording, 2=paused, 3=stopped, 4=armed)
     #[getter]
     fn state(&self) -> u8 {
         self.inner.as_ref().map_or(3, |s| s.state())
@@ -584,6 +760,46 @@ impl PyRecordSession {
         self.inner.as_ref().map_or(false, |s| s.is_paused())
     }
 
+    /// 录制实时信息，供 UI 轮询显示帧数与计时
+    ///
+    /// Returns:
+    ///     dict — {"state": str, "frame_count": int, "elapsed_secs": float,
+    ///             "total_duration_ms": int, "fps": int, "width": int, "height": int,
+    ///             "memory_usage_bytes": int, "dropped_frames": int}
+    ///     state 为 "idle" / "recording" / "paused" / "stopped" / "armed"，
+    ///     elapsed_secs 为已录制时长 (不含暂停)
+    fn get_recording_info<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let state = match self.state() {
+            STATE_IDLE => "idle",
+            STATE_RECORDING => "recording",
+            STATE_PAUSED => "paused",
+            STATE_ARMED => "armed",
+            _ => "stopped",
+        };
+        let elapsed_ms = self
+            .inner
+            .as_ref()
+            .map_or_else(|| self.store.total_duration_ms(), |s| s.elapsed_ms());
+
+        let info = PyDict::new_bound(py);
+        info.set_item("state", state)?;
+        info.set_item("frame_count", self.store.frame_count())?;
+        info.set_item("elapsed_secs", elapsed_ms as f64 / 1000.0)?;
+        info.set_item("total_duration_ms", self.store.total_duration_ms())?;
+        info.set_item("fps", self.store.fps())?;
+        info.set_item("width", self.store.width())?;
+        info.set_item("height", self.store.height())?;
+        info.set_item("memory_usage_bytes", self.store.memory_usage_bytes())?;
+        info.set_item("dropped_frames", self.store.dropped_frames())?;
+        Ok(info)
+    }
+
+    /// 是否处于预录待命状态
+    #[getter]
+    fn is_armed(&self) -> bool {
+        self.inner.as_ref().is_some_and(|s| s.is_armed())
+    }
+
     /// 是否已停止
     #[getter]
     fn is_stopped(&self) -> bool {
@@ -591,6 +807,37 @@ impl PyRecordSession {
     }
 }
 
+// ═══════════════════════════════════════════════
+//  显示器枚举
+// ═══════════════════════════════════════════════
+
+/// 列出所有显示器，主显示器在第一位
+///
+/// Returns:
+///     list[dict] — 每项 {"index", "left", "top", "width", "height", "primary"}，
+///     index 即 FrameStore 的 display_index
+///
+/// Raises:
+///     RuntimeError: 枚举显示器失败
+#[pyfunction]
+fn list_displays(py: Python<'_>) -> PyResult<Vec<Bound<'_, PyDict>>> {
+    let displays = capture::list_displays().map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e))?;
+    displays
+        .iter()
+        .enumerate()
+        .map(|(index, d)| {
+            let dict = PyDict::new_bound(py);
+            dict.set_item("index", index)?;
+            dict.set_item("left", d.left)?;
+            dict.set_item("top", d.top)?;
+            dict.set_item("width", d.width)?;
+            dict.set_item("height", d.height)?;
+            dict.set_item("primary", d.primary)?;
+            Ok(dict)
+        })
+        .collect()
+}
+
 // ═══════════════════════════════════════════════
 //  辅助: 解析 Python dict → CursorSprites
 // ═══════════════════════════════════════════════
@@ -666,10 +913,12 @@ fn buffer_as_bytes<'a>(py: Python<'a>, buf: &'a PyBuffer<u8>) -> PyResult<&'a [u
 ///     gifrecorder.STATE_RECORDING   # 1
 ///     gifrecorder.STATE_PAUSED      # 2
 ///     gifrecorder.STATE_STOPPED     # 3
+///     gifrecorder.STATE_ARMED       # 4 (仅 RecordSession.state，预录待命)
 const STATE_IDLE: u8 = 0;
 const STATE_RECORDING: u8 = 1;
 const STATE_PAUSED: u8 = 2;
 const STATE_STOPPED: u8 = 3;
+const STATE_ARMED: u8 = 4;
 
 // ═══════════════════════════════════════════════
 //  模块定义
@@ -683,17 +932,22 @@ const STATE_STOPPED: u8 = 3;
 ///   - RecordSession: Win32 截屏录制（独立 Rust 线程）
 ///   - FrameDecoder: 后台流式解码（回放用）
 ///   - export_gif: 高性能 GIF 导出
+///   - export_png_sequence: 编号 PNG 序列 + frames.txt 清单导出
+///   - export_contact_sheet: 联系表（缩略图网格）导出
+///   - list_displays: 枚举显示器（多显示器录制）
 #[pymodule]
 fn gifrecorder(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyFrameStore>()?;
     m.add_class::<PyRecordSession>()?;
     m.add_class::<PyFrameDecoder>()?;
+    m.add_function(wrap_pyfunction!(list_displays, m)?)?;
 
     // 状态常量
     m.add("STATE_IDLE", STATE_IDLE)?;
     m.add("STATE_RECORDING", STATE_RECORDING)?;
     m.add("STATE_PAUSED", STATE_PAUSED)?;
     m.add("STATE_STOPPED", STATE_STOPPED)?;
+    m.add("STATE_ARMED", STATE_ARMED)?;
 
     // 版本
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
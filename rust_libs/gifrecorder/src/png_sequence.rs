@@ -0,0 +1,87 @@
+//! PNG 序列导出 — 每帧一张编号 PNG，外加 frames.txt 清单
+//!
+//! 文件名为 frame_000001.png、frame_000002.png …（从 1 开始，6 位补零），
+//! 清单记录帧数与帧率，可直接交给 ffmpeg：
+//!   ffmpeg -framerate <fps> -i frame_%06d.png out.mp4
+//! 解码 + PNG 编码并行执行，复用回放 / GIF 导出的 JPEG 解码。
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::codecs::png::PngEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+use rayon::prelude::*;
+
+use crate::frame_store::FrameStore;
+use crate::jpeg;
+
+/// 清单文件名
+pub const MANIFEST_NAME: &str = "frames.txt";
+/// 帧文件名模式（printf 风格，与 ffmpeg 的图片序列输入一致）
+const FRAME_PATTERN: &str = "frame_%06d.png";
+
+/// 第 index 帧（从 0 开始）的文件名
+fn frame_file_name(index: usize) -> String {
+    format!("frame_{:06}.png", index + 1)
+}
+
+/// 把全部帧导出为 output_dir 下的 PNG 序列，并写入 frames.txt 清单
+///
+/// output_dir 不存在时自动创建，同名文件直接覆盖；返回导出的帧数
+pub fn export_png_sequence(store: &FrameStore, output_dir: &Path) -> Result<usize, String> {
+    let count = store.frame_count();
+    if count == 0 {
+        return Err("no frames to export".into());
+    }
+    fs::create_dir_all(output_dir).map_err(|e| format!("create dir {}: {e}", output_dir.display()))?;
+
+    // 并行: clone_jpeg → decode → PNG 编码写文件
+    (0..count).into_par_iter().try_for_each(|idx| {
+        let (jpeg_data, _elapsed) = store
+            .clone_jpeg(idx)
+            .ok_or_else(|| format!("frame {idx} missing"))?;
+        let d = jpeg::decode_to_rgb(&jpeg_data)?;
+        let path = output_dir.join(frame_file_name(idx));
+        let file = File::create(&path).map_err(|e| format!("create file {}: {e}", path.display()))?;
+        PngEncoder::new(BufWriter::new(file))
+            .write_image(&d.rgb, d.width, d.height, ExtendedColorType::Rgb8)
+            .map_err(|e| format!("PNG encode frame {idx}: {e}"))
+    })?;
+
+    let manifest = format!("frame_count={count}\nfps={}\npattern={FRAME_PATTERN}\n", store.fps());
+    let manifest_path = output_dir.join(MANIFEST_NAME);
+    fs::write(&manifest_path, manifest).map_err(|e| format!("write {}: {e}", manifest_path.display()))?;
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame_store::RecordConfig;
+
+    #[test]
+    fn png_sequence_with_manifest() {
+        let store = FrameStore::new(32, 24, 15, RecordConfig::default());
+        let dir = std::env::temp_dir().join(format!("gifrecorder_png_sequence_{}", std::process::id()));
+        assert!(export_png_sequence(&store, &dir).is_err());
+
+        for (i, gray) in [0u8, 100, 200].into_iter().enumerate() {
+            store.push_bgra(&vec![gray; 32 * 24 * 4], i as u32 * 67).unwrap();
+        }
+        assert_eq!(export_png_sequence(&store, &dir).unwrap(), 3);
+
+        for (i, gray) in [0u8, 100, 200].into_iter().enumerate() {
+            let img = image::open(dir.join(format!("frame_00000{}.png", i + 1))).unwrap().to_rgb8();
+            assert_eq!(img.dimensions(), (32, 24));
+            assert!(img.get_pixel(16, 12)[0].abs_diff(gray) <= 4);
+        }
+        assert!(!dir.join("frame_000004.png").exists());
+
+        let manifest = fs::read_to_string(dir.join(MANIFEST_NAME)).unwrap();
+        assert_eq!(manifest, "frame_count=3\nfps=15\npattern=frame_%06d.png\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
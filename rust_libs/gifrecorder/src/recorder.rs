@@ -4,39 +4,70 @@
 //!   Rust 线程: ScreenCapture::grab() → FrameStore::push_bgra()
 //!   Python 侧: 仅调用 start/pause/resume/stop，完全不碰像素
 //!
+//! 逐帧回调 (RecordSession::start 的 frame_callback)：存入的帧复制一份交给专用线程，
+//! 编码为 PNG 后调用回调；回调仍在处理上一帧时新帧直接丢弃，截屏节拍不受影响。
+//!
+//! 预录 (RecordConfig::pre_roll_secs > 0)：会话启动后处于待命状态，
+//! 截屏帧只进入环形缓冲；start_recording() 时缓冲中的帧作为录制开头存入 FrameStore。
+//!
 //! 优势：
 //!   - 零 GIL 争用（截屏 + JPEG 压缩全在 Rust 线程）
 //!   - 无 mss 依赖（直接 Win32 BitBlt）
 //!   - 精确 fps 节拍控制
 
-use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
-use crate::capture::ScreenCapture;
-use crate::frame_store::FrameStore;
+use crossbeam_channel::{bounded, Receiver, Sender};
+use image::codecs::png::PngEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+
+use crate::capture::{cursor_pos, list_displays, ScreenCapture};
+use crate::frame_store::{FrameStore, RecordState};
+
+/// 静止帧检测的分块边长（像素）
+const STATIC_BLOCK_SIZE: usize = 8;
+/// 两帧对应分块的平均亮度最多相差多少仍视为静止
+const STATIC_BLOCK_TOLERANCE: u8 = 2;
+/// 可变帧率：像素任一通道变化超过该值才算"变化的像素"
+const PIXEL_CHANGE_TOLERANCE: u8 = 2;
+
+/// 光标十字的边长（像素）
+const CURSOR_CROSS_SIZE: i32 = 5;
+/// 光标十字的颜色（BGRA 红色）
+const CURSOR_CROSS_BGRA: [u8; 4] = [0, 0, 255, 255];
 
 /// 录制会话状态
 const SESSION_IDLE: u8 = 0;
 const SESSION_RECORDING: u8 = 1;
 const SESSION_PAUSED: u8 = 2;
 const SESSION_STOPPED: u8 = 3;
+const SESSION_ARMED: u8 = 4;
+
+/// 逐帧回调：参数为存入 FrameStore 的一帧的 PNG 数据，返回 true 时停止录制
+pub type FrameCallback = Box<dyn FnMut(&[u8]) -> bool + Send>;
 
 /// 共享控制标志
 struct SessionControl {
-    /// 当前状态 (0=idle, 1=recording, 2=paused, 3=stopped)
+    /// 当前状态 (0=idle, 1=recording, 2=paused, 3=stopped, 4=armed)
     state: AtomicU8,
     /// 停止标志
     stop: AtomicBool,
     /// 暂停标志
     paused: AtomicBool,
+    /// 已录制时长（毫秒，不含暂停与预录待命），每截取一帧更新一次
+    elapsed_ms: AtomicU32,
 }
 
 /// 录制会话
 pub struct RecordSession {
     control: Arc<SessionControl>,
     handle: Option<JoinHandle<()>>,
+    /// 逐帧回调线程（未设置 frame_callback 时为 None）
+    callback_handle: Option<JoinHandle<()>>,
     store: Arc<FrameStore>,
 }
 
@@ -44,9 +75,14 @@ impl RecordSession {
     /// 启动录制会话
     ///
     /// * `store`  — 帧存储（共享 Arc）
-    /// * `left`, `top` — 屏幕截取起点
+    /// * `left`, `top` — 截取起点（相对于 RecordConfig::display_index 指定的显示器）
     /// * `width`, `height` — 截取区域大小
     /// * `fps` — 目标帧率
+    /// * `frame_callback` — 可选逐帧回调，在专用线程中以 PNG 数据调用，返回 true 时停止录制；
+    ///   回调忙时到达的帧不会排队，直接跳过
+    ///
+    /// 配置了 pre_roll_secs 时以待命状态启动，需调用 start_recording() 开始录制；
+    /// display_index 超出显示器数量时返回 Err
     pub fn start(
         store: Arc<FrameStore>,
         left: i32,
@@ -54,46 +90,108 @@ impl RecordSession {
         width: i32,
         height: i32,
         fps: u32,
+        frame_callback: Option<FrameCallback>,
     ) -> Result<Self, String> {
+        let display_index = store.config().display_index;
+        let display = list_displays()?
+            .into_iter()
+            .nth(display_index)
+            .ok_or_else(|| format!("display_index {display_index} out of range"))?;
+        let (left, top) = (left + display.left, top + display.top);
+
+        let initial_state = if store.config().pre_roll_secs > 0.0 {
+            SESSION_ARMED
+        } else {
+            SESSION_RECORDING
+        };
         let control = Arc::new(SessionControl {
-            state: AtomicU8::new(SESSION_RECORDING),
+            state: AtomicU8::new(initial_state),
             stop: AtomicBool::new(false),
             paused: AtomicBool::new(false),
+            elapsed_ms: AtomicU32::new(0),
         });
 
+        // 容量 1：回调处理期间最多积压一帧，其余丢弃
+        let (frame_tx, callback_handle) = match frame_callback {
+            Some(callback) => {
+                let (tx, rx) = bounded::<Vec<u8>>(1);
+                let ctrl = control.clone();
+                let store_clone = store.clone();
+                let handle = thread::spawn(move || {
+                    callback_loop(store_clone, ctrl, rx, callback);
+                });
+                (Some(tx), Some(handle))
+            }
+            None => (None, None),
+        };
+
         let ctrl = control.clone();
         let store_clone = store.clone();
 
         let handle = thread::spawn(move || {
-            capture_loop(store_clone, ctrl, left, top, width, height, fps);
+            capture_loop(store_clone, ctrl, (left, top, width, height), fps, frame_tx);
         });
 
         Ok(Self {
             control,
             handle: Some(handle),
+            callback_handle,
             store,
         })
     }
 
-    /// 暂停录制
-    pub fn pause(&self) {
-        self.control.paused.store(true, Ordering::Release);
-        self.control.state.store(SESSION_PAUSED, Ordering::Release);
+    /// 结束待命、开始录制（Armed → Recording），预录缓冲中的帧作为录制开头
+    ///
+    /// 返回 false 表示当前不在待命状态，未做任何改变
+    pub fn start_recording(&self) -> bool {
+        self.control
+            .state
+            .compare_exchange(SESSION_ARMED, SESSION_RECORDING, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
     }
 
-    /// 恢复录制
-    pub fn resume(&self) {
-        self.control.paused.store(false, Ordering::Release);
-        self.control.state.store(SESSION_RECORDING, Ordering::Release);
+    /// 暂停录制（Recording → Paused）
+    ///
+    /// 暂停期间不截屏，恢复后时间戳扣除暂停时长继续递增；
+    /// 返回 false 表示当前不在录制状态，未做任何改变
+    pub fn pause(&self) -> bool {
+        let paused = self
+            .control
+            .state
+            .compare_exchange(SESSION_RECORDING, SESSION_PAUSED, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok();
+        if paused {
+            self.control.paused.store(true, Ordering::Release);
+        }
+        paused
     }
 
-    /// 停止录制（阻塞等待线程退出）
+    /// 恢复录制（Paused → Recording）
+    ///
+    /// 返回 false 表示当前不在暂停状态，未做任何改变
+    pub fn resume(&self) -> bool {
+        let resumed = self
+            .control
+            .state
+            .compare_exchange(SESSION_PAUSED, SESSION_RECORDING, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok();
+        if resumed {
+            self.control.paused.store(false, Ordering::Release);
+        }
+        resumed
+    }
+
+    /// 停止录制（阻塞等待截屏线程和逐帧回调线程退出）
     pub fn stop(&mut self) {
         self.control.stop.store(true, Ordering::Release);
         self.control.paused.store(false, Ordering::Release); // 解除暂停
         if let Some(h) = self.handle.take() {
             let _ = h.join();
         }
+        // 截屏线程退出时关闭了 channel，回调线程处理完手头的帧即退出
+        if let Some(h) = self.callback_handle.take() {
+            let _ = h.join();
+        }
         self.control.state.store(SESSION_STOPPED, Ordering::Release);
     }
 
@@ -102,6 +200,11 @@ impl RecordSession {
         self.control.state.load(Ordering::Acquire)
     }
 
+    /// 已录制时长（毫秒，不含暂停；预录的帧从缓冲中最早一帧算起）
+    pub fn elapsed_ms(&self) -> u32 {
+        self.control.elapsed_ms.load(Ordering::Relaxed)
+    }
+
     /// 是否正在录制
     pub fn is_recording(&self) -> bool {
         self.state() == SESSION_RECORDING
@@ -112,6 +215,11 @@ impl RecordSession {
         self.state() == SESSION_PAUSED
     }
 
+    /// 是否处于预录待命状态
+    pub fn is_armed(&self) -> bool {
+        self.state() == SESSION_ARMED
+    }
+
     /// 是否已停止
     pub fn is_stopped(&self) -> bool {
         let s = self.state();
@@ -126,20 +234,24 @@ impl RecordSession {
 
 impl Drop for RecordSession {
     fn drop(&mut self) {
+        // 不等待回调线程：Drop 可能发生在持有 GIL 时，而 Python 回调正等待 GIL
+        self.callback_handle.take();
         self.stop();
     }
 }
 
 /// 截屏循环（在独立线程运行）
+///
+/// `region` 为截取区域 (left, top, width, height)；`frame_tx` 接收存入帧的 BGRA 副本（逐帧回调用）
 fn capture_loop(
     store: Arc<FrameStore>,
     ctrl: Arc<SessionControl>,
-    left: i32,
-    top: i32,
-    width: i32,
-    height: i32,
+    region: (i32, i32, i32, i32),
     fps: u32,
+    frame_tx: Option<Sender<Vec<u8>>>,
 ) {
+    let (left, top, width, height) = region;
+
     // 创建 GDI 截屏上下文
     let mut capturer = match ScreenCapture::new(left, top, width, height) {
         Ok(c) => c,
@@ -155,6 +267,24 @@ fn capture_loop(
     let mut frame_count: u64 = 0;
     let mut pause_offset = Duration::ZERO;
     let mut pause_start: Option<Instant> = None;
+    let drop_static = store.config().drop_static_frames;
+    let overlay_cursor = store.config().overlay_cursor;
+    let variable_fps = store.config().variable_fps;
+    let change_threshold = store.config().change_threshold_percent;
+    let max_duration = store.config().max_duration_secs.map(Duration::from_secs_f32);
+    let stop_after_frames = store.config().stop_after_frames;
+    // 本次会话存入 FrameStore 的帧数（含预录转存的帧）
+    let mut stored_frames: u32 = 0;
+    // 预录环形缓冲：待命期间的 (JPEG, 截取时刻)，开始录制时整体转存
+    let mut armed = ctrl.state.load(Ordering::Acquire) == SESSION_ARMED;
+    let pre_roll_capacity = ((store.config().pre_roll_secs * fps as f32).ceil() as usize).max(1);
+    let mut pre_roll: VecDeque<(Vec<u8>, Duration)> = VecDeque::new();
+    // 录制起点：帧时间戳与最大时长均从这里算起（预录时为缓冲中最早的一帧）
+    let mut time_base = Duration::ZERO;
+    // 上一张存入的帧的分块亮度 / 完整像素（可变帧率用），以及其后被跳过的最后一帧的时间戳
+    let mut prev_signature: Option<Vec<u8>> = None;
+    let mut prev_frame: Vec<u8> = Vec::new();
+    let mut static_tail_ms: Option<u32> = None;
 
     loop {
         // ── 检查停止 ──
@@ -162,6 +292,16 @@ fn capture_loop(
             break;
         }
 
+        // ── 待命 → 录制：预录缓冲中的帧作为开头存入 ──
+        if armed && ctrl.state.load(Ordering::Acquire) != SESSION_ARMED {
+            armed = false;
+            time_base = pre_roll.front().map_or(record_start.elapsed(), |&(_, t)| t);
+            for (jpeg_data, t) in pre_roll.drain(..) {
+                store.push_jpeg(jpeg_data, (t - time_base).as_millis() as u32);
+                stored_frames += 1;
+            }
+        }
+
         // ── 暂停处理 ──
         if ctrl.paused.load(Ordering::Acquire) {
             if pause_start.is_none() {
@@ -189,6 +329,16 @@ fn capture_loop(
             }
         }
 
+        // ── 达到最大录制时长或帧数：自动停止 ──
+        if !armed
+            && (max_duration.is_some_and(|limit| record_start.elapsed() - pause_offset - time_base >= limit)
+                || stop_after_frames.is_some_and(|limit| stored_frames >= limit))
+        {
+            ctrl.state.store(SESSION_STOPPED, Ordering::Release);
+            store.set_state(RecordState::Stopped);
+            break;
+        }
+
         // ── 截屏 ──
         let bgra = match capturer.grab() {
             Ok(data) => data,
@@ -200,17 +350,159 @@ fn capture_loop(
 
         // ── 计算 elapsed_ms（排除暂停时间）──
         let elapsed = record_start.elapsed() - pause_offset;
-        let elapsed_ms = elapsed.as_millis() as u32;
+
+        // ── 待命：只进入预录缓冲，超出容量时丢弃最旧帧 ──
+        if armed {
+            if let Ok(jpeg_data) = store.encode_bgra(bgra) {
+                if pre_roll.len() >= pre_roll_capacity {
+                    pre_roll.pop_front();
+                }
+                pre_roll.push_back((jpeg_data, elapsed));
+            }
+            frame_count += 1;
+            continue;
+        }
+
+        let elapsed_ms = elapsed.saturating_sub(time_base).as_millis() as u32;
+        ctrl.elapsed_ms.store(elapsed_ms, Ordering::Relaxed);
+
+        // ── 叠加光标（先于静止帧判断，光标移动也算画面变化）──
+        if overlay_cursor {
+            if let Some((x, y)) = cursor_pos() {
+                draw_cursor_cross(bgra, store.width() as usize, store.height() as usize, x - left, y - top);
+            }
+        }
+
+        // ── 静止帧 / 可变帧率：与上一张存入的帧几乎相同（分块亮度一致，
+        //    或变化像素比例不超过阈值）则跳过，
+        //    导出时按时间戳计算延迟，上一帧的显示时间自然覆盖跳过的时长 ──
+        let signature = drop_static.then(|| frame_signature(bgra, store.width() as usize, store.height() as usize));
+        let unchanged = signature
+            .as_ref()
+            .zip(prev_signature.as_ref())
+            .is_some_and(|(signature, prev)| signatures_match(prev, signature))
+            || (variable_fps && !prev_frame.is_empty() && changed_pixel_percent(&prev_frame, bgra) <= change_threshold);
+        if unchanged {
+            static_tail_ms = Some(elapsed_ms);
+            frame_count += 1;
+            continue;
+        }
+        if signature.is_some() {
+            prev_signature = signature;
+        }
+        if variable_fps {
+            prev_frame.clear();
+            prev_frame.extend_from_slice(bgra);
+        }
+        static_tail_ms = None;
 
         // ── 存入 FrameStore（JPEG 压缩在此发生）──
-        let _ = store.push_bgra(bgra, elapsed_ms);
+        if store.push_bgra(bgra, elapsed_ms).is_ok() {
+            stored_frames += 1;
+        }
+
+        // ── 逐帧回调：回调线程空闲时才复制一份，忙时丢弃 ──
+        if let Some(tx) = frame_tx.as_ref().filter(|tx| tx.is_empty()) {
+            let _ = tx.try_send(bgra.to_vec());
+        }
 
         frame_count += 1;
     }
 
+    // 结尾的静止画面全部被跳过时补一帧，保留这段时长
+    if let Some(ms) = static_tail_ms {
+        store.repeat_last_frame(ms);
+    }
+
     // 线程结束，capturer 在 Drop 中释放 GDI 资源
 }
 
+/// 逐帧回调线程：取出 BGRA 帧，编码为 PNG 后调用回调，回调返回 true 时停止录制
+///
+/// 截屏线程退出（channel 关闭）时结束
+fn callback_loop(store: Arc<FrameStore>, ctrl: Arc<SessionControl>, rx: Receiver<Vec<u8>>, mut callback: FrameCallback) {
+    for bgra in rx {
+        let png = match encode_png_bgra(&bgra, store.width(), store.height()) {
+            Ok(png) => png,
+            Err(e) => {
+                eprintln!("[gifrecorder] 逐帧回调 PNG 编码失败: {e}");
+                continue;
+            }
+        };
+        if callback(&png) {
+            ctrl.stop.store(true, Ordering::Release);
+            ctrl.paused.store(false, Ordering::Release);
+            ctrl.state.store(SESSION_STOPPED, Ordering::Release);
+            store.set_state(RecordState::Stopped);
+            break;
+        }
+    }
+}
+
+/// BGRA → PNG（RGB24）
+fn encode_png_bgra(bgra: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let rgb: Vec<u8> = bgra.chunks_exact(4).flat_map(|px| [px[2], px[1], px[0]]).collect();
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(&rgb, width, height, ExtendedColorType::Rgb8)
+        .map_err(|e| format!("PNG encode failed: {e}"))?;
+    Ok(png)
+}
+
+/// 在 BGRA 帧上以 (x, y) 为中心画一个 CURSOR_CROSS_SIZE 的红色十字，超出画面的部分忽略
+fn draw_cursor_cross(bgra: &mut [u8], width: usize, height: usize, x: i32, y: i32) {
+    let half = CURSOR_CROSS_SIZE / 2;
+    for d in -half..=half {
+        for (px, py) in [(x + d, y), (x, y + d)] {
+            if px >= 0 && py >= 0 && (px as usize) < width && (py as usize) < height {
+                let i = (py as usize * width + px as usize) * 4;
+                bgra[i..i + 4].copy_from_slice(&CURSOR_CROSS_BGRA);
+            }
+        }
+    }
+}
+
+/// BGRA 帧的分块亮度：每个 STATIC_BLOCK_SIZE × STATIC_BLOCK_SIZE 块的平均亮度（边缘不足一块的按实际像素算）
+fn frame_signature(bgra: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let blocks_x = width.div_ceil(STATIC_BLOCK_SIZE);
+    let blocks_y = height.div_ceil(STATIC_BLOCK_SIZE);
+    let mut sums = vec![0u32; blocks_x * blocks_y];
+    let mut counts = vec![0u32; blocks_x * blocks_y];
+    for (y, row) in bgra.chunks_exact(width * 4).take(height).enumerate() {
+        let block_row = (y / STATIC_BLOCK_SIZE) * blocks_x;
+        for (x, px) in row.chunks_exact(4).enumerate() {
+            // BT.601 近似：(B + 5G + 2R) / 8
+            let luma = (px[0] as u32 + 5 * px[1] as u32 + 2 * px[2] as u32) / 8;
+            let idx = block_row + x / STATIC_BLOCK_SIZE;
+            sums[idx] += luma;
+            counts[idx] += 1;
+        }
+    }
+    sums.iter()
+        .zip(&counts)
+        .map(|(&sum, &count)| (sum / count.max(1)) as u8)
+        .collect()
+}
+
+/// 两帧的每个分块亮度都相差不超过 STATIC_BLOCK_TOLERANCE
+fn signatures_match(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(&x, &y)| x.abs_diff(y) <= STATIC_BLOCK_TOLERANCE)
+}
+
+/// 两帧 BGRA 中变化像素所占的百分比（0 ~ 100），任一颜色通道相差超过 PIXEL_CHANGE_TOLERANCE 即算变化
+fn changed_pixel_percent(prev: &[u8], cur: &[u8]) -> f32 {
+    let total = prev.len().min(cur.len()) / 4;
+    if total == 0 {
+        return 100.0;
+    }
+    let changed = prev
+        .chunks_exact(4)
+        .zip(cur.chunks_exact(4))
+        .filter(|(a, b)| a[..3].iter().zip(&b[..3]).any(|(&x, &y)| x.abs_diff(y) > PIXEL_CHANGE_TOLERANCE))
+        .count();
+    changed as f32 * 100.0 / total as f32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -224,7 +516,7 @@ mod tests {
         }));
 
         let mut session = RecordSession::start(
-            store.clone(), 0, 0, 64, 48, 10,
+            store.clone(), 0, 0, 64, 48, 10, None,
         ).unwrap();
 
         assert!(session.is_recording());
@@ -234,6 +526,7 @@ mod tests {
 
         session.stop();
         assert!(session.is_stopped());
+        assert!((200..400).contains(&session.elapsed_ms()), "elapsed = {}", session.elapsed_ms());
 
         let count = store.frame_count();
         // 10fps × 0.3s ≈ 3 帧（允许 1~5）
@@ -241,6 +534,194 @@ mod tests {
         assert!(count <= 6, "frame_count = {count}");
     }
 
+    #[test]
+    fn record_session_max_duration() {
+        let store = Arc::new(FrameStore::new(64, 48, 20, RecordConfig {
+            jpeg_quality: 80,
+            max_duration_secs: Some(0.2),
+            ..Default::default()
+        }));
+
+        let mut session = RecordSession::start(
+            store.clone(), 0, 0, 64, 48, 20, None,
+        ).unwrap();
+
+        // 到时自动停止，无需调用 stop()
+        thread::sleep(Duration::from_millis(500));
+        assert!(session.is_stopped());
+        assert_eq!(store.state(), RecordState::Stopped);
+        assert!(store.total_duration_ms() < 200, "duration = {}", store.total_duration_ms());
+        // 20fps × 0.2s = 4 帧（允许 1~5）
+        let count = store.frame_count();
+        assert!((1..=5).contains(&count), "frame_count = {count}");
+
+        session.stop();
+        assert!(session.is_stopped());
+    }
+
+    #[test]
+    fn record_session_stop_after_frames() {
+        let store = Arc::new(FrameStore::new(64, 48, 20, RecordConfig {
+            jpeg_quality: 80,
+            stop_after_frames: Some(5),
+            ..Default::default()
+        }));
+
+        let mut session = RecordSession::start(
+            store.clone(), 0, 0, 64, 48, 20, None,
+        ).unwrap();
+
+        // 20fps 下 5 帧约 250ms，存满后自动停止，帧数不多不少
+        thread::sleep(Duration::from_millis(600));
+        assert!(session.is_stopped());
+        assert_eq!(store.state(), RecordState::Stopped);
+        assert_eq!(store.frame_count(), 5);
+
+        session.stop();
+        assert_eq!(store.frame_count(), 5);
+    }
+
+    #[test]
+    fn record_session_frame_callback_stops() {
+        let store = Arc::new(FrameStore::new(64, 48, 20, RecordConfig {
+            jpeg_quality: 80,
+            ..Default::default()
+        }));
+
+        // 第 3 次回调时要求停止；回调收到的是可解码的 PNG
+        let calls = Arc::new(AtomicU32::new(0));
+        let calls_clone = calls.clone();
+        let callback: FrameCallback = Box::new(move |png| {
+            let img = image::load_from_memory_with_format(png, image::ImageFormat::Png).unwrap();
+            assert_eq!((img.width(), img.height()), (64, 48));
+            calls_clone.fetch_add(1, Ordering::Relaxed) + 1 >= 3
+        });
+
+        let mut session = RecordSession::start(
+            store.clone(), 0, 0, 64, 48, 20, Some(callback),
+        ).unwrap();
+
+        thread::sleep(Duration::from_millis(800));
+        assert!(session.is_stopped());
+        assert_eq!(store.state(), RecordState::Stopped);
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+        // 回调请求停止时截屏线程可能刚好多存一帧
+        let count = store.frame_count();
+        assert!((3..=6).contains(&count), "frame_count = {count}");
+
+        session.stop();
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn record_session_pre_roll() {
+        let store = Arc::new(FrameStore::new(64, 48, 20, RecordConfig {
+            jpeg_quality: 80,
+            pre_roll_secs: 0.2,
+            ..Default::default()
+        }));
+
+        let mut session = RecordSession::start(
+            store.clone(), 0, 0, 64, 48, 20, None,
+        ).unwrap();
+
+        // 待命期间不写入 FrameStore
+        assert!(session.is_armed());
+        assert!(!session.pause(), "待命时不能暂停");
+        thread::sleep(Duration::from_millis(400));
+        assert_eq!(store.frame_count(), 0);
+
+        assert!(session.start_recording());
+        assert!(session.is_recording());
+        assert!(!session.start_recording());
+        thread::sleep(Duration::from_millis(150));
+        session.stop();
+
+        // 20fps × 0.2s = 4 帧预录在前，时间戳从 0 开始连续递增
+        let timestamps = store.frame_timestamps();
+        assert!(timestamps.len() >= 5, "{timestamps:?}");
+        assert_eq!(timestamps[0], 0);
+        assert!(timestamps[3] >= 100, "{timestamps:?}");
+        assert!(timestamps.windows(2).all(|w| w[1] >= w[0] && w[1] - w[0] < 150), "{timestamps:?}");
+    }
+
+    #[test]
+    fn record_session_display_out_of_range() {
+        let display_count = list_displays().unwrap().len();
+        let store = Arc::new(FrameStore::new(64, 48, 10, RecordConfig {
+            display_index: display_count,
+            ..Default::default()
+        }));
+        let err = RecordSession::start(store, 0, 0, 64, 48, 10, None).err().unwrap();
+        assert!(err.contains("out of range"), "{err}");
+    }
+
+    #[test]
+    fn cursor_cross_is_clipped() {
+        let (w, h) = (8, 6);
+        let mut bgra = vec![0u8; w * h * 4];
+        let red = |bgra: &[u8], x: usize, y: usize| bgra[(y * w + x) * 4..(y * w + x) * 4 + 4] == CURSOR_CROSS_BGRA;
+
+        draw_cursor_cross(&mut bgra, w, h, 3, 3);
+        assert!(red(&bgra, 3, 3) && red(&bgra, 1, 3) && red(&bgra, 5, 3) && red(&bgra, 3, 1) && red(&bgra, 3, 5));
+        assert!(!red(&bgra, 2, 2) && !red(&bgra, 0, 3) && !red(&bgra, 6, 3));
+        assert_eq!(bgra.chunks_exact(4).filter(|px| *px == CURSOR_CROSS_BGRA).count(), 9);
+
+        // 光标在画面角落或区域外：只画落在画面内的部分，不越界
+        let mut corner = vec![0u8; w * h * 4];
+        draw_cursor_cross(&mut corner, w, h, 0, 0);
+        assert_eq!(corner.chunks_exact(4).filter(|px| *px == CURSOR_CROSS_BGRA).count(), 5);
+        let mut outside = vec![0u8; w * h * 4];
+        draw_cursor_cross(&mut outside, w, h, -10, 50);
+        assert!(outside.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn static_frame_signature() {
+        let (w, h) = (20, 10);
+        let base = vec![200u8; w * h * 4];
+        let sig = frame_signature(&base, w, h);
+        // 20x10 → 3x2 块
+        assert_eq!(sig.len(), 6);
+
+        // 个别像素的细微色差仍视为静止
+        let mut noisy = base.clone();
+        noisy[0] = 203;
+        assert!(signatures_match(&sig, &frame_signature(&noisy, w, h)));
+
+        // 一个 2x8 的黑色笔画（如文字光标）足以判定为变化
+        let mut stroke = base.clone();
+        for y in 0..8 {
+            for x in 9..11 {
+                let i = (y * w + x) * 4;
+                stroke[i..i + 3].fill(0);
+            }
+        }
+        assert!(!signatures_match(&sig, &frame_signature(&stroke, w, h)));
+    }
+
+    #[test]
+    fn changed_pixels_for_variable_fps() {
+        let (w, h) = (20, 10);
+        let base = vec![200u8; w * h * 4];
+        assert_eq!(changed_pixel_percent(&base, &base), 0.0);
+
+        // 轻微噪声与 alpha 通道不算变化
+        let mut noisy = base.clone();
+        noisy[0] = 202;
+        noisy[3] = 0;
+        assert_eq!(changed_pixel_percent(&base, &noisy), 0.0);
+
+        // 200 个像素中 3 个变化 → 1.5%，超过默认阈值 1%
+        let mut typed = base.clone();
+        for px in [0, 57, 199] {
+            typed[px * 4 + 1] = 0;
+        }
+        let percent = changed_pixel_percent(&base, &typed);
+        assert!((percent - 1.5).abs() < 1e-4, "{percent}");
+        assert!(percent > RecordConfig::default().change_threshold_percent);
+    }
+
     #[test]
     fn record_session_pause_resume() {
         let store = Arc::new(FrameStore::new(64, 48, 10, RecordConfig {
@@ -249,7 +730,7 @@ mod tests {
         }));
 
         let mut session = RecordSession::start(
-            store.clone(), 0, 0, 64, 48, 10,
+            store.clone(), 0, 0, 64, 48, 10, None,
         ).unwrap();
 
         // 录制 200ms
@@ -257,20 +738,27 @@ mod tests {
         let count1 = store.frame_count();
 
         // 暂停 300ms
-        session.pause();
+        assert!(session.pause());
         assert!(session.is_paused());
+        assert!(!session.pause(), "重复暂停不应生效");
         thread::sleep(Duration::from_millis(300));
         let count_during_pause = store.frame_count();
         // 暂停信号有极短的竞争窗口，可能多抓 1 帧
         assert!(count_during_pause <= count1 + 1, "暂停期间帧数异常增长");
 
         // 恢复 200ms
-        session.resume();
+        assert!(session.resume());
         assert!(session.is_recording());
+        assert!(!session.resume(), "未暂停时恢复不应生效");
         thread::sleep(Duration::from_millis(200));
 
         session.stop();
+        assert!(!session.pause(), "停止后不能再暂停");
         let count_final = store.frame_count();
         assert!(count_final > count1, "恢复后应有新帧");
+
+        // 时间戳连续：暂停的 300ms 不计入，相邻帧间隔远小于暂停时长
+        let timestamps = store.frame_timestamps();
+        assert!(timestamps.windows(2).all(|w| w[1] >= w[0] && w[1] - w[0] < 250), "{timestamps:?}");
     }
 }
@@ -3,33 +3,50 @@ use rayon::prelude::*;
 
 // ========== 行哈希（长截图拼接专用）==========
 
+/// 列采样率下限（最多每 20 列取 1 列）
+const MIN_COLUMN_SAMPLE_RATE: f32 = 0.05;
+
 /// 从 PNG/JPEG 字节计算逐行哈希
+///
+/// column_sample_rate: 列采样率（0.05 ~ 1.0），小于 1.0 时每隔 1/rate 列取一列，
+/// 用于加速 4K 等宽图的哈希计算
 pub fn compute_row_hashes(
     image_bytes: &[u8],
     ignore_right_pixels: u32,
+    column_sample_rate: f32,
 ) -> Result<Vec<u64>, String> {
     let img =
         image::load_from_memory(image_bytes).map_err(|e| format!("Failed to load image: {}", e))?;
     let rgba_img = img.to_rgba8();
-    Ok(compute_row_hashes_from_rgba(&rgba_img, ignore_right_pixels, false))
+    Ok(compute_row_hashes_internal(&rgba_img, ignore_right_pixels, column_sample_rate, false))
 }
 
 /// 带调试输出的版本
 pub fn compute_row_hashes_debug(
     image_bytes: &[u8],
     ignore_right_pixels: u32,
+    column_sample_rate: f32,
 ) -> Result<Vec<u64>, String> {
     let img =
         image::load_from_memory(image_bytes).map_err(|e| format!("Failed to load image: {}", e))?;
     let rgba_img = img.to_rgba8();
-    Ok(compute_row_hashes_from_rgba(&rgba_img, ignore_right_pixels, true))
+    Ok(compute_row_hashes_internal(&rgba_img, ignore_right_pixels, column_sample_rate, true))
 }
 
-/// 直接从 RgbaImage 计算行哈希（零拷贝）
+/// 直接从 RgbaImage 计算行哈希（零拷贝，使用全部列）
 pub fn compute_row_hashes_from_rgba(
     rgba_img: &image::RgbaImage,
     ignore_right_pixels: u32,
     debug: bool,
+) -> Vec<u64> {
+    compute_row_hashes_internal(rgba_img, ignore_right_pixels, 1.0, debug)
+}
+
+fn compute_row_hashes_internal(
+    rgba_img: &image::RgbaImage,
+    ignore_right_pixels: u32,
+    column_sample_rate: f32,
+    debug: bool,
 ) -> Vec<u64> {
     let width = rgba_img.width();
     let height = rgba_img.height();
@@ -40,60 +57,56 @@ pub fn compute_row_hashes_from_rgba(
         width
     };
 
+    // 列采样步长：rate = 0.25 → 每 4 列取 1 列
+    let sample_rate = if column_sample_rate.is_nan() {
+        1.0
+    } else {
+        column_sample_rate.clamp(MIN_COLUMN_SAMPLE_RATE, 1.0)
+    };
+    let column_step = ((1.0 / sample_rate) as usize).max(1);
+
     let raw = rgba_img.as_raw();
     let stride = (width * 4) as usize;
 
+    // 计算某一行的量化 RGB 均值（只统计采样列）
+    let row_means = |y: u32| -> Option<(u64, u64, u64)> {
+        let mut r_sum: u64 = 0;
+        let mut g_sum: u64 = 0;
+        let mut b_sum: u64 = 0;
+        let mut pixel_count: u64 = 0;
+
+        let row_start = y as usize * stride;
+        let row_data = &raw[row_start..row_start + (effective_width as usize) * 4];
+        for chunk in row_data.chunks_exact(4).step_by(column_step) {
+            r_sum += chunk[0] as u64;
+            g_sum += chunk[1] as u64;
+            b_sum += chunk[2] as u64;
+            pixel_count += 1;
+        }
+
+        // 没有采样到像素（宽度为 0）时返回 None
+        let quantized_mean = |sum: u64| sum.checked_div(pixel_count).map(|mean| (mean / 8) * 8);
+        Some((quantized_mean(r_sum)?, quantized_mean(g_sum)?, quantized_mean(b_sum)?))
+    };
+
     let row_hashes: Vec<u64> = (0..height)
         .into_par_iter()
-        .map(|y| {
-            let mut r_sum: u64 = 0;
-            let mut g_sum: u64 = 0;
-            let mut b_sum: u64 = 0;
-            let pixel_count = effective_width as u64;
-
-            let row_start = y as usize * stride;
-            let row_data = &raw[row_start..row_start + (effective_width as usize) * 4];
-            for chunk in row_data.chunks_exact(4) {
-                r_sum += chunk[0] as u64;
-                g_sum += chunk[1] as u64;
-                b_sum += chunk[2] as u64;
-            }
-
-            if pixel_count > 0 {
-                let r_mean = ((r_sum / pixel_count) / 8) * 8;
-                let g_mean = ((g_sum / pixel_count) / 8) * 8;
-                let b_mean = ((b_sum / pixel_count) / 8) * 8;
-
-                r_mean
-                    .wrapping_mul(73856093)
-                    .wrapping_add(g_mean.wrapping_mul(19349663))
-                    .wrapping_add(b_mean.wrapping_mul(83492791))
-            } else {
-                0
-            }
+        .map(|y| match row_means(y) {
+            Some((r_mean, g_mean, b_mean)) => r_mean
+                .wrapping_mul(73856093)
+                .wrapping_add(g_mean.wrapping_mul(19349663))
+                .wrapping_add(b_mean.wrapping_mul(83492791)),
+            None => 0,
         })
         .collect();
 
     if debug {
+        if column_step > 1 {
+            println!("  ⚡ 列采样: 每{}列取1列", column_step);
+        }
         println!("  📊 样本哈希值（每100行）:");
         for y in (0..height).step_by(100).take(3) {
-            let mut r_sum: u64 = 0;
-            let mut g_sum: u64 = 0;
-            let mut b_sum: u64 = 0;
-
-            let row_start = y as usize * stride;
-            let row_data = &raw[row_start..row_start + (effective_width as usize) * 4];
-            for chunk in row_data.chunks_exact(4) {
-                r_sum += chunk[0] as u64;
-                g_sum += chunk[1] as u64;
-                b_sum += chunk[2] as u64;
-            }
-
-            let pixel_count = effective_width as u64;
-            if pixel_count > 0 {
-                let r_mean = ((r_sum / pixel_count) / 8) * 8;
-                let g_mean = ((g_sum / pixel_count) / 8) * 8;
-                let b_mean = ((b_sum / pixel_count) / 8) * 8;
+            if let Some((r_mean, g_mean, b_mean)) = row_means(y) {
                 let hash = row_hashes[y as usize];
 
                 println!(
@@ -125,7 +138,23 @@ mod tests {
         )
         .unwrap();
 
-        let hashes = compute_row_hashes(&bytes, 0).unwrap();
+        let hashes = compute_row_hashes(&bytes, 0, 1.0).unwrap();
         assert_eq!(hashes.len(), 50);
     }
+
+    #[test]
+    fn test_column_sampling() {
+        // 每行颜色均匀的图片，采样前后哈希应完全一致
+        let img = RgbaImage::from_fn(400, 30, |_x, y| {
+            Rgba([(y * 8) as u8, (255 - y * 8) as u8, (y * 3) as u8, 255])
+        });
+
+        let full = compute_row_hashes_internal(&img, 20, 1.0, false);
+        assert_eq!(full, compute_row_hashes_from_rgba(&img, 20, false));
+        assert_eq!(compute_row_hashes_internal(&img, 20, 0.25, false), full);
+
+        // 超出范围的采样率被夹到 [0.05, 1.0]
+        assert_eq!(compute_row_hashes_internal(&img, 20, 0.0, false), full);
+        assert_eq!(compute_row_hashes_internal(&img, 20, 5.0, false), full);
+    }
 }
@@ -7,6 +7,9 @@
 /// - 行哈希 (Row Hash): 用于长截图拼接的逐行哈希
 use image::GrayImage;
 use rayon::prelude::*;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
 
 /// 计算差值哈希 (dHash)
 ///
@@ -19,10 +22,55 @@ use rayon::prelude::*;
 ///
 /// 返回: u64 哈希值
 pub fn compute_dhash(image_bytes: &[u8], hash_size: usize) -> Result<u64, String> {
+    let key = hash_cache_key(image_bytes, hash_size as u64);
+    if let Some(hash) = DHASH_CACHE.with(|cache| cache.borrow_mut().get(key)) {
+        return Ok(hash);
+    }
+
     // 加载图像
     let img =
         image::load_from_memory(image_bytes).map_err(|e| format!("Failed to load image: {}", e))?;
 
+    let hash = dhash_from_image(&img, hash_size);
+    DHASH_CACHE.with(|cache| cache.borrow_mut().put(key, hash));
+    Ok(hash)
+}
+
+/// 计算带遮罩的差值哈希
+///
+/// 先把 mask_rect 区域涂黑（水印、时间戳、滚动条等固定区域），再计算 dHash，
+/// 使仅在遮罩区域不同的两张图得到相同哈希
+///
+/// 参数:
+///   image_bytes: PNG/JPEG 图像数据
+///   mask_rect: 遮罩区域 (x, y, width, height)，超出图像的部分会被裁剪
+///   hash_size: 哈希尺寸
+pub fn compute_dhash_masked(
+    image_bytes: &[u8],
+    mask_rect: (u32, u32, u32, u32),
+    hash_size: usize,
+) -> Result<u64, String> {
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+    let img =
+        image::load_from_memory(image_bytes).map_err(|e| format!("Failed to load image: {}", e))?;
+
+    let (width, height) = img.dimensions();
+    let (x, y, w, h) = mask_rect;
+    let mask_w = w.min(width.saturating_sub(x));
+    let mask_h = h.min(height.saturating_sub(y));
+
+    let mut masked = img.to_rgba8();
+    if mask_w > 0 && mask_h > 0 {
+        let patch = RgbaImage::from_pixel(mask_w, mask_h, Rgba([0, 0, 0, 255]));
+        image::imageops::replace(&mut masked, &patch, x as i64, y as i64);
+    }
+
+    Ok(dhash_from_image(&DynamicImage::ImageRgba8(masked), hash_size))
+}
+
+/// 从已解码图像计算 dHash
+fn dhash_from_image(img: &image::DynamicImage, hash_size: usize) -> u64 {
     // 转换为灰度并缩放到 (hash_size+1) x hash_size
     let gray = img.grayscale();
     let resized = image::imageops::resize(
@@ -49,7 +97,85 @@ pub fn compute_dhash(image_bytes: &[u8], hash_size: usize) -> Result<u64, String
         }
     }
 
-    Ok(hash)
+    hash
+}
+
+/// 多尺度 dHash 使用的哈希尺寸（粗 → 细）
+pub const MULTISCALE_DHASH_SIZES: [usize; 3] = [4, 8, 16];
+
+/// 每个尺度占用的 u64 个数（16×16 = 256 位需要 4 个 u64）
+fn multiscale_words(hash_size: usize) -> usize {
+    (hash_size * hash_size).div_ceil(64)
+}
+
+/// 多尺度 dHash 的总长度（u64 个数）
+pub fn multiscale_dhash_len() -> usize {
+    MULTISCALE_DHASH_SIZES.iter().map(|&s| multiscale_words(s)).sum()
+}
+
+/// 从已解码图像计算任意尺寸的 dHash，按 64 位分组存入多个 u64
+fn dhash_words(gray: &image::DynamicImage, hash_size: usize) -> Vec<u64> {
+    let resized = image::imageops::resize(
+        gray,
+        (hash_size + 1) as u32,
+        hash_size as u32,
+        image::imageops::FilterType::Triangle,
+    );
+
+    let mut words = vec![0u64; multiscale_words(hash_size)];
+    for y in 0..hash_size {
+        for x in 0..hash_size {
+            let left = resized.get_pixel(x as u32, y as u32)[0];
+            let right = resized.get_pixel((x + 1) as u32, y as u32)[0];
+            if left < right {
+                let bit_index = y * hash_size + x;
+                words[bit_index / 64] |= 1 << (bit_index % 64);
+            }
+        }
+    }
+    words
+}
+
+/// 计算多尺度 dHash（尺寸 4、8、16）
+///
+/// 粗尺度反映整体结构，细尺度反映局部细节，组合后比单一尺寸更稳定。
+///
+/// 返回: 按尺寸顺序拼接的哈希：[4×4 (1 个 u64), 8×8 (1 个 u64), 16×16 (4 个 u64)]，
+///       共 multiscale_dhash_len() 个 u64
+pub fn compute_multiscale_dhash(image_bytes: &[u8]) -> Result<Vec<u64>, String> {
+    let img =
+        image::load_from_memory(image_bytes).map_err(|e| format!("Failed to load image: {}", e))?;
+    let gray = img.grayscale();
+
+    Ok(MULTISCALE_DHASH_SIZES
+        .iter()
+        .flat_map(|&size| dhash_words(&gray, size))
+        .collect())
+}
+
+/// 多尺度相似度：各尺度归一化汉明距离取平均
+///
+/// 返回: 0.0-1.0 之间的相似度；任一输入长度不等于 multiscale_dhash_len() 时返回 0.0
+pub fn multiscale_similarity(hashes1: &[u64], hashes2: &[u64]) -> f64 {
+    let expected = multiscale_dhash_len();
+    if hashes1.len() != expected || hashes2.len() != expected {
+        return 0.0;
+    }
+
+    let mut offset = 0;
+    let mut total_distance = 0.0;
+    for &size in MULTISCALE_DHASH_SIZES.iter() {
+        let words = multiscale_words(size);
+        let distance: u32 = hashes1[offset..offset + words]
+            .iter()
+            .zip(&hashes2[offset..offset + words])
+            .map(|(&a, &b)| hamming_distance(a, b))
+            .sum();
+        total_distance += distance as f64 / (size * size) as f64;
+        offset += words;
+    }
+
+    1.0 - total_distance / MULTISCALE_DHASH_SIZES.len() as f64
 }
 
 /// 计算平均哈希 (aHash)
@@ -180,6 +306,49 @@ pub fn hash_similarity(hash1: u64, hash2: u64, hash_size: usize) -> f64 {
     1.0 - (distance / max_distance)
 }
 
+/// 超过该数量时距离矩阵会非常大（N² 个元素），打印警告
+const DISTANCE_MATRIX_WARN_SIZE: usize = 10_000;
+
+/// 计算 N×N 汉明距离矩阵（并行计算上三角，再对称填充下三角）
+///
+/// 参数:
+///   hashes: 哈希值列表
+///
+/// 返回: 对称矩阵，对角线为 0
+pub fn hash_distance_matrix(hashes: &[u64]) -> Vec<Vec<u32>> {
+    let n = hashes.len();
+    if n > DISTANCE_MATRIX_WARN_SIZE {
+        eprintln!(
+            "⚠️  hash_distance_matrix: N={} 超过 {}，结果将占用约 {} MB 内存",
+            n,
+            DISTANCE_MATRIX_WARN_SIZE,
+            n * n * 4 / (1024 * 1024)
+        );
+    }
+
+    // 上三角（含对角线）：第 i 行只计算 j >= i 的部分
+    let mut matrix: Vec<Vec<u32>> = (0..n)
+        .into_par_iter()
+        .map(|i| {
+            let mut row = vec![0u32; n];
+            for j in (i + 1)..n {
+                row[j] = hamming_distance(hashes[i], hashes[j]);
+            }
+            row
+        })
+        .collect();
+
+    // 对称填充下三角
+    for i in 1..n {
+        let (upper, lower) = matrix.split_at_mut(i);
+        for (j, row) in upper.iter().enumerate() {
+            lower[0][j] = row[i];
+        }
+    }
+
+    matrix
+}
+
 /// 批量计算哈希（并行处理）
 ///
 /// 参数:
@@ -187,7 +356,7 @@ pub fn hash_similarity(hash1: u64, hash2: u64, hash_size: usize) -> f64 {
 ///   method: "dhash", "ahash" 或 "phash"
 ///   hash_size: 哈希尺寸
 ///
-/// 返回: 哈希值列表
+/// 返回: 与输入顺序一致的列表，每项为哈希值或该图片的错误信息
 pub fn batch_compute_hash(
     image_bytes_list: &[Vec<u8>],
     method: &str,
@@ -204,6 +373,131 @@ pub fn batch_compute_hash(
         .collect()
 }
 
+/// 哈希序列序列化：每个 u64 按 8 字节小端序依次写入
+///
+/// 固定字节序，缓存文件可跨会话、跨平台读取
+pub fn serialize_hashes(hashes: &[u64]) -> Vec<u8> {
+    hashes.iter().flat_map(|h| h.to_le_bytes()).collect()
+}
+
+/// 哈希序列反序列化（serialize_hashes 的逆过程）
+///
+/// 输入长度必须是 8 的整数倍
+pub fn deserialize_hashes(data: &[u8]) -> Result<Vec<u64>, String> {
+    if !data.len().is_multiple_of(8) {
+        return Err(format!(
+            "Invalid hash data length {}: must be a multiple of 8",
+            data.len()
+        ));
+    }
+    Ok(data
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect())
+}
+
+/// 近似重复图片去重
+///
+/// 并行计算每张图的 dHash，汉明距离 ≤ threshold 的两张图视为相连，
+/// 按连通分量分组，每组只保留第一张（下标最小）
+///
+/// 参数:
+///   image_bytes_list: 图像字节数据列表
+///   threshold: 汉明距离阈值（dHash 64 位）
+///
+/// 返回: 每个分量代表图片的下标（升序）
+pub fn deduplicate_images(image_bytes_list: &[Vec<u8>], threshold: u32) -> Result<Vec<usize>, String> {
+    let hashes = image_bytes_list
+        .par_iter()
+        .enumerate()
+        .map(|(i, bytes)| compute_dhash(bytes, 8).map_err(|e| format!("Image {}: {}", i, e)))
+        .collect::<Result<Vec<u64>, String>>()?;
+
+    Ok(group_by_hash_distance(&hashes, threshold))
+}
+
+/// 对哈希列表按汉明距离做连通分量分组（并查集），返回每组最小下标
+fn group_by_hash_distance(hashes: &[u64], threshold: u32) -> Vec<usize> {
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let n = hashes.len();
+    let mut parent: Vec<usize> = (0..n).collect();
+
+    for i in 0..n {
+        for j in (i + 1)..n {
+            if hamming_distance(hashes[i], hashes[j]) <= threshold {
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                if ri != rj {
+                    // 始终以较小下标作为根，保证代表是分量中的第一张
+                    parent[ri.max(rj)] = ri.min(rj);
+                }
+            }
+        }
+    }
+
+    (0..n).filter(|&i| find(&mut parent, i) == i).collect()
+}
+
+/// 每个线程的行哈希 / dHash 缓存容量
+const HASH_CACHE_CAPACITY: usize = 32;
+
+/// 简单的 LRU 缓存：最近使用的条目在队尾，超出容量时淘汰队首
+///
+/// 容量很小，线性查找比维护哈希表 + 链表更快
+struct LruCache<V> {
+    entries: VecDeque<(u64, V)>,
+    capacity: usize,
+}
+
+impl<V: Clone> LruCache<V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn get(&mut self, key: u64) -> Option<V> {
+        let index = self.entries.iter().position(|(k, _)| *k == key)?;
+        let entry = self.entries.remove(index)?;
+        let value = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(value)
+    }
+
+    fn put(&mut self, key: u64, value: V) {
+        if let Some(index) = self.entries.iter().position(|(k, _)| *k == key) {
+            self.entries.remove(index);
+        } else if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, value));
+    }
+}
+
+thread_local! {
+    /// 同一张图片反复计算行哈希时（例如滚动截图中先去重再拼接）直接复用结果
+    static ROW_HASH_CACHE: RefCell<LruCache<Vec<u64>>> =
+        RefCell::new(LruCache::new(HASH_CACHE_CAPACITY));
+    static DHASH_CACHE: RefCell<LruCache<u64>> = RefCell::new(LruCache::new(HASH_CACHE_CAPACITY));
+}
+
+/// 缓存键：图像字节内容 + 计算参数
+///
+/// 不用 dHash 作键：滚动几行的相邻截图 dHash 往往相同，但行哈希不同
+fn hash_cache_key(image_bytes: &[u8], param: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    image_bytes.hash(&mut hasher);
+    param.hash(&mut hasher);
+    hasher.finish()
+}
+
 /// 逐行哈希 - 专为长截图拼接优化
 ///
 /// 计算图像每一行的快速哈希值，用于找到重叠区域
@@ -217,10 +511,17 @@ pub fn compute_row_hashes(
     image_bytes: &[u8],
     ignore_right_pixels: u32,
 ) -> Result<Vec<u64>, String> {
+    let key = hash_cache_key(image_bytes, ignore_right_pixels as u64);
+    if let Some(hashes) = ROW_HASH_CACHE.with(|cache| cache.borrow_mut().get(key)) {
+        return Ok(hashes);
+    }
+
     let img =
         image::load_from_memory(image_bytes).map_err(|e| format!("Failed to load image: {}", e))?;
     let rgba_img = img.to_rgba8();
-    Ok(compute_row_hashes_from_rgba(&rgba_img, ignore_right_pixels, false))
+    let hashes = compute_row_hashes_from_rgba(&rgba_img, ignore_right_pixels, false);
+    ROW_HASH_CACHE.with(|cache| cache.borrow_mut().put(key, hashes.clone()));
+    Ok(hashes)
 }
 
 /// 带调试输出的版本
@@ -280,12 +581,10 @@ pub fn compute_row_hashes_from_rgba(
                 let b_mean = ((b_sum / pixel_count) / 8) * 8;
 
                 // 使用简单的哈希函数
-                let hash = r_mean
+                r_mean
                     .wrapping_mul(73856093)
                     .wrapping_add(g_mean.wrapping_mul(19349663))
-                    .wrapping_add(b_mean.wrapping_mul(83492791));
-
-                hash
+                    .wrapping_add(b_mean.wrapping_mul(83492791))
             } else {
                 0
             }
@@ -333,14 +632,16 @@ pub fn compute_row_hashes_from_rgba(
 /// 参数:
 ///   seq1, seq2: 两个哈希序列
 ///   min_ratio: 最小重叠比例
+///   tolerance: 子串内允许的连续不匹配行数（抗锯齿/亚像素渲染导致个别行哈希不同）
 ///
 /// 返回: (seq1_start, seq2_start, length)
 pub fn find_longest_common_substring(
     seq1: &[u64],
     seq2: &[u64],
     min_ratio: f32,
+    tolerance: usize,
 ) -> (i32, i32, usize) {
-    find_longest_common_substring_internal(seq1, seq2, min_ratio, false)
+    find_longest_common_substring_internal(seq1, seq2, min_ratio, tolerance, false)
 }
 
 /// 找到多个公共子串候选（用于智能拼接纠错）
@@ -412,7 +713,7 @@ pub fn find_top_common_substrings(
     }
 
     // 按长度降序排序
-    substrings.sort_by(|a, b| b.2.cmp(&a.2));
+    substrings.sort_by_key(|s| std::cmp::Reverse(s.2));
 
     // 去重：移除完全相同的子串
     substrings.dedup();
@@ -456,14 +757,16 @@ pub fn find_longest_common_substring_debug(
     seq1: &[u64],
     seq2: &[u64],
     min_ratio: f32,
+    tolerance: usize,
 ) -> (i32, i32, usize) {
-    find_longest_common_substring_internal(seq1, seq2, min_ratio, true)
+    find_longest_common_substring_internal(seq1, seq2, min_ratio, tolerance, true)
 }
 
 fn find_longest_common_substring_internal(
     seq1: &[u64],
     seq2: &[u64],
     min_ratio: f32,
+    tolerance: usize,
     debug: bool,
 ) -> (i32, i32, usize) {
     let m = seq1.len();
@@ -497,8 +800,10 @@ fn find_longest_common_substring_internal(
     }
 
     // 动态规划（滚动数组，只保留 2 行，内存 O(n) 替代 O(m×n)）
-    let mut prev = vec![0usize; n + 1];
-    let mut curr = vec![0usize; n + 1];
+    // 每个位置记录 (当前子串长度, 末尾连续不匹配行数)：
+    // 匹配时清零不匹配计数；不匹配时只要连续不匹配不超过 tolerance 就延续子串
+    let mut prev = vec![(0usize, 0usize); n + 1];
+    let mut curr = vec![(0usize, 0usize); n + 1];
     let mut max_length = 0usize;
     let mut ending_pos_i = 0;
     let mut ending_pos_j = 0;
@@ -507,17 +812,21 @@ fn find_longest_common_substring_internal(
     for i in 1..=m {
         // curr 全部置零（复用已分配的 Vec）
         for val in curr.iter_mut() {
-            *val = 0;
+            *val = (0, 0);
         }
         for j in 1..=n {
+            let (prev_len, prev_miss) = prev[j - 1];
             if seq1[i - 1] == seq2[j - 1] {
-                curr[j] = prev[j - 1] + 1;
+                curr[j] = (prev_len + 1, 0);
                 match_count += 1;
-                if curr[j] > max_length {
-                    max_length = curr[j];
+                // 子串只在匹配行结束，避免把末尾的不匹配行计入重叠
+                if curr[j].0 > max_length {
+                    max_length = curr[j].0;
                     ending_pos_i = i;
                     ending_pos_j = j;
                 }
+            } else if prev_len > 0 && prev_miss < tolerance {
+                curr[j] = (prev_len + 1, prev_miss + 1);
             }
         }
         std::mem::swap(&mut prev, &mut curr);
@@ -551,51 +860,363 @@ fn find_longest_common_substring_internal(
     (start_i, start_j, max_length)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use image::{Rgba, RgbaImage};
+/// 由 RGB 通道总和计算单行哈希（与 compute_row_hashes_from_rgba 的量化方式一致）
+#[inline]
+fn row_hash_from_sums(r_sum: u64, g_sum: u64, b_sum: u64, pixel_count: u64) -> u64 {
+    if pixel_count == 0 {
+        return 0;
+    }
+    let r_mean = ((r_sum / pixel_count) / 8) * 8;
+    let g_mean = ((g_sum / pixel_count) / 8) * 8;
+    let b_mean = ((b_sum / pixel_count) / 8) * 8;
+
+    r_mean
+        .wrapping_mul(73856093)
+        .wrapping_add(g_mean.wrapping_mul(19349663))
+        .wrapping_add(b_mean.wrapping_mul(83492791))
+}
 
-    #[test]
-    fn test_dhash_identical_images() {
-        // 创建两个相同的测试图像
-        let img = RgbaImage::from_fn(64, 64, |x, y| {
-            Rgba([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8, 255])
-        });
+/// 在大图中查找小图（如截取的 UI 元素）出现的位置
+///
+/// 原理: 先用 needle 首行的行哈希在 haystack 每一行上滑动窗口（前缀和 O(1) 求窗口均值）
+/// 筛选候选位置，再对候选区域做逐像素比对确认
+///
+/// 参数:
+///   haystack: 大图数据（PNG/JPEG 等编码格式）
+///   needle: 要查找的小图数据
+///
+/// 返回: 找到时返回左上角坐标 Some((x, y))，否则 None
+pub fn find_subimage(haystack: &[u8], needle: &[u8]) -> Result<Option<(u32, u32)>, String> {
+    let haystack_img = image::load_from_memory(haystack)
+        .map_err(|e| format!("Failed to load haystack image: {}", e))?
+        .to_rgba8();
+    let needle_img = image::load_from_memory(needle)
+        .map_err(|e| format!("Failed to load needle image: {}", e))?
+        .to_rgba8();
+
+    Ok(find_subimage_rgba(&haystack_img, &needle_img))
+}
 
-        let mut bytes1 = Vec::new();
-        img.write_to(
-            &mut std::io::Cursor::new(&mut bytes1),
-            image::ImageFormat::Png,
-        )
-        .unwrap();
+/// find_subimage 的 RGBA 版本（跳过解码）
+pub fn find_subimage_rgba(
+    haystack: &image::RgbaImage,
+    needle: &image::RgbaImage,
+) -> Option<(u32, u32)> {
+    let (hw, hh) = haystack.dimensions();
+    let (nw, nh) = needle.dimensions();
 
-        let hash1 = compute_dhash(&bytes1, 8).unwrap();
-        let hash2 = compute_dhash(&bytes1, 8).unwrap();
+    if nw == 0 || nh == 0 || nw > hw || nh > hh {
+        return None;
+    }
 
-        assert_eq!(hash1, hash2);
-        assert_eq!(hamming_distance(hash1, hash2), 0);
+    // needle 首行哈希
+    let needle_raw = needle.as_raw();
+    let needle_row_bytes = (nw * 4) as usize;
+    let (mut r, mut g, mut b) = (0u64, 0u64, 0u64);
+    for chunk in needle_raw[..needle_row_bytes].chunks_exact(4) {
+        r += chunk[0] as u64;
+        g += chunk[1] as u64;
+        b += chunk[2] as u64;
     }
+    let target_hash = row_hash_from_sums(r, g, b, nw as u64);
+
+    let haystack_raw = haystack.as_raw();
+    let haystack_row_bytes = (hw * 4) as usize;
+
+    // 候选区域逐像素比对
+    let matches_at = |x: u32, y: u32| -> bool {
+        (0..nh as usize).all(|ny| {
+            let h_start = (y as usize + ny) * haystack_row_bytes + x as usize * 4;
+            let n_start = ny * needle_row_bytes;
+            haystack_raw[h_start..h_start + needle_row_bytes]
+                == needle_raw[n_start..n_start + needle_row_bytes]
+        })
+    };
 
-    #[test]
-    fn test_row_hashes() {
-        let img = RgbaImage::from_fn(100, 50, |x, y| {
-            Rgba([(y * 5) as u8, (y * 5) as u8, (y * 5) as u8, 255])
-        });
+    // 按行并行扫描，返回最靠上（同一行最靠左）的匹配
+    (0..=(hh - nh)).into_par_iter().find_map_first(|y| {
+        let row = &haystack_raw[y as usize * haystack_row_bytes..(y as usize + 1) * haystack_row_bytes];
+
+        // 每个通道的前缀和，用于 O(1) 计算任意窗口的均值
+        let mut prefix = vec![[0u64; 3]; hw as usize + 1];
+        for (i, chunk) in row.chunks_exact(4).enumerate() {
+            prefix[i + 1] = [
+                prefix[i][0] + chunk[0] as u64,
+                prefix[i][1] + chunk[1] as u64,
+                prefix[i][2] + chunk[2] as u64,
+            ];
+        }
 
-        let mut bytes = Vec::new();
-        img.write_to(
-            &mut std::io::Cursor::new(&mut bytes),
-            image::ImageFormat::Png,
-        )
-        .unwrap();
+        (0..=(hw - nw)).find_map(|x| {
+            let (start, end) = (x as usize, (x + nw) as usize);
+            let hash = row_hash_from_sums(
+                prefix[end][0] - prefix[start][0],
+                prefix[end][1] - prefix[start][1],
+                prefix[end][2] - prefix[start][2],
+                nw as u64,
+            );
+            if hash == target_hash && matches_at(x, y) {
+                Some((x, y))
+            } else {
+                None
+            }
+        })
+    })
+}
 
-        let hashes = compute_row_hashes(&bytes, 0).unwrap();
-        assert_eq!(hashes.len(), 50);
+/// 拼接结果及质量元数据
+///
+/// 调用方可以根据 overlap_rows / overlap_ratio 拒绝质量过低的拼接
+#[derive(Debug, Clone)]
+pub struct StitchResult {
+    /// 拼接后的 PNG 图片字节流
+    pub image_bytes: Vec<u8>,
+    /// 重叠区域的行数（0 表示未找到重叠，直接拼接）
+    pub overlap_rows: usize,
+    /// 接缝位置：结果图中 img2 内容开始的行号
+    pub seam_y: u32,
+    /// 重叠行数占两图中较短者高度的比例
+    pub overlap_ratio: f32,
+}
 
-        // 同一行的像素应该产生相同的哈希
-        assert_eq!(hashes[0], hashes[0]);
+/// 拼接选项（默认不做任何额外处理）
+#[derive(Debug, Clone, Copy)]
+pub struct StitchOptions {
+    /// 是否在接缝处做线性 alpha 混合，消除亮度差造成的硬接缝
+    pub blend_seam: bool,
+    /// 混合区域高度（以接缝为中心）
+    pub blend_height: u32,
+    /// 最长公共子串内允许的连续不匹配行数（仅 stitch_two_images 使用）
+    pub tolerance: usize,
+    /// 两图宽度不一致时的对齐方式
+    pub width_strategy: WidthStrategy,
+    /// Letterbox 填充色（RGBA）
+    pub pad_color: [u8; 4],
+    /// 结果图最大像素数（宽 × 高），超出时拒绝拼接，避免超大结果耗尽内存
+    pub max_output_pixels: Option<u64>,
+    /// 拼接前去掉两张图顶部 / 底部的纯色边带（仅 stitch_two_images 使用），
+    /// 返回的 seam_y 为去边后的坐标
+    pub auto_trim: bool,
+}
+
+/// auto_trim 判定纯色边带时允许的通道差（容忍轻微噪声 / 压缩失真）
+const AUTO_TRIM_TOLERANCE: u8 = 8;
+
+impl Default for StitchOptions {
+    fn default() -> Self {
+        Self {
+            blend_seam: false,
+            blend_height: 16,
+            tolerance: 0,
+            width_strategy: WidthStrategy::Scale,
+            pad_color: [255, 255, 255, 255],
+            max_output_pixels: None,
+            auto_trim: false,
+        }
+    }
+}
+
+impl StitchOptions {
+    /// 在分配结果缓冲之前检查输出尺寸是否超出 max_output_pixels
+    pub(crate) fn check_output_size(&self, width: u32, height: u32) -> Result<(), String> {
+        match self.max_output_pixels {
+            Some(limit) if width as u64 * height as u64 > limit => Err(format!(
+                "Result would exceed max_output_pixels limit ({}x{} > {})",
+                width, height, limit
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 宽度对齐方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WidthStrategy {
+    /// 把 img1 等比缩放到 img2 的宽度（内容会被拉伸/压缩）
+    Scale,
+    /// 用纯色把较窄的图片左右填充到较宽的宽度（内容居中，不变形）
+    Letterbox,
+    /// 把较宽的图片居中裁剪到较窄的宽度
+    Crop,
+}
+
+impl WidthStrategy {
+    /// 解析 Python 传入的字符串："scale" / "letterbox" / "crop"
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "scale" => Ok(Self::Scale),
+            "letterbox" => Ok(Self::Letterbox),
+            "crop" => Ok(Self::Crop),
+            _ => Err(format!(
+                "Unknown width_strategy '{}', expected 'scale', 'letterbox' or 'crop'",
+                name
+            )),
+        }
+    }
+
+    /// parse 的逆操作
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Scale => "scale",
+            Self::Letterbox => "letterbox",
+            Self::Crop => "crop",
+        }
+    }
+}
+
+/// 按 options.width_strategy 对齐两张图片的宽度，返回对齐后的 (img1, img2)
+pub(crate) fn align_widths(
+    img1: image::DynamicImage,
+    img2: image::DynamicImage,
+    options: &StitchOptions,
+    debug: bool,
+) -> (image::DynamicImage, image::DynamicImage) {
+    let (width1, height1) = (img1.width(), img1.height());
+    let width2 = img2.width();
+    if width1 == width2 || width1 == 0 || width2 == 0 {
+        return (img1, img2);
+    }
+
+    match options.width_strategy {
+        WidthStrategy::Scale => {
+            if debug {
+                println!("调整图片宽度: {} -> {}", width1, width2);
+            }
+            let new_height1 = (height1 as f32 * width2 as f32 / width1 as f32) as u32;
+            let img1 = img1.resize_exact(width2, new_height1, image::imageops::FilterType::Lanczos3);
+            (img1, img2)
+        }
+        WidthStrategy::Letterbox => {
+            let target = width1.max(width2);
+            if debug {
+                println!("填充图片宽度: {} / {} -> {}", width1, width2, target);
+            }
+            (
+                pad_to_width(img1, target, options.pad_color),
+                pad_to_width(img2, target, options.pad_color),
+            )
+        }
+        WidthStrategy::Crop => {
+            let target = width1.min(width2);
+            if debug {
+                println!("裁剪图片宽度: {} / {} -> {}", width1, width2, target);
+            }
+            (crop_to_width(img1, target), crop_to_width(img2, target))
+        }
+    }
+}
+
+/// 左右等量填充纯色到指定宽度（已不窄于目标宽度时原样返回）
+fn pad_to_width(img: image::DynamicImage, width: u32, color: [u8; 4]) -> image::DynamicImage {
+    if img.width() >= width {
+        return img;
+    }
+    crate::utils::pad_image_to_target(&img, width, img.height(), color)
+}
+
+/// 居中裁剪到指定宽度（已不宽于目标宽度时原样返回）
+fn crop_to_width(img: image::DynamicImage, width: u32) -> image::DynamicImage {
+    if img.width() <= width {
+        return img;
+    }
+    let offset_x = (img.width() - width) / 2;
+    img.crop_imm(offset_x, 0, width, img.height())
+}
+
+/// 接缝线性混合：对 [seam_y - h/2, seam_y + h/2] 内的每一行，
+/// 按 t = (r - seam_y + h/2) / h 计算 img1 * (1 - t) + img2 * t
+///
+/// result_buf 为已拼接好的 RGBA 缓冲（宽度与 img2 相同），
+/// img2_skip 为 img2 被跳过的行数（结果图第 seam_y 行对应 img2 第 img2_skip 行）。
+/// 某一行在 img1 或 img2 中不存在时保持原样。
+pub(crate) fn blend_seam_rows(
+    result_buf: &mut [u8],
+    img1_rgba: &image::RgbaImage,
+    img2_rgba: &image::RgbaImage,
+    seam_y: u32,
+    img2_skip: u32,
+    blend_height: u32,
+) {
+    if blend_height == 0 {
+        return;
+    }
+
+    let row_bytes = img2_rgba.width() as usize * 4;
+    if row_bytes == 0 || img1_rgba.width() != img2_rgba.width() {
+        return;
+    }
+    let result_height = (result_buf.len() / row_bytes) as i64;
+
+    let half = (blend_height / 2) as i64;
+    let seam = seam_y as i64;
+    let img1_raw = img1_rgba.as_raw();
+    let img2_raw = img2_rgba.as_raw();
+
+    for r in (seam - half).max(0)..=(seam + half) {
+        let r2 = r - seam + img2_skip as i64;
+        if r >= result_height || r >= img1_rgba.height() as i64 || r2 < 0 || r2 >= img2_rgba.height() as i64 {
+            continue;
+        }
+
+        let t = (r - seam + half) as f32 / blend_height as f32;
+        let dst = &mut result_buf[r as usize * row_bytes..(r as usize + 1) * row_bytes];
+        let src1 = &img1_raw[r as usize * row_bytes..(r as usize + 1) * row_bytes];
+        let src2 = &img2_raw[r2 as usize * row_bytes..(r2 as usize + 1) * row_bytes];
+        for ((d, &a), &b) in dst.iter_mut().zip(src1).zip(src2) {
+            *d = (a as f32 * (1.0 - t) + b as f32 * t).round() as u8;
+        }
+    }
+}
+
+/// 横向拼接的接缝线性混合：对 [seam_x - w/2, seam_x + w/2] 内的每一列，
+/// 按 t = (c - seam_x + w/2) / w 计算 left * (1 - t) + right * t
+///
+/// right_skip 为右图被跳过的列数（结果图第 seam_x 列对应右图第 right_skip 列）。
+/// 某一列在左图或右图中不存在时保持原样。
+pub(crate) fn blend_seam_columns(
+    result: &mut image::RgbaImage,
+    left_rgba: &image::RgbaImage,
+    right_rgba: &image::RgbaImage,
+    seam_x: u32,
+    right_skip: u32,
+    blend_width: u32,
+) {
+    if blend_width == 0 || left_rgba.height() != right_rgba.height() || result.height() != right_rgba.height() {
+        return;
+    }
+
+    let half = (blend_width / 2) as i64;
+    let seam = seam_x as i64;
+
+    for c in (seam - half).max(0)..=(seam + half) {
+        let c2 = c - seam + right_skip as i64;
+        if c >= result.width() as i64 || c >= left_rgba.width() as i64 || c2 < 0 || c2 >= right_rgba.width() as i64 {
+            continue;
+        }
+
+        let t = (c - seam + half) as f32 / blend_width as f32;
+        for y in 0..result.height() {
+            let a = left_rgba.get_pixel(c as u32, y).0;
+            let b = right_rgba.get_pixel(c2 as u32, y).0;
+            for ((d, &a), &b) in result.get_pixel_mut(c as u32, y).0.iter_mut().zip(&a).zip(&b) {
+                *d = (a as f32 * (1.0 - t) + b as f32 * t).round() as u8;
+            }
+        }
+    }
+}
+
+/// 裁掉图片顶部 / 底部的纯色边带
+fn trim_uniform_borders(img: image::DynamicImage, label: &str, debug: bool) -> image::DynamicImage {
+    let rgba = img.to_rgba8();
+    let (top, bottom) = crate::utils::detect_uniform_borders(&rgba, AUTO_TRIM_TOLERANCE);
+    if debug {
+        println!("✂️  {} 去除纯色边带: 顶部{}行, 底部{}行", label, top, bottom);
+    }
+    if top == 0 && bottom == 0 {
+        return image::DynamicImage::ImageRgba8(rgba);
     }
+    let height = rgba.height() - top - bottom;
+    image::DynamicImage::ImageRgba8(image::imageops::crop_imm(&rgba, 0, top, rgba.width(), height).to_image())
 }
 
 /// 完整的双图拼接函数 - 零拷贝高性能实现
@@ -608,18 +1229,20 @@ mod tests {
 ///   ignore_right_pixels: 忽略右侧像素数（排除滚动条）
 ///   min_overlap_ratio: 最小重叠比例（默认 0.1）
 ///
-/// 返回: 拼接后的 PNG 图片字节流，失败返回 None
+/// 返回: 拼接结果（PNG 字节流 + 重叠元数据）
 pub fn stitch_two_images(
     img1_bytes: &[u8],
     img2_bytes: &[u8],
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
-) -> Result<Vec<u8>, String> {
+    options: &StitchOptions,
+) -> Result<StitchResult, String> {
     stitch_two_images_internal(
         img1_bytes,
         img2_bytes,
         ignore_right_pixels,
         min_overlap_ratio,
+        options,
         false,
     )
 }
@@ -630,12 +1253,14 @@ pub fn stitch_two_images_debug(
     img2_bytes: &[u8],
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
-) -> Result<Vec<u8>, String> {
+    options: &StitchOptions,
+) -> Result<StitchResult, String> {
     stitch_two_images_internal(
         img1_bytes,
         img2_bytes,
         ignore_right_pixels,
         min_overlap_ratio,
+        options,
         true,
     )
 }
@@ -645,37 +1270,76 @@ fn stitch_two_images_internal(
     img2_bytes: &[u8],
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
+    options: &StitchOptions,
     debug: bool,
-) -> Result<Vec<u8>, String> {
-    use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
+) -> Result<StitchResult, String> {
+    use image::DynamicImage;
     use std::io::Cursor;
 
     // 1️⃣ 加载图片
-    let mut img1 = image::load_from_memory(img1_bytes)
+    let img1 = image::load_from_memory(img1_bytes)
         .map_err(|e| format!("Failed to load image 1: {}", e))?;
     let img2 = image::load_from_memory(img2_bytes)
         .map_err(|e| format!("Failed to load image 2: {}", e))?;
 
-    let (width1, height1) = img1.dimensions();
-    let (width2, height2) = img2.dimensions();
+    let stitched = stitch_decoded(img1, img2, ignore_right_pixels, min_overlap_ratio, options, debug)?;
 
-    if debug {
-        println!(
-            "处理图片: ({}, {}) + ({}, {})",
-            width1, height1, width2, height2
-        );
-    }
+    // 7️⃣ 编码为 PNG 字节流
+    let mut output = Vec::new();
+    DynamicImage::ImageRgba8(stitched.image)
+        .write_to(&mut Cursor::new(&mut output), image::ImageOutputFormat::Png)
+        .map_err(|e| format!("Failed to encode result: {}", e))?;
 
-    // 2️⃣ 宽度对齐（如果不同则缩放第一张图片）
-    if width1 != width2 {
-        if debug {
-            println!("调整图片宽度: {} -> {}", width1, width2);
-        }
-        let new_height1 = (height1 as f32 * width2 as f32 / width1 as f32) as u32;
-        img1 = img1.resize_exact(width2, new_height1, image::imageops::FilterType::Lanczos3);
+    Ok(StitchResult {
+        image_bytes: output,
+        overlap_rows: stitched.overlap_rows,
+        seam_y: stitched.seam_y,
+        overlap_ratio: stitched.overlap_ratio,
+    })
+}
+
+/// 已解码图片的拼接结果（未编码）
+struct DecodedStitch {
+    image: image::RgbaImage,
+    overlap_rows: usize,
+    seam_y: u32,
+    overlap_ratio: f32,
+}
+
+/// stitch_two_images 的核心：对已解码的两张图做宽度对齐、找重叠、拼接
+fn stitch_decoded(
+    img1: image::DynamicImage,
+    img2: image::DynamicImage,
+    ignore_right_pixels: u32,
+    min_overlap_ratio: f32,
+    options: &StitchOptions,
+    debug: bool,
+) -> Result<DecodedStitch, String> {
+    use image::{GenericImageView, ImageBuffer, Rgba};
+
+    let (width1, height1) = img1.dimensions();
+    let (width2, height2) = img2.dimensions();
+
+    if debug {
+        println!(
+            "处理图片: ({}, {}) + ({}, {})",
+            width1, height1, width2, height2
+        );
     }
 
+    // 去掉纯色边带（可选），避免边带内相同的行哈希干扰重叠检测
+    let (img1, img2) = if options.auto_trim {
+        (trim_uniform_borders(img1, "img1", debug), trim_uniform_borders(img2, "img2", debug))
+    } else {
+        (img1, img2)
+    };
+
+    // 2️⃣ 宽度对齐（按 options.width_strategy 缩放 / 填充 / 裁剪）
+    let (img1, img2) = align_widths(img1, img2, options, debug);
+
     let (final_width, final_height1) = img1.dimensions();
+    // 去边后 img2 的高度可能变化
+    let height2 = img2.height();
 
     if debug {
         println!("忽略右侧 {} 像素来排除滚动条影响", ignore_right_pixels);
@@ -693,11 +1357,7 @@ fn stitch_two_images_internal(
     // 因为滚动截图总是连续的，新截图一定是从上一张的底部开始
     let img1_len = img1_hashes.len();
     let img2_len = img2_hashes.len();
-    let search_start = if img1_len > img2_len {
-        img1_len - img2_len
-    } else {
-        0
-    };
+    let search_start = img1_len.saturating_sub(img2_len);
     let img1_search_region = &img1_hashes[search_start..];
 
     if debug {
@@ -713,9 +1373,9 @@ fn stitch_two_images_internal(
     }
 
     let (relative_start_i, start_j, overlap_length) = if debug {
-        find_longest_common_substring_debug(img1_search_region, &img2_hashes, min_overlap_ratio)
+        find_longest_common_substring_debug(img1_search_region, &img2_hashes, min_overlap_ratio, options.tolerance)
     } else {
-        find_longest_common_substring(img1_search_region, &img2_hashes, min_overlap_ratio)
+        find_longest_common_substring(img1_search_region, &img2_hashes, min_overlap_ratio, options.tolerance)
     };
 
     // 将相对位置转换回绝对位置
@@ -775,6 +1435,8 @@ fn stitch_two_images_internal(
         );
     }
 
+    options.check_output_size(final_width, result_height)?;
+
     // 6️⃣ 创建结果图片并拼接（行级批量拷贝，跳过逐像素边界检查）
     let row_bytes = (final_width * 4) as usize; // RGBA
     let mut result_buf: Vec<u8> = vec![0u8; row_bytes * result_height as usize];
@@ -797,16 +1459,82 @@ fn stitch_two_images_internal(
             .copy_from_slice(&img2_raw[src_start..src_start + row_bytes]);
     }
 
-    // 7️⃣ 编码为 PNG 字节流
+    // 接缝混合（可选）
+    if options.blend_seam {
+        blend_seam_rows(
+            &mut result_buf,
+            &img1_rgba,
+            &img2_rgba,
+            img1_keep_height,
+            img2_skip_height,
+            options.blend_height,
+        );
+    }
+
     let result: ImageBuffer<Rgba<u8>, Vec<u8>> =
         ImageBuffer::from_raw(final_width, result_height, result_buf)
             .ok_or_else(|| "Failed to create result image buffer".to_string())?;
-    let mut output = Vec::new();
-    DynamicImage::ImageRgba8(result)
-        .write_to(&mut Cursor::new(&mut output), image::ImageOutputFormat::Png)
-        .map_err(|e| format!("Failed to encode result: {}", e))?;
 
-    Ok(output)
+    Ok(DecodedStitch {
+        image: result,
+        overlap_rows: overlap_length,
+        seam_y: img1_keep_height,
+        overlap_ratio: overlap_length as f32 / img1_len.min(img2_len).max(1) as f32,
+    })
+}
+
+/// 多图拼接的进度回调：(已处理张数, 总张数)，返回 Err 时中止拼接
+pub type StitchProgress<'a> = &'a (dyn Fn(usize, usize) -> Result<(), String> + Sync);
+
+/// 多图拼接被取消时返回的错误信息
+pub const CANCELLED_ERROR: &str = "Cancelled";
+
+/// 依次把多张截图拼接成一张长图
+///
+/// 每张图都与当前的拼接结果做 stitch_two_images 同样的处理（只在结果底部搜索重叠），
+/// 中间结果不做 PNG 编解码。任意一张拼接失败即返回错误。
+///
+/// 参数:
+///   images: 按滚动顺序排列的图片字节数据
+///   progress: 可选进度回调，每处理完一张图调用一次
+///   cancel: 可选取消标志，每张图开始处理前检查，置位后返回 CANCELLED_ERROR
+///
+/// 返回: 拼接结果（PNG 字节流）
+pub fn stitch_images(
+    images: &[Vec<u8>],
+    ignore_right_pixels: u32,
+    min_overlap_ratio: f32,
+    options: &StitchOptions,
+    progress: Option<StitchProgress>,
+    cancel: Option<&std::sync::atomic::AtomicBool>,
+) -> Result<Vec<u8>, String> {
+    let check_cancel = || match cancel {
+        Some(flag) if flag.load(std::sync::atomic::Ordering::Relaxed) => Err(CANCELLED_ERROR.to_string()),
+        _ => Ok(()),
+    };
+
+    let total = images.len();
+    check_cancel()?;
+    let first = images.first().ok_or_else(|| "No images to stitch".to_string())?;
+    let mut result = image::load_from_memory(first)
+        .map_err(|e| format!("Failed to load image 0: {}", e))?;
+    if let Some(cb) = progress {
+        cb(1, total)?;
+    }
+
+    for (i, bytes) in images.iter().enumerate().skip(1) {
+        check_cancel()?;
+        let next = image::load_from_memory(bytes)
+            .map_err(|e| format!("Failed to load image {}: {}", i, e))?;
+        let stitched = stitch_decoded(result, next, ignore_right_pixels, min_overlap_ratio, options, false)
+            .map_err(|e| format!("Failed to stitch image {}: {}", i, e))?;
+        result = image::DynamicImage::ImageRgba8(stitched.image);
+        if let Some(cb) = progress {
+            cb(i + 1, total)?;
+        }
+    }
+
+    crate::utils::encode_png(&result)
 }
 
 /// 智能拼接函数 - 带多候选纠错机制
@@ -822,12 +1550,14 @@ pub fn stitch_two_images_smart(
     img2_bytes: &[u8],
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
-) -> Result<Vec<u8>, String> {
+    options: &StitchOptions,
+) -> Result<StitchResult, String> {
     stitch_two_images_smart_internal(
         img1_bytes,
         img2_bytes,
         ignore_right_pixels,
         min_overlap_ratio,
+        options,
         false,
     )
 }
@@ -838,12 +1568,14 @@ pub fn stitch_two_images_smart_debug(
     img2_bytes: &[u8],
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
-) -> Result<Vec<u8>, String> {
+    options: &StitchOptions,
+) -> Result<StitchResult, String> {
     stitch_two_images_smart_internal(
         img1_bytes,
         img2_bytes,
         ignore_right_pixels,
         min_overlap_ratio,
+        options,
         true,
     )
 }
@@ -853,13 +1585,14 @@ fn stitch_two_images_smart_internal(
     img2_bytes: &[u8],
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
+    options: &StitchOptions,
     debug: bool,
-) -> Result<Vec<u8>, String> {
+) -> Result<StitchResult, String> {
     use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
     use std::io::Cursor;
 
     // 1️⃣ 加载图片
-    let mut img1 = image::load_from_memory(img1_bytes)
+    let img1 = image::load_from_memory(img1_bytes)
         .map_err(|e| format!("Failed to load image 1: {}", e))?;
     let img2 = image::load_from_memory(img2_bytes)
         .map_err(|e| format!("Failed to load image 2: {}", e))?;
@@ -874,14 +1607,8 @@ fn stitch_two_images_smart_internal(
         );
     }
 
-    // 2️⃣ 宽度对齐
-    if width1 != width2 {
-        if debug {
-            println!("调整图片宽度: {} -> {}", width1, width2);
-        }
-        let new_height1 = (height1 as f32 * width2 as f32 / width1 as f32) as u32;
-        img1 = img1.resize_exact(width2, new_height1, image::imageops::FilterType::Lanczos3);
-    }
+    // 2️⃣ 宽度对齐（按 options.width_strategy 缩放 / 填充 / 裁剪）
+    let (img1, img2) = align_widths(img1, img2, options, debug);
 
     let (final_width, _final_height1) = img1.dimensions();
 
@@ -902,11 +1629,7 @@ fn stitch_two_images_smart_internal(
     let img1_len = img1_hashes.len();
     let img2_len = img2_hashes.len();
     let search_window = img2_len * 2; // 2倍搜索窗口，容忍回滚
-    let search_start = if img1_len > search_window {
-        img1_len - search_window
-    } else {
-        0
-    };
+    let search_start = img1_len.saturating_sub(search_window);
     let img1_search_region = &img1_hashes[search_start..];
 
     if debug {
@@ -1038,6 +1761,8 @@ fn stitch_two_images_smart_internal(
         );
     }
 
+    options.check_output_size(final_width, result_height)?;
+
     // 8️⃣ 创建结果图片并拼接（行级批量拷贝，跳过逐像素边界检查）
     let row_bytes = (final_width * 4) as usize; // RGBA
     let mut result_buf: Vec<u8> = vec![0u8; row_bytes * result_height as usize];
@@ -1060,6 +1785,18 @@ fn stitch_two_images_smart_internal(
             .copy_from_slice(&img2_raw[src_start..src_start + row_bytes]);
     }
 
+    // 接缝混合（可选）
+    if options.blend_seam {
+        blend_seam_rows(
+            &mut result_buf,
+            &img1_rgba,
+            &img2_rgba,
+            img1_keep_height,
+            img2_skip_height,
+            options.blend_height,
+        );
+    }
+
     // 9️⃣ 编码为 PNG 字节流
     let result: ImageBuffer<Rgba<u8>, Vec<u8>> =
         ImageBuffer::from_raw(final_width, result_height, result_buf)
@@ -1069,13 +1806,19 @@ fn stitch_two_images_smart_internal(
         .write_to(&mut Cursor::new(&mut output), image::ImageOutputFormat::Png)
         .map_err(|e| format!("Failed to encode result: {}", e))?;
 
-    Ok(output)
+    Ok(StitchResult {
+        image_bytes: output,
+        overlap_rows: overlap_length,
+        seam_y: img1_keep_height,
+        overlap_ratio: overlap_length as f32 / img1_len.min(img2_len).max(1) as f32,
+    })
 }
 
 // ========== RGBA 直通接口（跳过所有 PNG 编解码）==========
 
 /// 智能拼接 RGBA 版本：直接接收/返回 RGBA 原始字节
 /// 返回 (rgba_bytes, width, height)
+#[allow(clippy::too_many_arguments)]
 pub fn stitch_two_images_smart_rgba(
     img1_rgba_data: &[u8],
     img1_width: u32,
@@ -1139,11 +1882,7 @@ pub fn stitch_two_images_smart_rgba(
     let img1_len = img1_hashes.len();
     let img2_len = img2_hashes.len();
     let search_window = img2_len * 2;
-    let search_start = if img1_len > search_window {
-        img1_len - search_window
-    } else {
-        0
-    };
+    let search_start = img1_len.saturating_sub(search_window);
     let img1_search_region = &img1_hashes[search_start..];
 
     if debug {
@@ -1268,3 +2007,1035 @@ pub fn stitch_two_images_smart_rgba(
     // 9️⃣ 直接返回 RGBA 字节 + 宽高（零编码）
     Ok((result_buf, final_width, result_height))
 }
+
+// ========== 拼接质量校验 ==========
+
+/// 校验时在接缝上下各比较的行数
+const VALIDATE_SEAM_ROWS: usize = 10;
+/// 校验时忽略右侧的像素数（与 Python 接口的默认值一致，排除滚动条）
+const VALIDATE_IGNORE_RIGHT_PIXELS: u32 = 20;
+
+/// 拼接后质量校验：比较接缝附近的行哈希，返回 0.0 ~ 1.0 的相似度评分
+///
+/// 评分由三部分组成：
+/// - 结果图接缝上方的行与 img1 对应行一致
+/// - 结果图接缝下方的行能在 img2 中定位到（得到 img2 的跳过位置）
+/// - img1 接缝上方的行与 img2 被跳过区域的末尾一致（即重叠区域确实相同）
+///
+/// 两张不相关的图片拼接时第三项接近 0，正确的滚动截图拼接接近 1。
+///
+/// 参数:
+///   img1_bytes, img2_bytes: 拼接前的两张图片
+///   result_bytes: 拼接结果
+///   seam_y: 接缝位置（结果图中 img1 保留的高度，即 StitchResult::seam_y）
+pub fn validate_stitch(
+    img1_bytes: &[u8],
+    img2_bytes: &[u8],
+    result_bytes: &[u8],
+    seam_y: u32,
+) -> Result<f64, String> {
+    let mut img1 = image::load_from_memory(img1_bytes)
+        .map_err(|e| format!("Failed to load image 1: {}", e))?;
+    let img2 = image::load_from_memory(img2_bytes)
+        .map_err(|e| format!("Failed to load image 2: {}", e))?;
+    let result = image::load_from_memory(result_bytes)
+        .map_err(|e| format!("Failed to load result image: {}", e))?;
+
+    // 与拼接时相同的宽度对齐方式
+    if img1.width() != result.width() && img1.width() > 0 {
+        let new_height1 = (img1.height() as f32 * result.width() as f32 / img1.width() as f32) as u32;
+        img1 = img1.resize_exact(result.width(), new_height1, image::imageops::FilterType::Lanczos3);
+    }
+
+    let hashes1 = compute_row_hashes_from_rgba(&img1.to_rgba8(), VALIDATE_IGNORE_RIGHT_PIXELS, false);
+    let hashes2 = compute_row_hashes_from_rgba(&img2.to_rgba8(), VALIDATE_IGNORE_RIGHT_PIXELS, false);
+    let hashes_result =
+        compute_row_hashes_from_rgba(&result.to_rgba8(), VALIDATE_IGNORE_RIGHT_PIXELS, false);
+
+    let seam = seam_y as usize;
+    if seam == 0 || seam > hashes1.len() || seam > hashes_result.len() {
+        return Err(format!(
+            "seam_y {} out of range (img1 height {}, result height {})",
+            seam_y,
+            hashes1.len(),
+            hashes_result.len()
+        ));
+    }
+
+    // 1️⃣ 接缝上方：结果图应与 img1 一致
+    let above_start = seam.saturating_sub(VALIDATE_SEAM_ROWS);
+    let above_len = seam - above_start;
+    let above_matches = (above_start..seam)
+        .filter(|&y| hashes_result[y] == hashes1[y])
+        .count();
+    let above_fidelity = above_matches as f64 / above_len as f64;
+
+    // 2️⃣ 接缝下方：在 img2 中定位结果图的后续行，得到 img2 的跳过高度
+    let below = &hashes_result[seam..(seam + VALIDATE_SEAM_ROWS).min(hashes_result.len())];
+    let (img2_skip, below_fidelity) = if below.is_empty() {
+        // img2 整张都是重叠区域
+        (hashes2.len(), 1.0)
+    } else if below.len() > hashes2.len() {
+        return Ok(0.0);
+    } else {
+        let (offset, matches) = (0..=hashes2.len() - below.len())
+            .map(|offset| {
+                let matches = below
+                    .iter()
+                    .zip(&hashes2[offset..])
+                    .filter(|(a, b)| a == b)
+                    .count();
+                (offset, matches)
+            })
+            .fold((0, 0), |best, cur| if cur.1 > best.1 { cur } else { best });
+        (offset, matches as f64 / below.len() as f64)
+    };
+
+    // 3️⃣ 跨接缝连续性：img1 接缝上方的行应等于 img2 被跳过区域的末尾
+    let continuity_matches = (1..=above_len)
+        .filter(|&k| k <= img2_skip && hashes1[seam - k] == hashes2[img2_skip - k])
+        .count();
+    let continuity = continuity_matches as f64 / above_len as f64;
+
+    Ok(continuity * (above_fidelity + below_fidelity) / 2.0)
+}
+
+// ========== 周期性图像检测 ==========
+
+/// 周期检测时忽略右侧的像素数（排除滚动条）
+const TILED_IGNORE_RIGHT_PIXELS: u32 = 20;
+/// 判定为周期图像所需的最低行匹配比例
+const TILED_MATCH_THRESHOLD: f64 = 0.8;
+
+/// 检测图像是否按固定高度周期性重复（例如无限滚动页面中反复出现的页眉/页脚）
+///
+/// 对行哈希做暴力自相关：对每个候选周期 k ∈ [min_tile_height, len/2]，
+/// 统计 hashes[i] == hashes[i+k] 的位置占比，取占比最高（相同时取较小周期）的 k。
+///
+/// 返回: 占比超过 0.8 时返回 Some(周期高度)，否则 None
+pub fn detect_tiled_image(image_bytes: &[u8], min_tile_height: u32) -> Result<Option<u32>, String> {
+    let img =
+        image::load_from_memory(image_bytes).map_err(|e| format!("Failed to load image: {}", e))?;
+    let hashes = compute_row_hashes_from_rgba(&img.to_rgba8(), TILED_IGNORE_RIGHT_PIXELS, false);
+    Ok(detect_period(&hashes, min_tile_height.max(1) as usize).map(|k| k as u32))
+}
+
+fn detect_period(hashes: &[u64], min_lag: usize) -> Option<usize> {
+    let max_lag = hashes.len() / 2;
+    if min_lag > max_lag {
+        return None;
+    }
+
+    let (best_lag, best_fraction) = (min_lag..=max_lag)
+        .into_par_iter()
+        .map(|k| {
+            let compared = hashes.len() - k;
+            let matches = hashes
+                .iter()
+                .zip(&hashes[k..])
+                .filter(|(a, b)| a == b)
+                .count();
+            (k, matches as f64 / compared as f64)
+        })
+        .reduce(
+            || (0, 0.0),
+            |a, b| {
+                if b.1 > a.1 || (b.1 == a.1 && b.0 < a.0) {
+                    b
+                } else {
+                    a
+                }
+            },
+        );
+
+    (best_fraction > TILED_MATCH_THRESHOLD).then_some(best_lag)
+}
+
+// ========== 横向拼接（左右滚动截图）==========
+
+/// 逐列哈希 - 横向拼接使用
+///
+/// 与行哈希相同的量化方式，对每一列计算 RGB 均值哈希
+///
+/// 参数:
+///   image_bytes: 图像数据（PNG/JPEG 等编码格式）
+///   ignore_bottom_pixels: 忽略底部像素数（避免横向滚动条干扰）
+pub fn compute_column_hashes(
+    image_bytes: &[u8],
+    ignore_bottom_pixels: u32,
+) -> Result<Vec<u64>, String> {
+    let img =
+        image::load_from_memory(image_bytes).map_err(|e| format!("Failed to load image: {}", e))?;
+    Ok(compute_column_hashes_from_rgba(&img.to_rgba8(), ignore_bottom_pixels))
+}
+
+/// 直接从已解码的 RgbaImage 计算列哈希
+pub fn compute_column_hashes_from_rgba(
+    rgba_img: &image::RgbaImage,
+    ignore_bottom_pixels: u32,
+) -> Vec<u64> {
+    let width = rgba_img.width();
+    let height = rgba_img.height();
+
+    let effective_height = if ignore_bottom_pixels > 0 && height > ignore_bottom_pixels {
+        height - ignore_bottom_pixels
+    } else {
+        height
+    };
+
+    let raw = rgba_img.as_raw();
+    let stride = (width * 4) as usize;
+
+    (0..width)
+        .into_par_iter()
+        .map(|x| {
+            let (mut r_sum, mut g_sum, mut b_sum) = (0u64, 0u64, 0u64);
+            for y in 0..effective_height as usize {
+                let idx = y * stride + x as usize * 4;
+                r_sum += raw[idx] as u64;
+                g_sum += raw[idx + 1] as u64;
+                b_sum += raw[idx + 2] as u64;
+            }
+            row_hash_from_sums(r_sum, g_sum, b_sum, effective_height as u64)
+        })
+        .collect()
+}
+
+/// 横向双图拼接：在左图右侧查找与右图左侧相同的列，去掉重叠后左右拼接
+///
+/// 参数:
+///   left_bytes: 左侧（先截取的）图片
+///   right_bytes: 右侧（后截取的）图片
+///   ignore_bottom_pixels: 忽略底部像素数（排除横向滚动条）
+///   min_overlap_ratio: 最小重叠比例
+///
+/// 返回: 拼接后的 PNG 图片字节流
+pub fn stitch_two_images_horizontal(
+    left_bytes: &[u8],
+    right_bytes: &[u8],
+    ignore_bottom_pixels: u32,
+    min_overlap_ratio: f32,
+    options: &StitchOptions,
+) -> Result<Vec<u8>, String> {
+    stitch_horizontal_internal(left_bytes, right_bytes, ignore_bottom_pixels, min_overlap_ratio, options, false, false)
+}
+
+/// 带调试输出的横向拼接
+pub fn stitch_two_images_horizontal_debug(
+    left_bytes: &[u8],
+    right_bytes: &[u8],
+    ignore_bottom_pixels: u32,
+    min_overlap_ratio: f32,
+    options: &StitchOptions,
+) -> Result<Vec<u8>, String> {
+    stitch_horizontal_internal(left_bytes, right_bytes, ignore_bottom_pixels, min_overlap_ratio, options, false, true)
+}
+
+/// 横向智能拼接（多候选纠错，逻辑同 stitch_two_images_smart）
+pub fn stitch_two_images_horizontal_smart(
+    left_bytes: &[u8],
+    right_bytes: &[u8],
+    ignore_bottom_pixels: u32,
+    min_overlap_ratio: f32,
+    options: &StitchOptions,
+) -> Result<Vec<u8>, String> {
+    stitch_horizontal_internal(left_bytes, right_bytes, ignore_bottom_pixels, min_overlap_ratio, options, true, false)
+}
+
+/// 带调试输出的横向智能拼接
+pub fn stitch_two_images_horizontal_smart_debug(
+    left_bytes: &[u8],
+    right_bytes: &[u8],
+    ignore_bottom_pixels: u32,
+    min_overlap_ratio: f32,
+    options: &StitchOptions,
+) -> Result<Vec<u8>, String> {
+    stitch_horizontal_internal(left_bytes, right_bytes, ignore_bottom_pixels, min_overlap_ratio, options, true, true)
+}
+
+fn stitch_horizontal_internal(
+    left_bytes: &[u8],
+    right_bytes: &[u8],
+    ignore_bottom_pixels: u32,
+    min_overlap_ratio: f32,
+    options: &StitchOptions,
+    smart: bool,
+    debug: bool,
+) -> Result<Vec<u8>, String> {
+    use image::{DynamicImage, GenericImageView};
+
+    // 1️⃣ 加载图片
+    let mut left = image::load_from_memory(left_bytes)
+        .map_err(|e| format!("Failed to load left image: {}", e))?;
+    let right = image::load_from_memory(right_bytes)
+        .map_err(|e| format!("Failed to load right image: {}", e))?;
+
+    let (width1, height1) = left.dimensions();
+    let (width2, height2) = right.dimensions();
+
+    if debug {
+        println!("处理图片: ({}, {}) + ({}, {})", width1, height1, width2, height2);
+    }
+
+    // 2️⃣ 高度对齐（如果不同则缩放左图）
+    if height1 != height2 {
+        if debug {
+            println!("调整图片高度: {} -> {}", height1, height2);
+        }
+        let new_width1 = (width1 as f32 * height2 as f32 / height1 as f32) as u32;
+        left = left.resize_exact(new_width1, height2, image::imageops::FilterType::Lanczos3);
+    }
+
+    let final_height = height2;
+    let left_rgba = left.to_rgba8();
+    let right_rgba = right.to_rgba8();
+
+    // 3️⃣ 计算列哈希
+    let left_hashes = compute_column_hashes_from_rgba(&left_rgba, ignore_bottom_pixels);
+    let right_hashes = compute_column_hashes_from_rgba(&right_rgba, ignore_bottom_pixels);
+
+    // 4️⃣ 只在左图右侧搜索（智能模式 2 倍窗口，容忍回滚）
+    let left_len = left_hashes.len();
+    let right_len = right_hashes.len();
+    let search_window = if smart { right_len * 2 } else { right_len };
+    let search_start = left_len.saturating_sub(search_window);
+    let left_search_region = &left_hashes[search_start..];
+
+    if debug {
+        println!(
+            "  🔍 搜索重叠区域: left[{}:{}] (右侧{}列), right共{}列",
+            search_start,
+            left_len,
+            left_search_region.len(),
+            right_len
+        );
+    }
+
+    // 5️⃣ 找重叠列
+    let (start_i, start_j, overlap_length) = if smart {
+        let candidates = find_top_common_substrings(left_search_region, &right_hashes, min_overlap_ratio, 5);
+        if candidates.is_empty() {
+            return Err("No overlap found".to_string());
+        }
+        select_non_shrinking_candidate(&candidates, search_start, left_len, right_len, debug)
+    } else {
+        let (rel_i, j, len) = if debug {
+            find_longest_common_substring_debug(left_search_region, &right_hashes, min_overlap_ratio, 0)
+        } else {
+            find_longest_common_substring(left_search_region, &right_hashes, min_overlap_ratio, 0)
+        };
+        if len == 0 {
+            (left_len as i32, 0, 0)
+        } else {
+            (rel_i + search_start as i32, j, len)
+        }
+    };
+
+    // 6️⃣ 计算拼接参数
+    let left_keep_width = (start_i as usize + overlap_length) as u32;
+    let right_skip_width = (start_j as usize + overlap_length) as u32;
+    let right_keep_width = right_rgba.width().saturating_sub(right_skip_width);
+    let result_width = left_keep_width + right_keep_width;
+
+    if debug {
+        println!(
+            "拼接计算: 左图保留{}列 + 右图跳过{}列保留{}列 = 总计{}列",
+            left_keep_width, right_skip_width, right_keep_width, result_width
+        );
+    }
+
+    options.check_output_size(result_width, final_height)?;
+
+    // 7️⃣ 逐行拼接：左图前 left_keep_width 列 + 右图 right_skip_width 之后的列
+    let mut result = image::RgbaImage::new(result_width, final_height);
+    image::imageops::replace(
+        &mut result,
+        &image::imageops::crop_imm(&left_rgba, 0, 0, left_keep_width, final_height).to_image(),
+        0,
+        0,
+    );
+    image::imageops::replace(
+        &mut result,
+        &image::imageops::crop_imm(&right_rgba, right_skip_width, 0, right_keep_width, final_height)
+            .to_image(),
+        left_keep_width as i64,
+        0,
+    );
+
+    // 接缝混合（可选）
+    if options.blend_seam {
+        blend_seam_columns(
+            &mut result,
+            &left_rgba,
+            &right_rgba,
+            left_keep_width,
+            right_skip_width,
+            options.blend_height,
+        );
+    }
+
+    // 8️⃣ 编码为 PNG 字节流
+    let mut output = Vec::new();
+    DynamicImage::ImageRgba8(result)
+        .write_to(&mut std::io::Cursor::new(&mut output), image::ImageOutputFormat::Png)
+        .map_err(|e| format!("Failed to encode result: {}", e))?;
+
+    Ok(output)
+}
+
+/// 多候选中选择第一个不会缩短结果的可信候选；都不满足时使用最长候选（回滚场景）
+///
+/// candidates 的第一个坐标是相对 search_start 的位置，返回值为绝对位置
+fn select_non_shrinking_candidate(
+    candidates: &[(i32, i32, usize)],
+    search_start: usize,
+    len1: usize,
+    len2: usize,
+    debug: bool,
+) -> (i32, i32, usize) {
+    let longest_len = candidates[0].2;
+
+    for &(relative_start_i, start_j, overlap_length) in candidates {
+        let start_i = relative_start_i as usize + search_start;
+        let result_len = start_i + overlap_length + len2.saturating_sub(start_j as usize + overlap_length);
+
+        if result_len >= len1 {
+            if longest_len > overlap_length * 5 {
+                if debug {
+                    println!("  ⚠️  跳过: 匹配长度{}远小于最长候选{}，疑似噪声", overlap_length, longest_len);
+                }
+                continue;
+            }
+            return (start_i as i32, start_j, overlap_length);
+        }
+    }
+
+    if debug {
+        println!("  🔄 无可信的非缩短候选，使用最长匹配（可能是回滚场景）");
+    }
+    let first = candidates[0];
+    (first.0 + search_start as i32, first.1, first.2)
+}
+
+/// 滚动方向：纵向（上下拼接）
+pub const SCROLL_DIRECTION_VERTICAL: u8 = 0;
+/// 滚动方向：横向（左右拼接）
+pub const SCROLL_DIRECTION_HORIZONTAL: u8 = 1;
+
+/// 方向检测时忽略的右侧/底部像素数（排除滚动条，与拼接接口默认值一致）
+const DIRECTION_IGNORE_PIXELS: u32 = 20;
+/// 方向检测使用的最小重叠比例
+const DIRECTION_MIN_OVERLAP_RATIO: f32 = 0.01;
+
+/// 判断两张截图是上下滚动还是左右滚动
+///
+/// 分别用行哈希（纵向）和列哈希（横向）在 img1 的底部 / 右侧查找重叠，
+/// 比较重叠长度占对应边长的比例，比例高者胜出；两者相同（包括都没找到）时按纵向处理
+///
+/// 返回: SCROLL_DIRECTION_VERTICAL 或 SCROLL_DIRECTION_HORIZONTAL
+pub fn detect_scroll_direction(img1_bytes: &[u8], img2_bytes: &[u8]) -> Result<u8, String> {
+    let img1 = image::load_from_memory(img1_bytes)
+        .map_err(|e| format!("Failed to load image 1: {}", e))?
+        .to_rgba8();
+    let img2 = image::load_from_memory(img2_bytes)
+        .map_err(|e| format!("Failed to load image 2: {}", e))?
+        .to_rgba8();
+
+    let vertical = overlap_score(
+        &compute_row_hashes_from_rgba(&img1, DIRECTION_IGNORE_PIXELS, false),
+        &compute_row_hashes_from_rgba(&img2, DIRECTION_IGNORE_PIXELS, false),
+    );
+    let horizontal = overlap_score(
+        &compute_column_hashes_from_rgba(&img1, DIRECTION_IGNORE_PIXELS),
+        &compute_column_hashes_from_rgba(&img2, DIRECTION_IGNORE_PIXELS),
+    );
+
+    Ok(if horizontal > vertical {
+        SCROLL_DIRECTION_HORIZONTAL
+    } else {
+        SCROLL_DIRECTION_VERTICAL
+    })
+}
+
+/// 在 seq1 末尾（长度与 seq2 相同的窗口）查找重叠，返回重叠长度占较短序列的比例
+fn overlap_score(seq1: &[u64], seq2: &[u64]) -> f32 {
+    let shorter = seq1.len().min(seq2.len());
+    if shorter == 0 {
+        return 0.0;
+    }
+    let search_start = seq1.len().saturating_sub(seq2.len());
+    let (_, _, length) = find_longest_common_substring(
+        &seq1[search_start..],
+        seq2,
+        DIRECTION_MIN_OVERLAP_RATIO,
+        0,
+    );
+    length as f32 / shorter as f32
+}
+
+/// 自动判断方向后拼接
+///
+/// 纵向时调用 stitch_two_images（ignore_pixels 为忽略的右侧像素，使用 options），
+/// 横向时调用 stitch_two_images_horizontal（ignore_pixels 为忽略的底部像素，options 中仅 blend_seam / blend_height / max_output_pixels 生效）
+///
+/// 返回: (拼接后的 PNG 字节流, 实际使用的方向)
+pub fn stitch_auto(
+    img1_bytes: &[u8],
+    img2_bytes: &[u8],
+    ignore_pixels: u32,
+    min_overlap_ratio: f32,
+    options: &StitchOptions,
+) -> Result<(Vec<u8>, u8), String> {
+    match detect_scroll_direction(img1_bytes, img2_bytes)? {
+        SCROLL_DIRECTION_HORIZONTAL => {
+            stitch_two_images_horizontal(img1_bytes, img2_bytes, ignore_pixels, min_overlap_ratio, options)
+                .map(|bytes| (bytes, SCROLL_DIRECTION_HORIZONTAL))
+        }
+        _ => stitch_two_images(img1_bytes, img2_bytes, ignore_pixels, min_overlap_ratio, options)
+            .map(|r| (r.image_bytes, SCROLL_DIRECTION_VERTICAL)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_dhash_identical_images() {
+        // 创建两个相同的测试图像
+        let img = RgbaImage::from_fn(64, 64, |x, y| {
+            Rgba([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8, 255])
+        });
+
+        let mut bytes1 = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut bytes1),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+
+        let hash1 = compute_dhash(&bytes1, 8).unwrap();
+        let hash2 = compute_dhash(&bytes1, 8).unwrap();
+
+        assert_eq!(hash1, hash2);
+        assert_eq!(hamming_distance(hash1, hash2), 0);
+    }
+
+    #[test]
+    fn test_dhash_masked_ignores_watermark() {
+        let base = RgbaImage::from_fn(64, 64, |x, y| {
+            Rgba([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8, 255])
+        });
+        let mut watermarked = base.clone();
+        for y in 50..60 {
+            for x in 40..62 {
+                watermarked.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+            }
+        }
+
+        let encode = |img: &RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+        let (bytes1, bytes2) = (encode(&base), encode(&watermarked));
+
+        assert_ne!(compute_dhash(&bytes1, 8).unwrap(), compute_dhash(&bytes2, 8).unwrap());
+        assert_eq!(
+            compute_dhash_masked(&bytes1, (40, 50, 22, 10), 8).unwrap(),
+            compute_dhash_masked(&bytes2, (40, 50, 22, 10), 8).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_blend_seam_rows_is_gradual() {
+        // img1 亮度 100，img2 亮度 120（模拟两次截图亮度略有差异）
+        let img1 = RgbaImage::from_pixel(4, 20, Rgba([100, 100, 100, 255]));
+        let img2 = RgbaImage::from_pixel(4, 20, Rgba([120, 120, 120, 255]));
+
+        // 结果：img1 前 10 行 + img2 跳过 10 行后的 10 行
+        let mut result = img1.as_raw()[..10 * 16].to_vec();
+        result.extend_from_slice(&img2.as_raw()[10 * 16..]);
+
+        blend_seam_rows(&mut result, &img1, &img2, 10, 10, 8);
+
+        let column: Vec<u8> = (0..20).map(|y| result[y * 16]).collect();
+        assert_eq!(column[5], 100);
+        assert_eq!(column[14], 120);
+        assert!(column.windows(2).all(|w| w[1] >= w[0]));
+        assert!(column.windows(2).all(|w| w[1] - w[0] <= 3));
+    }
+
+    #[test]
+    fn test_blend_seam_columns_is_gradual() {
+        // 左图亮度 100，右图亮度 120
+        let left = RgbaImage::from_pixel(20, 4, Rgba([100, 100, 100, 255]));
+        let right = RgbaImage::from_pixel(20, 4, Rgba([120, 120, 120, 255]));
+
+        // 结果：左图前 10 列 + 右图跳过 10 列后的 10 列
+        let mut result = RgbaImage::from_fn(20, 4, |x, y| {
+            if x < 10 {
+                *left.get_pixel(x, y)
+            } else {
+                *right.get_pixel(x, y)
+            }
+        });
+
+        blend_seam_columns(&mut result, &left, &right, 10, 10, 8);
+
+        let row: Vec<u8> = (0..20).map(|x| result.get_pixel(x, 2)[0]).collect();
+        assert_eq!(row[5], 100);
+        assert_eq!(row[14], 120);
+        assert!(row.windows(2).all(|w| w[1] >= w[0]));
+        assert!(row.windows(2).all(|w| w[1] - w[0] <= 3));
+    }
+
+    #[test]
+    fn test_batch_compute_hash_keeps_errors() {
+        let img = RgbaImage::from_fn(32, 32, |x, y| Rgba([(x * 8) as u8, (y * 8) as u8, 0, 255]));
+        let mut png = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let results = batch_compute_hash(&[png.clone(), b"not an image".to_vec(), png.clone()], "dhash", 8);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], compute_dhash(&png, 8));
+        assert!(results[1].is_err());
+        assert_eq!(results[2], results[0]);
+        assert!(batch_compute_hash(&[png], "xhash", 8)[0].is_err());
+    }
+
+    #[test]
+    fn test_serialize_hashes_roundtrip() {
+        let hashes = vec![0, 1, u64::MAX, 0x0123_4567_89ab_cdef];
+        let data = serialize_hashes(&hashes);
+        assert_eq!(data.len(), hashes.len() * 8);
+        assert_eq!(&data[8..16], &[1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(deserialize_hashes(&data).unwrap(), hashes);
+        assert!(deserialize_hashes(&data[..7]).is_err());
+    }
+
+    #[test]
+    fn test_lcs_tolerance() {
+        let seq1: Vec<u64> = (0..100).collect();
+        // 后 60 行与 seq1 的 [40, 100) 相同，但每 20 行有一行哈希不同
+        let seq2: Vec<u64> = (40..100)
+            .chain(1000..1040)
+            .enumerate()
+            .map(|(idx, h)| if idx % 20 == 10 { h + 10_000 } else { h })
+            .collect();
+
+        assert_eq!(find_longest_common_substring(&seq1, &seq2, 0.5, 0), (-1, -1, 0));
+        assert_eq!(find_longest_common_substring(&seq1, &seq2, 0.5, 2), (40, 0, 60));
+    }
+
+    #[test]
+    fn test_validate_stitch() {
+        let page = |seed: u32| {
+            RgbaImage::from_fn(60, 200, move |_, y| {
+                Rgba([(y * 37 + seed) as u8, (y * 91 + seed * 3) as u8, (y * 13) as u8, 255])
+            })
+        };
+        let encode = |img: RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+
+        // 正确的滚动截图：[0,120) 与 [70,200) 重叠 50 行
+        let scrolled = page(0);
+        let top = encode(image::imageops::crop_imm(&scrolled, 0, 0, 60, 120).to_image());
+        let bottom = encode(image::imageops::crop_imm(&scrolled, 0, 70, 60, 130).to_image());
+        let good = stitch_two_images(&top, &bottom, 0, 0.1, &StitchOptions::default()).unwrap();
+        let score = validate_stitch(&top, &bottom, &good.image_bytes, good.seam_y).unwrap();
+        assert!(score > 0.9, "score = {}", score);
+
+        // 两张不相关的图片
+        let other = encode(image::imageops::crop_imm(&page(100), 0, 0, 60, 120).to_image());
+        let bad = stitch_two_images(&top, &other, 0, 0.1, &StitchOptions::default()).unwrap();
+        let score = validate_stitch(&top, &other, &bad.image_bytes, bad.seam_y).unwrap();
+        assert!(score < 0.5, "score = {}", score);
+    }
+
+    #[test]
+    fn test_width_strategy() {
+        // 左右各 10 列白边的页面；底部截图只截到中间 40 列
+        let page = RgbaImage::from_fn(60, 200, |x, y| {
+            if !(10..50).contains(&x) {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([(y * 37) as u8, (y * 91) as u8, (y * 13) as u8, 255])
+            }
+        });
+        let encode = |img: RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+        let top = encode(image::imageops::crop_imm(&page, 0, 0, 60, 120).to_image());
+        let bottom = encode(image::imageops::crop_imm(&page, 10, 70, 40, 130).to_image());
+
+        // letterbox：用白色补齐窄图，结果与原页面逐像素一致
+        let options = StitchOptions {
+            width_strategy: WidthStrategy::Letterbox,
+            ..Default::default()
+        };
+        let result = stitch_two_images(&top, &bottom, 0, 0.1, &options).unwrap();
+        let stitched = image::load_from_memory(&result.image_bytes).unwrap().to_rgba8();
+        assert_eq!(stitched.dimensions(), (60, 200));
+        assert_eq!(stitched.as_raw(), page.as_raw());
+
+        // crop：宽图居中裁剪到 40 列
+        let options = StitchOptions {
+            width_strategy: WidthStrategy::Crop,
+            ..Default::default()
+        };
+        let result = stitch_two_images(&top, &bottom, 0, 0.1, &options).unwrap();
+        let stitched = image::load_from_memory(&result.image_bytes).unwrap().to_rgba8();
+        let expected = image::imageops::crop_imm(&page, 10, 0, 40, 200).to_image();
+        assert_eq!(stitched.dimensions(), (40, 200));
+        assert_eq!(stitched.as_raw(), expected.as_raw());
+
+        assert!(WidthStrategy::parse("stretch").is_err());
+        for strategy in [WidthStrategy::Scale, WidthStrategy::Letterbox, WidthStrategy::Crop] {
+            assert_eq!(WidthStrategy::parse(strategy.name()), Ok(strategy));
+        }
+    }
+
+    #[test]
+    fn test_auto_trim_uniform_borders() {
+        let page = RgbaImage::from_fn(60, 200, |x, y| {
+            Rgba([(y * 37) as u8, (y * 91 + x) as u8, (y * 13) as u8, 255])
+        });
+        // 每张截图上下各有 15 行带轻微噪声的灰色边带
+        let with_bands = |y0: u32, h: u32| {
+            RgbaImage::from_fn(60, h + 30, |x, y| {
+                if y < 15 || y >= h + 15 {
+                    let v = 200 + ((x + y) % 3) as u8;
+                    Rgba([v, v, v, 255])
+                } else {
+                    *page.get_pixel(x, y0 + y - 15)
+                }
+            })
+        };
+        let encode = |img: RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+        let top = encode(with_bands(0, 120));
+        let bottom = encode(with_bands(70, 130));
+
+        let options = StitchOptions {
+            auto_trim: true,
+            ..Default::default()
+        };
+        let result = stitch_two_images(&top, &bottom, 0, 0.1, &options).unwrap();
+        let stitched = image::load_from_memory(&result.image_bytes).unwrap().to_rgba8();
+        assert_eq!(stitched.dimensions(), (60, 200));
+        assert_eq!(stitched.as_raw(), page.as_raw());
+        assert_eq!(result.overlap_rows, 50);
+    }
+
+    #[test]
+    fn test_stitch_images_reports_progress() {
+        let page = RgbaImage::from_fn(40, 300, |x, y| {
+            Rgba([(y * 37) as u8, (y * 91 + x) as u8, (y * 13) as u8, 255])
+        });
+        let encode = |img: RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+        let images: Vec<Vec<u8>> = [0, 80, 160]
+            .iter()
+            .map(|&y| encode(image::imageops::crop_imm(&page, 0, y, 40, 140).to_image()))
+            .collect();
+
+        let calls = std::sync::Mutex::new(Vec::new());
+        let progress = |current: usize, total: usize| {
+            calls.lock().unwrap().push((current, total));
+            Ok(())
+        };
+        let result = stitch_images(&images, 0, 0.1, &StitchOptions::default(), Some(&progress), None).unwrap();
+        let stitched = image::load_from_memory(&result).unwrap().to_rgba8();
+        assert_eq!(stitched.as_raw(), page.as_raw());
+        assert_eq!(*calls.lock().unwrap(), vec![(1, 3), (2, 3), (3, 3)]);
+
+        // 回调返回错误时中止
+        let abort = |current: usize, _: usize| if current >= 2 { Err("stop".to_string()) } else { Ok(()) };
+        assert_eq!(stitch_images(&images, 0, 0.1, &StitchOptions::default(), Some(&abort), None), Err("stop".to_string()));
+        assert!(stitch_images(&[], 0, 0.1, &StitchOptions::default(), None, None).is_err());
+    }
+
+    #[test]
+    fn test_stitch_images_cancel() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let encode = |img: RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+        let images: Vec<Vec<u8>> = (0..3)
+            .map(|i| encode(RgbaImage::from_pixel(20, 20, Rgba([i * 50, 0, 0, 255]))))
+            .collect();
+
+        // 处理完第 2 张后取消，第 3 张不再处理
+        let cancel = AtomicBool::new(false);
+        let calls = std::sync::Mutex::new(Vec::new());
+        let progress = |current: usize, _: usize| {
+            calls.lock().unwrap().push(current);
+            if current == 2 {
+                cancel.store(true, Ordering::Relaxed);
+            }
+            Ok(())
+        };
+        let result = stitch_images(&images, 0, 0.1, &StitchOptions::default(), Some(&progress), Some(&cancel));
+        assert_eq!(result, Err(CANCELLED_ERROR.to_string()));
+        assert_eq!(*calls.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_max_output_pixels() {
+        let page = RgbaImage::from_fn(60, 200, |_, y| {
+            Rgba([(y * 37) as u8, (y * 91) as u8, (y * 13) as u8, 255])
+        });
+        let encode = |img: RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+        let top = encode(image::imageops::crop_imm(&page, 0, 0, 60, 120).to_image());
+        let bottom = encode(image::imageops::crop_imm(&page, 0, 70, 60, 130).to_image());
+
+        // 结果为 60x200 = 12000 像素
+        let mut options = StitchOptions {
+            max_output_pixels: Some(12_000),
+            ..Default::default()
+        };
+        assert!(stitch_two_images(&top, &bottom, 0, 0.1, &options).is_ok());
+
+        options.max_output_pixels = Some(11_999);
+        let err = stitch_two_images(&top, &bottom, 0, 0.1, &options).unwrap_err();
+        assert!(err.starts_with("Result would exceed max_output_pixels limit"), "{}", err);
+        let err = crate::stitch::stitch_two_images_smart(&top, &bottom, 0, 0.1, &options).unwrap_err();
+        assert!(err.starts_with("Result would exceed max_output_pixels limit"), "{}", err);
+
+        // 横向拼接：结果为 200x30 = 6000 像素
+        let wide = RgbaImage::from_fn(200, 30, |x, y| {
+            Rgba([(x * 7 % 256) as u8, (x * 13 % 256) as u8, (y * 3) as u8, 255])
+        });
+        let left = encode(image::imageops::crop_imm(&wide, 0, 0, 120, 30).to_image());
+        let right = encode(image::imageops::crop_imm(&wide, 80, 0, 120, 30).to_image());
+        options.max_output_pixels = Some(6_000);
+        assert!(stitch_two_images_horizontal(&left, &right, 0, 0.1, &options).is_ok());
+        options.max_output_pixels = Some(5_999);
+        let err = stitch_two_images_horizontal(&left, &right, 0, 0.1, &options).unwrap_err();
+        assert!(err.starts_with("Result would exceed max_output_pixels limit"), "{}", err);
+    }
+
+    #[test]
+    fn test_detect_tiled_image() {
+        // 每 100 行重复一次的条带
+        let tiled = RgbaImage::from_fn(60, 450, |x, y| {
+            let band = y % 100;
+            Rgba([(band * 2) as u8, (band * 7 % 256) as u8, (x * 3) as u8, 255])
+        });
+        let encode = |img: RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+        assert_eq!(detect_tiled_image(&encode(tiled), 50).unwrap(), Some(100));
+
+        // 无重复内容
+        let plain = RgbaImage::from_fn(60, 450, |_, y| {
+            Rgba([(y % 256) as u8, (y / 256 * 90) as u8, (y * 7 % 256) as u8, 255])
+        });
+        assert_eq!(detect_tiled_image(&encode(plain), 50).unwrap(), None);
+    }
+
+    #[test]
+    fn test_column_hashes_ignore_bottom() {
+        // 底部 10 行是每列不同的"滚动条"，忽略后所有列哈希相同
+        let img = RgbaImage::from_fn(40, 50, |x, y| {
+            if y >= 40 {
+                Rgba([(x * 6) as u8, 0, 0, 255])
+            } else {
+                Rgba([90, 90, 90, 255])
+            }
+        });
+        let hashes = compute_column_hashes_from_rgba(&img, 10);
+        assert_eq!(hashes.len(), 40);
+        assert!(hashes.iter().all(|&h| h == hashes[0]));
+        assert_ne!(compute_column_hashes_from_rgba(&img, 0)[0], compute_column_hashes_from_rgba(&img, 0)[39]);
+    }
+
+    #[test]
+    fn test_horizontal_stitch_removes_overlap() {
+        // 一张宽 200 的"页面"，左图取 [0,120)，右图取 [80,200)，重叠 40 列
+        let page = RgbaImage::from_fn(200, 30, |x, y| {
+            Rgba([(x * 7 % 256) as u8, (x * 13 % 256) as u8, (y * 3) as u8, 255])
+        });
+        let encode = |img: RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+        let left = encode(image::imageops::crop_imm(&page, 0, 0, 120, 30).to_image());
+        let right = encode(image::imageops::crop_imm(&page, 80, 0, 120, 30).to_image());
+
+        let result = stitch_two_images_horizontal(&left, &right, 0, 0.1, &StitchOptions::default()).unwrap();
+        let stitched = image::load_from_memory(&result).unwrap().to_rgba8();
+        assert_eq!(stitched.dimensions(), (200, 30));
+        assert_eq!(stitched.as_raw(), page.as_raw());
+    }
+
+    #[test]
+    fn test_detect_scroll_direction() {
+        let page = RgbaImage::from_fn(200, 200, |x, y| {
+            Rgba([(x * 7 % 256) as u8, (y * 13 % 256) as u8, ((x * y) % 251) as u8, 255])
+        });
+        let encode = |img: RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+
+        // 左右滚动：[0,120) 与 [80,200) 两列区间
+        let left = encode(image::imageops::crop_imm(&page, 0, 0, 120, 100).to_image());
+        let right = encode(image::imageops::crop_imm(&page, 80, 0, 120, 100).to_image());
+        assert_eq!(detect_scroll_direction(&left, &right).unwrap(), SCROLL_DIRECTION_HORIZONTAL);
+
+        let (result, direction) = stitch_auto(&left, &right, 0, 0.1, &StitchOptions::default()).unwrap();
+        assert_eq!(direction, SCROLL_DIRECTION_HORIZONTAL);
+        let stitched = image::load_from_memory(&result).unwrap().to_rgba8();
+        assert_eq!(stitched.dimensions(), (200, 100));
+
+        // 上下滚动：[0,120) 与 [80,200) 两行区间
+        let top = encode(image::imageops::crop_imm(&page, 0, 0, 100, 120).to_image());
+        let bottom = encode(image::imageops::crop_imm(&page, 0, 80, 100, 120).to_image());
+        assert_eq!(detect_scroll_direction(&top, &bottom).unwrap(), SCROLL_DIRECTION_VERTICAL);
+    }
+
+    #[test]
+    fn test_multiscale_similarity() {
+        let encode = |img: RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+        // 相同的整体结构（水平渐变 + 中间一条竖线），叠加不同的细纹理
+        let structured = |texture: u32| {
+            RgbaImage::from_fn(170, 160, move |x, y| {
+                let base = if (80..90).contains(&x) { 0 } else { x + 40 };
+                let noise = ((x * 7 + y * 13 + texture) % 5) as i32 - 2;
+                let v = (base as i32 + noise).clamp(0, 255) as u8;
+                Rgba([v, v, v, 255])
+            })
+        };
+        // 不同的整体结构（反向渐变）
+        let different = RgbaImage::from_fn(170, 160, |x, _| {
+            let v = (250 - x) as u8;
+            Rgba([v, v, v, 255])
+        });
+
+        let a = compute_multiscale_dhash(&encode(structured(0))).unwrap();
+        let b = compute_multiscale_dhash(&encode(structured(3))).unwrap();
+        let c = compute_multiscale_dhash(&encode(different)).unwrap();
+
+        assert_eq!(a.len(), multiscale_dhash_len());
+        assert!(multiscale_similarity(&a, &b) > 0.9);
+        assert!(multiscale_similarity(&a, &c) < 0.5);
+    }
+
+    #[test]
+    fn test_row_hashes() {
+        let img = RgbaImage::from_fn(100, 50, |_x, y| {
+            Rgba([(y * 5) as u8, (y * 5) as u8, (y * 5) as u8, 255])
+        });
+
+        let mut bytes = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+
+        let hashes = compute_row_hashes(&bytes, 0).unwrap();
+        assert_eq!(hashes.len(), 50);
+
+        // 同一行的像素应该产生相同的哈希
+        assert_eq!(hashes[0], hashes[0]);
+    }
+
+    #[test]
+    fn test_row_hash_cache() {
+        let img = RgbaImage::from_fn(40, 30, |x, y| Rgba([(x * 6) as u8, (y * 8) as u8, 0, 255]));
+        let mut bytes = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .unwrap();
+
+        let first = compute_row_hashes(&bytes, 0).unwrap();
+        let key = hash_cache_key(&bytes, 0);
+        assert_eq!(ROW_HASH_CACHE.with(|cache| cache.borrow_mut().get(key)), Some(first.clone()));
+        assert_eq!(compute_row_hashes(&bytes, 0).unwrap(), first);
+
+        // 参数不同不命中缓存
+        assert_ne!(compute_row_hashes(&bytes, 10).unwrap(), first);
+        assert_eq!(compute_dhash(&bytes, 8).unwrap(), compute_dhash(&bytes, 8).unwrap());
+    }
+
+    #[test]
+    fn test_lru_cache_eviction() {
+        let mut cache = LruCache::new(2);
+        cache.put(1, "a");
+        cache.put(2, "b");
+        assert_eq!(cache.get(1), Some("a"));
+        // 1 刚被访问，淘汰最久未用的 2
+        cache.put(3, "c");
+        assert_eq!(cache.get(2), None);
+        assert_eq!(cache.get(1), Some("a"));
+        assert_eq!(cache.get(3), Some("c"));
+    }
+
+    #[test]
+    fn test_hash_distance_matrix() {
+        let matrix = hash_distance_matrix(&[0b0000, 0b0111, 0b1000]);
+        assert_eq!(matrix, vec![vec![0, 3, 1], vec![3, 0, 4], vec![1, 4, 0]]);
+    }
+
+    #[test]
+    fn test_group_by_hash_distance() {
+        // 0/2/3 两两相近（链式相连），1 和 4 各自独立
+        let hashes = [0b0000u64, u64::MAX, 0b0011, 0b0111, 0xFFFF_0000];
+        assert_eq!(group_by_hash_distance(&hashes, 2), vec![0, 1, 4]);
+        assert_eq!(group_by_hash_distance(&hashes, 0), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_find_subimage() {
+        let haystack = RgbaImage::from_fn(120, 80, |x, y| {
+            Rgba([(x * 2) as u8, (y * 3) as u8, ((x * y) % 251) as u8, 255])
+        });
+        let needle = image::imageops::crop_imm(&haystack, 37, 21, 16, 9).to_image();
+
+        assert_eq!(find_subimage_rgba(&haystack, &needle), Some((37, 21)));
+
+        let missing = RgbaImage::from_pixel(16, 9, Rgba([1, 2, 3, 255]));
+        assert_eq!(find_subimage_rgba(&haystack, &missing), None);
+    }
+}
@@ -1,27 +1,258 @@
 pub mod hash;
+pub mod image_hash;
 pub mod lcs;
+pub mod pool;
+pub mod ssim;
 pub mod stitch;
+pub mod utils;
 
 use pyo3::prelude::*;
 use pyo3::types::PyBytes;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// ========== 线程池 ==========
+
+/// 释放 GIL，并在 set_thread_pool_size 指定的线程池中执行（未设置时使用 rayon 全局线程池）
+fn allow_threads_pooled<T: Send>(py: Python<'_>, f: impl FnOnce() -> T + Send) -> T {
+    py.allow_threads(|| pool::install(f))
+}
+
+/// 设置并行计算（拼接、行哈希、批量哈希等）使用的线程数
+/// 
+/// 设置后使用独立的线程池，不再占用 rayon 全局线程池（默认线程数 = CPU 核数）；
+/// n = 0 恢复使用全局线程池。正在进行的计算不受影响
+#[pyfunction]
+fn set_thread_pool_size(n: usize) -> PyResult<()> {
+    pool::set_size(n).map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// 当前并行计算使用的线程数
+#[pyfunction]
+fn get_thread_pool_size() -> usize {
+    pool::current_size()
+}
 
 // ========== 拼接函数 ==========
 
-/// 智能双图拼接（多候选纠错）
+/// 拼接结果：(png_bytes, overlap_rows, seam_y, overlap_ratio)
+type StitchWithMeta<'py> = (Bound<'py, PyBytes>, usize, u32, f32);
+
+/// 拼接选项，通过各拼接函数的 options 参数传入（不传时全部使用默认值）
+///
+/// Args:
+///     blend_seam: 在接缝处做线性混合，消除亮度差造成的硬接缝 (默认 False)
+///     blend_height: 混合区域高度，以接缝为中心 (默认 16)
+///     tolerance: 最长公共子串内允许的连续不匹配行数，仅 stitch_two_images_rust 系列使用 (默认 0)
+///     width_strategy: 两图宽度不一致时的对齐方式 "scale" / "letterbox" / "crop" (默认 "scale")
+///     pad_color: letterbox 填充色 RGBA (默认白色)
+///     max_output_pixels: 结果图最大像素数 (宽 × 高)，超出时拒绝拼接 (默认 None 不限)
+///     auto_trim: 拼接前去掉顶部 / 底部的纯色边带，仅 stitch_two_images_rust 系列使用 (默认 False)
+///
+/// Raises:
+///     RuntimeError: width_strategy 无法识别
+#[pyclass(name = "StitchOptions")]
+#[derive(Clone, Copy, Default)]
+struct PyStitchOptions {
+    inner: image_hash::StitchOptions,
+}
+
+#[pymethods]
+impl PyStitchOptions {
+    #[new]
+    #[pyo3(signature = (blend_seam=false, blend_height=16, tolerance=0, width_strategy="scale", pad_color=(255, 255, 255, 255), max_output_pixels=None, auto_trim=false))]
+    fn new(
+        blend_seam: bool,
+        blend_height: u32,
+        tolerance: usize,
+        width_strategy: &str,
+        pad_color: (u8, u8, u8, u8),
+        max_output_pixels: Option<u64>,
+        auto_trim: bool,
+    ) -> PyResult<Self> {
+        let width_strategy = image_hash::WidthStrategy::parse(width_strategy)
+            .map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+        let (r, g, b, a) = pad_color;
+        Ok(Self {
+            inner: image_hash::StitchOptions {
+                blend_seam,
+                blend_height,
+                tolerance,
+                width_strategy,
+                pad_color: [r, g, b, a],
+                max_output_pixels,
+                auto_trim,
+            },
+        })
+    }
+
+    #[getter]
+    fn blend_seam(&self) -> bool {
+        self.inner.blend_seam
+    }
+
+    #[getter]
+    fn blend_height(&self) -> u32 {
+        self.inner.blend_height
+    }
+
+    #[getter]
+    fn tolerance(&self) -> usize {
+        self.inner.tolerance
+    }
+
+    #[getter]
+    fn width_strategy(&self) -> &'static str {
+        self.inner.width_strategy.name()
+    }
+
+    #[getter]
+    fn pad_color(&self) -> (u8, u8, u8, u8) {
+        let [r, g, b, a] = self.inner.pad_color;
+        (r, g, b, a)
+    }
+
+    #[getter]
+    fn max_output_pixels(&self) -> Option<u64> {
+        self.inner.max_output_pixels
+    }
+
+    #[getter]
+    fn auto_trim(&self) -> bool {
+        self.inner.auto_trim
+    }
+}
+
+/// 取出 Python 传入的拼接选项（None 时使用默认值）
+fn stitch_options(options: Option<PyRef<'_, PyStitchOptions>>) -> image_hash::StitchOptions {
+    options.map(|o| o.inner).unwrap_or_default()
+}
+
+/// 取消令牌：传给耗时的拼接函数，在其他线程调用 cancel() 即可中途中止
+#[pyclass]
+#[derive(Default)]
+struct CancelToken {
+    flag: Arc<AtomicBool>,
+}
+
+#[pymethods]
+impl CancelToken {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// 请求取消，正在进行的操作会在下一次检查时抛出 "Cancelled" 异常
+    fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// 是否已请求取消
+    fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    /// 清除取消状态，令牌可以复用
+    fn reset(&self) {
+        self.flag.store(false, Ordering::Relaxed);
+    }
+}
+
+/// 双图拼接（最长公共子串），附带重叠元数据
+/// options: 可选 StitchOptions，不传时使用默认值
+/// 返回 (png_bytes, overlap_rows, seam_y, overlap_ratio)，失败返回 None
+#[pyfunction]
+#[pyo3(signature = (img1_bytes, img2_bytes, ignore_right_pixels=None, min_overlap_ratio=None, options=None))]
+fn stitch_two_images_rust<'py>(
+    py: Python<'py>,
+    img1_bytes: Vec<u8>,
+    img2_bytes: Vec<u8>,
+    ignore_right_pixels: Option<u32>,
+    min_overlap_ratio: Option<f32>,
+    options: Option<PyRef<'_, PyStitchOptions>>,
+) -> PyResult<Option<StitchWithMeta<'py>>> {
+    let ignore = ignore_right_pixels.unwrap_or(20);
+    let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
+
+    match allow_threads_pooled(py, || image_hash::stitch_two_images(&img1_bytes, &img2_bytes, ignore, ratio, &options)) {
+        Ok(r) => Ok(Some((
+            PyBytes::new_bound(py, &r.image_bytes),
+            r.overlap_rows,
+            r.seam_y,
+            r.overlap_ratio,
+        ))),
+        Err(e) => {
+            eprintln!("⚠️  Rust 拼接失败: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// 双图拼接（调试模式）
 #[pyfunction]
-#[pyo3(signature = (img1_bytes, img2_bytes, ignore_right_pixels=None, min_overlap_ratio=None))]
+#[pyo3(signature = (img1_bytes, img2_bytes, ignore_right_pixels=None, min_overlap_ratio=None, options=None))]
+fn stitch_two_images_rust_debug<'py>(
+    py: Python<'py>,
+    img1_bytes: Vec<u8>,
+    img2_bytes: Vec<u8>,
+    ignore_right_pixels: Option<u32>,
+    min_overlap_ratio: Option<f32>,
+    options: Option<PyRef<'_, PyStitchOptions>>,
+) -> PyResult<Option<StitchWithMeta<'py>>> {
+    let ignore = ignore_right_pixels.unwrap_or(20);
+    let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
+
+    println!("\n======================================================================");
+    println!("🧩 Rust 拼接接口（最长公共子串 + 调试模式）");
+    println!("======================================================================");
+
+    match allow_threads_pooled(py, || image_hash::stitch_two_images_debug(&img1_bytes, &img2_bytes, ignore, ratio, &options)) {
+        Ok(r) => {
+            println!(
+                "✅ Rust 拼接完成: 重叠{}行, 接缝y={}, 占比{:.2}%",
+                r.overlap_rows,
+                r.seam_y,
+                r.overlap_ratio * 100.0
+            );
+            Ok(Some((
+                PyBytes::new_bound(py, &r.image_bytes),
+                r.overlap_rows,
+                r.seam_y,
+                r.overlap_ratio,
+            )))
+        }
+        Err(e) => {
+            eprintln!("⚠️  Rust 拼接失败: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// 智能双图拼接（多候选纠错），附带重叠元数据
+/// 返回 (png_bytes, overlap_rows, seam_y, overlap_ratio)，失败返回 None
+#[pyfunction]
+#[pyo3(signature = (img1_bytes, img2_bytes, ignore_right_pixels=None, min_overlap_ratio=None, options=None))]
 fn stitch_two_images_rust_smart<'py>(
     py: Python<'py>,
     img1_bytes: Vec<u8>,
     img2_bytes: Vec<u8>,
     ignore_right_pixels: Option<u32>,
     min_overlap_ratio: Option<f32>,
-) -> PyResult<Option<Bound<'py, PyBytes>>> {
+    options: Option<PyRef<'_, PyStitchOptions>>,
+) -> PyResult<Option<StitchWithMeta<'py>>> {
     let ignore = ignore_right_pixels.unwrap_or(20);
     let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
 
-    match stitch::stitch_two_images_smart(&img1_bytes, &img2_bytes, ignore, ratio) {
-        Ok(result_bytes) => Ok(Some(PyBytes::new_bound(py, &result_bytes))),
+    match allow_threads_pooled(py, || image_hash::stitch_two_images_smart(&img1_bytes, &img2_bytes, ignore, ratio, &options)) {
+        Ok(r) => Ok(Some((
+            PyBytes::new_bound(py, &r.image_bytes),
+            r.overlap_rows,
+            r.seam_y,
+            r.overlap_ratio,
+        ))),
         Err(e) => {
             eprintln!("⚠️  Rust 智能拼接失败: {}", e);
             Ok(None)
@@ -31,25 +262,37 @@ fn stitch_two_images_rust_smart<'py>(
 
 /// 智能双图拼接（调试模式）
 #[pyfunction]
-#[pyo3(signature = (img1_bytes, img2_bytes, ignore_right_pixels=None, min_overlap_ratio=None))]
+#[pyo3(signature = (img1_bytes, img2_bytes, ignore_right_pixels=None, min_overlap_ratio=None, options=None))]
 fn stitch_two_images_rust_smart_debug<'py>(
     py: Python<'py>,
     img1_bytes: Vec<u8>,
     img2_bytes: Vec<u8>,
     ignore_right_pixels: Option<u32>,
     min_overlap_ratio: Option<f32>,
-) -> PyResult<Option<Bound<'py, PyBytes>>> {
+    options: Option<PyRef<'_, PyStitchOptions>>,
+) -> PyResult<Option<StitchWithMeta<'py>>> {
     let ignore = ignore_right_pixels.unwrap_or(20);
     let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
 
     println!("\n======================================================================");
     println!("🧠 Rust 智能拼接接口（多候选纠错 + 调试模式）");
     println!("======================================================================");
 
-    match stitch::stitch_two_images_smart_debug(&img1_bytes, &img2_bytes, ignore, ratio) {
-        Ok(result_bytes) => {
-            println!("✅ Rust 智能拼接完成");
-            Ok(Some(PyBytes::new_bound(py, &result_bytes)))
+    match allow_threads_pooled(py, || image_hash::stitch_two_images_smart_debug(&img1_bytes, &img2_bytes, ignore, ratio, &options)) {
+        Ok(r) => {
+            println!(
+                "✅ Rust 智能拼接完成: 重叠{}行, 接缝y={}, 占比{:.2}%",
+                r.overlap_rows,
+                r.seam_y,
+                r.overlap_ratio * 100.0
+            );
+            Ok(Some((
+                PyBytes::new_bound(py, &r.image_bytes),
+                r.overlap_rows,
+                r.seam_y,
+                r.overlap_ratio,
+            )))
         }
         Err(e) => {
             eprintln!("⚠️  Rust 智能拼接失败: {}", e);
@@ -62,18 +305,20 @@ fn stitch_two_images_rust_smart_debug<'py>(
 /// 返回 (png_bytes, direction_str)，direction_str: "forward" 或 "reverse"
 /// "reverse" 时返回翻转态结果，调用方负责最终输出时翻转还原
 #[pyfunction]
-#[pyo3(signature = (img1_bytes, img2_bytes, ignore_right_pixels=None, min_overlap_ratio=None))]
+#[pyo3(signature = (img1_bytes, img2_bytes, ignore_right_pixels=None, min_overlap_ratio=None, options=None))]
 fn stitch_two_images_rust_smart_auto<'py>(
     py: Python<'py>,
     img1_bytes: Vec<u8>,
     img2_bytes: Vec<u8>,
     ignore_right_pixels: Option<u32>,
     min_overlap_ratio: Option<f32>,
+    options: Option<PyRef<'_, PyStitchOptions>>,
 ) -> PyResult<Option<(Bound<'py, PyBytes>, String)>> {
     let ignore = ignore_right_pixels.unwrap_or(20);
     let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
 
-    match stitch::stitch_two_images_smart_auto(&img1_bytes, &img2_bytes, ignore, ratio) {
+    match allow_threads_pooled(py, || stitch::stitch_two_images_smart_auto(&img1_bytes, &img2_bytes, ignore, ratio, &options)) {
         Ok((result_bytes, direction)) => {
             Ok(Some((PyBytes::new_bound(py, &result_bytes), direction)))
         }
@@ -86,22 +331,24 @@ fn stitch_two_images_rust_smart_auto<'py>(
 
 /// 自动方向检测（调试模式）
 #[pyfunction]
-#[pyo3(signature = (img1_bytes, img2_bytes, ignore_right_pixels=None, min_overlap_ratio=None))]
+#[pyo3(signature = (img1_bytes, img2_bytes, ignore_right_pixels=None, min_overlap_ratio=None, options=None))]
 fn stitch_two_images_rust_smart_auto_debug<'py>(
     py: Python<'py>,
     img1_bytes: Vec<u8>,
     img2_bytes: Vec<u8>,
     ignore_right_pixels: Option<u32>,
     min_overlap_ratio: Option<f32>,
+    options: Option<PyRef<'_, PyStitchOptions>>,
 ) -> PyResult<Option<(Bound<'py, PyBytes>, String)>> {
     let ignore = ignore_right_pixels.unwrap_or(20);
     let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
 
     println!("\n======================================================================");
     println!("🧭 Rust 自动方向检测拼接（调试模式）");
     println!("======================================================================");
 
-    match stitch::stitch_two_images_smart_auto_debug(&img1_bytes, &img2_bytes, ignore, ratio) {
+    match allow_threads_pooled(py, || stitch::stitch_two_images_smart_auto_debug(&img1_bytes, &img2_bytes, ignore, ratio, &options)) {
         Ok((result_bytes, direction)) => {
             println!("✅ 自动方向拼接完成，方向: {}", direction);
             Ok(Some((PyBytes::new_bound(py, &result_bytes), direction)))
@@ -113,12 +360,535 @@ fn stitch_two_images_rust_smart_auto_debug<'py>(
     }
 }
 
+/// 批量双图拼接（rayon 并行）
+/// 返回与输入顺序一致的列表，拼接失败的位置为 None
+#[pyfunction]
+#[pyo3(signature = (pairs, ignore_right_pixels=None, min_overlap_ratio=None, options=None))]
+fn batch_stitch_rust<'py>(
+    py: Python<'py>,
+    pairs: Vec<(Vec<u8>, Vec<u8>)>,
+    ignore_right_pixels: Option<u32>,
+    min_overlap_ratio: Option<f32>,
+    options: Option<PyRef<'_, PyStitchOptions>>,
+) -> PyResult<Vec<Option<Bound<'py, PyBytes>>>> {
+    use rayon::prelude::*;
+
+    let ignore = ignore_right_pixels.unwrap_or(20);
+    let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
+
+    let results: Vec<Option<Vec<u8>>> = allow_threads_pooled(py, || {
+        pairs
+            .par_iter()
+            .enumerate()
+            .map(|(i, (img1, img2))| {
+                match image_hash::stitch_two_images(img1, img2, ignore, ratio, &options) {
+                    Ok(r) => Some(r.image_bytes),
+                    Err(e) => {
+                        eprintln!("⚠️  Rust 批量拼接第{}对失败: {}", i, e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    });
+
+    Ok(results
+        .into_iter()
+        .map(|r| r.map(|bytes| PyBytes::new_bound(py, &bytes)))
+        .collect())
+}
+
+/// 多图顺序拼接为一张长图
+/// progress_callback(current, total) 每处理完一张图调用一次，回调抛出的异常会中止拼接并原样抛出
+/// cancel_token 被取消时抛出 RuntimeError("Cancelled")
+/// 返回 png_bytes，拼接失败返回 None
+#[pyfunction]
+#[pyo3(signature = (images, ignore_right_pixels=None, min_overlap_ratio=None, options=None, progress_callback=None, cancel_token=None))]
+fn stitch_images_rust<'py>(
+    py: Python<'py>,
+    images: Vec<Vec<u8>>,
+    ignore_right_pixels: Option<u32>,
+    min_overlap_ratio: Option<f32>,
+    options: Option<PyRef<'_, PyStitchOptions>>,
+    progress_callback: Option<PyObject>,
+    cancel_token: Option<PyRef<'_, CancelToken>>,
+) -> PyResult<Option<Bound<'py, PyBytes>>> {
+    let ignore = ignore_right_pixels.unwrap_or(20);
+    let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
+
+    // 每次回调重新获取 GIL；回调异常先保存，拼接中止后再抛给调用方
+    let callback_error: std::sync::Mutex<Option<PyErr>> = std::sync::Mutex::new(None);
+    let progress = |current: usize, total: usize| -> Result<(), String> {
+        let Some(cb) = progress_callback.as_ref() else {
+            return Ok(());
+        };
+        Python::with_gil(|py| cb.call1(py, (current, total)).map(|_| ())).map_err(|e| {
+            if let Ok(mut slot) = callback_error.lock() {
+                *slot = Some(e);
+            }
+            "progress callback raised an exception".to_string()
+        })
+    };
+
+    let cancel = cancel_token.map(|token| token.flag.clone());
+
+    let result = allow_threads_pooled(py, || {
+        image_hash::stitch_images(&images, ignore, ratio, &options, Some(&progress), cancel.as_deref())
+    });
+
+    if let Some(e) = callback_error.into_inner().ok().flatten() {
+        return Err(e);
+    }
+    match result {
+        Ok(result_bytes) => Ok(Some(PyBytes::new_bound(py, &result_bytes))),
+        Err(e) if e == image_hash::CANCELLED_ERROR => Err(pyo3::exceptions::PyRuntimeError::new_err(e)),
+        Err(e) => {
+            eprintln!("⚠️  Rust 多图拼接失败: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+// ========== 横向拼接 ==========
+
+/// 横向双图拼接（左右滚动截图）
+/// options 中仅 blend_seam / blend_height / max_output_pixels 生效
+#[pyfunction]
+#[pyo3(signature = (left_bytes, right_bytes, ignore_bottom_pixels=None, min_overlap_ratio=None, options=None))]
+fn stitch_two_images_horizontal_rust<'py>(
+    py: Python<'py>,
+    left_bytes: Vec<u8>,
+    right_bytes: Vec<u8>,
+    ignore_bottom_pixels: Option<u32>,
+    min_overlap_ratio: Option<f32>,
+    options: Option<PyRef<'_, PyStitchOptions>>,
+) -> PyResult<Option<Bound<'py, PyBytes>>> {
+    let ignore = ignore_bottom_pixels.unwrap_or(20);
+    let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
+
+    match allow_threads_pooled(py, || image_hash::stitch_two_images_horizontal(&left_bytes, &right_bytes, ignore, ratio, &options)) {
+        Ok(result_bytes) => Ok(Some(PyBytes::new_bound(py, &result_bytes))),
+        Err(e) => {
+            eprintln!("⚠️  Rust 横向拼接失败: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// 横向双图拼接（调试模式）
+#[pyfunction]
+#[pyo3(signature = (left_bytes, right_bytes, ignore_bottom_pixels=None, min_overlap_ratio=None, options=None))]
+fn stitch_two_images_horizontal_rust_debug<'py>(
+    py: Python<'py>,
+    left_bytes: Vec<u8>,
+    right_bytes: Vec<u8>,
+    ignore_bottom_pixels: Option<u32>,
+    min_overlap_ratio: Option<f32>,
+    options: Option<PyRef<'_, PyStitchOptions>>,
+) -> PyResult<Option<Bound<'py, PyBytes>>> {
+    let ignore = ignore_bottom_pixels.unwrap_or(20);
+    let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
+
+    println!("\n======================================================================");
+    println!("↔️  Rust 横向拼接接口（调试模式）");
+    println!("======================================================================");
+
+    match allow_threads_pooled(py, || image_hash::stitch_two_images_horizontal_debug(&left_bytes, &right_bytes, ignore, ratio, &options)) {
+        Ok(result_bytes) => {
+            println!("✅ Rust 横向拼接完成");
+            Ok(Some(PyBytes::new_bound(py, &result_bytes)))
+        }
+        Err(e) => {
+            eprintln!("⚠️  Rust 横向拼接失败: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// 横向智能拼接（多候选纠错）
+#[pyfunction]
+#[pyo3(signature = (left_bytes, right_bytes, ignore_bottom_pixels=None, min_overlap_ratio=None, options=None))]
+fn stitch_two_images_horizontal_rust_smart<'py>(
+    py: Python<'py>,
+    left_bytes: Vec<u8>,
+    right_bytes: Vec<u8>,
+    ignore_bottom_pixels: Option<u32>,
+    min_overlap_ratio: Option<f32>,
+    options: Option<PyRef<'_, PyStitchOptions>>,
+) -> PyResult<Option<Bound<'py, PyBytes>>> {
+    let ignore = ignore_bottom_pixels.unwrap_or(20);
+    let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
+
+    match allow_threads_pooled(py, || image_hash::stitch_two_images_horizontal_smart(&left_bytes, &right_bytes, ignore, ratio, &options)) {
+        Ok(result_bytes) => Ok(Some(PyBytes::new_bound(py, &result_bytes))),
+        Err(e) => {
+            eprintln!("⚠️  Rust 横向智能拼接失败: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// 横向智能拼接（调试模式）
+#[pyfunction]
+#[pyo3(signature = (left_bytes, right_bytes, ignore_bottom_pixels=None, min_overlap_ratio=None, options=None))]
+fn stitch_two_images_horizontal_rust_smart_debug<'py>(
+    py: Python<'py>,
+    left_bytes: Vec<u8>,
+    right_bytes: Vec<u8>,
+    ignore_bottom_pixels: Option<u32>,
+    min_overlap_ratio: Option<f32>,
+    options: Option<PyRef<'_, PyStitchOptions>>,
+) -> PyResult<Option<Bound<'py, PyBytes>>> {
+    let ignore = ignore_bottom_pixels.unwrap_or(20);
+    let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
+
+    println!("\n======================================================================");
+    println!("↔️  Rust 横向智能拼接接口（多候选纠错 + 调试模式）");
+    println!("======================================================================");
+
+    match allow_threads_pooled(py, || image_hash::stitch_two_images_horizontal_smart_debug(&left_bytes, &right_bytes, ignore, ratio, &options)) {
+        Ok(result_bytes) => {
+            println!("✅ Rust 横向智能拼接完成");
+            Ok(Some(PyBytes::new_bound(py, &result_bytes)))
+        }
+        Err(e) => {
+            eprintln!("⚠️  Rust 横向智能拼接失败: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+// ========== 自动方向 ==========
+
+/// 判断两张截图的滚动方向
+/// 返回 0 表示纵向（上下拼接），1 表示横向（左右拼接）
+#[pyfunction]
+fn detect_scroll_direction(py: Python<'_>, img1_bytes: Vec<u8>, img2_bytes: Vec<u8>) -> PyResult<u8> {
+    allow_threads_pooled(py, || image_hash::detect_scroll_direction(&img1_bytes, &img2_bytes))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// 自动判断方向后拼接
+/// 纵向时 ignore_pixels 为忽略的右侧像素，横向时为忽略的底部像素（横向时拼接选项仅 blend_seam / blend_height / max_output_pixels 生效）
+/// 返回 (png_bytes, direction)，direction 同 detect_scroll_direction，失败返回 None
+#[pyfunction]
+#[pyo3(signature = (img1_bytes, img2_bytes, ignore_pixels=None, min_overlap_ratio=None, options=None))]
+fn stitch_auto_rust<'py>(
+    py: Python<'py>,
+    img1_bytes: Vec<u8>,
+    img2_bytes: Vec<u8>,
+    ignore_pixels: Option<u32>,
+    min_overlap_ratio: Option<f32>,
+    options: Option<PyRef<'_, PyStitchOptions>>,
+) -> PyResult<Option<(Bound<'py, PyBytes>, u8)>> {
+    let ignore = ignore_pixels.unwrap_or(20);
+    let ratio = min_overlap_ratio.unwrap_or(0.01);
+    let options = stitch_options(options);
+
+    match allow_threads_pooled(py, || image_hash::stitch_auto(&img1_bytes, &img2_bytes, ignore, ratio, &options)) {
+        Ok((result_bytes, direction)) => Ok(Some((PyBytes::new_bound(py, &result_bytes), direction))),
+        Err(e) => {
+            eprintln!("⚠️  Rust 自动方向拼接失败: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+// ========== 行哈希 ==========
+
+/// 计算逐行哈希（可缓存到磁盘，配合 serialize_hashes 使用）
+///
+/// column_sample_rate: 列采样率（0.05 ~ 1.0，默认 1.0 使用全部列），小于 1.0 时按列采样，宽图可显著加速。
+/// 仅影响本函数，拼接函数内部的行哈希始终使用全部列
+#[pyfunction]
+#[pyo3(signature = (image_bytes, ignore_right_pixels=20, column_sample_rate=1.0))]
+fn compute_row_hashes(
+    py: Python<'_>,
+    image_bytes: Vec<u8>,
+    ignore_right_pixels: u32,
+    column_sample_rate: f32,
+) -> PyResult<Vec<u64>> {
+    allow_threads_pooled(py, || hash::compute_row_hashes(&image_bytes, ignore_right_pixels, column_sample_rate))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// 计算逐列哈希（横向拼接使用，可配合 serialize_hashes 缓存）
+#[pyfunction]
+#[pyo3(signature = (image_bytes, ignore_bottom_pixels=20))]
+fn compute_column_hashes(py: Python<'_>, image_bytes: Vec<u8>, ignore_bottom_pixels: u32) -> PyResult<Vec<u64>> {
+    allow_threads_pooled(py, || image_hash::compute_column_hashes(&image_bytes, ignore_bottom_pixels))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+// ========== 行 SSIM ==========
+
+/// 计算相邻两行的简化 SSIM 序列（长度为 height - 1），对渐变和亮度漂移更稳健
+#[pyfunction]
+fn compute_row_ssim(py: Python<'_>, image_bytes: Vec<u8>) -> PyResult<Vec<f32>> {
+    allow_threads_pooled(py, || ssim::compute_row_ssim(&image_bytes))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// 在两段行 SSIM 序列中找最长公共子序列
+/// 返回 (seq1_start, seq2_start, length)，未找到时为 (-1, -1, 0)
+#[pyfunction]
+#[pyo3(signature = (ssim1, ssim2, threshold=0.98))]
+fn find_overlap_by_ssim(py: Python<'_>, ssim1: Vec<f32>, ssim2: Vec<f32>, threshold: f32) -> (i32, i32, usize) {
+    allow_threads_pooled(py, || ssim::find_overlap_by_ssim(&ssim1, &ssim2, threshold))
+}
+
+// ========== 拼接质量校验 ==========
+
+/// 拼接后质量校验，返回接缝附近的相似度评分（0.0 ~ 1.0）
+#[pyfunction]
+fn validate_stitch_rust(
+    py: Python<'_>,
+    img1_bytes: Vec<u8>,
+    img2_bytes: Vec<u8>,
+    result_bytes: Vec<u8>,
+    seam_y: u32,
+) -> PyResult<f64> {
+    allow_threads_pooled(py, || image_hash::validate_stitch(&img1_bytes, &img2_bytes, &result_bytes, seam_y))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// 检测图像是否按固定高度周期性重复，返回周期高度（像素），无周期返回 None
+#[pyfunction]
+#[pyo3(signature = (image_bytes, min_tile_height=50))]
+fn detect_tiled_image(py: Python<'_>, image_bytes: Vec<u8>, min_tile_height: u32) -> PyResult<Option<u32>> {
+    allow_threads_pooled(py, || image_hash::detect_tiled_image(&image_bytes, min_tile_height))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+// ========== 图像查找 ==========
+
+/// 在大图中查找小图出现的位置
+/// 返回左上角坐标 (x, y)，未找到返回 None
+#[pyfunction]
+fn find_subimage(py: Python<'_>, haystack_bytes: Vec<u8>, needle_bytes: Vec<u8>) -> PyResult<Option<(u32, u32)>> {
+    allow_threads_pooled(py, || image_hash::find_subimage(&haystack_bytes, &needle_bytes))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// 近似重复图片去重（dHash + 连通分量）
+/// 返回每组代表图片的下标列表
+#[pyfunction]
+#[pyo3(signature = (image_bytes_list, threshold=5))]
+fn deduplicate_images(py: Python<'_>, image_bytes_list: Vec<Vec<u8>>, threshold: u32) -> PyResult<Vec<usize>> {
+    allow_threads_pooled(py, || image_hash::deduplicate_images(&image_bytes_list, threshold))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// 批量计算哈希（并行），method 为 "dhash" / "ahash" / "phash"
+/// 返回 (hashes, errors) 两个与输入等长的列表：成功的位置 hashes 为哈希值、errors 为 None，
+/// 失败的位置 hashes 为 None、errors 为错误信息
+#[pyfunction]
+#[pyo3(signature = (image_bytes_list, method="dhash", hash_size=8))]
+fn batch_compute_hash(
+    py: Python<'_>,
+    image_bytes_list: Vec<Vec<u8>>,
+    method: &str,
+    hash_size: usize,
+) -> (Vec<Option<u64>>, Vec<Option<String>>) {
+    allow_threads_pooled(py, || image_hash::batch_compute_hash(&image_bytes_list, method, hash_size))
+        .into_iter()
+        .map(|result| match result {
+            Ok(hash) => (Some(hash), None),
+            Err(e) => (None, Some(e)),
+        })
+        .unzip()
+}
+
+/// 将哈希序列序列化为字节（每个 u64 为 8 字节小端序），便于缓存到磁盘
+#[pyfunction]
+fn serialize_hashes(py: Python<'_>, hashes: Vec<u64>) -> Bound<'_, PyBytes> {
+    PyBytes::new_bound(py, &image_hash::serialize_hashes(&hashes))
+}
+
+/// 从字节恢复哈希序列（长度必须是 8 的整数倍）
+#[pyfunction]
+fn deserialize_hashes(data: &[u8]) -> PyResult<Vec<u64>> {
+    image_hash::deserialize_hashes(data).map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// 计算多尺度 dHash（尺寸 4、8、16，按顺序拼接为 u64 列表）
+#[pyfunction]
+fn compute_multiscale_dhash(image_bytes: Vec<u8>) -> PyResult<Vec<u64>> {
+    image_hash::compute_multiscale_dhash(&image_bytes)
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// 多尺度相似度（0.0 ~ 1.0），输入为 compute_multiscale_dhash 的结果
+#[pyfunction]
+fn multiscale_similarity(hashes1: Vec<u64>, hashes2: Vec<u64>) -> PyResult<f64> {
+    let expected = image_hash::multiscale_dhash_len();
+    if hashes1.len() != expected || hashes2.len() != expected {
+        return Err(pyo3::exceptions::PyRuntimeError::new_err(format!(
+            "multiscale hashes must have {} elements, got {} and {}",
+            expected,
+            hashes1.len(),
+            hashes2.len()
+        )));
+    }
+    Ok(image_hash::multiscale_similarity(&hashes1, &hashes2))
+}
+
+/// 计算 N×N 汉明距离矩阵（对称，对角线为 0）
+#[pyfunction]
+fn hash_distance_matrix(py: Python<'_>, hashes: Vec<u64>) -> Vec<Vec<u32>> {
+    allow_threads_pooled(py, || image_hash::hash_distance_matrix(&hashes))
+}
+
+/// 计算带遮罩的 dHash（遮罩区域 (x, y, w, h) 涂黑后再计算）
+#[pyfunction]
+#[pyo3(signature = (image_bytes, mask_rect, hash_size=8))]
+fn compute_dhash_masked(image_bytes: Vec<u8>, mask_rect: (u32, u32, u32, u32), hash_size: usize) -> PyResult<u64> {
+    image_hash::compute_dhash_masked(&image_bytes, mask_rect, hash_size)
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+// ========== 图像工具 ==========
+
+/// 按矩形 (x, y, w, h) 裁剪，超出图像的部分自动截断，返回 PNG 字节
+#[pyfunction]
+fn crop_to_bounding_box<'py>(
+    py: Python<'py>,
+    image_bytes: Vec<u8>,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let png = allow_threads_pooled(py, || {
+        let img = utils::load_image(&image_bytes)?;
+        utils::encode_png(&utils::crop_to_bounding_box(img, x, y, w, h))
+    })
+    .map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+    Ok(PyBytes::new_bound(py, &png))
+}
+
+/// 自动去除纯色边框（以左上角像素为边框色），返回 PNG 字节
+#[pyfunction]
+#[pyo3(signature = (image_bytes, threshold=10))]
+fn smart_crop<'py>(py: Python<'py>, image_bytes: Vec<u8>, threshold: u8) -> PyResult<Bound<'py, PyBytes>> {
+    let png = allow_threads_pooled(py, || {
+        let img = utils::load_image(&image_bytes)?;
+        utils::encode_png(&utils::smart_crop(&img, threshold))
+    })
+    .map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+    Ok(PyBytes::new_bound(py, &png))
+}
+
+/// 检测纯色行（行内亮度与行均值相差不超过 threshold），返回行号列表
+#[pyfunction]
+#[pyo3(signature = (image_bytes, threshold=5))]
+fn detect_solid_color_rows(py: Python<'_>, image_bytes: Vec<u8>, threshold: u8) -> PyResult<Vec<u32>> {
+    allow_threads_pooled(py, || {
+        utils::load_image(&image_bytes).map(|img| utils::detect_solid_color_rows(&img, threshold))
+    })
+    .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// 将图像居中放到 w × h 的纯色画布上，返回 PNG 字节
+#[pyfunction]
+#[pyo3(signature = (image_bytes, w, h, color=(255, 255, 255, 255)))]
+fn pad_image<'py>(
+    py: Python<'py>,
+    image_bytes: Vec<u8>,
+    w: u32,
+    h: u32,
+    color: (u8, u8, u8, u8),
+) -> PyResult<Bound<'py, PyBytes>> {
+    let (r, g, b, a) = color;
+    let png = allow_threads_pooled(py, || {
+        let img = utils::load_image(&image_bytes)?;
+        utils::encode_png(&utils::pad_image_to_target(&img, w, h, [r, g, b, a]))
+    })
+    .map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+    Ok(PyBytes::new_bound(py, &png))
+}
+
+/// 保持宽高比缩放到 w × h 以内，剩余部分用 bg 填充，返回 PNG 字节
+#[pyfunction]
+#[pyo3(signature = (image_bytes, w, h, bg=(0, 0, 0, 255)))]
+fn resize_letterbox<'py>(
+    py: Python<'py>,
+    image_bytes: Vec<u8>,
+    w: u32,
+    h: u32,
+    bg: (u8, u8, u8, u8),
+) -> PyResult<Bound<'py, PyBytes>> {
+    let (r, g, b, a) = bg;
+    let png = allow_threads_pooled(py, || {
+        let img = utils::load_image(&image_bytes)?;
+        utils::encode_png(&utils::resize_letterbox(&img, w, h, [r, g, b, a]))
+    })
+    .map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+    Ok(PyBytes::new_bound(py, &png))
+}
+
+/// 自动检测右侧滚动条的 x 坐标（可用于动态设置 ignore_right_pixels），未检测到返回 None
+#[pyfunction]
+fn detect_scrollbar_x(py: Python<'_>, image_bytes: Vec<u8>) -> PyResult<Option<u32>> {
+    allow_threads_pooled(py, || utils::load_image(&image_bytes).map(|img| utils::detect_scrollbar_x(&img)))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
+/// 计算清晰度（拉普拉斯方差，越大越清晰），可用于拼接前剔除模糊帧
+#[pyfunction]
+fn compute_sharpness(py: Python<'_>, image_bytes: Vec<u8>) -> PyResult<f32> {
+    allow_threads_pooled(py, || utils::load_image(&image_bytes).map(|img| utils::compute_sharpness(&img)))
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)
+}
+
 /// Python 模块定义
 #[pymodule]
 fn longstitch(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyStitchOptions>()?;
+    m.add_class::<CancelToken>()?;
+    m.add_function(wrap_pyfunction!(set_thread_pool_size, m)?)?;
+    m.add_function(wrap_pyfunction!(get_thread_pool_size, m)?)?;
+    m.add_function(wrap_pyfunction!(stitch_two_images_rust, m)?)?;
+    m.add_function(wrap_pyfunction!(stitch_two_images_rust_debug, m)?)?;
+    m.add_function(wrap_pyfunction!(batch_stitch_rust, m)?)?;
+    m.add_function(wrap_pyfunction!(stitch_images_rust, m)?)?;
+    m.add_function(wrap_pyfunction!(stitch_two_images_horizontal_rust, m)?)?;
+    m.add_function(wrap_pyfunction!(stitch_two_images_horizontal_rust_debug, m)?)?;
+    m.add_function(wrap_pyfunction!(stitch_two_images_horizontal_rust_smart, m)?)?;
+    m.add_function(wrap_pyfunction!(stitch_two_images_horizontal_rust_smart_debug, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_scroll_direction, m)?)?;
+    m.add_function(wrap_pyfunction!(stitch_auto_rust, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_row_ssim, m)?)?;
+    m.add_function(wrap_pyfunction!(find_overlap_by_ssim, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_stitch_rust, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_row_hashes, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_column_hashes, m)?)?;
     m.add_function(wrap_pyfunction!(stitch_two_images_rust_smart, m)?)?;
     m.add_function(wrap_pyfunction!(stitch_two_images_rust_smart_debug, m)?)?;
     m.add_function(wrap_pyfunction!(stitch_two_images_rust_smart_auto, m)?)?;
     m.add_function(wrap_pyfunction!(stitch_two_images_rust_smart_auto_debug, m)?)?;
+    m.add_function(wrap_pyfunction!(find_subimage, m)?)?;
+    m.add_function(wrap_pyfunction!(deduplicate_images, m)?)?;
+    m.add_function(wrap_pyfunction!(hash_distance_matrix, m)?)?;
+    m.add_function(wrap_pyfunction!(batch_compute_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_multiscale_dhash, m)?)?;
+    m.add_function(wrap_pyfunction!(multiscale_similarity, m)?)?;
+    m.add_function(wrap_pyfunction!(serialize_hashes, m)?)?;
+    m.add_function(wrap_pyfunction!(deserialize_hashes, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_dhash_masked, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_tiled_image, m)?)?;
+    m.add_function(wrap_pyfunction!(crop_to_bounding_box, m)?)?;
+    m.add_function(wrap_pyfunction!(smart_crop, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_solid_color_rows, m)?)?;
+    m.add_function(wrap_pyfunction!(pad_image, m)?)?;
+    m.add_function(wrap_pyfunction!(resize_letterbox, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_scrollbar_x, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_sharpness, m)?)?;
     Ok(())
 }
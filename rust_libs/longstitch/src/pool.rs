@@ -0,0 +1,63 @@
+//! 并行计算使用的线程池
+//!
+//! 默认直接使用 rayon 全局线程池（线程数 = CPU 核数）；调用 set_size 后，
+//! 经 install 执行的计算改用独立的线程池，用于限制本库占用的 CPU
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::{Arc, RwLock};
+
+/// None 表示使用全局线程池
+static POOL: RwLock<Option<Arc<ThreadPool>>> = RwLock::new(None);
+
+fn current_pool() -> Option<Arc<ThreadPool>> {
+    POOL.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// 设置独立线程池的线程数，0 表示恢复使用全局线程池
+///
+/// 已在旧线程池中运行的计算会继续使用旧线程池直到结束
+pub fn set_size(threads: usize) -> Result<(), String> {
+    let pool = if threads == 0 {
+        None
+    } else {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("longstitch-{}", i))
+            .build()
+            .map_err(|e| format!("创建线程池失败: {}", e))?;
+        Some(Arc::new(pool))
+    };
+    *POOL.write().unwrap_or_else(|e| e.into_inner()) = pool;
+    Ok(())
+}
+
+/// 当前使用的线程数
+pub fn current_size() -> usize {
+    current_pool().map_or_else(rayon::current_num_threads, |pool| pool.current_num_threads())
+}
+
+/// 在当前线程池中执行 op，op 内部的 par_iter 等并行操作都使用该线程池
+pub fn install<R: Send>(op: impl FnOnce() -> R + Send) -> R {
+    match current_pool() {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedicated_pool_limits_threads() {
+        set_size(2).unwrap();
+        assert_eq!(current_size(), 2);
+        assert_eq!(install(rayon::current_num_threads), 2);
+        let name = install(|| std::thread::current().name().map(str::to_string));
+        assert!(name.is_some_and(|n| n.starts_with("longstitch-")));
+
+        set_size(0).unwrap();
+        assert_eq!(current_size(), rayon::current_num_threads());
+        assert!(install(|| std::thread::current().name().map(str::to_string)).is_none_or(|n| !n.starts_with("longstitch-")));
+    }
+}
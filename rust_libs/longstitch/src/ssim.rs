@@ -0,0 +1,153 @@
+/// 行 SSIM 模块 - 渐变/亮度偏移场景下的重叠检测
+///
+/// 行哈希对渐变背景和整体亮度漂移比较敏感（量化后整行哈希全部改变），
+/// 这里改用相邻两行的简化 SSIM（仅亮度 + 对比度项）作为每行的特征，
+/// 整体亮度偏移只会让特征值产生极小变化。
+use rayon::prelude::*;
+
+/// SSIM 稳定常数 C1 = (0.01 × 255)²
+const SSIM_C1: f64 = 6.5025;
+/// SSIM 稳定常数 C2 = (0.03 × 255)²
+const SSIM_C2: f64 = 58.5225;
+
+/// 计算相邻两行的简化 SSIM（亮度 + 对比度，忽略结构项以提速）
+///
+/// 返回长度为 height - 1 的序列，第 i 项为第 i 行与第 i+1 行的 SSIM
+pub fn compute_row_ssim(image_bytes: &[u8]) -> Result<Vec<f32>, String> {
+    let img =
+        image::load_from_memory(image_bytes).map_err(|e| format!("Failed to load image: {}", e))?;
+    Ok(compute_row_ssim_from_gray(&img.to_luma8()))
+}
+
+/// 直接从灰度图计算相邻行 SSIM
+pub fn compute_row_ssim_from_gray(gray: &image::GrayImage) -> Vec<f32> {
+    let width = gray.width() as usize;
+    let height = gray.height() as usize;
+    if width == 0 || height < 2 {
+        return Vec::new();
+    }
+
+    // 每行的 (均值, 方差)
+    let raw = gray.as_raw();
+    let stats: Vec<(f64, f64)> = raw
+        .par_chunks_exact(width)
+        .map(|row| {
+            let n = row.len() as f64;
+            let mean = row.iter().map(|&v| v as f64).sum::<f64>() / n;
+            let var = row.iter().map(|&v| (v as f64 - mean).powi(2)).sum::<f64>() / n;
+            (mean, var)
+        })
+        .collect();
+
+    stats
+        .windows(2)
+        .map(|pair| {
+            let (mu_x, var_x) = pair[0];
+            let (mu_y, var_y) = pair[1];
+            let luminance = (2.0 * mu_x * mu_y + SSIM_C1) / (mu_x * mu_x + mu_y * mu_y + SSIM_C1);
+            let contrast =
+                (2.0 * var_x.sqrt() * var_y.sqrt() + SSIM_C2) / (var_x + var_y + SSIM_C2);
+            (luminance * contrast) as f32
+        })
+        .collect()
+}
+
+/// 在两段行 SSIM 序列中找最长的公共子序列（连续），用于定位重叠区域
+///
+/// 两个 SSIM 值满足 1 - |a - b| >= threshold 时视为相同
+///
+/// 返回: (seq1_start, seq2_start, length)，未找到时返回 (-1, -1, 0)
+pub fn find_overlap_by_ssim(ssim1: &[f32], ssim2: &[f32], threshold: f32) -> (i32, i32, usize) {
+    let m = ssim1.len();
+    let n = ssim2.len();
+    if m == 0 || n == 0 {
+        return (-1, -1, 0);
+    }
+
+    let max_diff = 1.0 - threshold;
+
+    // 动态规划（滚动数组，与行哈希的最长公共子串相同）
+    let mut prev = vec![0usize; n + 1];
+    let mut curr = vec![0usize; n + 1];
+    let mut max_length = 0usize;
+    let mut ending_pos_i = 0;
+    let mut ending_pos_j = 0;
+
+    for i in 1..=m {
+        for j in 1..=n {
+            curr[j] = if (ssim1[i - 1] - ssim2[j - 1]).abs() <= max_diff {
+                prev[j - 1] + 1
+            } else {
+                0
+            };
+            if curr[j] > max_length {
+                max_length = curr[j];
+                ending_pos_i = i;
+                ending_pos_j = j;
+            }
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    if max_length == 0 {
+        return (-1, -1, 0);
+    }
+
+    (
+        (ending_pos_i - max_length) as i32,
+        (ending_pos_j - max_length) as i32,
+        max_length,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_ssim_overlap_with_brightness_shift() {
+        // 每行亮度和纹理强度都不同（伪随机、无周期）的页面
+        let page = RgbaImage::from_fn(60, 300, |x, y| {
+            let mix = y.wrapping_mul(2_654_435_761) >> 8;
+            let v = (60 + mix % 140 + (x * (mix % 7 + 1)) % 50) as u8;
+            Rgba([v, v, v, 255])
+        });
+        let encode = |img: RgbaImage| {
+            let mut bytes = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .unwrap();
+            bytes
+        };
+
+        // 底图整体变亮 6 级（例如截图期间屏幕亮度变化）
+        let top = image::imageops::crop_imm(&page, 0, 0, 60, 180).to_image();
+        let mut bottom = image::imageops::crop_imm(&page, 0, 100, 60, 200).to_image();
+        for p in bottom.pixels_mut() {
+            for c in 0..3 {
+                p[c] += 6;
+            }
+        }
+        let top = encode(top);
+        let bottom = encode(bottom);
+
+        // 亮度偏移后行哈希找不到完整的 80 行重叠
+        let hashes1 = crate::hash::compute_row_hashes(&top, 0, 1.0).unwrap();
+        let hashes2 = crate::hash::compute_row_hashes(&bottom, 0, 1.0).unwrap();
+        let (_, _, hash_len) =
+            crate::image_hash::find_longest_common_substring(&hashes1, &hashes2, 0.1, 0);
+        assert!(hash_len < 79, "hash_len = {}", hash_len);
+
+        // SSIM 序列能定位到 [100, 179) 与 [0, 79) 的重叠
+        let ssim1 = compute_row_ssim(&top).unwrap();
+        let ssim2 = compute_row_ssim(&bottom).unwrap();
+        assert_eq!(ssim1.len(), 179);
+        assert_eq!(find_overlap_by_ssim(&ssim1, &ssim2, 0.97), (100, 0, 79));
+    }
+
+    #[test]
+    fn test_find_overlap_by_ssim_no_match() {
+        assert_eq!(find_overlap_by_ssim(&[0.9, 0.8], &[0.1, 0.2], 0.99), (-1, -1, 0));
+        assert_eq!(find_overlap_by_ssim(&[], &[0.1], 0.99), (-1, -1, 0));
+    }
+}
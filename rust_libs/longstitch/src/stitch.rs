@@ -8,6 +8,7 @@ use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
 use std::io::Cursor;
 
 use crate::hash::compute_row_hashes_from_rgba;
+use crate::image_hash::{align_widths, blend_seam_rows, StitchOptions};
 use crate::lcs::find_top_common_substrings;
 
 // ========== 内部工具函数 ==========
@@ -114,17 +115,16 @@ fn select_best_candidate(
 
 /// 用候选参数执行实际的像素拼接
 ///
-/// 返回 RGBA 字节 + 宽高
+/// 返回 RGBA 字节 + 宽高；结果超出 options.max_output_pixels 时返回 Err
 fn do_pixel_stitch(
     img1_rgba: &image::RgbaImage,
     img2_rgba: &image::RgbaImage,
     final_width: u32,
     height2: u32,
-    start_i: i32,
-    start_j: i32,
-    overlap_length: usize,
+    (start_i, start_j, overlap_length): (i32, i32, usize),
+    options: &StitchOptions,
     debug: bool,
-) -> (Vec<u8>, u32, u32) {
+) -> Result<(Vec<u8>, u32, u32), String> {
     let img1_keep_height = (start_i as usize + overlap_length) as u32;
     let img2_skip_height = (start_j as usize + overlap_length) as u32;
     let img2_keep_height = height2.saturating_sub(img2_skip_height);
@@ -137,6 +137,8 @@ fn do_pixel_stitch(
         );
     }
 
+    options.check_output_size(final_width, result_height)?;
+
     let row_bytes = (final_width * 4) as usize;
     let mut result_buf: Vec<u8> = vec![0u8; row_bytes * result_height as usize];
 
@@ -156,7 +158,14 @@ fn do_pixel_stitch(
             .copy_from_slice(&img2_raw[src_start..src_start + row_bytes]);
     }
 
-    (result_buf, final_width, result_height)
+    if options.blend_seam {
+        blend_seam_rows(
+            &mut result_buf, img1_rgba, img2_rgba,
+            img1_keep_height, img2_skip_height, options.blend_height,
+        );
+    }
+
+    Ok((result_buf, final_width, result_height))
 }
 
 /// RGBA 字节编码为 PNG
@@ -180,6 +189,7 @@ fn smart_stitch_core(
     final_width: u32,
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
+    options: &StitchOptions,
     debug: bool,
 ) -> Result<(Vec<u8>, u32, u32), String> {
     let height2 = img2_rgba.height();
@@ -222,7 +232,7 @@ fn smart_stitch_core(
     );
 
     // 智能选择
-    let (start_i, start_j, overlap_length) = select_best_candidate(
+    let best = select_best_candidate(
         &candidates,
         search_start,
         img1_len,
@@ -231,10 +241,7 @@ fn smart_stitch_core(
     )?;
 
     // 执行像素拼接
-    Ok(do_pixel_stitch(
-        img1_rgba, img2_rgba, final_width, height2,
-        start_i, start_j, overlap_length, debug,
-    ))
+    do_pixel_stitch(img1_rgba, img2_rgba, final_width, height2, best, options, debug)
 }
 
 // ========== 公开 API ==========
@@ -245,8 +252,9 @@ pub fn stitch_two_images_smart(
     img2_bytes: &[u8],
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
+    options: &StitchOptions,
 ) -> Result<Vec<u8>, String> {
-    stitch_two_images_smart_internal(img1_bytes, img2_bytes, ignore_right_pixels, min_overlap_ratio, false)
+    stitch_two_images_smart_internal(img1_bytes, img2_bytes, ignore_right_pixels, min_overlap_ratio, options, false)
 }
 
 /// 智能双图拼接（调试模式）
@@ -255,8 +263,9 @@ pub fn stitch_two_images_smart_debug(
     img2_bytes: &[u8],
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
+    options: &StitchOptions,
 ) -> Result<Vec<u8>, String> {
-    stitch_two_images_smart_internal(img1_bytes, img2_bytes, ignore_right_pixels, min_overlap_ratio, true)
+    stitch_two_images_smart_internal(img1_bytes, img2_bytes, ignore_right_pixels, min_overlap_ratio, options, true)
 }
 
 fn stitch_two_images_smart_internal(
@@ -264,10 +273,11 @@ fn stitch_two_images_smart_internal(
     img2_bytes: &[u8],
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
+    options: &StitchOptions,
     debug: bool,
 ) -> Result<Vec<u8>, String> {
     // 加载图片
-    let mut img1 = image::load_from_memory(img1_bytes)
+    let img1 = image::load_from_memory(img1_bytes)
         .map_err(|e| format!("Failed to load image 1: {}", e))?;
     let img2 = image::load_from_memory(img2_bytes)
         .map_err(|e| format!("Failed to load image 2: {}", e))?;
@@ -279,12 +289,8 @@ fn stitch_two_images_smart_internal(
         println!("处理图片: ({}, {}) + ({}, {})", width1, height1, width2, height2);
     }
 
-    // 宽度对齐
-    if width1 != width2 {
-        if debug { println!("调整图片宽度: {} -> {}", width1, width2); }
-        let new_height1 = (height1 as f32 * width2 as f32 / width1 as f32) as u32;
-        img1 = img1.resize_exact(width2, new_height1, image::imageops::FilterType::Lanczos3);
-    }
+    // 宽度对齐（按 options.width_strategy 缩放 / 填充 / 裁剪）
+    let (img1, img2) = align_widths(img1, img2, options, debug);
 
     let (final_width, _) = img1.dimensions();
     let img1_rgba = img1.to_rgba8();
@@ -292,7 +298,7 @@ fn stitch_two_images_smart_internal(
 
     let (result_buf, w, h) = smart_stitch_core(
         &img1_rgba, &img2_rgba, final_width,
-        ignore_right_pixels, min_overlap_ratio, debug,
+        ignore_right_pixels, min_overlap_ratio, options, debug,
     )?;
 
     encode_png(result_buf, w, h)
@@ -318,9 +324,10 @@ pub fn stitch_two_images_smart_auto(
     img2_bytes: &[u8],
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
+    options: &StitchOptions,
 ) -> Result<(Vec<u8>, String), String> {
     stitch_two_images_smart_auto_internal(
-        img1_bytes, img2_bytes, ignore_right_pixels, min_overlap_ratio, false,
+        img1_bytes, img2_bytes, ignore_right_pixels, min_overlap_ratio, options, false,
     )
 }
 
@@ -330,9 +337,10 @@ pub fn stitch_two_images_smart_auto_debug(
     img2_bytes: &[u8],
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
+    options: &StitchOptions,
 ) -> Result<(Vec<u8>, String), String> {
     stitch_two_images_smart_auto_internal(
-        img1_bytes, img2_bytes, ignore_right_pixels, min_overlap_ratio, true,
+        img1_bytes, img2_bytes, ignore_right_pixels, min_overlap_ratio, options, true,
     )
 }
 
@@ -341,10 +349,11 @@ fn stitch_two_images_smart_auto_internal(
     img2_bytes: &[u8],
     ignore_right_pixels: u32,
     min_overlap_ratio: f32,
+    options: &StitchOptions,
     debug: bool,
 ) -> Result<(Vec<u8>, String), String> {
     // 加载图片
-    let mut img1 = image::load_from_memory(img1_bytes)
+    let img1 = image::load_from_memory(img1_bytes)
         .map_err(|e| format!("Failed to load image 1: {}", e))?;
     let img2 = image::load_from_memory(img2_bytes)
         .map_err(|e| format!("Failed to load image 2: {}", e))?;
@@ -356,12 +365,8 @@ fn stitch_two_images_smart_auto_internal(
         println!("处理图片: ({}, {}) + ({}, {})", width1, height1, width2, height2);
     }
 
-    // 宽度对齐
-    if width1 != width2 {
-        if debug { println!("调整图片宽度: {} -> {}", width1, width2); }
-        let new_height1 = (height1 as f32 * width2 as f32 / width1 as f32) as u32;
-        img1 = img1.resize_exact(width2, new_height1, image::imageops::FilterType::Lanczos3);
-    }
+    // 宽度对齐（按 options.width_strategy 缩放 / 填充 / 裁剪）
+    let (img1, img2) = align_widths(img1, img2, options, debug);
 
     let (final_width, _) = img1.dimensions();
     let img1_rgba = img1.to_rgba8();
@@ -375,11 +380,11 @@ fn stitch_two_images_smart_auto_internal(
 
     let forward_result = smart_stitch_core(
         &img1_rgba, &img2_rgba, final_width,
-        ignore_right_pixels, min_overlap_ratio, debug,
+        ignore_right_pixels, min_overlap_ratio, options, debug,
     );
 
     let forward_ok = match &forward_result {
-        Ok((_, _, h)) => *h >= img1_h as u32,  // 没有缩短
+        Ok((_, _, h)) => *h >= img1_h,  // 没有缩短
         Err(_) => false,
     };
 
@@ -409,7 +414,7 @@ fn stitch_two_images_smart_auto_internal(
 
     let reverse_result = smart_stitch_core(
         &img1_flipped, &img2_flipped, final_width,
-        ignore_right_pixels, min_overlap_ratio, debug,
+        ignore_right_pixels, min_overlap_ratio, options, debug,
     );
 
     // ===== 3. 比较正/反向结果 =====
@@ -417,7 +422,7 @@ fn stitch_two_images_smart_auto_internal(
         (_, Ok((rev_buf, rev_w, rev_h))) => {
             let rev_h_val = *rev_h;
             // 反向成功
-            if rev_h_val >= img1_h as u32 {
+            if rev_h_val >= img1_h {
                 // 反向不缩短 → 使用反向（保持翻转态，不翻转回来）
                 if debug {
                     println!("✅ 反向拼接成功 ({}行 → {}行)，检测到反向滚动", img1_h, rev_h_val);
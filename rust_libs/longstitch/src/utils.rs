@@ -0,0 +1,344 @@
+/// 图像工具模块 - 裁剪、边框检测等与拼接算法无关的通用处理
+use image::{DynamicImage, GenericImageView, Rgba};
+use rayon::prelude::*;
+use std::io::Cursor;
+
+/// 从字节加载图像
+pub fn load_image(image_bytes: &[u8]) -> Result<DynamicImage, String> {
+    image::load_from_memory(image_bytes).map_err(|e| format!("Failed to load image: {}", e))
+}
+
+/// 将图像编码为 PNG 字节
+pub fn encode_png(img: &DynamicImage) -> Result<Vec<u8>, String> {
+    let mut output = Vec::new();
+    img.write_to(&mut Cursor::new(&mut output), image::ImageOutputFormat::Png)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(output)
+}
+
+// ============================================
+// 裁剪
+// ============================================
+
+/// 按矩形 (x, y, w, h) 裁剪，超出图像的部分自动截断
+pub fn crop_to_bounding_box(img: DynamicImage, x: u32, y: u32, w: u32, h: u32) -> DynamicImage {
+    DynamicImage::ImageRgba8(image::imageops::crop_imm(&img, x, y, w, h).to_image())
+}
+
+/// 两个像素的 RGB 最大通道差
+fn channel_diff(a: Rgba<u8>, b: Rgba<u8>) -> u8 {
+    (0..3).map(|c| a[c].abs_diff(b[c])).max().unwrap_or(0)
+}
+
+/// 自动去除纯色边框：以左上角像素为边框色，
+/// 从四边向内裁掉所有像素与边框色相差不超过 threshold 的行/列
+///
+/// 整张图都是边框色时原样返回
+pub fn smart_crop(img: &DynamicImage, threshold: u8) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return img.clone();
+    }
+
+    let rgba = img.to_rgba8();
+    let border = *rgba.get_pixel(0, 0);
+    let is_border_row = |y: u32| (0..width).all(|x| channel_diff(*rgba.get_pixel(x, y), border) <= threshold);
+
+    let Some(top) = (0..height).find(|&y| !is_border_row(y)) else {
+        return img.clone();
+    };
+    // top 行不是边框，bottom 一定能找到
+    let bottom = (top..height).rev().find(|&y| !is_border_row(y)).unwrap_or(top);
+
+    let is_border_col =
+        |x: u32| (top..=bottom).all(|y| channel_diff(*rgba.get_pixel(x, y), border) <= threshold);
+    let left = (0..width).find(|&x| !is_border_col(x)).unwrap_or(0);
+    let right = (left..width).rev().find(|&x| !is_border_col(x)).unwrap_or(left);
+
+    crop_to_bounding_box(
+        DynamicImage::ImageRgba8(rgba),
+        left,
+        top,
+        right - left + 1,
+        bottom - top + 1,
+    )
+}
+
+/// 检测纯色行：行内所有像素的亮度与该行平均亮度相差不超过 threshold
+///
+/// 返回: 纯色行的行号（升序）
+pub fn detect_solid_color_rows(img: &DynamicImage, threshold: u8) -> Vec<u32> {
+    let gray = img.to_luma8();
+    let width = gray.width() as usize;
+    if width == 0 {
+        return Vec::new();
+    }
+
+    gray.as_raw()
+        .par_chunks_exact(width)
+        .enumerate()
+        .filter_map(|(y, row)| {
+            let mean = row.iter().map(|&v| v as u32).sum::<u32>() as f32 / width as f32;
+            row.iter()
+                .all(|&v| (v as f32 - mean).abs() <= threshold as f32)
+                .then_some(y as u32)
+        })
+        .collect()
+}
+
+/// 检测顶部 / 底部的纯色边带：从边缘向内，连续的、所有像素与边缘颜色相差不超过 tolerance 的行
+///
+/// 整张图都是同一种颜色时不裁剪
+///
+/// 返回: (顶部边带行数, 底部边带行数)
+pub fn detect_uniform_borders(rgba: &image::RgbaImage, tolerance: u8) -> (u32, u32) {
+    let (width, height) = rgba.dimensions();
+    if width == 0 || height == 0 {
+        return (0, 0);
+    }
+
+    let is_band_row = |y: u32, color: Rgba<u8>| {
+        (0..width).all(|x| channel_diff(*rgba.get_pixel(x, y), color) <= tolerance)
+    };
+
+    let top_color = *rgba.get_pixel(0, 0);
+    let Some(top) = (0..height).find(|&y| !is_band_row(y, top_color)) else {
+        return (0, 0);
+    };
+    let bottom_color = *rgba.get_pixel(0, height - 1);
+    let bottom = (top..height)
+        .rev()
+        .take_while(|&y| is_band_row(y, bottom_color))
+        .count() as u32;
+
+    (top, bottom)
+}
+
+// ============================================
+// 填充与缩放
+// ============================================
+
+/// 将图像居中放到 target_w × target_h 的纯色画布上（源图更大时居中截断）
+pub fn pad_image_to_target(img: &DynamicImage, target_w: u32, target_h: u32, color: [u8; 4]) -> DynamicImage {
+    let mut canvas = image::RgbaImage::from_pixel(target_w, target_h, Rgba(color));
+    let offset_x = (target_w as i64 - img.width() as i64) / 2;
+    let offset_y = (target_h as i64 - img.height() as i64) / 2;
+    image::imageops::replace(&mut canvas, &img.to_rgba8(), offset_x, offset_y);
+    DynamicImage::ImageRgba8(canvas)
+}
+
+/// 保持宽高比缩放到 target_w × target_h 以内，剩余部分用 bg_color 居中填充
+pub fn resize_letterbox(img: &DynamicImage, target_w: u32, target_h: u32, bg_color: [u8; 4]) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 || target_w == 0 || target_h == 0 {
+        return pad_image_to_target(img, target_w, target_h, bg_color);
+    }
+
+    let scale = (target_w as f64 / width as f64).min(target_h as f64 / height as f64);
+    let new_w = ((width as f64 * scale).round() as u32).clamp(1, target_w);
+    let new_h = ((height as f64 * scale).round() as u32).clamp(1, target_h);
+    let resized = img.resize_exact(new_w, new_h, image::imageops::FilterType::Lanczos3);
+    pad_image_to_target(&resized, target_w, target_h, bg_color)
+}
+
+// ============================================
+// 滚动条检测
+// ============================================
+
+/// 滚动条最大宽度（像素），超过此宽度的纯色带视为页面边距而非滚动条
+const SCROLLBAR_MAX_WIDTH: u32 = 40;
+/// 滚动槽内亮度允许的波动范围
+const SCROLLBAR_LUMA_TOLERANCE: u8 = 5;
+/// 滚动槽至少连续覆盖的图像高度比例
+const SCROLLBAR_MIN_HEIGHT_RATIO: f32 = 0.5;
+
+/// 该列是否存在覆盖至少一半高度、亮度几乎不变的连续竖条（滚动槽特征）
+fn is_scroll_track_column(gray: &image::GrayImage, x: u32) -> bool {
+    let height = gray.height();
+    let min_run = ((height as f32 * SCROLLBAR_MIN_HEIGHT_RATIO).ceil() as u32).max(1);
+
+    let mut run_start_value = gray.get_pixel(x, 0)[0];
+    let mut run = 0u32;
+    for y in 0..height {
+        let v = gray.get_pixel(x, y)[0];
+        if v.abs_diff(run_start_value) < SCROLLBAR_LUMA_TOLERANCE {
+            run += 1;
+        } else {
+            run_start_value = v;
+            run = 1;
+        }
+        if run >= min_run {
+            return true;
+        }
+    }
+    false
+}
+
+/// 自动检测右侧滚动条的位置
+///
+/// 在最右侧 SCROLLBAR_MAX_WIDTH 列内寻找一段连续的滚动槽列（亮度波动 < 5、
+/// 连续覆盖至少 50% 高度），并要求其左侧紧邻的列不再符合（排除大片空白边距）
+///
+/// 返回: 滚动条最左列的 x 坐标，未检测到时返回 None
+pub fn detect_scrollbar_x(img: &DynamicImage) -> Option<u32> {
+    let gray = img.to_luma8();
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let scan_start = width.saturating_sub(SCROLLBAR_MAX_WIDTH);
+    // 允许最右侧有几列窗口边框，从右往左找第一列滚动槽
+    let right = (scan_start..width).rev().find(|&x| is_scroll_track_column(&gray, x))?;
+    let left = (scan_start..=right)
+        .rev()
+        .take_while(|&x| is_scroll_track_column(&gray, x))
+        .last()?;
+
+    // 一直延伸到扫描区域边界：是整片纯色区域而非滚动条
+    if left == scan_start && (left == 0 || is_scroll_track_column(&gray, left - 1)) {
+        return None;
+    }
+    Some(left)
+}
+
+// ============================================
+// 清晰度
+// ============================================
+
+/// 计算清晰度：灰度图与拉普拉斯核 [[0,1,0],[1,-4,1],[0,1,0]] 卷积后的方差
+///
+/// 方差越大边缘越锐利，可用于拼接前剔除模糊帧；小于 3x3 的图像返回 0
+pub fn compute_sharpness(img: &DynamicImage) -> f32 {
+    let gray = img.to_luma8();
+    let (width, height) = (gray.width() as usize, gray.height() as usize);
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let raw = gray.as_raw();
+    // 只计算内部像素（边界像素缺少完整邻域）
+    let (sum, sum_sq) = (1..height - 1)
+        .into_par_iter()
+        .map(|y| {
+            let mut sum = 0f64;
+            let mut sum_sq = 0f64;
+            for x in 1..width - 1 {
+                let at = |dx: usize, dy: usize| raw[(y + dy - 1) * width + x + dx - 1] as i32;
+                let lap = (at(1, 0) + at(0, 1) + at(2, 1) + at(1, 2) - 4 * at(1, 1)) as f64;
+                sum += lap;
+                sum_sq += lap * lap;
+            }
+            (sum, sum_sq)
+        })
+        .reduce(|| (0.0, 0.0), |a, b| (a.0 + b.0, a.1 + b.1));
+
+    let n = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / n;
+    (sum_sq / n - mean * mean).max(0.0) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_smart_crop_white_border() {
+        // 10 像素白边包围 30x20 的内容区（内容区左上角为黑色，其余为渐变）
+        let img = RgbaImage::from_fn(50, 40, |x, y| {
+            if (10..40).contains(&x) && (10..30).contains(&y) {
+                let v = ((x - 10) * 4 + (y - 10) * 2) as u8;
+                Rgba([v, v, 128, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+        let bytes = encode_png(&DynamicImage::ImageRgba8(img)).unwrap();
+
+        let cropped = smart_crop(&load_image(&bytes).unwrap(), 8);
+        assert_eq!(cropped.dimensions(), (30, 20));
+        assert_eq!(cropped.get_pixel(0, 0), Rgba([0, 0, 128, 255]));
+
+        // 纯白图片不裁剪
+        let blank = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 255])));
+        assert_eq!(smart_crop(&blank, 8).dimensions(), (8, 8));
+    }
+
+    #[test]
+    fn test_pad_and_letterbox() {
+        let red = Rgba([255, 0, 0, 255]);
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(200, 100, red));
+
+        // 200x100 填充到 400x200：原图居中，四周白边
+        let padded = pad_image_to_target(&img, 400, 200, [255, 255, 255, 255]);
+        assert_eq!(padded.dimensions(), (400, 200));
+        assert_eq!(padded.get_pixel(100, 50), red);
+        assert_eq!(padded.get_pixel(299, 149), red);
+        assert_eq!(padded.get_pixel(99, 50), Rgba([255, 255, 255, 255]));
+        assert_eq!(padded.get_pixel(100, 49), Rgba([255, 255, 255, 255]));
+        assert_eq!(padded.get_pixel(300, 150), Rgba([255, 255, 255, 255]));
+
+        // 200x100 放入 100x100：缩放为 100x50，上下各 25 行黑边
+        let boxed = resize_letterbox(&img, 100, 100, [0, 0, 0, 255]);
+        assert_eq!(boxed.dimensions(), (100, 100));
+        assert_eq!(boxed.get_pixel(50, 24), Rgba([0, 0, 0, 255]));
+        assert_eq!(boxed.get_pixel(50, 25), red);
+        assert_eq!(boxed.get_pixel(50, 74), red);
+        assert_eq!(boxed.get_pixel(50, 75), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_detect_scrollbar_x() {
+        // 伪随机内容 + 右侧 17 像素滚动条（浅灰滚动槽，20..60 行为深色滑块）+ 1 像素边框
+        let content = |x: u32, y: u32| {
+            let v = ((x.wrapping_mul(7919) ^ y.wrapping_mul(104_729)) % 200) as u8;
+            Rgba([v, v, v, 255])
+        };
+        let img = RgbaImage::from_fn(300, 200, |x, y| match x {
+            299 => Rgba([100, 100, 100, 255]),
+            282..=298 if (20..60).contains(&y) => Rgba([190, 190, 190, 255]),
+            282..=298 => Rgba([240, 240, 240, 255]),
+            _ => content(x, y),
+        });
+        assert_eq!(detect_scrollbar_x(&DynamicImage::ImageRgba8(img)), Some(282));
+
+        // 无滚动条
+        let plain = RgbaImage::from_fn(300, 200, content);
+        assert_eq!(detect_scrollbar_x(&DynamicImage::ImageRgba8(plain)), None);
+
+        // 整张纯色图片：纯色带超过最大宽度，不是滚动条
+        let blank = RgbaImage::from_pixel(300, 200, Rgba([255, 255, 255, 255]));
+        assert_eq!(detect_scrollbar_x(&DynamicImage::ImageRgba8(blank)), None);
+    }
+
+    #[test]
+    fn test_compute_sharpness() {
+        // 棋盘格文字状的锐利图像，模糊后清晰度应显著下降
+        let sharp = DynamicImage::ImageRgba8(RgbaImage::from_fn(120, 80, |x, y| {
+            let v = if (x / 4 + y / 4) % 2 == 0 { 20 } else { 235 };
+            Rgba([v, v, v, 255])
+        }));
+        let blurred = DynamicImage::ImageRgba8(image::imageops::blur(&sharp, 2.0));
+
+        let sharp_score = compute_sharpness(&sharp);
+        let blurred_score = compute_sharpness(&blurred);
+        assert!(sharp_score > blurred_score * 5.0, "{} vs {}", sharp_score, blurred_score);
+
+        let flat = DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 10, Rgba([128, 128, 128, 255])));
+        assert_eq!(compute_sharpness(&flat), 0.0);
+    }
+
+    #[test]
+    fn test_crop_and_solid_rows() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(20, 6, |x, y| {
+            let v = if y % 2 == 0 { 200 } else { (x * 10) as u8 };
+            Rgba([v, v, v, 255])
+        }));
+        assert_eq!(detect_solid_color_rows(&img, 3), vec![0, 2, 4]);
+
+        // 越界部分被截断
+        let cropped = crop_to_bounding_box(img, 15, 2, 10, 3);
+        assert_eq!(cropped.dimensions(), (5, 3));
+    }
+}
@@ -1,21 +1,244 @@
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, params};
+use crate::error::ClipboardError;
+use crate::heuristics::{detect_content_subtype, extract_urls};
+use crate::image_store::{ImageStore, MEMORY_IMAGES_DIR};
 use crate::types::{PyClipboardItem, PyPaginatedResult, PyGroup};
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
 
 // 压缩阈值：超过 100KB 的 data 用 zstd 压缩
 const COMPRESS_THRESHOLD: usize = 100 * 1024;
 
+// 文本压缩（set_text_compression）：超过 16KB 的文本记录用 zstd 压缩后存入 content_compressed，
+// content 列只保留前 1000 个字符（列表显示和搜索用）
+const TEXT_COMPRESS_THRESHOLD: usize = 16 * 1024;
+const COMPRESSED_TEXT_PREVIEW_CHARS: usize = 1000;
+
+// 孤立图片宽限期：修改时间在此之内的图片可能正等待插入记录，不视为孤立
+const ORPHAN_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// 判断两个路径是否指向同一目录
+fn same_dir(a: &std::path::Path, b: &std::path::Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// 根据像素内容计算图片 ID：sha256(宽 + 高 + RGBA 原始像素) 的前 16 位十六进制
+///
+/// 不对 PNG 编码结果做哈希，因为不同 image 版本/平台的编码结果可能不同，
+/// 同一张图片会得到不同的 ID 而重复落盘
+pub fn compute_image_id(rgba: &image::RgbaImage) -> String {
+    use sha2::{Digest, Sha256};
+    
+    let mut hasher = Sha256::new();
+    hasher.update(rgba.width().to_le_bytes());
+    hasher.update(rgba.height().to_le_bytes());
+    hasher.update(rgba.as_raw());
+    let hash = format!("{:x}", hasher.finalize());
+    hash[..16].to_string()
+}
+
+/// 计算 64 位 dHash（缩放到 9x8 灰度，比较每行相邻像素的亮度）
+///
+/// 两张图片的 dHash 汉明距离越小越相似，只差几个像素的截图距离通常为 0~2
+pub fn compute_image_dhash(rgba: &image::RgbaImage) -> u64 {
+    use image::imageops::{self, FilterType};
+
+    let gray = imageops::grayscale(rgba);
+    let small = imageops::resize(&gray, 9, 8, FilterType::Triangle);
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// 计算内容哈希：sha256(content + html_content) 的十六进制，用于精确去重
+///
+/// html_content 为 None 与空字符串区分开，避免两者被当作同一内容
+pub fn compute_content_hash(content: &str, html_content: Option<&str>) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update((content.len() as u64).to_le_bytes());
+    hasher.update(content.as_bytes());
+    match html_content {
+        Some(html) => {
+            hasher.update([1u8]);
+            hasher.update(html.as_bytes());
+        }
+        None => hasher.update([0u8]),
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// 文本记录的存储形式：(content 列, content_compressed 列)
+///
+/// 开启压缩且文本超过 TEXT_COMPRESS_THRESHOLD 时，content 只保留前缀，完整文本压缩后另存；
+/// 其他情况（包括压缩失败）原样存储
+fn encode_text_content<'a>(content: &'a str, content_type: &str, compress: bool) -> (Cow<'a, str>, Option<Vec<u8>>) {
+    if compress && content_type == "text" && content.len() > TEXT_COMPRESS_THRESHOLD {
+        if let Ok(data) = zstd::encode_all(content.as_bytes(), 3) {
+            let preview: String = content.chars().take(COMPRESSED_TEXT_PREVIEW_CHARS).collect();
+            return (Cow::Owned(preview), Some(data));
+        }
+    }
+    (Cow::Borrowed(content), None)
+}
+
+/// 文本记录的子类型（见 heuristics），其他类型为 None
+fn detect_subtype(content: &str, content_type: &str) -> Option<&'static str> {
+    if content_type == "text" {
+        detect_content_subtype(content)
+    } else {
+        None
+    }
+}
+
+/// 文本记录中提取出的链接（JSON 数组），没有链接或非文本记录为 None
+fn urls_json(content: &str, content_type: &str) -> Option<String> {
+    if content_type != "text" {
+        return None;
+    }
+    let urls = extract_urls(content);
+    if urls.is_empty() {
+        None
+    } else {
+        serde_json::to_string(&urls).ok()
+    }
+}
+
+/// 历史记录的 keyset 分页游标：上一页最后一条记录的排序键
+///
+/// 排序键与 query_items 的排序一致（置顶优先、pin_order、item_order），id 用于打破并列。
+/// 对外编码为 "is_pinned:pin_order:item_order:id" 字符串，调用方无需理解其内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryCursor {
+    is_pinned: i64,
+    pin_order: i64,
+    item_order: i64,
+    id: i64,
+}
+
+impl HistoryCursor {
+    pub fn encode(&self) -> String {
+        format!("{}:{}:{}:{}", self.is_pinned, self.pin_order, self.item_order, self.id)
+    }
+
+    pub fn parse(cursor: &str) -> Result<Self, ClipboardError> {
+        let parts: Vec<i64> = cursor
+            .split(':')
+            .map(|p| p.parse::<i64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| ClipboardError::InvalidArgument(format!("无效的分页游标: {}", cursor)))?;
+        match parts[..] {
+            [is_pinned, pin_order, item_order, id] => Ok(Self { is_pinned, pin_order, item_order, id }),
+            _ => Err(ClipboardError::InvalidArgument(format!("无效的分页游标: {}", cursor))),
+        }
+    }
+}
+
+/// 重复内容的判定范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupScope {
+    /// 内容相同即视为重复，不区分来源应用
+    Global,
+    /// 内容相同且来源应用相同才视为重复
+    PerApp,
+}
+
+impl DedupScope {
+    pub fn parse(scope: &str) -> Result<Self, ClipboardError> {
+        match scope {
+            "global" => Ok(DedupScope::Global),
+            "per_app" => Ok(DedupScope::PerApp),
+            other => Err(ClipboardError::InvalidArgument(format!("不支持的去重范围: {}", other))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DedupScope::Global => "global",
+            DedupScope::PerApp => "per_app",
+        }
+    }
+}
+
+/// 重复内容的判定方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeduplicationStrategy {
+    /// 按哈希找到候选后再逐字比较 content 和 html_content（默认）
+    Exact,
+    /// 只比较 content + html_content 的 SHA256 哈希，不再逐字比较
+    ContentHash,
+    /// 不去重，相同内容每次都插入新记录
+    Disabled,
+}
+
+impl DeduplicationStrategy {
+    pub fn parse(strategy: &str) -> Result<Self, ClipboardError> {
+        match strategy {
+            "exact" => Ok(DeduplicationStrategy::Exact),
+            "content_hash" => Ok(DeduplicationStrategy::ContentHash),
+            "disabled" => Ok(DeduplicationStrategy::Disabled),
+            other => Err(ClipboardError::InvalidArgument(format!("不支持的去重方式: {}", other))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DeduplicationStrategy::Exact => "exact",
+            DeduplicationStrategy::ContentHash => "content_hash",
+            DeduplicationStrategy::Disabled => "disabled",
+        }
+    }
+}
+
+/// 等待其他连接释放写锁的默认时间（毫秒），超时后才返回 "database is locked"
+pub const DEFAULT_BUSY_TIMEOUT_MS: u32 = 5000;
+
 /// SQLite 数据库管理
+/// 
+/// PyClipboardManager 为同一个数据库文件打开多个连接，每个连接由一把 Mutex 串行：
+/// - 写连接（`new`）：监听线程入库、所有修改操作，以及依赖图片目录的维护操作
+/// - 读连接（`open_reader`，由 ReaderPool 管理多个）：列表、搜索、计数等纯查询，
+///   `PRAGMA query_only` 防止误写
+///
+/// WAL 模式下读不阻塞写、写也不阻塞读，长写入（大图片的格式数据、cleanup、compact）期间
+/// 查询无需排队，多个线程的查询也各用一个读连接并行执行；读连接只能看到已提交的数据。写连接之间的 "database is locked"
+/// （第二个管理器实例、外部工具等）由 busy_timeout 等待解决
 pub struct Database {
     conn: Connection,
     db_path: String,
+    /// 自定义图片存储目录，None 时使用数据库旁的 images 子目录
+    images_dir: Option<PathBuf>,
+    /// 内存数据库的图片存储（不写文件），此时 images_dir 不生效
+    memory_images: Option<ImageStore>,
+    /// 最大置顶数量，0 表示不限制
+    max_pinned: u32,
+    /// 重复内容的判定范围
+    dedup_scope: DedupScope,
+    /// 重复内容的判定方式
+    dedup_strategy: DeduplicationStrategy,
+    /// 是否压缩存储大段文本
+    compress_text: bool,
 }
 
 impl Database {
     /// 创建或打开数据库
-    pub fn new(db_path: &str) -> Result<Self, String> {
-        let conn = Connection::open(db_path)
-            .map_err(|e| format!("打开数据库失败: {}", e))?;
+    /// 
+    /// busy_timeout_ms: 其他连接持有锁时的最长等待毫秒数，0 表示遇到锁立即失败
+    pub fn new(db_path: &str, busy_timeout_ms: u32) -> Result<Self, ClipboardError> {
+        let mut conn = Connection::open(db_path)?;
+        // 最先设置：后面的建表/迁移也可能遇到其他连接持有的锁
+        conn.busy_timeout(std::time::Duration::from_millis(busy_timeout_ms as u64))?;
         
         // 创建剪贴板表
         conn.execute(
@@ -37,10 +260,58 @@ impl Database {
                 updated_at INTEGER NOT NULL
             )",
             [],
-        ).map_err(|e| format!("创建表失败: {}", e))?;
+        )?;
         
         // 迁移：添加 title 字段（如果不存在）
         let _ = conn.execute("ALTER TABLE clipboard ADD COLUMN title TEXT", []);
+        
+        // 迁移：添加 pin_order 字段（置顶项独立排序，DESC，大的在上面）
+        // 首次添加时用 item_order 初始化已置顶项，保持原有的置顶顺序
+        if conn.execute("ALTER TABLE clipboard ADD COLUMN pin_order INTEGER NOT NULL DEFAULT 0", []).is_ok() {
+            let _ = conn.execute("UPDATE clipboard SET pin_order = item_order WHERE is_pinned = 1", []);
+        }
+
+        // 迁移：添加 image_dhash 字段（图片感知哈希，近似重复去重时直接读取，无需重新计算）
+        let _ = conn.execute("ALTER TABLE clipboard ADD COLUMN image_dhash INTEGER", []);
+        
+        // 迁移：添加 content_hash 字段（去重时按哈希走索引，避免大文本逐字比较）
+        // 首次添加时为已有记录补算哈希
+        if conn.execute("ALTER TABLE clipboard ADD COLUMN content_hash TEXT", []).is_ok() {
+            Self::backfill_content_hash(&mut conn)?;
+        }
+
+        // 迁移：添加图片元信息字段（原图宽高、落盘文件字节数、格式），列表展示时无需读取图片文件
+        let _ = conn.execute("ALTER TABLE clipboard ADD COLUMN image_width INTEGER", []);
+        let _ = conn.execute("ALTER TABLE clipboard ADD COLUMN image_height INTEGER", []);
+        let _ = conn.execute("ALTER TABLE clipboard ADD COLUMN image_bytes INTEGER", []);
+        let _ = conn.execute("ALTER TABLE clipboard ADD COLUMN image_format TEXT", []);
+
+        // 迁移：添加 formats 字段（采集时剪贴板上的格式列表 JSON，仅开启 set_capture_formats 时写入）
+        let _ = conn.execute("ALTER TABLE clipboard ADD COLUMN formats TEXT", []);
+
+        // 迁移：添加 protected 字段（保护的记录不参与自动清理，与置顶的显示优先级相互独立）
+        let _ = conn.execute("ALTER TABLE clipboard ADD COLUMN protected INTEGER NOT NULL DEFAULT 0", []);
+
+        // 迁移：添加 content_compressed 字段（zstd 压缩的完整文本，非空即表示该记录压缩存储，content 只是前缀）
+        let _ = conn.execute("ALTER TABLE clipboard ADD COLUMN content_compressed BLOB", []);
+
+        // 迁移：添加 deleted_at 字段（移入回收站的时间，非空的记录不出现在历史和分组中）
+        let _ = conn.execute("ALTER TABLE clipboard ADD COLUMN deleted_at INTEGER", []);
+
+        // 迁移：添加 is_favorite 字段（收藏夹中的记录只出现在 get_favorites 中，不参与清理）
+        let _ = conn.execute("ALTER TABLE clipboard ADD COLUMN is_favorite INTEGER NOT NULL DEFAULT 0", []);
+
+        // 迁移：添加 subtype 字段（文本子类型，见 heuristics），首次添加时为已有文本记录补算
+        if conn.execute("ALTER TABLE clipboard ADD COLUMN subtype TEXT", []).is_ok() {
+            Self::backfill_text_column(&mut conn, "subtype", |content| {
+                detect_content_subtype(content).map(str::to_string)
+            })?;
+        }
+
+        // 迁移：添加 urls 字段（文本中提取出的链接 JSON 数组，没有链接时为 NULL），首次添加时为已有文本记录补算
+        if conn.execute("ALTER TABLE clipboard ADD COLUMN urls TEXT", []).is_ok() {
+            Self::backfill_text_column(&mut conn, "urls", |content| urls_json(content, "text"))?;
+        }
 
         // ── Ditto 风格：原始格式数据表 ──────────────────────────────────────
         // clipboard_formats 与 clipboard 通过 event_id 关联（一次复制对应一个 event_id）
@@ -57,7 +328,7 @@ impl Database {
                 FOREIGN KEY (event_id) REFERENCES clipboard(id) ON DELETE CASCADE
             )",
             [],
-        ).map_err(|e| format!("创建 clipboard_formats 表失败: {}", e))?;
+        )?;
 
         // 兼容旧数据库：若 compressed 列不存在则添加
         let _ = conn.execute(
@@ -89,7 +360,7 @@ impl Database {
                 created_at INTEGER NOT NULL
             )",
             [],
-        ).map_err(|e| format!("创建分组表失败: {}", e))?;
+        )?;
         
         // 创建索引
         let _ = conn.execute(
@@ -107,57 +378,221 @@ impl Database {
             [],
         );
         
+        let _ = conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_clipboard_content_hash ON clipboard(content_hash)",
+            [],
+        );
+        
         // 为 image_id 创建索引（优化图片去重查询）
         let _ = conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_clipboard_image_id ON clipboard(image_id)",
             [],
         );
         
+        // 回收站按删除时间排序
+        let _ = conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_clipboard_deleted_at ON clipboard(deleted_at)",
+            [],
+        );
+        
         // 性能优化 + 启用外键（必须开启，否则 ON DELETE CASCADE 不生效）
         conn.execute_batch(
             "PRAGMA foreign_keys = ON;
              PRAGMA journal_mode = WAL;
              PRAGMA synchronous = NORMAL;
              PRAGMA cache_size = 10000;"
-        ).map_err(|e| format!("设置参数失败: {}", e))?;
+        )?;
         
         Ok(Self { 
             conn,
             db_path: db_path.to_string(),
+            images_dir: None,
+            memory_images: (db_path == ":memory:").then(ImageStore::memory),
+            max_pinned: 0,
+            dedup_scope: DedupScope::Global,
+            dedup_strategy: DeduplicationStrategy::Exact,
+            compress_text: false,
+        })
+    }
+    
+    /// 打开只读的查询连接
+    /// 
+    /// 不建表、不迁移（由写连接负责，须先用 `new` 打开同一文件）；
+    /// 通过 `PRAGMA query_only` 拒绝任何写入，WAL 模式下查询不会阻塞写连接
+    pub fn open_reader(db_path: &str, busy_timeout_ms: u32) -> Result<Self, ClipboardError> {
+        // 不用 SQLITE_OPEN_READ_ONLY：只读打开时 WAL 的 -shm 文件可能无法创建，统一靠 query_only 限制
+        let conn = Connection::open(db_path)?;
+        conn.busy_timeout(std::time::Duration::from_millis(busy_timeout_ms as u64))?;
+        conn.execute_batch(
+            "PRAGMA query_only = ON;
+             PRAGMA cache_size = 10000;"
+        )?;
+        
+        Ok(Self {
+            conn,
+            db_path: db_path.to_string(),
+            images_dir: None,
+            memory_images: None,
+            max_pinned: 0,
+            dedup_scope: DedupScope::Global,
+            dedup_strategy: DeduplicationStrategy::Exact,
+            compress_text: false,
         })
     }
     
-    /// 获取图片存储目录
+    /// 获取图片存储目录（内存数据库返回不对应真实目录的占位路径）
     pub fn get_images_dir(&self) -> PathBuf {
-        let db_dir = std::path::Path::new(&self.db_path).parent()
-            .unwrap_or_else(|| std::path::Path::new("."));
-        let images_dir = db_dir.join("images");
+        if self.memory_images.is_some() {
+            return PathBuf::from(MEMORY_IMAGES_DIR);
+        }
+        let images_dir = match &self.images_dir {
+            Some(dir) => dir.clone(),
+            None => {
+                let db_dir = std::path::Path::new(&self.db_path).parent()
+                    .unwrap_or_else(|| std::path::Path::new("."));
+                db_dir.join("images")
+            }
+        };
         let _ = std::fs::create_dir_all(&images_dir);
         images_dir
     }
     
+    /// 图片存储：内存数据库为进程内存储，否则为图片目录
+    pub fn image_store(&self) -> ImageStore {
+        match &self.memory_images {
+            Some(store) => store.clone(),
+            None => ImageStore::Dir(self.get_images_dir()),
+        }
+    }
+    
+    /// 设置图片存储目录
+    /// 
+    /// migrate 为 true 时把旧目录中的图片文件移动到新目录（image_id 即文件名，数据库无需更新），
+    /// 返回迁移的文件数量
+    pub fn set_images_dir(&mut self, dir: PathBuf, migrate: bool) -> Result<usize, ClipboardError> {
+        if self.memory_images.is_some() {
+            return Err(ClipboardError::InvalidArgument("内存数据库的图片不写入文件，不能设置图片目录".to_string()));
+        }
+        std::fs::create_dir_all(&dir)?;
+        
+        let old_dir = self.get_images_dir();
+        self.images_dir = Some(dir.clone());
+        
+        if !migrate || same_dir(&old_dir, &dir) {
+            return Ok(0);
+        }
+        
+        let entries = std::fs::read_dir(&old_dir)?;
+        let mut moved = 0;
+        for entry in entries.flatten() {
+            let src = entry.path();
+            if !src.is_file() {
+                continue;
+            }
+            let dst = dir.join(entry.file_name());
+            if dst.exists() {
+                // 同名即同内容，直接删除旧文件
+                let _ = std::fs::remove_file(&src);
+                continue;
+            }
+            // rename 不能跨磁盘，失败时退回到复制 + 删除
+            let result = std::fs::rename(&src, &dst).or_else(|_| {
+                std::fs::copy(&src, &dst).and_then(|_| std::fs::remove_file(&src))
+            });
+            result.map_err(|e| std::io::Error::new(e.kind(), format!("迁移图片 {:?} 失败: {}", src, e)))?;
+            moved += 1;
+        }
+        Ok(moved)
+    }
+    
     /// 插入新记录
-    pub fn insert_item(&self, item: &PyClipboardItem) -> Result<i64, String> {
+    /// 
+    /// Returns:
+    ///     (记录 ID, 是否新插入)；内容重复时只把已有记录移到最前，返回 false
+    #[tracing::instrument(skip(self, item), fields(content_type = %item.content_type))]
+    pub fn insert_item(&self, item: &PyClipboardItem) -> Result<(i64, bool), ClipboardError> {
+        let now = chrono::Local::now().timestamp();
+        
+        // 获取最大顺序
+        let max_order: i64 = self.conn.query_row(
+            "SELECT COALESCE(MAX(item_order), 0) FROM clipboard",
+            [],
+            |row| row.get(0)
+        ).unwrap_or(0);
+        
+        Self::insert_item_at(
+            &self.conn, item, max_order + 1000, now, self.dedup_scope, self.dedup_strategy, self.compress_text,
+        )
+    }
+    
+    /// 批量插入（单个事务，全部成功或全部回滚）
+    /// 
+    /// 去重规则与 insert_item 相同；批内 item_order 依次递增，后面的记录排在前面
+    /// 
+    /// Returns:
+    ///     按输入顺序排列的记录 ID（重复内容返回已有记录的 ID）
+    pub fn insert_items(&mut self, items: &[PyClipboardItem]) -> Result<Vec<i64>, ClipboardError> {
         let now = chrono::Local::now().timestamp();
+        let (dedup_scope, dedup_strategy, compress_text) = (self.dedup_scope, self.dedup_strategy, self.compress_text);
+        let tx = self.conn.transaction()?;
+        
+        let max_order: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(item_order), 0) FROM clipboard",
+            [],
+            |row| row.get(0)
+        ).unwrap_or(0);
+        
+        let mut ids = Vec::with_capacity(items.len());
+        for (i, item) in items.iter().enumerate() {
+            let order = max_order + 1000 * (i as i64 + 1);
+            let (id, _) = Self::insert_item_at(&tx, item, order, now, dedup_scope, dedup_strategy, compress_text)?;
+            ids.push(id);
+        }
+        
+        tx.commit()?;
+        Ok(ids)
+    }
+    
+    /// 以指定的 item_order 插入记录；内容重复时把已有记录移到该位置
+    fn insert_item_at(
+        conn: &Connection,
+        item: &PyClipboardItem,
+        order: i64,
+        now: i64,
+        dedup_scope: DedupScope,
+        dedup_strategy: DeduplicationStrategy,
+        compress_text: bool,
+    ) -> Result<(i64, bool), ClipboardError> {
         let char_count = item.content.chars().count() as i64;
+        let content_hash = compute_content_hash(&item.content, item.html_content.as_deref());
         
-        // 检查重复：
+        // 检查重复（去重方式为 disabled 时跳过）：
         // 1. 如果有 title（收藏内容），则不去重，允许相同内容不同标题的多条记录
         // 2. 如果是图片类型，用 image_id 去重（避免相同尺寸的不同图片被误判为重复）
         // 3. 如果是文本/文件类型，用 content 和 html_content 去重
-        let existing_id: Option<i64> = if item.title.is_none() {
+        //    （去重方式为 exact 时，哈希相同后再逐字比较，content_hash 只比较哈希；
+        //     已压缩存储的记录 content 只是前缀，只比较哈希）
+        // 回收站和收藏夹中的记录不参与去重，再次复制相同内容会新增历史记录
+        // 去重范围为 per_app 时，以上两种还要求来源应用相同（IS 比较，两边都未知也算相同）
+        let existing_id: Option<i64> = if item.title.is_none() && dedup_strategy != DeduplicationStrategy::Disabled {
+            let per_app = dedup_scope == DedupScope::PerApp;
             if item.content_type == "image" && item.image_id.is_some() {
                 // 图片类型：用 image_id 去重（精确匹配，不会误判）
-                self.conn.query_row(
-                    "SELECT id FROM clipboard WHERE image_id = ?1 AND content_type = 'image' ORDER BY created_at DESC LIMIT 1",
-                    params![&item.image_id],
+                conn.query_row(
+                    "SELECT id FROM clipboard WHERE image_id = ?1 AND content_type = 'image' AND deleted_at IS NULL AND is_favorite = 0
+                     AND (?2 = 0 OR source_app IS ?3) ORDER BY created_at DESC LIMIT 1",
+                    params![&item.image_id, per_app, &item.source_app],
                     |row| row.get(0)
                 ).ok()
             } else {
-                // 文本/文件类型：用 content 去重
-                self.conn.query_row(
-                    "SELECT id FROM clipboard WHERE content = ?1 AND content_type = ?2 AND (html_content IS ?3 OR (html_content IS NULL AND ?3 IS NULL)) AND title IS NULL ORDER BY created_at DESC LIMIT 1",
-                    params![&item.content, &item.content_type, &item.html_content],
+                // 文本/文件类型：用 content + html_content 的哈希去重（走索引，不比较大文本）
+                let exact = dedup_strategy == DeduplicationStrategy::Exact;
+                conn.query_row(
+                    "SELECT id FROM clipboard WHERE content_hash = ?1 AND content_type = ?2 AND title IS NULL AND deleted_at IS NULL AND is_favorite = 0
+                     AND (?3 = 0 OR source_app IS ?4)
+                     AND (?5 = 0 OR content_compressed IS NOT NULL OR (content = ?6 AND html_content IS ?7)) ORDER BY created_at DESC LIMIT 1",
+                    params![&content_hash, &item.content_type, per_app, &item.source_app,
+                            exact, &item.content, &item.html_content],
                     |row| row.get(0)
                 ).ok()
             }
@@ -167,60 +602,97 @@ impl Database {
         
         if let Some(id) = existing_id {
             // 内容完全相同，只更新顺序和时间，让它排到最前面
-            self.conn.execute(
-                "UPDATE clipboard SET updated_at = ?1, item_order = (SELECT COALESCE(MAX(item_order), 0) + 1000 FROM clipboard) WHERE id = ?2",
-                params![now, id],
-            ).map_err(|e| format!("更新失败: {}", e))?;
-            return Ok(id);
+            conn.execute(
+                "UPDATE clipboard SET updated_at = ?1, item_order = ?2 WHERE id = ?3",
+                params![now, order, id],
+            )?;
+            tracing::debug!(id, "内容重复，已有记录移到最前");
+            return Ok((id, false));
         }
         
-        // 获取最大顺序
-        let max_order: i64 = self.conn.query_row(
-            "SELECT COALESCE(MAX(item_order), 0) FROM clipboard",
-            [],
-            |row| row.get(0)
-        ).unwrap_or(0);
-        
         // 插入新记录
-        self.conn.execute(
-            "INSERT INTO clipboard (title, content, html_content, content_type, image_id, thumbnail, item_order, 
-             is_pinned, paste_count, source_app, char_count, created_at, updated_at) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        let formats = item.formats.as_ref().and_then(|f| serde_json::to_string(f).ok());
+        let (content, content_compressed) = encode_text_content(&item.content, &item.content_type, compress_text);
+        let subtype = detect_subtype(&item.content, &item.content_type);
+        let urls = urls_json(&item.content, &item.content_type);
+        conn.execute(
+            "INSERT INTO clipboard (title, content, html_content, content_hash, content_type, image_id, thumbnail, item_order, 
+             is_pinned, paste_count, source_app, char_count, created_at, updated_at,
+             image_width, image_height, image_bytes, image_format, formats, content_compressed, subtype, urls) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
             params![
                 &item.title,
-                &item.content,
+                content.as_ref(),
                 &item.html_content,
+                &content_hash,
                 &item.content_type,
                 &item.image_id,
                 &item.thumbnail,
-                max_order + 1000,
+                order,
                 item.is_pinned,
                 item.paste_count,
                 &item.source_app,
                 char_count,
                 now,
                 now,
+                item.image_width,
+                item.image_height,
+                item.image_bytes,
+                &item.image_format,
+                formats,
+                content_compressed,
+                subtype,
+                urls,
             ],
-        ).map_err(|e| format!("插入失败: {}", e))?;
+        )?;
         
-        Ok(self.conn.last_insert_rowid())
+        Ok((conn.last_insert_rowid(), true))
     }
     
-    /// 分页查询
-    pub fn query_items(
-        &self,
-        offset: i64,
-        limit: i64,
+    /// 搜索条件：search_fields 中每个字段对应一个 LIKE 子句，任一匹配即可
+    /// 
+    /// 可选字段：content（主内容）、html（HTML 富文本）、title（标题）、
+    /// files（文件记录中解析出的路径，不受 JSON 转义影响）、
+    /// urls（文本中提取出的链接，压缩存储的长文本也能完整匹配）
+    /// 
+    /// Returns:
+    ///     (WHERE 子句, 占位符个数)
+    fn search_clause(search_fields: &[String]) -> Result<(String, usize), ClipboardError> {
+        let mut clauses: Vec<&str> = Vec::new();
+        for field in search_fields {
+            let clause = match field.as_str() {
+                "content" => "content LIKE ?",
+                "html" => "html_content LIKE ?",
+                "title" => "title LIKE ?",
+                "files" => "(content_type = 'file' AND EXISTS (
+                     SELECT 1 FROM json_each(clipboard.content, '$.files') WHERE value LIKE ?))",
+                "urls" => "EXISTS (SELECT 1 FROM json_each(clipboard.urls) WHERE value LIKE ?)",
+                other => return Err(ClipboardError::InvalidArgument(format!("不支持的搜索字段: {}", other))),
+            };
+            if !clauses.contains(&clause) {
+                clauses.push(clause);
+            }
+        }
+        if clauses.is_empty() {
+            return Err(ClipboardError::InvalidArgument("search_fields 不能为空".to_string()));
+        }
+        Ok((format!("({})", clauses.join(" OR ")), clauses.len()))
+    }
+    
+    /// 搜索关键词 + 类型过滤对应的 WHERE 子句（AND 连接）及其参数，始终排除回收站和收藏夹中的记录
+    fn filter_clauses(
         search: Option<String>,
         content_type: Option<String>,
-    ) -> Result<PyPaginatedResult, String> {
-        let mut where_clauses = vec![];
+        search_fields: &[String],
+    ) -> Result<(Vec<String>, Vec<String>), ClipboardError> {
+        let mut where_clauses = vec!["deleted_at IS NULL".to_string(), "is_favorite = 0".to_string()];
         let mut params_vec: Vec<String> = vec![];
         
         if let Some(ref s) = search {
             if !s.trim().is_empty() {
-                where_clauses.push("content LIKE ?".to_string());
-                params_vec.push(format!("%{}%", s));
+                let (clause, placeholders) = Self::search_clause(search_fields)?;
+                where_clauses.push(clause);
+                params_vec.extend(std::iter::repeat_n(format!("%{}%", s), placeholders));
             }
         }
         
@@ -231,6 +703,23 @@ impl Database {
             }
         }
         
+        Ok((where_clauses, params_vec))
+    }
+    
+    /// 分页查询
+    /// 
+    /// search_fields 指定关键词匹配的字段（见 search_clause），默认只搜 content
+    #[tracing::instrument(skip(self))]
+    pub fn query_items(
+        &self,
+        offset: i64,
+        limit: i64,
+        search: Option<String>,
+        content_type: Option<String>,
+        search_fields: &[String],
+    ) -> Result<PyPaginatedResult, ClipboardError> {
+        let (where_clauses, params_vec) = Self::filter_clauses(search, content_type, search_fields)?;
+        
         let where_clause = if where_clauses.is_empty() {
             String::new()
         } else {
@@ -239,99 +728,235 @@ impl Database {
         
         // 获取总数
         let count_sql = format!("SELECT COUNT(*) FROM clipboard {}", where_clause);
-        let total_count: i64 = if params_vec.is_empty() {
-            self.conn.query_row(&count_sql, [], |row| row.get(0)).unwrap_or(0)
-        } else if params_vec.len() == 1 {
-            self.conn.query_row(&count_sql, [&params_vec[0]], |row| row.get(0)).unwrap_or(0)
-        } else {
-            self.conn.query_row(&count_sql, [&params_vec[0], &params_vec[1]], |row| row.get(0)).unwrap_or(0)
-        };
+        let total_count: i64 = self.conn
+            .query_row(&count_sql, rusqlite::params_from_iter(&params_vec), |row| row.get(0))
+            .unwrap_or(0);
         
         // 查询数据
         let query_sql = format!(
             "SELECT id, title, content, html_content, content_type, image_id, thumbnail, is_pinned, 
-             paste_count, source_app, char_count, created_at, updated_at 
+             paste_count, source_app, char_count, created_at, updated_at,
+             image_width, image_height, image_bytes, image_format, formats, content_compressed, subtype 
              FROM clipboard {} 
-             ORDER BY is_pinned DESC, item_order DESC 
+             ORDER BY is_pinned DESC, pin_order DESC, item_order DESC 
              LIMIT ? OFFSET ?",
             where_clause
         );
         
-        let mut stmt = self.conn.prepare(&query_sql)
-            .map_err(|e| format!("准备查询失败: {}", e))?;
+        let mut stmt = self.conn.prepare(&query_sql)?;
         
-        let map_row = |row: &rusqlite::Row| -> rusqlite::Result<PyClipboardItem> {
-            Ok(PyClipboardItem {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                html_content: row.get(3)?,
-                content_type: row.get(4)?,
-                image_id: row.get(5)?,
-                thumbnail: row.get(6)?,
-                is_pinned: row.get::<_, i64>(7)? != 0,
-                paste_count: row.get(8)?,
-                source_app: row.get(9)?,
-                char_count: row.get(10)?,
-                created_at: row.get(11)?,
-                updated_at: row.get(12)?,
-            })
-        };
+        let map_row = Self::item_from_row;
+        
+        let mut query_params: Vec<&dyn rusqlite::ToSql> = params_vec.iter()
+            .map(|p| p as &dyn rusqlite::ToSql)
+            .collect();
+        query_params.push(&limit);
+        query_params.push(&offset);
+        
+        let items: Vec<PyClipboardItem> = stmt.query_map(query_params.as_slice(), map_row)?
+            .filter_map(|r| r.ok())
+            .collect();
+        
+        Ok(PyPaginatedResult::new(total_count, items, offset, limit))
+    }
+    
+    /// 布尔条件搜索（语法见 query_parser），排序与 query_items 相同，不含回收站和收藏夹
+    pub fn search_boolean(&self, query: &str, offset: i64, limit: i64) -> Result<PyPaginatedResult, ClipboardError> {
+        let (condition, params_vec) = crate::query_parser::where_clause(query)?;
+        let where_clause = format!("WHERE deleted_at IS NULL AND is_favorite = 0 AND {}", condition);
+        
+        let total_count: i64 = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM clipboard {}", where_clause),
+            rusqlite::params_from_iter(&params_vec),
+            |row| row.get(0)
+        )?;
+        
+        let query_sql = format!(
+            "SELECT id, title, content, html_content, content_type, image_id, thumbnail, is_pinned, 
+             paste_count, source_app, char_count, created_at, updated_at,
+             image_width, image_height, image_bytes, image_format, formats, content_compressed, subtype 
+             FROM clipboard {} 
+             ORDER BY is_pinned DESC, pin_order DESC, item_order DESC 
+             LIMIT ? OFFSET ?",
+            where_clause
+        );
+        let mut stmt = self.conn.prepare(&query_sql)?;
         
-        let items: Vec<PyClipboardItem> = if params_vec.is_empty() {
-            stmt.query_map([limit, offset], map_row)
-        } else if params_vec.len() == 1 {
-            stmt.query_map(params![&params_vec[0], limit, offset], map_row)
+        let mut query_params: Vec<&dyn rusqlite::ToSql> = params_vec.iter()
+            .map(|p| p as &dyn rusqlite::ToSql)
+            .collect();
+        query_params.push(&limit);
+        query_params.push(&offset);
+        
+        let items: Vec<PyClipboardItem> = stmt.query_map(query_params.as_slice(), Self::item_from_row)?
+            .filter_map(|r| r.ok())
+            .collect();
+        
+        Ok(PyPaginatedResult::new(total_count, items, offset, limit))
+    }
+    
+    /// keyset 分页查询：返回排在 cursor 之后的至多 limit 条记录，以及下一页的游标
+    /// 
+    /// 排序与 query_items 相同，但按排序键定位起点而不是 OFFSET 跳过前面的行，
+    /// 翻到很深的位置也不会变慢；cursor 为 None 时从第一页开始。
+    /// 不足一页时下一页游标为 None
+    pub fn query_items_after(
+        &self,
+        cursor: Option<HistoryCursor>,
+        limit: i64,
+        search: Option<String>,
+        content_type: Option<String>,
+        search_fields: &[String],
+    ) -> Result<(Vec<PyClipboardItem>, Option<HistoryCursor>), ClipboardError> {
+        let (mut where_clauses, params_vec) = Self::filter_clauses(search, content_type, search_fields)?;
+        if cursor.is_some() {
+            where_clauses.push("(is_pinned, pin_order, item_order, id) < (?, ?, ?, ?)".to_string());
+        }
+        
+        let where_clause = if where_clauses.is_empty() {
+            String::new()
         } else {
-            stmt.query_map(params![&params_vec[0], &params_vec[1], limit, offset], map_row)
-        }.map_err(|e| format!("查询失败: {}", e))?
+            format!("WHERE {}", where_clauses.join(" AND "))
+        };
+        
+        let query_sql = format!(
+            "SELECT id, title, content, html_content, content_type, image_id, thumbnail, is_pinned, 
+             paste_count, source_app, char_count, created_at, updated_at,
+             image_width, image_height, image_bytes, image_format, formats, content_compressed, subtype, pin_order, item_order 
+             FROM clipboard {} 
+             ORDER BY is_pinned DESC, pin_order DESC, item_order DESC, id DESC 
+             LIMIT ?",
+            where_clause
+        );
+        
+        let mut stmt = self.conn.prepare(&query_sql)?;
+        
+        let mut query_params: Vec<&dyn rusqlite::ToSql> = params_vec.iter()
+            .map(|p| p as &dyn rusqlite::ToSql)
+            .collect();
+        if let Some(ref c) = cursor {
+            query_params.extend([&c.is_pinned, &c.pin_order, &c.item_order, &c.id] as [&dyn rusqlite::ToSql; 4]);
+        }
+        query_params.push(&limit);
+        
+        let rows: Vec<(PyClipboardItem, HistoryCursor)> = stmt.query_map(query_params.as_slice(), |row| {
+            let item = Self::item_from_row(row)?;
+            let key = HistoryCursor {
+                is_pinned: row.get(7)?,
+                pin_order: row.get(20)?,
+                item_order: row.get(21)?,
+                id: item.id,
+            };
+            Ok((item, key))
+        })?
         .filter_map(|r| r.ok())
         .collect();
         
-        Ok(PyPaginatedResult::new(total_count, items, offset, limit))
+        let next_cursor = rows.last()
+            .filter(|_| rows.len() as i64 >= limit)
+            .map(|(_, key)| *key);
+        Ok((rows.into_iter().map(|(item, _)| item).collect(), next_cursor))
+    }
+    
+    /// 把查询结果的一行转换为记录，列顺序为各查询 SELECT 的前 20 列；
+    /// content_compressed 非空时解压出完整文本替换 content 中的前缀
+    fn item_from_row(row: &rusqlite::Row) -> rusqlite::Result<PyClipboardItem> {
+        let mut content: String = row.get(2)?;
+        if let Some(data) = row.get::<_, Option<Vec<u8>>>(18)? {
+            match zstd::decode_all(data.as_slice()).map(String::from_utf8) {
+                Ok(Ok(text)) => content = text,
+                _ => tracing::warn!("解压文本失败，只返回前缀"),
+            }
+        }
+        Ok(PyClipboardItem {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            content,
+            html_content: row.get(3)?,
+            content_type: row.get(4)?,
+            image_id: row.get(5)?,
+            thumbnail: row.get(6)?,
+            is_pinned: row.get::<_, i64>(7)? != 0,
+            paste_count: row.get(8)?,
+            source_app: row.get(9)?,
+            char_count: row.get(10)?,
+            created_at: row.get(11)?,
+            updated_at: row.get(12)?,
+            image_width: row.get(13)?,
+            image_height: row.get(14)?,
+            image_bytes: row.get(15)?,
+            image_format: row.get(16)?,
+            formats: row.get::<_, Option<String>>(17)?.and_then(|f| serde_json::from_str(&f).ok()),
+            subtype: row.get(19)?,
+        })
     }
     
-    /// 获取总记录数
-    pub fn get_count(&self) -> Result<i64, String> {
-        self.conn.query_row("SELECT COUNT(*) FROM clipboard", [], |row| row.get(0))
-            .map_err(|e| format!("查询失败: {}", e))
+    /// 获取历史记录数（不含回收站和收藏夹，与历史列表一致）
+    pub fn get_count(&self) -> Result<i64, ClipboardError> {
+        self.conn.query_row("SELECT COUNT(*) FROM clipboard WHERE deleted_at IS NULL AND is_favorite = 0", [], |row| row.get(0))
+            .map_err(ClipboardError::from)
     }
     
     /// 根据 ID 获取记录
-    pub fn get_item_by_id(&self, id: i64) -> Result<Option<PyClipboardItem>, String> {
+    pub fn get_item_by_id(&self, id: i64) -> Result<Option<PyClipboardItem>, ClipboardError> {
         let result = self.conn.query_row(
             "SELECT id, title, content, html_content, content_type, image_id, thumbnail, is_pinned, 
-             paste_count, source_app, char_count, created_at, updated_at 
+             paste_count, source_app, char_count, created_at, updated_at,
+             image_width, image_height, image_bytes, image_format, formats, content_compressed, subtype 
              FROM clipboard WHERE id = ?",
             params![id],
-            |row| {
-                Ok(PyClipboardItem {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    content: row.get(2)?,
-                    html_content: row.get(3)?,
-                    content_type: row.get(4)?,
-                    image_id: row.get(5)?,
-                    thumbnail: row.get(6)?,
-                    is_pinned: row.get::<_, i64>(7)? != 0,
-                    paste_count: row.get(8)?,
-                    source_app: row.get(9)?,
-                    char_count: row.get(10)?,
-                    created_at: row.get(11)?,
-                    updated_at: row.get(12)?,
-                })
-            }
+            Self::item_from_row,
         );
         
         match result {
             Ok(item) => Ok(Some(item)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(format!("查询失败: {}", e)),
+            Err(e) => Err(e.into()),
         }
     }
     
+    /// 按 id 升序取 id 大于 after_id 的至多 limit 条记录，用于流式遍历全表
+    /// 
+    /// 按主键定位起点（keyset 分页），深翻页时不会像 OFFSET 那样越来越慢
+    pub fn get_items_after(&self, after_id: i64, limit: i64) -> Result<Vec<PyClipboardItem>, ClipboardError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, title, content, html_content, content_type, image_id, thumbnail, is_pinned, 
+             paste_count, source_app, char_count, created_at, updated_at,
+             image_width, image_height, image_bytes, image_format, formats, content_compressed, subtype 
+             FROM clipboard WHERE id > ? AND deleted_at IS NULL 
+             ORDER BY id ASC 
+             LIMIT ?"
+        )?;
+        
+        let items: Vec<PyClipboardItem> = stmt.query_map(params![after_id, limit], Self::item_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+        
+        Ok(items)
+    }
+    
+    /// 获取 id 大于 last_id 的记录（新的在前），用于 UI 增量刷新
+    /// 
+    /// 注意：重复内容置顶只更新已有记录，id 不变，不会出现在结果中
+    pub fn get_items_since(&self, last_id: i64) -> Result<Vec<PyClipboardItem>, ClipboardError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, title, content, html_content, content_type, image_id, thumbnail, is_pinned, 
+             paste_count, source_app, char_count, created_at, updated_at,
+             image_width, image_height, image_bytes, image_format, formats, content_compressed, subtype 
+             FROM clipboard WHERE id > ? AND deleted_at IS NULL AND is_favorite = 0 
+             ORDER BY id DESC"
+        )?;
+        
+        let items: Vec<PyClipboardItem> = stmt.query_map(params![last_id], Self::item_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+        
+        Ok(items)
+    }
+    
     /// 删除记录
-    pub fn delete_item(&self, id: i64) -> Result<(), String> {
+    #[tracing::instrument(skip(self))]
+    pub fn delete_item(&self, id: i64) -> Result<(), ClipboardError> {
         // 先获取 image_id，以便删除图片文件
         let image_id: Option<String> = self.conn.query_row(
             "SELECT image_id FROM clipboard WHERE id = ?",
@@ -339,91 +964,423 @@ impl Database {
             |row| row.get(0)
         ).ok();
         
+        self.conn.execute("DELETE FROM clipboard WHERE id = ?", params![id])?;
+        
         // 删除图片文件
         if let Some(img_id) = image_id {
             if !img_id.is_empty() {
-                let images_dir = self.get_images_dir();
-                let image_path = images_dir.join(format!("{}.png", img_id));
-                let _ = std::fs::remove_file(&image_path);
+                self.remove_unreferenced_images(vec![img_id]);
             }
         }
-        
-        self.conn.execute("DELETE FROM clipboard WHERE id = ?", params![id])
-            .map_err(|e| format!("删除失败: {}", e))?;
         Ok(())
     }
     
-    /// 清空记录
-    ///
-    /// Args:
-    ///     keep_grouped: true = 保留已加入分组的条目（只删历史区），false = 删除全部
-    pub fn clear_all(&self, keep_grouped: bool) -> Result<(), String> {
-        // 构建 WHERE 条件
-        let where_clause = if keep_grouped {
-            "WHERE group_id IS NULL"
-        } else {
-            ""
-        };
+    /// 移入回收站：记录保留在数据库中（图片文件也保留），但不再出现在历史、分组和去重中
+    /// 
+    /// 已在回收站中的记录不变（保留原来的删除时间）
+    pub fn soft_delete(&self, id: i64) -> Result<(), ClipboardError> {
+        let deleted_at: Option<i64> = self.conn.query_row(
+            "SELECT deleted_at FROM clipboard WHERE id = ?",
+            params![id],
+            |row| row.get(0)
+        ).optional()?
+        .ok_or(ClipboardError::NotFound(id))?;
+        if deleted_at.is_none() {
+            self.conn.execute(
+                "UPDATE clipboard SET deleted_at = ? WHERE id = ?",
+                params![chrono::Local::now().timestamp(), id],
+            )?;
+        }
+        Ok(())
+    }
+    
+    /// 分页查询回收站，最近删除的在前
+    pub fn get_trash(&self, offset: i64, limit: i64) -> Result<PyPaginatedResult, ClipboardError> {
+        let total_count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM clipboard WHERE deleted_at IS NOT NULL",
+            [],
+            |row| row.get(0)
+        )?;
+        
+        let mut stmt = self.conn.prepare(
+            "SELECT id, title, content, html_content, content_type, image_id, thumbnail, is_pinned, 
+             paste_count, source_app, char_count, created_at, updated_at,
+             image_width, image_height, image_bytes, image_format, formats, content_compressed, subtype 
+             FROM clipboard WHERE deleted_at IS NOT NULL 
+             ORDER BY deleted_at DESC, id DESC 
+             LIMIT ? OFFSET ?"
+        )?;
+        let items: Vec<PyClipboardItem> = stmt.query_map(params![limit, offset], Self::item_from_row)?
+            .filter_map(|r| r.ok())
+            .collect();
+        
+        Ok(PyPaginatedResult::new(total_count, items, offset, limit))
+    }
+    
+    /// 从回收站恢复记录，排到历史最前（置顶、分组等状态保持删除前的样子）
+    pub fn restore_item(&self, id: i64) -> Result<(), ClipboardError> {
+        let deleted_at: Option<i64> = self.conn.query_row(
+            "SELECT deleted_at FROM clipboard WHERE id = ?",
+            params![id],
+            |row| row.get(0)
+        ).optional()?
+        .ok_or(ClipboardError::NotFound(id))?;
+        if deleted_at.is_none() {
+            return Err(ClipboardError::InvalidArgument(format!("记录 {} 不在回收站中", id)));
+        }
+        
+        self.conn.execute(
+            "UPDATE clipboard SET deleted_at = NULL,
+             item_order = (SELECT COALESCE(MAX(item_order), 0) + 1000 FROM clipboard)
+             WHERE id = ?",
+            params![id],
+        )?;
+        Ok(())
+    }
+    
+    /// 清空回收站：彻底删除其中的全部记录，并删除不再被引用的图片文件
+    /// 
+    /// Returns:
+    ///     删除的记录数
+    pub fn empty_trash(&self) -> Result<i64, ClipboardError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT image_id FROM clipboard
+             WHERE deleted_at IS NOT NULL AND image_id IS NOT NULL AND image_id != ''"
+        )?;
+        let image_ids: Vec<String> = stmt.query_map([], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        
+        // ON DELETE CASCADE 自动清理 clipboard_formats
+        let deleted = self.conn.execute("DELETE FROM clipboard WHERE deleted_at IS NOT NULL", [])?;
+        self.remove_unreferenced_images(image_ids);
+        Ok(deleted as i64)
+    }
+    
+    /// 加入收藏夹：记录从历史中移出，不参与自动清理和清空历史，只能通过 get_favorites 查询
+    pub fn add_to_favorites(&self, id: i64) -> Result<(), ClipboardError> {
+        self.set_favorite(id, true)
+    }
+    
+    /// 移出收藏夹，记录回到历史中原来的位置
+    pub fn remove_from_favorites(&self, id: i64) -> Result<(), ClipboardError> {
+        self.set_favorite(id, false)
+    }
+    
+    fn set_favorite(&self, id: i64, favorite: bool) -> Result<(), ClipboardError> {
+        let updated = self.conn.execute(
+            "UPDATE clipboard SET is_favorite = ? WHERE id = ?",
+            params![favorite as i64, id],
+        )?;
+        if updated == 0 {
+            return Err(ClipboardError::NotFound(id));
+        }
+        Ok(())
+    }
+    
+    /// 分页查询收藏夹，排序与历史相同（新的在前）
+    pub fn get_favorites(&self, offset: i64, limit: i64) -> Result<PyPaginatedResult, ClipboardError> {
+        let total_count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM clipboard WHERE is_favorite = 1 AND deleted_at IS NULL",
+            [],
+            |row| row.get(0)
+        )?;
+        
+        let mut stmt = self.conn.prepare(
+            "SELECT id, title, content, html_content, content_type, image_id, thumbnail, is_pinned, 
+             paste_count, source_app, char_count, created_at, updated_at,
+             image_width, image_height, image_bytes, image_format, formats, content_compressed, subtype 
+             FROM clipboard WHERE is_favorite = 1 AND deleted_at IS NULL 
+             ORDER BY item_order DESC, id DESC 
+             LIMIT ? OFFSET ?"
+        )?;
+        let items: Vec<PyClipboardItem> = stmt.query_map(params![limit, offset], Self::item_from_row)?
+            .filter_map(|r| r.ok())
+            .collect();
+        
+        Ok(PyPaginatedResult::new(total_count, items, offset, limit))
+    }
+    
+    /// 清空记录（收藏夹始终保留）
+    ///
+    /// Args:
+    ///     keep_grouped: true = 保留已加入分组的条目（只删历史区），false = 删除全部
+    pub fn clear_all(&self, keep_grouped: bool) -> Result<(), ClipboardError> {
+        // 构建 WHERE 条件
+        let where_clause = if keep_grouped {
+            "WHERE is_favorite = 0 AND group_id IS NULL"
+        } else {
+            "WHERE is_favorite = 0"
+        };
 
         // 先获取要删除记录的 image_id，以便清理图片文件
         let sql_images = if keep_grouped {
-            "SELECT image_id FROM clipboard WHERE group_id IS NULL AND image_id IS NOT NULL AND image_id != ''"
+            "SELECT image_id FROM clipboard WHERE is_favorite = 0 AND group_id IS NULL AND image_id IS NOT NULL AND image_id != ''"
         } else {
-            "SELECT image_id FROM clipboard WHERE image_id IS NOT NULL AND image_id != ''"
+            "SELECT image_id FROM clipboard WHERE is_favorite = 0 AND image_id IS NOT NULL AND image_id != ''"
         };
 
-        let mut stmt = self.conn.prepare(sql_images)
-            .map_err(|e| format!("准备查询失败: {}", e))?;
+        let mut stmt = self.conn.prepare(sql_images)?;
 
-        let image_ids: Vec<String> = stmt.query_map([], |row| row.get(0))
-            .map_err(|e| format!("查询失败: {}", e))?
+        let image_ids: Vec<String> = stmt.query_map([], |row| row.get(0))?
             .filter_map(|r| r.ok())
             .collect();
 
-        // 删除图片文件
-        let images_dir = self.get_images_dir();
-        for img_id in image_ids {
-            let image_path = images_dir.join(format!("{}.png", img_id));
-            let _ = std::fs::remove_file(&image_path);
-        }
-
         // 删除记录（ON DELETE CASCADE 自动清理 clipboard_formats）
         let sql_delete = format!("DELETE FROM clipboard {}", where_clause);
-        self.conn.execute(&sql_delete, [])
-            .map_err(|e| format!("清空失败: {}", e))?;
+        self.conn.execute(&sql_delete, [])?;
+
+        // 删除图片文件
+        self.remove_unreferenced_images(image_ids);
 
         // WAL checkpoint：把 WAL 文件的内容合并回主库并截断 WAL 文件
         let _ = self.conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);");
 
         // VACUUM：整理主库文件，将空闲页回收给操作系统，文件大小真正缩小
-        self.conn.execute_batch("VACUUM;")
-            .map_err(|e| format!("VACUUM 失败: {}", e))?;
+        self.conn.execute_batch("VACUUM;")?;
 
         Ok(())
     }
     
+    /// 设置最大置顶数量（0 表示不限制），已置顶的项不受影响
+    pub fn set_max_pinned(&mut self, limit: u32) {
+        self.max_pinned = limit;
+    }
+    
+    /// 获取最大置顶数量
+    pub fn get_max_pinned(&self) -> u32 {
+        self.max_pinned
+    }
+    
+    /// 设置重复内容的判定范围，只影响之后插入的记录
+    pub fn set_dedup_scope(&mut self, scope: DedupScope) {
+        self.dedup_scope = scope;
+    }
+    
+    /// 获取重复内容的判定范围
+    pub fn get_dedup_scope(&self) -> DedupScope {
+        self.dedup_scope
+    }
+    
+    /// 设置重复内容的判定方式，只影响之后插入的记录
+    pub fn set_dedup_strategy(&mut self, strategy: DeduplicationStrategy) {
+        self.dedup_strategy = strategy;
+    }
+    
+    /// 获取重复内容的判定方式
+    pub fn get_dedup_strategy(&self) -> DeduplicationStrategy {
+        self.dedup_strategy
+    }
+    
+    /// 设置是否压缩存储大段文本，只影响之后插入或修改的记录
+    /// 
+    /// 压缩存储的记录 content 列只保留前缀，关键词搜索（LIKE）只能匹配到前缀部分
+    pub fn set_text_compression(&mut self, enabled: bool) {
+        self.compress_text = enabled;
+    }
+    
+    /// 获取是否压缩存储大段文本
+    pub fn get_text_compression(&self) -> bool {
+        self.compress_text
+    }
+    
     /// 切换置顶状态
-    pub fn toggle_pin(&self, id: i64) -> Result<bool, String> {
-        let current: i64 = self.conn.query_row(
+    /// 
+    /// 先读取当前状态再取反：两次切换请求几乎同时到达时会互相抵消，
+    /// 需要确定结果的场景请用 set_pin
+    pub fn toggle_pin(&self, id: i64) -> Result<bool, ClipboardError> {
+        let current = self.is_pinned(id)?;
+        self.set_pin(id, !current)
+    }
+    
+    /// 设置置顶状态（幂等：已是目标状态时不做任何修改）
+    /// 
+    /// 新置顶的项排在所有置顶项的最上面；达到置顶上限时返回错误
+    /// 
+    /// Returns:
+    ///     最终的置顶状态
+    pub fn set_pin(&self, id: i64, pinned: bool) -> Result<bool, ClipboardError> {
+        let now = chrono::Local::now().timestamp();
+        
+        if !pinned {
+            self.conn.execute(
+                "UPDATE clipboard SET is_pinned = 0, pin_order = 0, updated_at = ? WHERE id = ? AND is_pinned = 1",
+                params![now, id]
+            )?;
+            return self.is_pinned(id);
+        }
+        
+        if self.is_pinned(id)? {
+            return Ok(true);
+        }
+        
+        if self.max_pinned > 0 {
+            let pinned: i64 = self.conn.query_row(
+                "SELECT COUNT(*) FROM clipboard WHERE is_pinned = 1 AND deleted_at IS NULL",
+                [],
+                |row| row.get(0)
+            )?;
+            if pinned >= self.max_pinned as i64 {
+                return Err(ClipboardError::InvalidArgument(format!("置顶数量已达上限 ({})", self.max_pinned)));
+            }
+        }
+        
+        // 只更新未置顶的记录，避免重复置顶改变已有的 pin_order
+        self.conn.execute(
+            "UPDATE clipboard SET is_pinned = 1,
+             pin_order = (SELECT COALESCE(MAX(pin_order), 0) + 1000 FROM clipboard WHERE is_pinned = 1),
+             updated_at = ? WHERE id = ? AND is_pinned = 0",
+            params![now, id]
+        )?;
+        
+        Ok(true)
+    }
+    
+    /// 设置记录的保护状态：受保护的记录不会被 cleanup_old_items / cleanup_by_bytes 删除
+    pub fn set_protected(&self, id: i64, protected: bool) -> Result<(), ClipboardError> {
+        let updated = self.conn.execute(
+            "UPDATE clipboard SET protected = ?, updated_at = ? WHERE id = ?",
+            params![protected as i64, chrono::Local::now().timestamp(), id],
+        )?;
+        if updated == 0 {
+            return Err(ClipboardError::NotFound(id));
+        }
+        Ok(())
+    }
+    
+    /// 查询记录是否受保护（记录不存在时返回错误）
+    pub fn is_protected(&self, id: i64) -> Result<bool, ClipboardError> {
+        self.conn.query_row(
+            "SELECT protected FROM clipboard WHERE id = ?",
+            params![id],
+            |row| row.get::<_, i64>(0)
+        ).optional()?
+        .map(|v| v != 0)
+        .ok_or(ClipboardError::NotFound(id))
+    }
+    
+    /// 查询记录当前是否置顶（记录不存在时返回错误）
+    fn is_pinned(&self, id: i64) -> Result<bool, ClipboardError> {
+        self.conn.query_row(
+            "SELECT is_pinned FROM clipboard WHERE id = ?",
+            params![id],
+            |row| row.get::<_, i64>(0)
+        ).optional()?
+        .map(|v| v != 0)
+        .ok_or(ClipboardError::NotFound(id))
+    }
+    
+    /// 移动置顶项到指定位置（置顶区拖拽排序，与 item_order 相互独立）
+    /// 
+    /// 置顶项按 pin_order DESC 排序（大的在上面）
+    /// 
+    /// Args:
+    ///     id: 要移动的置顶项
+    ///     before_id: 它上方的置顶项（None = 移到最前）
+    ///     after_id: 它下方的置顶项（None = 移到最后）
+    pub fn move_pinned_between(
+        &self,
+        id: i64,
+        before_id: Option<i64>,
+        after_id: Option<i64>,
+    ) -> Result<(), ClipboardError> {
+        let is_pinned: i64 = self.conn.query_row(
             "SELECT is_pinned FROM clipboard WHERE id = ?",
             params![id],
             |row| row.get(0)
-        ).map_err(|e| format!("查询失败: {}", e))?;
+        ).optional()?
+        .ok_or(ClipboardError::NotFound(id))?;
+        if is_pinned == 0 {
+            return Err(ClipboardError::InvalidArgument(format!("记录 {} 未置顶", id)));
+        }
+        
+        self.move_pinned_between_impl(id, before_id, after_id, 0)
+    }
+    
+    /// 内部实现，带递归深度检查
+    fn move_pinned_between_impl(
+        &self,
+        id: i64,
+        before_id: Option<i64>,
+        after_id: Option<i64>,
+        depth: i32,
+    ) -> Result<(), ClipboardError> {
+        if depth > 5 {
+            return Err(ClipboardError::InvalidArgument("重新索引次数过多，请检查 before_id / after_id 是否相邻".to_string()));
+        }
+        
+        // 获取上方项的 pin_order（应该更大）
+        let upper_order = if let Some(bid) = before_id {
+            self.conn.query_row(
+                "SELECT pin_order FROM clipboard WHERE id = ?",
+                params![bid],
+                |row| row.get::<_, i64>(0)
+            ).unwrap_or(i64::MAX)
+        } else {
+            self.conn.query_row(
+                "SELECT COALESCE(MAX(pin_order), 0) + 1000 FROM clipboard WHERE is_pinned = 1",
+                [],
+                |row| row.get::<_, i64>(0)
+            ).unwrap_or(1000)
+        };
+        
+        // 获取下方项的 pin_order（应该更小）
+        let lower_order = if let Some(aid) = after_id {
+            self.conn.query_row(
+                "SELECT pin_order FROM clipboard WHERE id = ?",
+                params![aid],
+                |row| row.get::<_, i64>(0)
+            ).unwrap_or(i64::MIN)
+        } else {
+            self.conn.query_row(
+                "SELECT COALESCE(MIN(pin_order), 1000) - 1000 FROM clipboard WHERE is_pinned = 1",
+                [],
+                |row| row.get::<_, i64>(0)
+            ).unwrap_or(0)
+        };
+        
+        // 检查空间是否足够（upper 应该 > lower）
+        if upper_order <= lower_order || upper_order - lower_order < 10 {
+            self.reindex_pinned_items()?;
+            return self.move_pinned_between_impl(id, before_id, after_id, depth + 1);
+        }
         
-        let new_state = if current == 0 { 1 } else { 0 };
+        let new_order = lower_order + (upper_order - lower_order) / 2;
         
         self.conn.execute(
-            "UPDATE clipboard SET is_pinned = ?, updated_at = ? WHERE id = ?",
-            params![new_state, chrono::Local::now().timestamp(), id]
-        ).map_err(|e| format!("更新失败: {}", e))?;
+            "UPDATE clipboard SET pin_order = ?, updated_at = ? WHERE id = ?",
+            params![new_order, chrono::Local::now().timestamp(), id],
+        )?;
+        
+        Ok(())
+    }
+    
+    /// 重新索引置顶项的 pin_order（按当前顺序重新分配稀疏值）
+    fn reindex_pinned_items(&self) -> Result<(), ClipboardError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM clipboard WHERE is_pinned = 1 ORDER BY pin_order DESC, item_order DESC"
+        )?;
+        
+        let ids: Vec<i64> = stmt.query_map([], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        
+        // 倒序分配（DESC 排序，第一个最大）
+        for (index, id) in ids.iter().enumerate() {
+            let new_order = (ids.len() - index) as i64 * 1000;
+            self.conn.execute(
+                "UPDATE clipboard SET pin_order = ? WHERE id = ?",
+                params![new_order, id],
+            )?;
+        }
         
-        Ok(new_state == 1)
+        Ok(())
     }
     
     // ==================== 分组功能 ====================
     
     /// 创建分组
-    pub fn create_group(&self, name: &str, color: Option<&str>, icon: Option<&str>) -> Result<i64, String> {
+    pub fn create_group(&self, name: &str, color: Option<&str>, icon: Option<&str>) -> Result<i64, ClipboardError> {
         let now = chrono::Local::now().timestamp();
         let max_order: i64 = self.conn.query_row(
             "SELECT COALESCE(MAX(item_order), 0) FROM groups",
@@ -431,19 +1388,33 @@ impl Database {
             |row| row.get(0)
         ).unwrap_or(0);
         
-        self.conn.execute(
+        let inserted = self.conn.execute(
             "INSERT INTO groups (name, color, icon, item_order, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
             params![name, color, icon, max_order + 1000, now],
-        ).map_err(|e| format!("创建分组失败: {}", e))?;
+        );
+        if let Err(rusqlite::Error::SqliteFailure(e, _)) = &inserted {
+            // name 有唯一约束：同名分组已存在时返回已有分组的 ID
+            if e.code == rusqlite::ErrorCode::ConstraintViolation {
+                let existing: Option<i64> = self.conn.query_row(
+                    "SELECT id FROM groups WHERE name = ?",
+                    params![name],
+                    |row| row.get(0)
+                ).optional()?;
+                if let Some(existing) = existing {
+                    return Err(ClipboardError::Duplicate(existing));
+                }
+            }
+        }
+        inserted?;
         
         Ok(self.conn.last_insert_rowid())
     }
     
     /// 获取所有分组
-    pub fn get_groups(&self) -> Result<Vec<PyGroup>, String> {
+    pub fn get_groups(&self) -> Result<Vec<PyGroup>, ClipboardError> {
         let mut stmt = self.conn.prepare(
             "SELECT id, name, color, icon, item_order, created_at FROM groups ORDER BY item_order ASC"
-        ).map_err(|e| format!("查询分组失败: {}", e))?;
+        )?;
         
         let groups = stmt.query_map([], |row| {
             Ok(PyGroup {
@@ -454,7 +1425,7 @@ impl Database {
                 item_order: row.get(4)?,
                 created_at: row.get(5)?,
             })
-        }).map_err(|e| format!("查询分组失败: {}", e))?
+        })?
         .filter_map(|r| r.ok())
         .collect();
         
@@ -462,56 +1433,55 @@ impl Database {
     }
     
     /// 删除分组
-    pub fn delete_group(&self, id: i64) -> Result<(), String> {
+    pub fn delete_group(&self, id: i64) -> Result<(), ClipboardError> {
         // 先将该分组下的项目移到无分组
         self.conn.execute(
             "UPDATE clipboard SET group_id = NULL WHERE group_id = ?",
             params![id],
-        ).map_err(|e| format!("更新项目失败: {}", e))?;
+        )?;
         
-        self.conn.execute("DELETE FROM groups WHERE id = ?", params![id])
-            .map_err(|e| format!("删除分组失败: {}", e))?;
+        self.conn.execute("DELETE FROM groups WHERE id = ?", params![id])?;
         Ok(())
     }
     
     /// 重命名分组
-    pub fn rename_group(&self, id: i64, name: &str) -> Result<(), String> {
+    pub fn rename_group(&self, id: i64, name: &str) -> Result<(), ClipboardError> {
         self.conn.execute(
             "UPDATE groups SET name = ? WHERE id = ?",
             params![name, id],
-        ).map_err(|e| format!("重命名分组失败: {}", e))?;
+        )?;
         Ok(())
     }
     
     /// 更新分组（名称、颜色、图标）
-    pub fn update_group(&self, id: i64, name: &str, color: Option<&str>, icon: Option<&str>) -> Result<(), String> {
+    pub fn update_group(&self, id: i64, name: &str, color: Option<&str>, icon: Option<&str>) -> Result<(), ClipboardError> {
         self.conn.execute(
             "UPDATE groups SET name = ?, color = ?, icon = ? WHERE id = ?",
             params![name, color, icon, id],
-        ).map_err(|e| format!("更新分组失败: {}", e))?;
+        )?;
         Ok(())
     }
     
     /// 将项目移动到分组
-    pub fn move_to_group(&self, item_id: i64, group_id: Option<i64>) -> Result<(), String> {
+    pub fn move_to_group(&self, item_id: i64, group_id: Option<i64>) -> Result<(), ClipboardError> {
         self.conn.execute(
             "UPDATE clipboard SET group_id = ?, updated_at = ? WHERE id = ?",
             params![group_id, chrono::Local::now().timestamp(), item_id],
-        ).map_err(|e| format!("移动到分组失败: {}", e))?;
+        )?;
         Ok(())
     }
     
     /// 按分组查询
-    pub fn query_by_group(&self, group_id: Option<i64>, offset: i64, limit: i64) -> Result<PyPaginatedResult, String> {
+    pub fn query_by_group(&self, group_id: Option<i64>, offset: i64, limit: i64) -> Result<PyPaginatedResult, ClipboardError> {
         let (where_clause, _count_params, _query_params): (String, Vec<i64>, Vec<i64>) = if let Some(gid) = group_id {
             (
-                "WHERE group_id = ?".to_string(),
+                "WHERE group_id = ? AND deleted_at IS NULL".to_string(),
                 vec![gid],
                 vec![gid, limit, offset]
             )
         } else {
             (
-                "WHERE group_id IS NULL".to_string(),
+                "WHERE group_id IS NULL AND deleted_at IS NULL AND is_favorite = 0".to_string(),
                 vec![],
                 vec![limit, offset]
             )
@@ -535,39 +1505,23 @@ impl Database {
         // 查询数据 - 分组内按 ASC 排序（新内容在下，适合收藏内容）
         let query_sql = format!(
             "SELECT id, title, content, html_content, content_type, image_id, thumbnail, is_pinned, 
-             paste_count, source_app, char_count, created_at, updated_at 
+             paste_count, source_app, char_count, created_at, updated_at,
+             image_width, image_height, image_bytes, image_format, formats, content_compressed, subtype 
              FROM clipboard {} 
              ORDER BY is_pinned DESC, item_order ASC 
              LIMIT ? OFFSET ?",
             where_clause
         );
         
-        let mut stmt = self.conn.prepare(&query_sql)
-            .map_err(|e| format!("准备查询失败: {}", e))?;
+        let mut stmt = self.conn.prepare(&query_sql)?;
         
-        let map_row = |row: &rusqlite::Row| -> rusqlite::Result<PyClipboardItem> {
-            Ok(PyClipboardItem {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                html_content: row.get(3)?,
-                content_type: row.get(4)?,
-                image_id: row.get(5)?,
-                thumbnail: row.get(6)?,
-                is_pinned: row.get::<_, i64>(7)? != 0,
-                paste_count: row.get(8)?,
-                source_app: row.get(9)?,
-                char_count: row.get(10)?,
-                created_at: row.get(11)?,
-                updated_at: row.get(12)?,
-            })
-        };
+        let map_row = Self::item_from_row;
         
         let items: Vec<PyClipboardItem> = if group_id.is_some() {
             stmt.query_map(params![group_id.unwrap(), limit, offset], map_row)
         } else {
             stmt.query_map(params![limit, offset], map_row)
-        }.map_err(|e| format!("查询失败: {}", e))?
+        }?
         .filter_map(|r| r.ok())
         .collect();
         
@@ -575,11 +1529,11 @@ impl Database {
     }
     
     /// 增加粘贴次数
-    pub fn increment_paste_count(&self, id: i64) -> Result<i64, String> {
+    pub fn increment_paste_count(&self, id: i64) -> Result<i64, ClipboardError> {
         self.conn.execute(
             "UPDATE clipboard SET paste_count = paste_count + 1, updated_at = ? WHERE id = ?",
             params![chrono::Local::now().timestamp(), id],
-        ).map_err(|e| format!("更新粘贴次数失败: {}", e))?;
+        )?;
         
         let count: i64 = self.conn.query_row(
             "SELECT paste_count FROM clipboard WHERE id = ?",
@@ -591,11 +1545,11 @@ impl Database {
     }
     
     /// 将某项移到最前（更新 item_order 为最大值 + 1000）
-    pub fn move_item_to_top(&self, id: i64) -> Result<(), String> {
+    pub fn move_item_to_top(&self, id: i64) -> Result<(), ClipboardError> {
         self.conn.execute(
             "UPDATE clipboard SET item_order = (SELECT COALESCE(MAX(item_order), 0) + 1000 FROM clipboard), updated_at = ? WHERE id = ?",
             params![chrono::Local::now().timestamp(), id],
-        ).map_err(|e| format!("移动到最前失败: {}", e))?;
+        )?;
         Ok(())
     }
     
@@ -612,7 +1566,7 @@ impl Database {
         id: i64,
         before_id: Option<i64>,
         after_id: Option<i64>,
-    ) -> Result<(), String> {
+    ) -> Result<(), ClipboardError> {
         self.move_item_between_impl(id, before_id, after_id, 0)
     }
     
@@ -623,10 +1577,10 @@ impl Database {
         before_id: Option<i64>,
         after_id: Option<i64>,
         depth: i32,
-    ) -> Result<(), String> {
+    ) -> Result<(), ClipboardError> {
         // 防止无限递归
         if depth > 5 {
-            return Err("重新索引次数过多，可能存在问题".to_string());
+            return Err(ClipboardError::InvalidArgument("重新索引次数过多，请检查 before_id / after_id 是否相邻".to_string()));
         }
         
         // 注意：分组内容使用 ASC 排序（小的在上面）
@@ -686,7 +1640,7 @@ impl Database {
         self.conn.execute(
             "UPDATE clipboard SET item_order = ?, updated_at = ? WHERE id = ?",
             params![new_order, chrono::Local::now().timestamp(), id],
-        ).map_err(|e| format!("移动失败: {}", e))?;
+        )?;
         
         Ok(())
     }
@@ -704,7 +1658,7 @@ impl Database {
         id: i64,
         before_id: Option<i64>,
         after_id: Option<i64>,
-    ) -> Result<(), String> {
+    ) -> Result<(), ClipboardError> {
         self.move_group_between_impl(id, before_id, after_id, 0)
     }
     
@@ -715,10 +1669,10 @@ impl Database {
         before_id: Option<i64>,
         after_id: Option<i64>,
         depth: i32,
-    ) -> Result<(), String> {
+    ) -> Result<(), ClipboardError> {
         // 防止无限递归
         if depth > 5 {
-            return Err("分组重新索引次数过多，可能存在问题".to_string());
+            return Err(ClipboardError::InvalidArgument("分组重新索引次数过多，请检查 before_id / after_id 是否相邻".to_string()));
         }
         
         // 注意：界面按 item_order ASC 排序（小的在上面）
@@ -772,7 +1726,7 @@ impl Database {
         self.conn.execute(
             "UPDATE groups SET item_order = ? WHERE id = ?",
             params![new_order, id],
-        ).map_err(|e| format!("移动分组失败: {}", e))?;
+        )?;
         
         Ok(())
     }
@@ -781,14 +1735,13 @@ impl Database {
     /// 
     /// 只在空间不足时调用，重新分配为 1000, 2000, 3000, ...
     #[allow(dead_code)]
-    fn reindex_clipboard_items(&self) -> Result<(), String> {
+    fn reindex_clipboard_items(&self) -> Result<(), ClipboardError> {
         // 按当前排序获取所有 ID
         let mut stmt = self.conn.prepare(
-            "SELECT id FROM clipboard ORDER BY is_pinned DESC, item_order DESC"
-        ).map_err(|e| format!("准备查询失败: {}", e))?;
+            "SELECT id FROM clipboard ORDER BY is_pinned DESC, pin_order DESC, item_order DESC"
+        )?;
         
-        let ids: Vec<i64> = stmt.query_map([], |row| row.get(0))
-            .map_err(|e| format!("查询失败: {}", e))?
+        let ids: Vec<i64> = stmt.query_map([], |row| row.get(0))?
             .filter_map(|r| r.ok())
             .collect();
         
@@ -798,21 +1751,20 @@ impl Database {
             self.conn.execute(
                 "UPDATE clipboard SET item_order = ? WHERE id = ?",
                 params![new_order, id],
-            ).map_err(|e| format!("重新索引失败: {}", e))?;
+            )?;
         }
         
         Ok(())
     }
     
     /// 重新索引分组的 item_order（按当前顺序重新分配稀疏值）
-    fn reindex_groups(&self) -> Result<(), String> {
+    fn reindex_groups(&self) -> Result<(), ClipboardError> {
         // 按当前排序获取所有 ID（ASC：小的在前，旧的在前）
         let mut stmt = self.conn.prepare(
             "SELECT id FROM groups ORDER BY item_order ASC"
-        ).map_err(|e| format!("准备查询失败: {}", e))?;
+        )?;
         
-        let ids: Vec<i64> = stmt.query_map([], |row| row.get(0))
-            .map_err(|e| format!("查询失败: {}", e))?
+        let ids: Vec<i64> = stmt.query_map([], |row| row.get(0))?
             .filter_map(|r| r.ok())
             .collect();
         
@@ -822,21 +1774,20 @@ impl Database {
             self.conn.execute(
                 "UPDATE groups SET item_order = ? WHERE id = ?",
                 params![new_order, id],
-            ).map_err(|e| format!("重新索引分组失败: {}", e))?;
+            )?;
         }
         
         Ok(())
     }
     
     /// 重新索引分组内容的 item_order（按当前顺序重新分配稀疏值）
-    fn reindex_group_items(&self, group_id: i64) -> Result<(), String> {
+    fn reindex_group_items(&self, group_id: i64) -> Result<(), ClipboardError> {
         // 按当前排序获取该分组内所有内容的 ID（ASC：小的在前）
         let mut stmt = self.conn.prepare(
             "SELECT id FROM clipboard WHERE group_id = ? ORDER BY item_order ASC"
-        ).map_err(|e| format!("准备查询失败: {}", e))?;
+        )?;
         
-        let ids: Vec<i64> = stmt.query_map(params![group_id], |row| row.get(0))
-            .map_err(|e| format!("查询失败: {}", e))?
+        let ids: Vec<i64> = stmt.query_map(params![group_id], |row| row.get(0))?
             .filter_map(|r| r.ok())
             .collect();
         
@@ -846,26 +1797,92 @@ impl Database {
             self.conn.execute(
                 "UPDATE clipboard SET item_order = ? WHERE id = ?",
                 params![new_order, id],
-            ).map_err(|e| format!("重新索引分组内容失败: {}", e))?;
+            )?;
         }
         
         Ok(())
     }
     
     /// 更新内容项（标题和内容）
-    pub fn update_item(&self, id: i64, title: Option<&str>, content: &str) -> Result<(), String> {
+    pub fn update_item(&self, id: i64, title: Option<&str>, content: &str) -> Result<(), ClipboardError> {
+        // 内容变化后同步更新哈希（哈希包含 html_content，需要先取出）
+        let (html_content, content_type): (Option<String>, String) = match self.conn.query_row(
+            "SELECT html_content, content_type FROM clipboard WHERE id = ?",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ) {
+            Ok(row) => row,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let content_hash = compute_content_hash(content, html_content.as_deref());
+        // 按当前的压缩设置重新存储（未超过阈值时清空 content_compressed）
+        let (stored, content_compressed) = encode_text_content(content, &content_type, self.compress_text);
+        let subtype = detect_subtype(content, &content_type);
+        let urls = urls_json(content, &content_type);
+
         self.conn.execute(
-            "UPDATE clipboard SET title = ?, content = ?, updated_at = ? WHERE id = ?",
-            params![title, content, chrono::Local::now().timestamp(), id],
-        ).map_err(|e| format!("更新内容失败: {}", e))?;
+            "UPDATE clipboard SET title = ?, content = ?, content_compressed = ?, content_hash = ?, subtype = ?, urls = ?,
+             updated_at = ? WHERE id = ?",
+            params![
+                title,
+                stored.as_ref(),
+                content_compressed,
+                content_hash,
+                subtype,
+                urls,
+                chrono::Local::now().timestamp(),
+                id
+            ],
+        )?;
         Ok(())
     }
 
+    /// 为已有的文本记录补算由内容派生的字段（新增 subtype / urls 字段时执行一次）
+    fn backfill_text_column(
+        conn: &mut Connection,
+        column: &str,
+        derive: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), ClipboardError> {
+        let tx = conn.transaction()?;
+        {
+            let mut select = tx.prepare("SELECT id, content FROM clipboard WHERE content_type = 'text'")?;
+            let mut update = tx.prepare(&format!("UPDATE clipboard SET {} = ? WHERE id = ?", column))?;
+            let rows = select.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (id, content) = row?;
+                if let Some(value) = derive(&content) {
+                    update.execute(params![value, id])?;
+                }
+            }
+        }
+        tx.commit().map_err(ClipboardError::from)
+    }
+
+    /// 为 content_hash 为空的记录补算哈希（新增 content_hash 字段时执行一次）
+    fn backfill_content_hash(conn: &mut Connection) -> Result<(), ClipboardError> {
+        let tx = conn.transaction()?;
+        {
+            let mut select = tx.prepare(
+                "SELECT id, content, html_content FROM clipboard WHERE content_hash IS NULL",
+            )?;
+            let mut update = tx.prepare("UPDATE clipboard SET content_hash = ? WHERE id = ?")?;
+            let rows = select.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?))
+            })?;
+            for row in rows {
+                let (id, content, html_content) = row?;
+                update.execute(params![compute_content_hash(&content, html_content.as_deref()), id])?;
+            }
+        }
+        tx.commit().map_err(ClipboardError::from)
+    }
+
     // ==================== 原始格式存取（Ditto 风格）====================
 
     /// 保存一批原始剪贴板格式数据，关联到指定 event_id（即 clipboard.id）
     /// 数据在此函数内进行 zstd 压缩（超过阈值时），适合外部传入原始数据的场景
-    pub fn insert_formats(&self, event_id: i64, formats: &[(u32, String, Vec<u8>)]) -> Result<(), String> {
+    pub fn insert_formats(&self, event_id: i64, formats: &[(u32, String, Vec<u8>)]) -> Result<(), ClipboardError> {
         for (format_id, format_name, data) in formats {
             let (store_data, compressed): (Vec<u8>, i64) =
                 if data.len() > COMPRESS_THRESHOLD {
@@ -881,7 +1898,7 @@ impl Database {
                 "INSERT OR IGNORE INTO clipboard_formats (event_id, format_id, format_name, data, compressed)
                  VALUES (?1, ?2, ?3, ?4, ?5)",
                 params![event_id, *format_id as i64, format_name, store_data, compressed],
-            ).map_err(|e| format!("插入 format 失败: {}", e))?;
+            )?;
         }
         Ok(())
     }
@@ -893,24 +1910,24 @@ impl Database {
         &self,
         event_id: i64,
         formats: &[(u32, String, Vec<u8>, bool)],
-    ) -> Result<(), String> {
+    ) -> Result<(), ClipboardError> {
         for (format_id, format_name, data, is_compressed) in formats {
             let compressed_flag: i64 = if *is_compressed { 1 } else { 0 };
             self.conn.execute(
                 "INSERT OR IGNORE INTO clipboard_formats (event_id, format_id, format_name, data, compressed)
                  VALUES (?1, ?2, ?3, ?4, ?5)",
                 params![event_id, *format_id as i64, format_name, data, compressed_flag],
-            ).map_err(|e| format!("插入 format 失败: {}", e))?;
+            )?;
         }
         Ok(())
     }
 
     /// 读取某个 event 的所有原始格式数据（自动解压 zstd 数据）
     /// 返回 Vec<(format_id, format_name, data)>
-    pub fn get_formats(&self, event_id: i64) -> Result<Vec<(u32, String, Vec<u8>)>, String> {
+    pub fn get_formats(&self, event_id: i64) -> Result<Vec<(u32, String, Vec<u8>)>, ClipboardError> {
         let mut stmt = self.conn.prepare(
             "SELECT format_id, format_name, data, compressed FROM clipboard_formats WHERE event_id = ? ORDER BY format_id ASC"
-        ).map_err(|e| format!("准备查询 formats 失败: {}", e))?;
+        )?;
 
         let rows = stmt.query_map(params![event_id], |row| {
             Ok((
@@ -919,7 +1936,7 @@ impl Database {
                 row.get::<_, Vec<u8>>(2)?,
                 row.get::<_, i64>(3).unwrap_or(0),
             ))
-        }).map_err(|e| format!("查询 formats 失败: {}", e))?
+        })?
         .filter_map(|r| r.ok())
         .map(|(fid, fname, data, compressed)| {
             let decoded = if compressed == 1 {
@@ -936,31 +1953,31 @@ impl Database {
 
     /// 删除某个 event 的所有原始格式数据（级联删除时自动触发，也可手动调用）
     #[allow(dead_code)]
-    pub fn delete_formats(&self, event_id: i64) -> Result<(), String> {
+    pub fn delete_formats(&self, event_id: i64) -> Result<(), ClipboardError> {
         self.conn.execute(
             "DELETE FROM clipboard_formats WHERE event_id = ?",
             params![event_id],
-        ).map_err(|e| format!("删除 formats 失败: {}", e))?;
+        )?;
         Ok(())
     }
     
     /// 清理超出限制的旧记录
     /// 
-    /// 保留置顶项和分组内容，只删除非置顶、非分组的旧记录
+    /// 保留置顶项、受保护项、分组内容和收藏夹，只删除其余的旧记录
     /// 
     /// Args:
     ///     limit: 保留的最大记录数
     /// 
     /// Returns:
     ///     删除的记录数
-    pub fn cleanup_old_items(&self, limit: i64) -> Result<i64, String> {
+    pub fn cleanup_old_items(&self, limit: i64) -> Result<i64, ClipboardError> {
         if limit <= 0 {
             return Ok(0);
         }
         
         // 获取当前非分组内容的总数（只统计自动监听的历史记录）
         let total: i64 = self.conn.query_row(
-            "SELECT COUNT(*) FROM clipboard WHERE group_id IS NULL",
+            "SELECT COUNT(*) FROM clipboard WHERE group_id IS NULL AND is_favorite = 0",
             [],
             |row| row.get(0)
         ).unwrap_or(0);
@@ -978,38 +1995,954 @@ impl Database {
             "SELECT image_id FROM clipboard 
              WHERE id IN (
                  SELECT id FROM clipboard 
-                 WHERE is_pinned = 0 AND group_id IS NULL
+                 WHERE is_pinned = 0 AND protected = 0 AND group_id IS NULL AND is_favorite = 0
                  ORDER BY item_order ASC 
                  LIMIT ?
              )
              AND image_id IS NOT NULL AND image_id != ''"
-        ).map_err(|e| format!("准备查询失败: {}", e))?;
+        )?;
         
-        let image_ids: Vec<String> = stmt.query_map(params![to_delete], |row| row.get(0))
-            .map_err(|e| format!("查询失败: {}", e))?
+        let image_ids: Vec<String> = stmt.query_map(params![to_delete], |row| row.get(0))?
             .filter_map(|r| r.ok())
             .collect();
         
-        // 删除图片文件
-        let images_dir = self.get_images_dir();
-        for img_id in image_ids {
-            let image_path = images_dir.join(format!("{}.png", img_id));
-            let _ = std::fs::remove_file(&image_path);
-        }
-        
         // 删除最旧的非置顶、非分组记录
         // 按 item_order 升序（最旧的在前）
-        // 只清理自动监听的历史记录，不清理分组内的收藏内容
+        // 只清理自动监听的历史记录，不清理分组内的收藏内容和收藏夹
         let deleted = self.conn.execute(
             "DELETE FROM clipboard WHERE id IN (
                 SELECT id FROM clipboard 
-                WHERE is_pinned = 0 AND group_id IS NULL
+                WHERE is_pinned = 0 AND protected = 0 AND group_id IS NULL AND is_favorite = 0
                 ORDER BY item_order ASC 
                 LIMIT ?
             )",
             params![to_delete],
-        ).map_err(|e| format!("清理失败: {}", e))?;
+        )?;
         
+        // 删除图片文件
+        self.remove_unreferenced_images(image_ids);
+        
+        tracing::info!(limit, deleted, "超出条数上限，已清理旧记录");
         Ok(deleted as i64)
     }
+    
+    /// 记录图片的感知哈希
+    pub fn set_image_dhash(&self, id: i64, dhash: u64) -> Result<(), ClipboardError> {
+        // SQLite INTEGER 为有符号 64 位，按位原样存储
+        self.conn.execute(
+            "UPDATE clipboard SET image_dhash = ? WHERE id = ?",
+            params![dhash as i64, id],
+        )?;
+        Ok(())
+    }
+    
+    /// 获取最近一条图片记录的 (image_id, dHash)
+    pub fn latest_image_dhash(&self) -> Option<(String, u64)> {
+        self.conn.query_row(
+            "SELECT image_id, image_dhash FROM clipboard
+             WHERE content_type = 'image' AND image_id IS NOT NULL AND image_dhash IS NOT NULL AND deleted_at IS NULL
+             ORDER BY item_order DESC LIMIT 1",
+            [],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64)),
+        ).ok()
+    }
+    
+    /// 查找与指定图片记录相似的其他图片记录（按 dHash 汉明距离）
+    /// 
+    /// 距离在 Rust 中逐条计算，只读取 id 和哈希两列；没有记录 dHash 的旧记录、回收站和收藏夹中的记录不参与比较
+    /// 
+    /// Returns:
+    ///     (记录, 距离) 列表，距离从小到大，至多 limit 条
+    pub fn find_similar_images(&self, id: i64, max_distance: u32, limit: usize) -> Result<Vec<(PyClipboardItem, u32)>, ClipboardError> {
+        let target: Option<i64> = self.conn.query_row(
+            "SELECT image_dhash FROM clipboard WHERE id = ?",
+            params![id],
+            |row| row.get(0)
+        ).optional()?
+        .ok_or(ClipboardError::NotFound(id))?;
+        let target = target
+            .ok_or_else(|| ClipboardError::InvalidArgument(format!("记录 {} 没有图片哈希", id)))? as u64;
+        
+        let mut stmt = self.conn.prepare(
+            "SELECT id, image_dhash FROM clipboard
+             WHERE content_type = 'image' AND image_dhash IS NOT NULL AND id != ? AND deleted_at IS NULL AND is_favorite = 0"
+        )?;
+        let mut matches: Vec<(i64, u32)> = stmt
+            .query_map(params![id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? as u64)))?
+            .filter_map(|r| r.ok())
+            .map(|(other_id, hash)| (other_id, (hash ^ target).count_ones()))
+            .filter(|&(_, distance)| distance <= max_distance)
+            .collect();
+        matches.sort_by_key(|&(other_id, distance)| (distance, std::cmp::Reverse(other_id)));
+        matches.truncate(limit);
+        
+        let mut items = Vec::with_capacity(matches.len());
+        for (other_id, distance) in matches {
+            if let Some(item) = self.get_item_by_id(other_id)? {
+                items.push((item, distance));
+            }
+        }
+        Ok(items)
+    }
+    
+    /// 按相关度排序的搜索（范围同历史列表，不含回收站和收藏夹）
+    /// 
+    /// 两步完成：先用 LIKE 取出 content 包含任一关键词（按空白拆分）的记录的 id、内容、粘贴次数和顺序，
+    /// 再在 Rust 中逐条打分，只为得分最高的 limit 条读取完整记录：
+    /// 
+    ///     score = log2(1 + paste_count) * 0.4 + recency * 0.4 + match_ratio * 0.2
+    /// 
+    /// recency = 1 / (1 + 比它新的记录数)，按 item_order 与最大 item_order 的差（每条间隔 1000）估算；
+    /// match_ratio 为查询中（不含空白）的字符出现在内容里的比例，不区分大小写
+    /// 
+    /// Returns:
+    ///     (记录, 得分) 列表，得分从高到低；query 为空白时返回空列表
+    pub fn search_ranked(&self, query: &str, limit: usize) -> Result<Vec<(PyClipboardItem, f32)>, ClipboardError> {
+        let terms: Vec<String> = query.split_whitespace().map(|t| format!("%{}%", t)).collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let max_order: i64 = self.conn.query_row(
+            "SELECT COALESCE(MAX(item_order), 0) FROM clipboard WHERE deleted_at IS NULL AND is_favorite = 0",
+            [],
+            |row| row.get(0)
+        )?;
+        let sql = format!(
+            "SELECT id, content, paste_count, item_order FROM clipboard
+             WHERE deleted_at IS NULL AND is_favorite = 0 AND ({})",
+            vec!["content LIKE ?"; terms.len()].join(" OR ")
+        );
+        let query_chars: Vec<char> = query.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+        
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut scored: Vec<(i64, f32)> = stmt
+            .query_map(rusqlite::params_from_iter(&terms), |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?))
+            })?
+            .filter_map(|r| r.ok())
+            .map(|(id, content, paste_count, item_order)| {
+                let content = content.to_lowercase();
+                let matched = query_chars.iter().filter(|&&c| content.contains(c)).count();
+                let match_ratio = matched as f32 / query_chars.len() as f32;
+                let newer = (max_order - item_order).max(0) as f32 / 1000.0;
+                let recency = 1.0 / (1.0 + newer);
+                let frequency = (1.0 + paste_count.max(0) as f32).log2();
+                (id, frequency * 0.4 + recency * 0.4 + match_ratio * 0.2)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(b.0.cmp(&a.0)));
+        scored.truncate(limit);
+        
+        let mut items = Vec::with_capacity(scored.len());
+        for (id, score) in scored {
+            if let Some(item) = self.get_item_by_id(id)? {
+                items.push((item, score));
+            }
+        }
+        Ok(items)
+    }
+    
+    /// 按存储字节数清理：总占用（文本 + HTML 字节数 + 图片文件大小）超过 limit 时，
+    /// （压缩存储的文本按前缀 + 压缩后的字节数计算）
+    /// 从最旧的非置顶、非保护、非分组、不在收藏夹中的记录开始删除，直到降到 limit 以下
+    /// 
+    /// 多条记录共享同一图片文件时，图片大小只在最后一条引用被删除时计入释放量
+    /// 
+    /// Returns:
+    ///     删除的记录数
+    pub fn cleanup_by_bytes(&self, limit: u64) -> Result<i64, ClipboardError> {
+        if limit == 0 {
+            return Ok(0);
+        }
+        
+        let text_bytes: i64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(length(CAST(content AS BLOB)) + COALESCE(length(CAST(html_content AS BLOB)), 0)
+                                + COALESCE(length(content_compressed), 0)), 0) FROM clipboard",
+            [],
+            |row| row.get(0)
+        )?;
+        
+        // 每张图片的引用数和文件大小
+        let store = self.image_store();
+        let mut stmt = self.conn.prepare(
+            "SELECT image_id, COUNT(*) FROM clipboard
+             WHERE image_id IS NOT NULL AND image_id != ''
+             GROUP BY image_id"
+        )?;
+        let mut images: std::collections::HashMap<String, (i64, u64)> = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?
+            .filter_map(|r| r.ok())
+            .map(|(img_id, refs)| {
+                let size = store.size(&img_id).unwrap_or(0);
+                (img_id, (refs, size))
+            })
+            .collect();
+        
+        let mut total = text_bytes.max(0) as u64 + images.values().map(|(_, size)| size).sum::<u64>();
+        if total <= limit {
+            return Ok(0);
+        }
+        
+        // 与 cleanup_old_items 相同：只清理自动监听的历史记录，按 item_order 升序（最旧的在前）
+        let mut stmt = self.conn.prepare(
+            "SELECT id, length(CAST(content AS BLOB)) + COALESCE(length(CAST(html_content AS BLOB)), 0)
+                    + COALESCE(length(content_compressed), 0), image_id
+             FROM clipboard
+             WHERE is_pinned = 0 AND protected = 0 AND group_id IS NULL AND is_favorite = 0
+             ORDER BY item_order ASC"
+        )?;
+        let candidates = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, Option<String>>(2)?)))?;
+        
+        let mut ids_to_delete: Vec<i64> = Vec::new();
+        let mut image_ids: Vec<String> = Vec::new();
+        for candidate in candidates {
+            if total <= limit {
+                break;
+            }
+            let (id, bytes, image_id) = candidate?;
+            total = total.saturating_sub(bytes.max(0) as u64);
+            if let Some((refs, size)) = image_id.as_ref().and_then(|img_id| images.get_mut(img_id)) {
+                *refs -= 1;
+                if *refs == 0 {
+                    total = total.saturating_sub(*size);
+                }
+            }
+            ids_to_delete.push(id);
+            image_ids.extend(image_id.filter(|img_id| !img_id.is_empty()));
+        }
+        
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut delete = tx.prepare("DELETE FROM clipboard WHERE id = ?")?;
+            for id in &ids_to_delete {
+                delete.execute(params![id])?;
+            }
+        }
+        tx.commit()?;
+        
+        // 删除图片文件
+        self.remove_unreferenced_images(image_ids);
+        
+        tracing::info!(limit, deleted = ids_to_delete.len(), remaining_bytes = total, "超出存储上限，已清理旧记录");
+        Ok(ids_to_delete.len() as i64)
+    }
+    
+    /// 合并历史列表中相邻的重复记录（关闭去重、per_app 去重或导入数据后可能出现）
+    /// 
+    /// 按列表顺序扫描，连续几条 content_type、content_hash、image_id 都相同时只保留最上面一条：
+    /// paste_count 累加、updated_at 取最新，其余记录连同原始格式数据和不再被引用的图片一起删除。
+    /// 置顶、受保护、已加入分组或有标题的记录不参与合并，并会打断相邻关系
+    /// 
+    /// Returns:
+    ///     删除的记录数
+    pub fn collapse_adjacent_duplicates(&mut self) -> Result<i64, ClipboardError> {
+        /// 一组相邻重复记录中保留的那一条
+        struct Survivor {
+            id: i64,
+            key: (String, Option<String>, Option<String>),
+            paste_count: i64,
+            updated_at: i64,
+            merged: bool,
+        }
+        
+        let tx = self.conn.transaction()?;
+        let mut removed_ids: Vec<i64> = Vec::new();
+        let mut image_ids: Vec<String> = Vec::new();
+        let mut survivors: Vec<Survivor> = Vec::new();
+        {
+            let mut stmt = tx.prepare(
+                "SELECT id, content_type, content_hash, image_id, paste_count, updated_at,
+                        is_pinned = 0 AND protected = 0 AND group_id IS NULL AND title IS NULL
+                        AND is_favorite = 0 AND deleted_at IS NULL
+                 FROM clipboard ORDER BY is_pinned DESC, item_order DESC, id DESC"
+            )?;
+            let mut rows = stmt.query([])?;
+            let mut current: Option<Survivor> = None;
+            while let Some(row) = rows.next()? {
+                let eligible: bool = row.get(6)?;
+                let key = (row.get(1)?, row.get(2)?, row.get(3)?);
+                let (id, paste_count, updated_at): (i64, i64, i64) = (row.get(0)?, row.get(4)?, row.get(5)?);
+                match current.as_mut() {
+                    Some(survivor) if eligible && survivor.key == key => {
+                        survivor.paste_count += paste_count;
+                        survivor.updated_at = survivor.updated_at.max(updated_at);
+                        survivor.merged = true;
+                        removed_ids.push(id);
+                        image_ids.extend(key.2.filter(|img| !img.is_empty()));
+                    }
+                    _ => {
+                        survivors.extend(current.take().filter(|s| s.merged));
+                        current = eligible.then_some(Survivor { id, key, paste_count, updated_at, merged: false });
+                    }
+                }
+            }
+            survivors.extend(current.filter(|s| s.merged));
+        }
+        
+        for survivor in &survivors {
+            tx.execute(
+                "UPDATE clipboard SET paste_count = ?1, updated_at = ?2 WHERE id = ?3",
+                params![survivor.paste_count, survivor.updated_at, survivor.id],
+            )?;
+        }
+        // 原始格式数据随 ON DELETE CASCADE 一起删除
+        for id in &removed_ids {
+            tx.execute("DELETE FROM clipboard WHERE id = ?", params![id])?;
+        }
+        tx.commit()?;
+        
+        image_ids.dedup();
+        self.remove_unreferenced_images(image_ids);
+        tracing::info!(groups = survivors.len(), removed = removed_ids.len(), "已合并相邻的重复记录");
+        Ok(removed_ids.len() as i64)
+    }
+    
+    /// 删除已不再被任何记录引用的图片文件
+    /// （同一张图片可能同时挂在文本记录和图片记录上，需在删除记录之后调用）
+    pub fn remove_unreferenced_images(&self, image_ids: Vec<String>) {
+        let store = self.image_store();
+        for img_id in image_ids {
+            let still_referenced: bool = self.conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM clipboard WHERE image_id = ?)",
+                params![&img_id],
+                |row| row.get(0)
+            ).unwrap_or(true);
+            if !still_referenced {
+                store.remove(&img_id);
+                self.remove_cached_thumbnails(&img_id);
+            }
+        }
+    }
+    
+    /// 缩略图缓存目录（图片目录下的 thumbs 子目录），内存数据库不缓存，返回 None
+    fn thumbs_dir(&self) -> Option<PathBuf> {
+        match self.memory_images {
+            Some(_) => None,
+            None => Some(self.get_images_dir().join("thumbs")),
+        }
+    }
+    
+    /// 删除某张图片的所有尺寸缓存缩略图
+    fn remove_cached_thumbnails(&self, image_id: &str) {
+        let prefix = format!("{}_", image_id);
+        let Some(thumbs_dir) = self.thumbs_dir() else {
+            return;
+        };
+        if let Ok(entries) = std::fs::read_dir(thumbs_dir) {
+            for entry in entries.flatten() {
+                if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+    }
+    
+    /// 获取图片记录的缩略图 PNG（长边缩放到 max_size）
+    /// 
+    /// 首次请求时从原图生成并缓存到 thumbs/{image_id}_{max_size}.png；
+    /// 原图缺失时退回到记录中保存的 base64 缩略图
+    /// 
+    /// Returns:
+    ///     PNG 字节；记录不存在或没有图片时返回 None
+    pub fn get_image_thumbnail(&self, id: i64, max_size: u32) -> Result<Option<Vec<u8>>, ClipboardError> {
+        use base64::{Engine as _, engine::general_purpose};
+        
+        if max_size == 0 {
+            return Err(ClipboardError::InvalidArgument("max_size 必须大于 0".to_string()));
+        }
+        let Some(item) = self.get_item_by_id(id)? else {
+            return Ok(None);
+        };
+        let Some(image_id) = item.image_id.filter(|img_id| !img_id.is_empty()) else {
+            return Ok(None);
+        };
+        
+        let store = self.image_store();
+        if !store.contains(&image_id) {
+            // 原图缺失：解码记录里的 data:image/png;base64,... 缩略图
+            return Ok(item.thumbnail.as_deref()
+                .and_then(|thumb| thumb.split_once("base64,"))
+                .and_then(|(_, data)| general_purpose::STANDARD.decode(data).ok()));
+        }
+        
+        let thumb_path = self.thumbs_dir()
+            .map(|dir| dir.join(format!("{}_{}.png", image_id, max_size)));
+        if let Some(data) = thumb_path.as_ref().and_then(|path| std::fs::read(path).ok()) {
+            return Ok(Some(data));
+        }
+        
+        let img = store.open_image(&image_id)?;
+        // 原图不超过目标尺寸时直接返回原图，不放大
+        if img.width().max(img.height()) <= max_size {
+            return store.read(&image_id)
+                .map(Some)
+                .map_err(ClipboardError::from);
+        }
+        
+        let thumb = img.thumbnail(max_size, max_size);
+        let mut png_data = Vec::new();
+        thumb.write_to(&mut std::io::Cursor::new(&mut png_data), image::ImageFormat::Png)?;
+        
+        // 缓存写入失败不影响返回结果
+        if let Some(thumb_path) = thumb_path {
+            if thumb_path.parent().is_some_and(|dir| std::fs::create_dir_all(dir).is_ok()) {
+                let _ = std::fs::write(&thumb_path, &png_data);
+            }
+        }
+        Ok(Some(png_data))
+    }
+    
+    /// 把图片记录的原图保存到 dest
+    /// 
+    /// 按 dest 的扩展名决定格式：.png 直接复制文件，.jpg/.webp 等其他格式解码后重新编码；
+    /// 没有扩展名时补上 .png。目标目录不存在时自动创建
+    /// 
+    /// Returns:
+    ///     最终写入的文件路径
+    pub fn save_item_image(&self, id: i64, dest: &Path) -> Result<PathBuf, ClipboardError> {
+        let item = self.get_item_by_id(id)?
+            .ok_or(ClipboardError::NotFound(id))?;
+        let image_id = item.image_id
+            .filter(|img_id| item.content_type == "image" && !img_id.is_empty())
+            .ok_or_else(|| ClipboardError::InvalidArgument(format!("记录 {} 不是图片", id)))?;
+        let store = self.image_store();
+        if !store.contains(&image_id) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("图片文件不存在: {}", image_id),
+            ).into());
+        }
+        
+        let mut dest = dest.to_path_buf();
+        if dest.extension().is_none() {
+            dest.set_extension("png");
+        }
+        let format = image::ImageFormat::from_path(&dest)
+            .map_err(|_| ClipboardError::InvalidArgument(format!("不支持的图片格式: {}", dest.display())))?;
+        if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        
+        if format == image::ImageFormat::Png {
+            match store.path(&image_id) {
+                Some(image_path) => std::fs::copy(&image_path, &dest).map(|_| ())?,
+                None => std::fs::write(&dest, store.read(&image_id)?)?,
+            }
+            return Ok(dest);
+        }
+        
+        let img = store.open_image(&image_id)?;
+        // JPEG 不支持透明通道，先去掉 alpha
+        let img = if format == image::ImageFormat::Jpeg {
+            image::DynamicImage::ImageRgb8(img.to_rgb8())
+        } else {
+            img
+        };
+        img.save_with_format(&dest, format)?;
+        Ok(dest)
+    }
+    
+    /// 执行 WAL 检查点，把 WAL 文件中的内容写回主数据库文件
+    /// 
+    /// Args:
+    ///     mode: "PASSIVE"（不等待读写者）/ "FULL" / "RESTART" / "TRUNCATE"（完成后截断 WAL 文件）
+    /// 
+    /// Returns:
+    ///     (是否因其他连接占用未能完成, WAL 中的帧数, 已写回的帧数)
+    pub fn checkpoint_wal(&self, mode: &str) -> Result<(bool, i64, i64), ClipboardError> {
+        let mode = mode.to_ascii_uppercase();
+        if !["PASSIVE", "FULL", "RESTART", "TRUNCATE"].contains(&mode.as_str()) {
+            return Err(ClipboardError::InvalidArgument(format!(
+                "无效的检查点模式: {}，可选: PASSIVE, FULL, RESTART, TRUNCATE", mode)));
+        }
+        self.conn.query_row(
+            &format!("PRAGMA wal_checkpoint({})", mode),
+            [],
+            |row| Ok((row.get::<_, i64>(0)? != 0, row.get(1)?, row.get(2)?)),
+        ).map_err(ClipboardError::from)
+    }
+    
+    /// 压缩数据库：VACUUM 重建数据库文件，并截断 WAL 文件
+    /// 
+    /// Returns:
+    ///     回收的磁盘字节数（数据库文件 + WAL 文件）
+    pub fn compact(&self) -> Result<u64, ClipboardError> {
+        // VACUUM 不能在事务中执行
+        if !self.conn.is_autocommit() {
+            return Err(ClipboardError::InvalidArgument("存在未提交的事务，无法压缩数据库".to_string()));
+        }
+        
+        let before = self.disk_usage();
+        
+        // WAL 模式下 VACUUM 的结果先写入 WAL，需要再做一次 TRUNCATE 检查点才能落盘并收缩 WAL
+        self.conn.execute_batch(
+            "VACUUM;
+             PRAGMA wal_checkpoint(TRUNCATE);"
+        )?;
+        
+        Ok(before.saturating_sub(self.disk_usage()))
+    }
+    
+    /// 统计图片的存储占用（内存数据库统计内存中的图片）
+    /// 
+    /// Returns:
+    ///     (图片文件数, 总字节数)
+    pub fn get_images_disk_usage(&self) -> Result<(usize, u64), ClipboardError> {
+        let files = self.image_store().list()?;
+        Ok((files.len(), files.iter().map(|file| file.size).sum()))
+    }
+    
+    /// 查找孤立图片：磁盘上存在、但没有任何记录引用的 .png 文件
+    /// 
+    /// 刚写入不久的文件会被跳过（监听线程先写文件再插入记录，避免误判）
+    /// 
+    /// Returns:
+    ///     孤立图片的 (image_id, 文件大小) 列表
+    pub fn find_orphan_images(&self) -> Result<Vec<(String, u64)>, ClipboardError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT image_id FROM clipboard WHERE image_id IS NOT NULL AND image_id != ''"
+        )?;
+        
+        let referenced: std::collections::HashSet<String> = stmt.query_map([], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        
+        let mut orphans: Vec<(String, u64)> = self.image_store().list()?
+            .into_iter()
+            .filter(|file| !referenced.contains(&file.image_id))
+            .filter(|file| file.age.is_some_and(|age| age >= ORPHAN_GRACE_PERIOD))
+            .map(|file| (file.image_id, file.size))
+            .collect();
+        
+        orphans.sort();
+        Ok(orphans)
+    }
+    
+    /// 删除孤立图片
+    /// 
+    /// Returns:
+    ///     (删除的文件数, 释放的字节数)
+    pub fn cleanup_orphan_images(&self) -> Result<(usize, u64), ClipboardError> {
+        let store = self.image_store();
+        let mut count = 0;
+        let mut bytes = 0;
+        for (image_id, size) in self.find_orphan_images()? {
+            if store.remove(&image_id) {
+                count += 1;
+                bytes += size;
+            }
+        }
+        Ok((count, bytes))
+    }
+    
+    /// 查找图片文件已丢失的记录（UI 可据此显示损坏图片占位符）
+    /// 
+    /// Returns:
+    ///     记录 ID 列表
+    pub fn find_items_with_missing_images(&self) -> Result<Vec<i64>, ClipboardError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, image_id FROM clipboard WHERE image_id IS NOT NULL AND image_id != '' ORDER BY id"
+        )?;
+        
+        let rows: Vec<(i64, String)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+        
+        let store = self.image_store();
+        Ok(rows
+            .into_iter()
+            .filter(|(_, image_id)| !store.contains(image_id))
+            .map(|(id, _)| id)
+            .collect())
+    }
+    
+    /// 一次性迁移：按像素内容重新计算已有图片的 ID（旧 ID 为 PNG 编码结果的哈希），
+    /// 重命名图片文件并更新记录；新 ID 的文件已存在时直接删除旧文件（同一张图片）
+    /// 
+    /// 文件丢失或无法解码的图片保持原样。
+    /// 
+    /// Returns:
+    ///     更新了 ID 的图片数量
+    pub fn migrate_image_ids(&mut self) -> Result<usize, ClipboardError> {
+        let image_ids: Vec<String> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT DISTINCT image_id FROM clipboard WHERE image_id IS NOT NULL AND image_id != ''"
+            )?;
+            let rows = stmt.query_map([], |row| row.get(0))?
+                .filter_map(|r| r.ok())
+                .collect();
+            rows
+        };
+        
+        let store = self.image_store();
+        let tx = self.conn.transaction()?;
+        let mut migrated = 0;
+        for old_id in image_ids {
+            let Ok(img) = store.open_image(&old_id) else {
+                continue;
+            };
+            let new_id = compute_image_id(&img.to_rgba8());
+            if new_id == old_id {
+                continue;
+            }
+            
+            if store.contains(&new_id) {
+                store.remove(&old_id);
+            } else {
+                store.rename(&old_id, &new_id)?;
+            }
+            tx.execute(
+                "UPDATE clipboard SET image_id = ?1 WHERE image_id = ?2",
+                params![&new_id, &old_id],
+            )?;
+            migrated += 1;
+        }
+        tx.commit()?;
+        Ok(migrated)
+    }
+    
+    /// 数据库文件与 WAL 文件的总大小
+    fn disk_usage(&self) -> u64 {
+        [self.db_path.clone(), format!("{}-wal", self.db_path)]
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|meta| meta.len())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    fn temp_db_path(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("pyclipboard_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("clipboard.db").to_string_lossy().into_owned()
+    }
+
+    fn text_item(content: String) -> PyClipboardItem {
+        PyClipboardItem::new(0, content, "text".to_string())
+    }
+
+    #[test]
+    fn reader_is_not_blocked_by_open_write() {
+        let path = temp_db_path("reader_block");
+        let writer = Database::new(&path, DEFAULT_BUSY_TIMEOUT_MS).unwrap();
+        writer.insert_item(&text_item("committed".into())).unwrap();
+        let reader = Database::open_reader(&path, DEFAULT_BUSY_TIMEOUT_MS).unwrap();
+
+        // 写连接持有未提交的写事务（模拟长写入）时，读连接立即返回已提交的数据
+        writer.conn.execute_batch("BEGIN IMMEDIATE").unwrap();
+        writer.insert_item(&text_item("pending".into())).unwrap();
+        let start = Instant::now();
+        let result = reader.query_items(0, 50, None, None, &[]).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert_eq!(result.total_count, 1);
+        writer.conn.execute_batch("COMMIT").unwrap();
+        assert_eq!(reader.get_count().unwrap(), 2);
+
+        // 读连接拒绝写入
+        assert!(reader.insert_item(&text_item("nope".into())).is_err());
+        let _ = std::fs::remove_dir_all(Path::new(&path).parent().unwrap());
+    }
+
+    #[test]
+    fn dedup_scope_per_app() {
+        let path = temp_db_path("dedup_scope");
+        let mut db = Database::new(&path, DEFAULT_BUSY_TIMEOUT_MS).unwrap();
+        let from = |app: Option<&str>| {
+            let mut item = text_item("OK".into());
+            item.source_app = app.map(str::to_string);
+            item
+        };
+
+        let (first, _) = db.insert_item(&from(Some("a.exe"))).unwrap();
+        assert_eq!(db.insert_item(&from(Some("b.exe"))).unwrap(), (first, false));
+
+        db.set_dedup_scope(DedupScope::parse("per_app").unwrap());
+        let (second, inserted) = db.insert_item(&from(Some("b.exe"))).unwrap();
+        assert!(inserted && second != first);
+        assert_eq!(db.insert_item(&from(Some("a.exe"))).unwrap(), (first, false));
+        let (unknown, inserted) = db.insert_item(&from(None)).unwrap();
+        assert!(inserted);
+        assert_eq!(db.insert_item(&from(None)).unwrap(), (unknown, false));
+        assert_eq!(db.get_count().unwrap(), 3);
+
+        assert!(DedupScope::parse("app").is_err());
+        let _ = std::fs::remove_dir_all(Path::new(&path).parent().unwrap());
+    }
+
+    #[test]
+    fn dedup_strategies() {
+        let mut db = Database::new(":memory:", 0).unwrap();
+        let (first, _) = db.insert_item(&text_item("same".into())).unwrap();
+        assert_eq!(db.insert_item(&text_item("same".into())).unwrap(), (first, false));
+
+        db.set_dedup_strategy(DeduplicationStrategy::parse("content_hash").unwrap());
+        assert_eq!(db.insert_item(&text_item("same".into())).unwrap(), (first, false));
+
+        db.set_dedup_strategy(DeduplicationStrategy::parse("disabled").unwrap());
+        let (second, inserted) = db.insert_item(&text_item("same".into())).unwrap();
+        assert!(inserted && second != first);
+        assert_eq!(db.get_count().unwrap(), 2);
+
+        assert!(DeduplicationStrategy::parse("fuzzy").is_err());
+    }
+
+    #[test]
+    fn trash_delete_restore_and_empty() {
+        let db = Database::new(":memory:", 0).unwrap();
+        let (first, _) = db.insert_item(&text_item("first".into())).unwrap();
+        let (second, _) = db.insert_item(&text_item("second".into())).unwrap();
+
+        db.soft_delete(first).unwrap();
+        assert_eq!(db.get_count().unwrap(), 1);
+        assert_eq!(db.query_items(0, 10, None, None, &[]).unwrap().items[0].id, second);
+        let trash = db.get_trash(0, 10).unwrap();
+        assert_eq!((trash.total_count, trash.items[0].id), (1, first));
+
+        // 回收站中的内容不参与去重
+        let (again, inserted) = db.insert_item(&text_item("first".into())).unwrap();
+        assert!(inserted && again != first);
+
+        // 恢复后排到最前
+        db.restore_item(first).unwrap();
+        assert_eq!(db.query_items(0, 10, None, None, &[]).unwrap().items[0].id, first);
+        assert!(matches!(db.restore_item(first), Err(ClipboardError::InvalidArgument(_))));
+        assert!(matches!(db.soft_delete(-1), Err(ClipboardError::NotFound(-1))));
+
+        db.soft_delete(second).unwrap();
+        assert_eq!(db.empty_trash().unwrap(), 1);
+        assert!(db.get_item_by_id(second).unwrap().is_none());
+        assert_eq!(db.get_count().unwrap(), 2);
+    }
+
+    #[test]
+    fn search_ranked_prefers_frequent_matches() {
+        let db = Database::new(":memory:", 0).unwrap();
+        let (frequent, _) = db.insert_item(&text_item("hello world".into())).unwrap();
+        for _ in 0..10 {
+            db.increment_paste_count(frequent).unwrap();
+        }
+        for i in 0..20 {
+            db.insert_item(&text_item(format!("filler {}", i))).unwrap();
+        }
+        let (recent, _) = db.insert_item(&text_item("world".into())).unwrap();
+
+        let ranked = db.search_ranked("hello world", 10).unwrap();
+        let ids: Vec<i64> = ranked.iter().map(|(item, _)| item.id).collect();
+        assert_eq!(ids, vec![frequent, recent]);
+        assert!(ranked[0].1 > ranked[1].1);
+        assert!(db.search_ranked("  ", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn favorites_are_separate_from_history() {
+        let db = Database::new(":memory:", 0).unwrap();
+        let (favorite, _) = db.insert_item(&text_item("keep me".into())).unwrap();
+        db.insert_item(&text_item("history".into())).unwrap();
+        db.add_to_favorites(favorite).unwrap();
+
+        let history = db.query_items(0, 10, None, None, &[]).unwrap();
+        assert_eq!(history.total_count, 1);
+        assert_eq!(db.get_count().unwrap(), 1);
+        assert_eq!(db.get_favorites(0, 10).unwrap().items[0].id, favorite);
+        assert!(db.search_ranked("keep", 10).unwrap().is_empty());
+
+        // 不参与清理和清空历史；再次复制相同内容会新增历史记录
+        assert_eq!(db.cleanup_old_items(1).unwrap(), 0);
+        let (copy, inserted) = db.insert_item(&text_item("keep me".into())).unwrap();
+        assert!(inserted && copy != favorite);
+        db.clear_all(false).unwrap();
+        assert_eq!(db.get_favorites(0, 10).unwrap().total_count, 1);
+        assert_eq!(db.query_items(0, 10, None, None, &[]).unwrap().total_count, 0);
+
+        db.remove_from_favorites(favorite).unwrap();
+        assert_eq!(db.query_items(0, 10, None, None, &[]).unwrap().items[0].id, favorite);
+        assert!(matches!(db.add_to_favorites(-1), Err(ClipboardError::NotFound(-1))));
+    }
+
+    #[test]
+    fn search_boolean_combines_terms() {
+        let db = Database::new(":memory:", 0).unwrap();
+        let mut ids = Vec::new();
+        for content in ["learn python", "rust book", "python pandas", "go", "Rust 100%"] {
+            ids.push(db.insert_item(&text_item(content.into())).unwrap().0);
+        }
+        let mut html = text_item("plain".into());
+        html.html_content = Some("<b>python</b>".into());
+        ids.push(db.insert_item(&html).unwrap().0);
+
+        let found = |query: &str| {
+            let mut found: Vec<i64> = db.search_boolean(query, 0, 50).unwrap().items.iter().map(|i| i.id).collect();
+            found.sort();
+            found
+        };
+        assert_eq!(found("python OR rust NOT pandas"), vec![ids[0], ids[1], ids[4], ids[5]]);
+        assert_eq!(found("\"100%\""), vec![ids[4]]);
+        assert_eq!(found("NOT (python OR rust)"), vec![ids[3]]);
+        assert!(matches!(db.search_boolean("python OR", 0, 50), Err(ClipboardError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn text_compression_round_trip() {
+        let mut db = Database::new(":memory:", 0).unwrap();
+        db.set_text_compression(true);
+        let large = format!("{}needle", "长文本".repeat(TEXT_COMPRESS_THRESHOLD));
+        let (id, _) = db.insert_item(&text_item(large.clone())).unwrap();
+        let (small, _) = db.insert_item(&text_item("short".into())).unwrap();
+
+        let stored: (String, Option<Vec<u8>>) = db.conn.query_row(
+            "SELECT content, content_compressed FROM clipboard WHERE id = ?", params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!(stored.0.chars().count(), COMPRESSED_TEXT_PREVIEW_CHARS);
+        assert!(stored.1.is_some_and(|data| data.len() < large.len()));
+        assert_eq!(db.get_item_by_id(id).unwrap().unwrap().content, large);
+
+        // 相同内容仍然去重；搜索只能匹配到前缀
+        assert_eq!(db.insert_item(&text_item(large.clone())).unwrap(), (id, false));
+        let search = |keyword: &str| db.query_items(0, 10, Some(keyword.into()), None, &["content".into()]).unwrap();
+        assert_eq!(search("长文本").items[0].content, large);
+        assert_eq!(search("needle").total_count, 0);
+
+        // 修改为短文本后不再压缩
+        db.update_item(id, None, "edited").unwrap();
+        assert_eq!(db.get_item_by_id(id).unwrap().unwrap().content, "edited");
+        let compressed: i64 = db.conn.query_row(
+            "SELECT COUNT(*) FROM clipboard WHERE content_compressed IS NOT NULL", [], |row| row.get(0),
+        ).unwrap();
+        assert_eq!(compressed, 0);
+        assert_eq!(db.get_item_by_id(small).unwrap().unwrap().content, "short");
+    }
+
+    #[test]
+    fn collapse_adjacent_duplicates_merges_runs() {
+        let path = temp_db_path("collapse");
+        let mut db = Database::new(&path, DEFAULT_BUSY_TIMEOUT_MS).unwrap();
+        // per_app 下不同来源的相同内容各自入库，制造相邻重复
+        db.set_dedup_scope(DedupScope::PerApp);
+        let insert = |db: &Database, content: &str, app: &str| {
+            let mut item = text_item(content.into());
+            item.source_app = Some(app.into());
+            item.paste_count = 1;
+            db.insert_item(&item).unwrap().0
+        };
+
+        // 列表从上到下：OK(c) OK(b) OK(a) other OK(d) OK(e)，其中 OK(d) 受保护
+        insert(&db, "OK", "e");
+        let protected = insert(&db, "OK", "d");
+        db.set_protected(protected, true).unwrap();
+        insert(&db, "other", "x");
+        insert(&db, "OK", "a");
+        insert(&db, "OK", "b");
+        let top = insert(&db, "OK", "c");
+
+        assert_eq!(db.collapse_adjacent_duplicates().unwrap(), 2);
+        assert_eq!(db.get_count().unwrap(), 4);
+        assert_eq!(db.get_item_by_id(top).unwrap().unwrap().paste_count, 3);
+        assert!(db.get_item_by_id(protected).unwrap().is_some());
+        // 已经没有可合并的记录
+        assert_eq!(db.collapse_adjacent_duplicates().unwrap(), 0);
+        let _ = std::fs::remove_dir_all(Path::new(&path).parent().unwrap());
+    }
+
+    #[test]
+    fn in_memory_database_keeps_images_in_memory() {
+        let db = Database::new(":memory:", 0).unwrap();
+        let store = db.image_store();
+        let mut png = Vec::new();
+        image::RgbaImage::from_pixel(300, 100, image::Rgba([0, 0, 255, 255]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        store.write("img", &png).unwrap();
+
+        let mut item = PyClipboardItem::new(0, "[300x100]".into(), "image".into());
+        item.image_id = Some("img".into());
+        let (id, _) = db.insert_item(&item).unwrap();
+        let thumb = db.get_image_thumbnail(id, 30).unwrap().unwrap();
+        assert_eq!(image::load_from_memory(&thumb).unwrap().width(), 30);
+        assert!(db.find_items_with_missing_images().unwrap().is_empty());
+        assert_eq!(db.get_images_disk_usage().unwrap(), (1, png.len() as u64));
+
+        // 图片目录只是占位路径，不会创建
+        assert!(!db.get_images_dir().exists());
+        db.delete_item(id).unwrap();
+        assert!(!store.contains("img"));
+    }
+
+    #[test]
+    fn text_subtype_is_detected_on_insert_and_update() {
+        let db = Database::new(":memory:", 0).unwrap();
+        let (id, _) = db.insert_item(&text_item("https://example.com".into())).unwrap();
+        assert_eq!(db.get_item_by_id(id).unwrap().unwrap().subtype.as_deref(), Some("url"));
+
+        db.update_item(id, None, "fn main() {\n    run();\n}").unwrap();
+        assert_eq!(db.get_item_by_id(id).unwrap().unwrap().subtype.as_deref(), Some("code"));
+        db.update_item(id, None, "普通文字").unwrap();
+        assert_eq!(db.get_item_by_id(id).unwrap().unwrap().subtype, None);
+    }
+
+    #[test]
+    fn urls_are_extracted_and_searchable() {
+        let db = Database::new(":memory:", 0).unwrap();
+        let fields = ["urls".to_string()];
+        let (id, _) = db.insert_item(&text_item("仓库 https://github.com/a/b 和文档 http://docs.rs".into())).unwrap();
+        db.insert_item(&text_item("github.com 不是链接".into())).unwrap();
+
+        let found = db.query_items(0, 10, Some("https://github.com".into()), None, &fields).unwrap();
+        assert_eq!(found.items.iter().map(|i| i.id).collect::<Vec<_>>(), [id]);
+        let urls: String = db.conn.query_row("SELECT urls FROM clipboard WHERE id = ?", params![id], |row| row.get(0)).unwrap();
+        assert_eq!(urls, r#"["https://github.com/a/b","http://docs.rs"]"#);
+
+        db.update_item(id, None, "没有链接了").unwrap();
+        assert_eq!(db.query_items(0, 10, Some("github".into()), None, &fields).unwrap().total_count, 0);
+    }
+
+    #[test]
+    fn concurrent_inserts_and_queries() {
+        const INSERTS: usize = 300;
+        let path = temp_db_path("reader_stress");
+        let writer = Database::new(&path, DEFAULT_BUSY_TIMEOUT_MS).unwrap();
+        let reader = Arc::new(parking_lot::Mutex::new(Database::open_reader(&path, DEFAULT_BUSY_TIMEOUT_MS).unwrap()));
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..2)
+            .map(|_| {
+                let reader = reader.clone();
+                let done = done.clone();
+                std::thread::spawn(move || {
+                    let mut last_count = 0;
+                    let mut queries = 0;
+                    while !done.load(std::sync::atomic::Ordering::Acquire) {
+                        let db = reader.lock();
+                        let count = db.get_count().unwrap();
+                        db.query_items(0, 20, Some("item".into()), None, &["content".to_string()]).unwrap();
+                        // 读到的总是已提交的前缀，计数只增不减
+                        assert!(count >= last_count);
+                        last_count = count;
+                        queries += 1;
+                    }
+                    queries
+                })
+            })
+            .collect();
+
+        for i in 0..INSERTS {
+            writer.insert_item(&text_item(format!("item {}", i))).unwrap();
+        }
+        done.store(true, std::sync::atomic::Ordering::Release);
+
+        for handle in readers {
+            assert!(handle.join().unwrap() > 0);
+        }
+        assert_eq!(reader.lock().get_count().unwrap(), INSERTS as i64);
+        let _ = std::fs::remove_dir_all(Path::new(&path).parent().unwrap());
+    }
 }
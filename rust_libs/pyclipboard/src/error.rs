@@ -0,0 +1,74 @@
+//! 数据库操作的错误类型：区分"记录不存在"、SQL 错误、文件错误等，
+//! 转换为 Python 异常时分别对应 KeyError / ValueError / RuntimeError。
+
+use pyo3::exceptions::{PyKeyError, PyRuntimeError, PyValueError};
+use pyo3::PyErr;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ClipboardError {
+    /// 记录不存在（记录 ID）
+    NotFound(i64),
+    /// SQLite 错误
+    SqlError(rusqlite::Error),
+    /// 文件读写错误（图片文件、图片目录等）
+    IoError(std::io::Error),
+    /// 参数无效或当前状态不允许该操作
+    InvalidArgument(String),
+    /// 与已有数据重复（已有数据的 ID）
+    Duplicate(i64),
+}
+
+impl fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClipboardError::NotFound(id) => write!(f, "记录不存在: {}", id),
+            ClipboardError::SqlError(e) => write!(f, "数据库错误: {}", e),
+            ClipboardError::IoError(e) => write!(f, "文件操作失败: {}", e),
+            ClipboardError::InvalidArgument(msg) => f.write_str(msg),
+            ClipboardError::Duplicate(id) => write!(f, "与已有数据重复: {}", id),
+        }
+    }
+}
+
+impl std::error::Error for ClipboardError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClipboardError::SqlError(e) => Some(e),
+            ClipboardError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ClipboardError {
+    fn from(e: rusqlite::Error) -> Self {
+        ClipboardError::SqlError(e)
+    }
+}
+
+impl From<std::io::Error> for ClipboardError {
+    fn from(e: std::io::Error) -> Self {
+        ClipboardError::IoError(e)
+    }
+}
+
+/// 图片解码/编码失败归为文件错误
+impl From<image::ImageError> for ClipboardError {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::IoError(e) => ClipboardError::IoError(e),
+            other => ClipboardError::IoError(std::io::Error::new(std::io::ErrorKind::InvalidData, other)),
+        }
+    }
+}
+
+impl From<ClipboardError> for PyErr {
+    fn from(e: ClipboardError) -> Self {
+        match e {
+            ClipboardError::NotFound(_) => PyKeyError::new_err(e.to_string()),
+            ClipboardError::InvalidArgument(_) => PyValueError::new_err(e.to_string()),
+            _ => PyRuntimeError::new_err(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! 文件记录的缩略图：复制的文件中有图片时取首张图片的缩略图，
+//! 否则按首个文件的类型（文件夹 / 扩展名）绘制一个通用图标
+
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+
+/// 缩略图/图标尺寸（与图片记录的缩略图一致）
+const THUMBNAIL_SIZE: u32 = 64;
+
+/// 超过该大小的图片文件不解码（避免监听线程卡在超大图片上）
+const MAX_PREVIEW_FILE_BYTES: u64 = 50 * 1024 * 1024;
+
+/// 可以生成预览的图片扩展名
+const IMAGE_EXTENSIONS: [&str; 9] = ["png", "jpg", "jpeg", "gif", "bmp", "webp", "ico", "tif", "tiff"];
+
+/// 文件类型分类，决定通用图标的颜色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    Folder,
+    Image,
+    Media,
+    Archive,
+    Document,
+    Code,
+    Executable,
+    Other,
+}
+
+impl FileKind {
+    fn from_path(path: &Path) -> Self {
+        if path.is_dir() {
+            return FileKind::Folder;
+        }
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        Self::from_extension(&ext)
+    }
+
+    fn from_extension(ext: &str) -> Self {
+        match ext {
+            e if IMAGE_EXTENSIONS.contains(&e) => FileKind::Image,
+            "svg" | "psd" | "raw" | "heic" => FileKind::Image,
+            "mp3" | "wav" | "flac" | "aac" | "ogg" | "m4a" | "mp4" | "mkv" | "avi" | "mov" | "wmv"
+            | "flv" | "webm" => FileKind::Media,
+            "zip" | "rar" | "7z" | "tar" | "gz" | "bz2" | "xz" | "iso" => FileKind::Archive,
+            "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "txt" | "md" | "rtf"
+            | "csv" | "odt" => FileKind::Document,
+            "rs" | "py" | "js" | "ts" | "c" | "cpp" | "h" | "java" | "go" | "cs" | "html" | "css"
+            | "json" | "xml" | "yaml" | "yml" | "toml" | "sh" | "bat" | "ps1" => FileKind::Code,
+            "exe" | "msi" | "dll" | "lnk" | "com" => FileKind::Executable,
+            _ => FileKind::Other,
+        }
+    }
+
+    fn color(self) -> [u8; 3] {
+        match self {
+            FileKind::Folder => [240, 190, 60],
+            FileKind::Image => [76, 175, 80],
+            FileKind::Media => [156, 39, 176],
+            FileKind::Archive => [141, 110, 99],
+            FileKind::Document => [33, 150, 243],
+            FileKind::Code => [96, 125, 139],
+            FileKind::Executable => [244, 81, 30],
+            FileKind::Other => [158, 158, 158],
+        }
+    }
+}
+
+/// 为文件记录生成缩略图 Base64
+///
+/// content 为文件记录的 JSON（{"files": [...]}）；没有文件或 JSON 无法解析时返回 None
+pub fn file_thumbnail(content: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(content).ok()?;
+    let files: Vec<&str> = value.get("files")?.as_array()?.iter().filter_map(|f| f.as_str()).collect();
+
+    // 多个文件时取第一张能解码的图片
+    let preview = files.iter().find_map(|file| image_preview(Path::new(file)));
+    if preview.is_some() {
+        return preview;
+    }
+
+    let first = files.first()?;
+    crate::generate_thumbnail(&file_type_icon(FileKind::from_path(Path::new(first))), THUMBNAIL_SIZE)
+}
+
+/// 图片文件的缩略图（不是图片、过大或解码失败时返回 None）
+fn image_preview(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_string_lossy().to_ascii_lowercase();
+    if !IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+        return None;
+    }
+    let size = std::fs::metadata(path).ok()?.len();
+    if size == 0 || size > MAX_PREVIEW_FILE_BYTES {
+        return None;
+    }
+    let img = image::open(path).ok()?;
+    crate::generate_thumbnail(&img.to_rgba8(), THUMBNAIL_SIZE)
+}
+
+/// 绘制通用类型图标：文件夹为带标签的矩形，其他为右上角折角的纸张
+fn file_type_icon(kind: FileKind) -> RgbaImage {
+    let [r, g, b] = kind.color();
+    let fill = Rgba([r, g, b, 255]);
+    // 折角与文件夹标签用稍暗的颜色
+    let shade = Rgba([r / 4 * 3, g / 4 * 3, b / 4 * 3, 255]);
+    let size = THUMBNAIL_SIZE;
+
+    RgbaImage::from_fn(size, size, |x, y| {
+        if kind == FileKind::Folder {
+            let tab = (8..28).contains(&x) && (12..18).contains(&y);
+            let body = (8..56).contains(&x) && (18..52).contains(&y);
+            if tab {
+                shade
+            } else if body {
+                fill
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        } else {
+            // 纸张区域 [12, 52) × [6, 58)，右上角 12px 折角
+            const FOLD: u32 = 12;
+            let inside = (12..52).contains(&x) && (6..58).contains(&y);
+            let corner_x = x.saturating_sub(52 - FOLD);
+            let corner_y = y.saturating_sub(6);
+            if !inside || (x >= 52 - FOLD && y < 6 + FOLD && corner_x > corner_y) {
+                Rgba([0, 0, 0, 0])
+            } else if x >= 52 - FOLD && y < 6 + FOLD {
+                shade
+            } else {
+                fill
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_by_extension() {
+        assert_eq!(FileKind::from_extension("png"), FileKind::Image);
+        assert_eq!(FileKind::from_extension("docx"), FileKind::Document);
+        assert_eq!(FileKind::from_extension("7z"), FileKind::Archive);
+        assert_eq!(FileKind::from_extension(""), FileKind::Other);
+        assert_eq!(FileKind::from_path(&std::env::temp_dir()), FileKind::Folder);
+    }
+
+    #[test]
+    fn prefers_first_image_file() {
+        let dir = std::env::temp_dir().join(format!("pyclipboard_thumb_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let text = dir.join("notes.txt");
+        let png = dir.join("shot.png");
+        std::fs::write(&text, "hello").unwrap();
+        RgbaImage::from_pixel(200, 100, Rgba([255, 0, 0, 255])).save(&png).unwrap();
+
+        let content = serde_json::json!({ "files": [text, png] }).to_string();
+        let expected = crate::generate_thumbnail(&image::open(&png).unwrap().to_rgba8(), THUMBNAIL_SIZE);
+        assert_eq!(file_thumbnail(&content), expected);
+
+        // 没有图片时使用类型图标
+        let content = serde_json::json!({ "files": [text] }).to_string();
+        let icon = crate::generate_thumbnail(&file_type_icon(FileKind::Document), THUMBNAIL_SIZE);
+        assert_eq!(file_thumbnail(&content), icon);
+
+        assert_eq!(file_thumbnail("not json"), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
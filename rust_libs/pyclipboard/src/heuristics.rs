@@ -0,0 +1,243 @@
+//! 文本记录的子类型识别：区分链接、邮箱、JSON、代码、CSV 和普通文字；以及从文本中提取链接、邮箱和电话号码
+//!
+//! 只做廉价的启发式判断（不完整解析），插入文本记录时自动计算并存入 subtype、urls 列
+
+/// 行首出现即视为代码的关键字
+const CODE_LINE_PREFIXES: [&str; 14] = [
+    "def ", "fn ", "pub fn ", "function ", "class ", "import ", "from ", "#include", "return ",
+    "const ", "let ", "var ", "public ", "private ",
+];
+
+/// 链接中不允许出现的字符（空白以外）
+const URL_STOP_CHARS: &str = "<>\"{}|\\^`[]";
+
+/// 电话号码中允许出现在数字之间的分隔符
+const PHONE_SEPARATORS: &[u8] = b" -()";
+
+/// 识别文本的子类型
+///
+/// Returns:
+///     "url" / "email" / "json" / "code" / "csv"，都不符合（普通文字）时返回 None
+pub fn detect_content_subtype(content: &str) -> Option<&'static str> {
+    let trimmed = content.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let single_token = !trimmed.contains(char::is_whitespace);
+    if single_token && (trimmed.starts_with("http://") || trimmed.starts_with("https://")) {
+        return Some("url");
+    }
+    if single_token && is_email(trimmed) {
+        return Some("email");
+    }
+    if is_json(trimmed) {
+        return Some("json");
+    }
+    if is_code(trimmed) {
+        return Some("code");
+    }
+    if is_csv(trimmed) {
+        return Some("csv");
+    }
+    None
+}
+
+/// 按出现顺序提取文本中的所有 http(s) 链接（不去重）
+///
+/// 等价于正则 `https?://[^\s<>"{}|\\^`\[\]]+`：从 http:// 或 https:// 开始，
+/// 到空白或 URL_STOP_CHARS 中的字符之前结束
+pub fn extract_urls(content: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("http") {
+        let candidate = &rest[start..];
+        let scheme_len = if candidate.starts_with("https://") {
+            8
+        } else if candidate.starts_with("http://") {
+            7
+        } else {
+            rest = &candidate[4..];
+            continue;
+        };
+        let body = &candidate[scheme_len..];
+        let body_len = body
+            .find(|c: char| c.is_whitespace() || URL_STOP_CHARS.contains(c))
+            .unwrap_or(body.len());
+        let end = scheme_len + body_len;
+        if body_len > 0 {
+            urls.push(candidate[..end].to_string());
+        }
+        rest = &candidate[end..];
+    }
+    urls
+}
+
+/// 按出现顺序提取文本中的邮箱地址（去重）
+pub fn extract_emails(content: &str) -> Vec<String> {
+    let mut emails: Vec<String> = Vec::new();
+    for (at, _) in content.match_indices('@') {
+        let start = content[..at]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_email_local_char(c))
+            .last()
+            .map_or(at, |(i, _)| i);
+        let rest = &content[at + 1..];
+        let domain_len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '.' || c == '-'))
+            .unwrap_or(rest.len());
+        // 句末的点号等不属于域名
+        let domain = rest[..domain_len].trim_end_matches(['.', '-']);
+        let email = format!("{}@{}", content[start..at].trim_start_matches('.'), domain);
+        if is_email(&email) && !emails.contains(&email) {
+            emails.push(email);
+        }
+    }
+    emails
+}
+
+/// 按出现顺序提取文本中的电话号码（保留原文格式，去重）
+///
+/// 号码由数字和空格、-、括号组成，可以 + 开头，共 7~15 位数字（E.164 上限）；
+/// 没有 + 也没有分隔符的纯数字至少 10 位，日期（2024-01-15、2019-2024 等）不算
+pub fn extract_phones(content: &str) -> Vec<String> {
+    let bytes = content.as_bytes();
+    let mut phones: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let at_boundary = i == 0 || !(bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'+');
+        if !at_boundary || !(b.is_ascii_digit() || b == b'+' || b == b'(') {
+            i += 1;
+            continue;
+        }
+        let mut end = i + 1;
+        while end < bytes.len()
+            && (bytes[end].is_ascii_digit() || PHONE_SEPARATORS.contains(&bytes[end]))
+            && !(bytes[end] == b' ' && bytes[end - 1] == b' ')
+        {
+            end += 1;
+        }
+        // 去掉结尾的分隔符；紧跟字母的数字串（如 123abc）不是号码
+        let mut last = end;
+        while last > i && !bytes[last - 1].is_ascii_digit() {
+            last -= 1;
+        }
+        let followed_by_word = last == end && bytes.get(end).is_some_and(u8::is_ascii_alphabetic);
+        let candidate = &content[i..last];
+        if !followed_by_word && is_phone(candidate) && !phones.iter().any(|p| p == candidate) {
+            phones.push(candidate.to_string());
+        }
+        i = end;
+    }
+    phones
+}
+
+fn is_phone(candidate: &str) -> bool {
+    let digits = candidate.bytes().filter(u8::is_ascii_digit).count();
+    if !(7..=15).contains(&digits) {
+        return false;
+    }
+    let groups: Vec<usize> = candidate
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|g| !g.is_empty())
+        .map(str::len)
+        .collect();
+    if matches!(groups.as_slice(), [4, 2, 2] | [2, 2, 4] | [4, 4]) {
+        return false;
+    }
+    candidate.starts_with('+') || groups.len() > 1 || digits >= 10
+}
+
+fn is_email_local_char(c: char) -> bool {
+    c.is_alphanumeric() || "._%+-".contains(c)
+}
+
+/// local@domain.tld：local 部分为字母数字和 ._%+-，域名至少两段，每段为字母数字和 -
+fn is_email(text: &str) -> bool {
+    let Some((local, domain)) = text.split_once('@') else {
+        return false;
+    };
+    let local_ok = !local.is_empty() && local.chars().all(is_email_local_char);
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_ok = labels.len() >= 2
+        && labels.iter().all(|l| !l.is_empty() && l.chars().all(|c| c.is_alphanumeric() || c == '-'));
+    local_ok && domain_ok
+}
+
+/// 以 { 或 [ 开头并以对应的括号结尾
+fn is_json(text: &str) -> bool {
+    (text.starts_with('{') && text.ends_with('}')) || (text.starts_with('[') && text.ends_with(']'))
+}
+
+/// 至少两行带有代码特征：以 ; { } 结尾，或以常见关键字开头
+fn is_code(text: &str) -> bool {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| {
+            line.ends_with(';')
+                || line.ends_with('{')
+                || line.ends_with('}')
+                || CODE_LINE_PREFIXES.iter().any(|p| line.starts_with(p))
+        })
+        .nth(1)
+        .is_some()
+}
+
+/// 至少两行，且每行的逗号（或制表符）数量相同且不为 0
+fn is_csv(text: &str) -> bool {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    if lines.len() < 2 {
+        return false;
+    }
+    [',', '\t'].iter().any(|&sep| {
+        let first = lines[0].matches(sep).count();
+        first > 0 && lines.iter().all(|l| l.matches(sep).count() == first)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_subtypes() {
+        assert_eq!(detect_content_subtype("  https://example.com/a?b=1\n"), Some("url"));
+        assert_eq!(detect_content_subtype("see https://example.com"), None);
+        assert_eq!(detect_content_subtype("someone.name+tag@mail.example.org"), Some("email"));
+        assert_eq!(detect_content_subtype("a@b"), None);
+        assert_eq!(detect_content_subtype("{\"a\": [1, 2]}"), Some("json"));
+        assert_eq!(detect_content_subtype("[1] 参考文献"), None);
+        assert_eq!(detect_content_subtype("fn main() {\n    println!(\"hi\");\n}"), Some("code"));
+        assert_eq!(detect_content_subtype("def f(x):\n    return x"), Some("code"));
+        assert_eq!(detect_content_subtype("name,age\nalice,30\nbob,25"), Some("csv"));
+        assert_eq!(detect_content_subtype("今天天气不错。\n我们出去走走吧"), None);
+        assert_eq!(detect_content_subtype("   "), None);
+    }
+
+    #[test]
+    fn extracts_urls() {
+        assert_eq!(
+            extract_urls("文档见 https://github.com/a/b?x=1 备用<http://例子.cn/路径>\nhttps:// httpx://no"),
+            ["https://github.com/a/b?x=1", "http://例子.cn/路径"]
+        );
+        assert_eq!(extract_urls("[https://a.com]{https://b.com} \"http://c\""), ["https://a.com", "https://b.com", "http://c"]);
+        assert!(extract_urls("没有链接 http").is_empty());
+    }
+
+    #[test]
+    fn extracts_contacts() {
+        let text = "Contact us at info@example.com or +1 (555) 867-5309.";
+        assert_eq!(extract_emails(text), ["info@example.com"]);
+        assert_eq!(extract_phones(text), ["+1 (555) 867-5309"]);
+
+        assert_eq!(
+            extract_emails("邮箱：a.b+c@mail.example.org。抄送 x@y.cn, x@y.cn；不是 @handle 或 me@localhost"),
+            ["a.b+c@mail.example.org", "x@y.cn"]
+        );
+        assert_eq!(
+            extract_phones("电话13800138000，座机 010-12345678，订单 2024-01-15 / 1234567 / abc12345678 / 5551234567x"),
+            ["13800138000", "010-12345678"]
+        );
+    }
+}
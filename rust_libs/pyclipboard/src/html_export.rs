@@ -0,0 +1,232 @@
+//! 导出为 HTML 归档（export_to_html）：单个自包含的 HTML 文件，浏览器直接打开即可查看和搜索
+//!
+//! 顶部为统计信息和搜索框，记录按日期分组（新的在前）：
+//! 代码 / JSON / CSV 文本用 <pre>，其他文本用 <blockquote>，图片以 base64 内嵌，文件记录列出路径。
+//! 不依赖任何模板库或外部资源，样式和搜索脚本都内联在页面中。
+
+use crate::database::Database;
+use crate::error::ClipboardError;
+use crate::image_store::ImageStore;
+use crate::types::PyClipboardItem;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::TimeZone;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// 每批从数据库读取的记录数
+const EXPORT_BATCH_SIZE: i64 = 500;
+
+/// 按 <pre> 原样展示的文本子类型
+const PREFORMATTED_SUBTYPES: [&str; 3] = ["code", "json", "csv"];
+
+const STYLE: &str = "body{font-family:system-ui,\"Microsoft YaHei\",sans-serif;max-width:960px;margin:0 auto;padding:16px;color:#222}
+header{position:sticky;top:0;background:#fff;padding:8px 0;border-bottom:1px solid #ddd}
+#search{width:100%;box-sizing:border-box;padding:6px 8px;font-size:15px}
+.stats{color:#666;font-size:13px;margin:6px 0}
+h2{margin-top:28px;border-bottom:2px solid #eee}
+.item{border:1px solid #e4e4e4;border-radius:6px;padding:8px 12px;margin:10px 0}
+.meta{color:#888;font-size:12px;margin-bottom:4px}
+pre{background:#f6f8fa;padding:8px;overflow-x:auto;white-space:pre-wrap;word-break:break-all}
+blockquote{margin:0;padding-left:10px;border-left:3px solid #ccc;white-space:pre-wrap;word-break:break-word}
+img{max-width:100%;height:auto}
+.missing{color:#b00}";
+
+const SCRIPT: &str = "document.getElementById('search').addEventListener('input',function(){
+var q=this.value.toLowerCase();
+document.querySelectorAll('.item').forEach(function(el){el.style.display=el.textContent.toLowerCase().includes(q)?'':'none';});
+document.querySelectorAll('section').forEach(function(s){s.style.display=s.querySelector('.item:not([style*=\"none\"])')?'':'none';});
+});";
+
+/// 读取全部记录（回收站除外，按 id 升序）
+pub fn load_items(db: &Database) -> Result<Vec<PyClipboardItem>, ClipboardError> {
+    let mut items = Vec::new();
+    loop {
+        let after_id = items.last().map_or(0, |item: &PyClipboardItem| item.id);
+        let batch = db.get_items_after(after_id, EXPORT_BATCH_SIZE)?;
+        let done = (batch.len() as i64) < EXPORT_BATCH_SIZE;
+        items.extend(batch);
+        if done {
+            return Ok(items);
+        }
+    }
+}
+
+/// 把记录写入 path（目录不存在时自动创建）
+pub fn write_html(path: &Path, items: &[PyClipboardItem], images: &ImageStore) -> Result<(), ClipboardError> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut out = io::BufWriter::new(std::fs::File::create(path)?);
+    render(&mut out, items, images, chrono::Local::now().timestamp())?;
+    out.flush()?;
+    Ok(())
+}
+
+/// 转义 HTML 特殊字符
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn local_time(timestamp: i64, format: &str) -> String {
+    chrono::Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map_or_else(|| timestamp.to_string(), |t| t.format(format).to_string())
+}
+
+fn render(out: &mut impl Write, items: &[PyClipboardItem], images: &ImageStore, generated_at: i64) -> io::Result<()> {
+    let mut sorted: Vec<&PyClipboardItem> = items.iter().collect();
+    sorted.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+
+    let count = |content_type: &str| items.iter().filter(|item| item.content_type == content_type).count();
+    writeln!(out, "<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>剪贴板历史</title>\n<style>\n{}\n</style>\n</head>\n<body>", STYLE)?;
+    writeln!(out, "<header>\n<h1>剪贴板历史</h1>")?;
+    write!(
+        out,
+        "<div class=\"stats\">共 {} 条记录：文本 {}，图片 {}，文件 {}",
+        items.len(),
+        count("text"),
+        count("image"),
+        count("file")
+    )?;
+    if let (Some(newest), Some(oldest)) = (sorted.first(), sorted.last()) {
+        write!(
+            out,
+            " · {} ~ {}",
+            local_time(oldest.created_at, "%Y-%m-%d"),
+            local_time(newest.created_at, "%Y-%m-%d")
+        )?;
+    }
+    writeln!(out, " · 导出于 {}</div>", local_time(generated_at, "%Y-%m-%d %H:%M:%S"))?;
+    writeln!(out, "<input id=\"search\" type=\"search\" placeholder=\"搜索…\">\n</header>")?;
+
+    let mut current_date: Option<String> = None;
+    for item in sorted {
+        let date = local_time(item.created_at, "%Y-%m-%d");
+        if current_date.as_deref() != Some(date.as_str()) {
+            if current_date.is_some() {
+                writeln!(out, "</section>")?;
+            }
+            writeln!(out, "<section>\n<h2>{}</h2>", date)?;
+            current_date = Some(date);
+        }
+        render_item(out, item, images)?;
+    }
+    if current_date.is_some() {
+        writeln!(out, "</section>")?;
+    }
+
+    writeln!(out, "<script>\n{}\n</script>\n</body>\n</html>", SCRIPT)
+}
+
+fn render_item(out: &mut impl Write, item: &PyClipboardItem, images: &ImageStore) -> io::Result<()> {
+    write!(out, "<div class=\"item\" id=\"item-{}\">\n<div class=\"meta\">{}", item.id, local_time(item.created_at, "%H:%M:%S"))?;
+    if let Some(title) = item.title.as_deref().filter(|t| !t.is_empty()) {
+        write!(out, " · <b>{}</b>", escape(title))?;
+    }
+    if let Some(app) = item.source_app.as_deref().filter(|a| !a.is_empty()) {
+        write!(out, " · {}", escape(app))?;
+    }
+    if item.is_pinned {
+        write!(out, " · 置顶")?;
+    }
+    writeln!(out, "</div>")?;
+
+    match item.content_type.as_str() {
+        "image" => {
+            let data = item.image_id.as_deref().and_then(|image_id| images.read(image_id).ok());
+            match (data, item.thumbnail.as_deref()) {
+                (Some(data), _) => writeln!(
+                    out,
+                    "<img src=\"data:image/png;base64,{}\" alt=\"{}\">",
+                    general_purpose::STANDARD.encode(data),
+                    escape(&item.content)
+                )?,
+                // 原图缺失时退回到记录中保存的缩略图
+                (None, Some(thumbnail)) if thumbnail.starts_with("data:image/") => {
+                    writeln!(out, "<img src=\"{}\" alt=\"{}\">", escape(thumbnail), escape(&item.content))?
+                }
+                (None, _) => writeln!(out, "<p class=\"missing\">图片缺失：{}</p>", escape(&item.content))?,
+            }
+        }
+        "file" => {
+            let files: Vec<String> = serde_json::from_str::<serde_json::Value>(&item.content)
+                .ok()
+                .and_then(|json| {
+                    json.get("files")?
+                        .as_array()
+                        .map(|files| files.iter().filter_map(|f| f.as_str().map(str::to_string)).collect())
+                })
+                .unwrap_or_default();
+            writeln!(out, "<ul>")?;
+            for file in files {
+                writeln!(out, "<li>{}</li>", escape(&file))?;
+            }
+            writeln!(out, "</ul>")?;
+        }
+        _ => {
+            let tag = if item.subtype.as_deref().is_some_and(|s| PREFORMATTED_SUBTYPES.contains(&s)) {
+                "pre"
+            } else {
+                "blockquote"
+            };
+            writeln!(out, "<{tag}>{}</{tag}>", escape(&item.content))?;
+        }
+    }
+    writeln!(out, "</div>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: i64, content: &str, content_type: &str, created_at: i64) -> PyClipboardItem {
+        let mut item = PyClipboardItem::new(id, content.to_string(), content_type.to_string());
+        item.created_at = created_at;
+        item
+    }
+
+    #[test]
+    fn renders_all_content_types() {
+        let images = ImageStore::memory();
+        images.write("img", &[1, 2, 3]).unwrap();
+        let day = 86_400;
+        let t0 = chrono::Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap().timestamp();
+
+        let mut code = item(1, "fn main() { if a < b {} }", "text", t0);
+        code.subtype = Some("code".to_string());
+        let prose = item(2, "<script>alert('x')</script> & 你好", "text", t0 + 60);
+        let mut image = item(3, "[2x2]", "image", t0 + day);
+        image.image_id = Some("img".to_string());
+        let mut missing = item(4, "[1x1]", "image", t0 + day);
+        missing.image_id = Some("gone".to_string());
+        let files = item(5, r#"{"files":["C:\\a.txt","D:\\b & c"]}"#, "file", t0 + day);
+
+        let mut out = Vec::new();
+        render(&mut out, &[code, prose, image, missing, files], &images, t0 + 2 * day).unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert!(html.contains("共 5 条记录：文本 2，图片 2，文件 1"));
+        assert!(html.contains("<pre>fn main() { if a &lt; b {} }</pre>"));
+        assert!(html.contains("<blockquote>&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; &amp; 你好</blockquote>"));
+        assert!(html.contains("<img src=\"data:image/png;base64,AQID\""));
+        assert!(html.contains("<p class=\"missing\">图片缺失：[1x1]</p>"));
+        assert!(html.contains("<li>C:\\a.txt</li>\n<li>D:\\b &amp; c</li>"));
+        // 新的日期在前
+        let (newer, older) = (html.find("<h2>2024-05-02</h2>").unwrap(), html.find("<h2>2024-05-01</h2>").unwrap());
+        assert!(newer < older);
+        assert_eq!(html.matches("<section>").count(), html.matches("</section>").count());
+    }
+}
@@ -0,0 +1,171 @@
+//! 图片存储：默认是图片目录下的 {image_id}.png 文件；
+//! 内存数据库（PyClipboardManager(in_memory=True)）使用进程内的 HashMap，不写任何文件，管理器释放后随之丢失。
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// 内存模式下 get_images_dir 返回的占位路径（不对应真实目录）
+pub const MEMORY_IMAGES_DIR: &str = ":memory:/images";
+
+/// 内存中的一张图片
+pub struct MemoryImage {
+    data: Vec<u8>,
+    written_at: SystemTime,
+}
+
+/// 存储中的一张图片（孤立图片检查用）
+pub struct StoredFile {
+    pub image_id: String,
+    pub size: u64,
+    /// 距写入的时间，无法获取时为 None
+    pub age: Option<Duration>,
+}
+
+#[derive(Clone)]
+pub enum ImageStore {
+    /// 图片目录
+    Dir(PathBuf),
+    /// image_id -> PNG 数据
+    Memory(Arc<Mutex<HashMap<String, MemoryImage>>>),
+}
+
+impl ImageStore {
+    pub fn memory() -> Self {
+        ImageStore::Memory(Arc::new(Mutex::new(HashMap::new())))
+    }
+
+    /// 图片文件路径（内存模式为 None）
+    pub fn path(&self, image_id: &str) -> Option<PathBuf> {
+        match self {
+            ImageStore::Dir(dir) => Some(dir.join(format!("{}.png", image_id))),
+            ImageStore::Memory(_) => None,
+        }
+    }
+
+    /// 读取 PNG 数据，不存在时返回 NotFound
+    pub fn read(&self, image_id: &str) -> io::Result<Vec<u8>> {
+        match self {
+            ImageStore::Dir(dir) => std::fs::read(dir.join(format!("{}.png", image_id))),
+            ImageStore::Memory(images) => images
+                .lock()
+                .get(image_id)
+                .map(|image| image.data.clone())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("图片不存在: {}", image_id))),
+        }
+    }
+
+    pub fn write(&self, image_id: &str, data: &[u8]) -> io::Result<()> {
+        match self {
+            ImageStore::Dir(dir) => std::fs::write(dir.join(format!("{}.png", image_id)), data),
+            ImageStore::Memory(images) => {
+                let image = MemoryImage { data: data.to_vec(), written_at: SystemTime::now() };
+                images.lock().insert(image_id.to_string(), image);
+                Ok(())
+            }
+        }
+    }
+
+    /// 图片字节数，不存在时返回 None
+    pub fn size(&self, image_id: &str) -> Option<u64> {
+        match self {
+            ImageStore::Dir(dir) => std::fs::metadata(dir.join(format!("{}.png", image_id))).ok().map(|m| m.len()),
+            ImageStore::Memory(images) => images.lock().get(image_id).map(|image| image.data.len() as u64),
+        }
+    }
+
+    pub fn contains(&self, image_id: &str) -> bool {
+        self.size(image_id).is_some()
+    }
+
+    /// 删除图片，返回是否确实删除了
+    pub fn remove(&self, image_id: &str) -> bool {
+        match self {
+            ImageStore::Dir(dir) => std::fs::remove_file(dir.join(format!("{}.png", image_id))).is_ok(),
+            ImageStore::Memory(images) => images.lock().remove(image_id).is_some(),
+        }
+    }
+
+    pub fn rename(&self, old_id: &str, new_id: &str) -> io::Result<()> {
+        match self {
+            ImageStore::Dir(dir) => {
+                let old_path = dir.join(format!("{}.png", old_id));
+                std::fs::rename(&old_path, dir.join(format!("{}.png", new_id)))
+                    .map_err(|e| io::Error::new(e.kind(), format!("重命名图片 {:?} 失败: {}", old_path, e)))
+            }
+            ImageStore::Memory(images) => {
+                let mut images = images.lock();
+                let image = images
+                    .remove(old_id)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("图片不存在: {}", old_id)))?;
+                images.insert(new_id.to_string(), image);
+                Ok(())
+            }
+        }
+    }
+
+    /// 解码图片（目录模式直接从文件流式解码）
+    pub fn open_image(&self, image_id: &str) -> image::ImageResult<image::DynamicImage> {
+        match self.path(image_id) {
+            Some(path) => image::open(path),
+            None => image::load_from_memory(&self.read(image_id)?),
+        }
+    }
+
+    /// 所有 .png 图片
+    pub fn list(&self) -> io::Result<Vec<StoredFile>> {
+        match self {
+            ImageStore::Dir(dir) => {
+                let now = SystemTime::now();
+                let mut files = Vec::new();
+                for entry in std::fs::read_dir(dir)?.flatten() {
+                    let path = entry.path();
+                    if path.extension().and_then(|e| e.to_str()) != Some("png") {
+                        continue;
+                    }
+                    let (Some(image_id), Ok(meta)) = (path.file_stem().and_then(|s| s.to_str()), entry.metadata()) else {
+                        continue;
+                    };
+                    if meta.is_file() {
+                        let age = meta.modified().ok().and_then(|t| now.duration_since(t).ok());
+                        files.push(StoredFile { image_id: image_id.to_string(), size: meta.len(), age });
+                    }
+                }
+                Ok(files)
+            }
+            ImageStore::Memory(images) => Ok(images
+                .lock()
+                .iter()
+                .map(|(image_id, image)| StoredFile {
+                    image_id: image_id.clone(),
+                    size: image.data.len() as u64,
+                    age: image.written_at.elapsed().ok(),
+                })
+                .collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_store_round_trip() {
+        let store = ImageStore::memory();
+        let shared = store.clone();
+        store.write("a", &[1, 2, 3]).unwrap();
+        assert_eq!(shared.read("a").unwrap(), vec![1, 2, 3]);
+        assert_eq!(shared.size("a"), Some(3));
+        assert!(store.path("a").is_none());
+
+        store.rename("a", "b").unwrap();
+        assert!(!store.contains("a") && store.contains("b"));
+        assert_eq!(store.list().unwrap().len(), 1);
+        assert!(store.remove("b"));
+        assert_eq!(store.read("b").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
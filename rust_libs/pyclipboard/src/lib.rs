@@ -2,10 +2,24 @@ use pyo3::prelude::*;
 use pyo3::exceptions::PyRuntimeError;
 
 mod database;
+mod error;
+mod file_thumbnail;
+mod heuristics;
+mod html_export;
+mod image_store;
+mod metrics;
+mod plain_text;
+mod query_parser;
+mod reader_pool;
+mod suppress;
 mod types;
 
 use database::Database;
-use types::{PyClipboardItem, PyQueryParams, PyPaginatedResult, PyGroup};
+use error::ClipboardError;
+use image_store::ImageStore;
+use metrics::Phase;
+use reader_pool::ReaderPool;
+use types::{PyClipboardItem, PyClipboardEvent, PyClipboardManagerConfig, PyFileInfo, PyQueryParams, PyPaginatedResult, PyGroup};
 
 use std::sync::Arc;
 use parking_lot::Mutex;
@@ -19,8 +33,13 @@ use zstd;
 
 static IS_RUNNING: AtomicBool = AtomicBool::new(false);
 static CALLBACK: Lazy<Arc<Mutex<Option<PyObject>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
-// 跳过下一次剪贴板变化（用于防止 paste_item 自己触发监听）
-static SKIP_NEXT_CHANGE: AtomicBool = AtomicBool::new(false);
+// 监听线程的停止通道与线程句柄（stop_monitor 用于主动结束 start_watch 并等待线程退出）
+static MONITOR_SHUTDOWN: Lazy<Mutex<Option<clipboard_rs::WatcherShutdown>>> = Lazy::new(|| Mutex::new(None));
+static MONITOR_THREAD: Lazy<Mutex<Option<thread::JoinHandle<()>>>> = Lazy::new(|| Mutex::new(None));
+// wait_for_change 的等待者：监听运行时由监听线程在写入记录后逐个通知（id 用于超时后注销）
+type ChangeWaiter = (u64, std::sync::mpsc::Sender<PyClipboardItem>);
+static CHANGE_WAITERS: Lazy<Mutex<Vec<ChangeWaiter>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static NEXT_WAITER_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
 
 // ============== Python 模块 ==============
 
@@ -29,15 +48,23 @@ static SKIP_NEXT_CHANGE: AtomicBool = AtomicBool::new(false);
 fn pyclipboard(m: &Bound<'_, PyModule>) -> PyResult<()> {
     // 注册类
     m.add_class::<PyClipboardManager>()?;
+    m.add_class::<MonitorGuard>()?;
+    m.add_class::<ClipboardItemIter>()?;
     m.add_class::<PyClipboardItem>()?;
+    m.add_class::<PyClipboardEvent>()?;
     m.add_class::<PyQueryParams>()?;
+    m.add_class::<PyClipboardManagerConfig>()?;
     m.add_class::<PyPaginatedResult>()?;
     m.add_class::<PyGroup>()?;
+    m.add_class::<PyFileInfo>()?;
     
     // 注册函数
     m.add_function(wrap_pyfunction!(get_clipboard_text, m)?)?;
     m.add_function(wrap_pyfunction!(set_clipboard_text, m)?)?;
+    m.add_function(wrap_pyfunction!(set_clipboard_text_keep_formats, m)?)?;
+    m.add_function(wrap_pyfunction!(set_clipboard, m)?)?;
     m.add_function(wrap_pyfunction!(get_clipboard_image, m)?)?;
+    m.add_function(wrap_pyfunction!(get_clipboard_image_raw, m)?)?;
     m.add_function(wrap_pyfunction!(set_clipboard_image, m)?)?;
     m.add_function(wrap_pyfunction!(get_clipboard_html, m)?)?;
     m.add_function(wrap_pyfunction!(get_clipboard_rtf, m)?)?;
@@ -45,6 +72,7 @@ fn pyclipboard(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(set_clipboard_files, m)?)?;
     m.add_function(wrap_pyfunction!(get_available_formats, m)?)?;
     m.add_function(wrap_pyfunction!(get_clipboard_owner, m)?)?;
+    m.add_function(wrap_pyfunction!(get_clipboard_sequence, m)?)?;
     
     Ok(())
 }
@@ -84,6 +112,426 @@ fn generate_cf_html(html: &str) -> String {
     )
 }
 
+/// 解析 CF_HTML（generate_cf_html 的逆过程）：去掉 Version/StartHTML 等头部，只保留片段
+///
+/// 优先按头部中的 StartFragment/EndFragment 字节偏移截取，偏移无效时退回到
+/// <!--StartFragment--> / <!--EndFragment--> 标记；不是 CF_HTML 时原样返回
+fn parse_cf_html(raw: &str) -> String {
+    const START_MARKER: &str = "<!--StartFragment-->";
+    const END_MARKER: &str = "<!--EndFragment-->";
+
+    if !raw.starts_with("Version:") {
+        return raw.to_string();
+    }
+
+    let header_offset = |key: &str| -> Option<usize> {
+        raw.lines()
+            .take_while(|line| !line.starts_with('<'))
+            .find_map(|line| line.strip_prefix(key))
+            .and_then(|value| value.trim().parse().ok())
+    };
+    if let (Some(start), Some(end)) = (header_offset("StartFragment:"), header_offset("EndFragment:")) {
+        if let Some(fragment) = raw.get(start..end) {
+            return fragment.trim().to_string();
+        }
+    }
+
+    // 偏移缺失或不在字符边界上（部分程序按字符数而非字节数写偏移）
+    let body = raw.find('<').map_or("", |start| &raw[start..]);
+    match (body.find(START_MARKER), body.find(END_MARKER)) {
+        (Some(start), Some(end)) if start + START_MARKER.len() <= end => {
+            body[start + START_MARKER.len()..end].trim().to_string()
+        }
+        _ => body.to_string(),
+    }
+}
+
+// ============== 剪贴板内容识别 ==============
+
+/// 生成缩略图 Base64
+fn generate_thumbnail(rgba: &image::RgbaImage, max_size: u32) -> Option<String> {
+    use image::imageops::FilterType;
+    use image::codecs::png::PngEncoder;
+    use image::ImageEncoder;
+    use base64::{Engine as _, engine::general_purpose};
+    
+    let (w, h) = (rgba.width(), rgba.height());
+    let (new_w, new_h) = if w > h {
+        (max_size, (max_size as f32 * h as f32 / w as f32) as u32)
+    } else {
+        ((max_size as f32 * w as f32 / h as f32) as u32, max_size)
+    };
+    
+    let thumbnail = image::imageops::resize(rgba, new_w.max(1), new_h.max(1), FilterType::Triangle);
+    
+    let mut png_data = Vec::new();
+    let encoder = PngEncoder::new(&mut png_data);
+    if encoder.write_image(
+        thumbnail.as_raw(),
+        thumbnail.width(),
+        thumbnail.height(),
+        image::ExtendedColorType::Rgba8,
+    ).is_ok() {
+        let base64_str = general_purpose::STANDARD.encode(&png_data);
+        Some(format!("data:image/png;base64,{}", base64_str))
+    } else {
+        None
+    }
+}
+
+/// 识别出的图片（image_id 即落盘文件名）
+struct StoredImage {
+    image_id: String,
+    thumbnail: Option<String>,
+    width: u32,
+    height: u32,
+    /// 落盘文件字节数（预览时不落盘，为 None）
+    bytes: Option<u64>,
+    /// 感知哈希（dHash），用于近似重复图片去重
+    dhash: u64,
+}
+
+// ── Ditto 风格：按白名单逐个取，不枚举全部格式 ─────────────────
+// 策略：先用 IsClipboardFormatAvailable 轻量探测（不分配内存），
+//       命中后才调用 GetClipboardData + GlobalLock 真正读取。
+// 优势：Word/WPS 等程序会往剪贴板塞几十种私有格式（总计可达数十 MB），
+//       先全枚举再筛选会把这些全读进内存再丢掉；按白名单取则完全跳过它们。
+//
+// 同时做一次轻量的"全格式探测"（只拿名称+ID，不读数据），
+// 用于兜底判断剪贴板是否含有图片类数据（raw_image_fallback 逻辑）。
+#[cfg(target_os = "windows")]
+fn read_whitelisted_formats() -> (Vec<(u32, String, Vec<u8>)>, Vec<(u32, String)>) {
+    // 返回值：
+    //   .0  whitelisted_data  — 白名单格式的完整数据（存入 DB）
+    //   .1  all_format_names  — 剪贴板上所有格式的 (id, name)（仅用于兜底探测）
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStringExt;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn OpenClipboard(hwnd: *mut std::ffi::c_void) -> i32;
+        fn CloseClipboard() -> i32;
+        fn IsClipboardFormatAvailable(format: u32) -> i32;
+        fn EnumClipboardFormats(format: u32) -> u32;
+        fn GetClipboardData(format: u32) -> *mut std::ffi::c_void;
+        fn GetClipboardFormatNameW(fmt: u32, buf: *mut u16, max: i32) -> i32;
+        fn RegisterClipboardFormatW(lpszFormat: *const u16) -> u32;
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GlobalLock(hmem: *mut std::ffi::c_void) -> *mut std::ffi::c_void;
+        fn GlobalUnlock(hmem: *mut std::ffi::c_void) -> i32;
+        fn GlobalSize(hmem: *mut std::ffi::c_void) -> usize;
+    }
+
+    // 把格式名称字符串转为 wide 用于 RegisterClipboardFormatW
+    fn to_wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    // 标准格式名称
+    fn standard_name(id: u32) -> Option<&'static str> {
+        match id {
+            1  => Some("CF_TEXT"),
+            7  => Some("CF_OEMTEXT"),
+            8  => Some("CF_DIB"),
+            13 => Some("CF_UNICODETEXT"),
+            15 => Some("CF_HDROP"),
+            16 => Some("CF_LOCALE"),
+            17 => Some("CF_DIBV5"),
+            _  => None,
+        }
+    }
+
+    // 白名单定义：(format_id_or_0, name)
+    // format_id=0 表示需要用 RegisterClipboardFormatW 动态查询 ID
+    // format_id 已知的标准格式直接填写
+    struct WlEntry { id: u32, name: &'static str }
+    let whitelist: &[WlEntry] = &[
+        WlEntry { id: 1,  name: "CF_TEXT" },
+        WlEntry { id: 8,  name: "CF_DIB" },
+        WlEntry { id: 13, name: "CF_UNICODETEXT" },
+        WlEntry { id: 15, name: "CF_HDROP" },
+        WlEntry { id: 16, name: "CF_LOCALE" },
+        WlEntry { id: 17, name: "CF_DIBV5" },
+        WlEntry { id: 0,  name: "PNG" },
+        // GIF：保留动画帧，粘贴时原样还原
+        WlEntry { id: 0,  name: "GIF" },
+        WlEntry { id: 0,  name: "HTML Format" },
+        WlEntry { id: 0,  name: "Rich Text Format" },
+    ];
+
+    let mut data_result: Vec<(u32, String, Vec<u8>)> = Vec::new();
+    let mut all_names: Vec<(u32, String)> = Vec::new();
+
+    unsafe {
+        // ── 阶段1：轻量探测 + 全格式枚举（仅取名称，不读数据）────
+        // 目的：为 raw_image_fallback 收集全部格式名称列表
+        if OpenClipboard(std::ptr::null_mut()) == 0 {
+            return (data_result, all_names);
+        }
+        let mut fmt: u32 = 0;
+        loop {
+            fmt = EnumClipboardFormats(fmt);
+            if fmt == 0 { break; }
+            let name = if let Some(s) = standard_name(fmt) {
+                s.to_string()
+            } else {
+                let mut buf = [0u16; 256];
+                let len = GetClipboardFormatNameW(fmt, buf.as_mut_ptr(), 256);
+                if len > 0 {
+                    OsString::from_wide(&buf[..len as usize]).to_string_lossy().into_owned()
+                } else {
+                    format!("UNKNOWN_{}", fmt)
+                }
+            };
+            all_names.push((fmt, name));
+        }
+        CloseClipboard();
+
+        // ── 阶段2：按白名单逐个取数据（Ditto 风格）──────────────
+        // IsClipboardFormatAvailable 不需要打开剪贴板，直接探测
+        // 先收集命中的 (id, name) 列表，再一次性打开剪贴板读取
+        let mut to_read: Vec<(u32, &'static str)> = Vec::new();
+        for entry in whitelist {
+            let fmt_id = if entry.id != 0 {
+                entry.id
+            } else {
+                // 动态格式：用 RegisterClipboardFormatW 获取 ID（若未注册则返回 0）
+                let wide = to_wide(entry.name);
+                RegisterClipboardFormatW(wide.as_ptr())
+            };
+            if fmt_id == 0 { continue; }
+            if IsClipboardFormatAvailable(fmt_id) != 0 {
+                to_read.push((fmt_id, entry.name));
+            }
+        }
+
+        if to_read.is_empty() {
+            return (data_result, all_names);
+        }
+
+        // 一次打开剪贴板，读取所有命中的白名单格式
+        if OpenClipboard(std::ptr::null_mut()) == 0 {
+            return (data_result, all_names);
+        }
+        for (fmt_id, name) in &to_read {
+            let hmem = GetClipboardData(*fmt_id);
+            if hmem.is_null() { continue; }
+            let ptr = GlobalLock(hmem);
+            if ptr.is_null() { continue; }
+            let size = GlobalSize(hmem);
+            let data = if size > 0 && size <= 64 * 1024 * 1024 {
+                std::slice::from_raw_parts(ptr as *const u8, size).to_vec()
+            } else {
+                GlobalUnlock(hmem);
+                continue;
+            };
+            GlobalUnlock(hmem);
+            data_result.push((*fmt_id, name.to_string(), data));
+        }
+        CloseClipboard();
+    }
+
+    (data_result, all_names)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn read_whitelisted_formats() -> (Vec<(u32, String, Vec<u8>)>, Vec<(u32, String)>) {
+    (Vec::new(), Vec::new())
+}
+
+/// 一次剪贴板读取的识别结果
+struct DetectedClipboard {
+    /// 识别出的记录（id 为 0，尚未入库）
+    item: PyClipboardItem,
+    /// 白名单格式的原始数据（监听时随记录一起存库）
+    raw_formats: Vec<(u32, String, Vec<u8>)>,
+    /// 剪贴板图片的感知哈希（没有图片时为 None）
+    image_dhash: Option<u64>,
+}
+
+/// 读取当前剪贴板并识别为一条记录，监听线程与 peek_clipboard 共用同一套逻辑
+///
+/// 图片交给 store_image 处理：监听时落盘，预览时只计算 ID 和缩略图
+fn detect_clipboard_item(
+    store_image: impl FnOnce(&clipboard_rs::RustImageData) -> Option<StoredImage>,
+) -> Option<DetectedClipboard> {
+    // ── 第一步：Ditto 风格按白名单读取格式数据 ────────────────
+    // raw_formats  = 白名单格式的完整数据（直接存 DB，已经过滤好）
+    // all_names    = 剪贴板上所有格式的 (id, name)（仅用于 fallback 探测）
+    let (raw_formats, all_names) = read_whitelisted_formats();
+
+    // ── 第二步：高层 API 读取全部可用表示（同一次复制的文本/HTML/图片都保留）──
+    use clipboard_rs::{Clipboard, ClipboardContext};
+    let ctx = ClipboardContext::new().ok()?;
+
+    // 剪贴板上没有任何格式（例如被清空）时直接忽略
+    let available = ctx.available_formats().unwrap_or_default();
+    if available.is_empty() && raw_formats.is_empty() && all_names.is_empty() {
+        return None;
+    }
+
+    let source_app = get_clipboard_owner().ok().flatten();
+    // Windows 下为带头部的 CF_HTML，只保存片段（粘贴时由 generate_cf_html 重新包装）
+    let html_content = ctx.get_html().ok()
+        .map(|h| parse_cf_html(&h))
+        .filter(|h| !h.trim().is_empty());
+
+    let text_val  = ctx.get_text().ok().filter(|t| !t.trim().is_empty());
+    let files_val = ctx.get_files().ok().filter(|f| !f.is_empty());
+    // 图片与文本并存时也落盘（例如富文本同时提供渲染图），避免只保留其中一种
+    let stored_image = ctx.get_image().ok().and_then(|img| store_image(&img));
+
+    // 高层 API 全部失败时，检查白名单数据或全格式名称列表是否含图片类格式
+    // 场景：Word 复制多张图片时 get_image() 返回 None，但 raw_formats 里有 PNG/DIB
+    let raw_image_fallback = if text_val.is_none() && files_val.is_none() && stored_image.is_none() {
+        let has_image_data = raw_formats.iter().any(|(fid, fname, data)| {
+            !data.is_empty() && (*fid == 8 || *fid == 17 || fname.eq_ignore_ascii_case("PNG"))
+        });
+        // 也检查 all_names，防止白名单中没有 PNG/DIB 但剪贴板里有其他图片格式
+        let has_image_name = all_names.iter().any(|(fid, fname)| {
+            *fid == 8 || *fid == 17 || fname.eq_ignore_ascii_case("PNG")
+        });
+        has_image_data || has_image_name
+    } else {
+        false
+    };
+
+    // ── 第三步：构造主记录 ────────────────────────────────────
+    // content_type 按 文本 → 文件 → 图片 的优先级决定（影响 UI 展示与去重），
+    // 其余表示形式一并挂到同一条记录上：HTML 存 html_content，图片存 image_id
+    let mut main_item: PyClipboardItem = if let Some(text) = text_val {
+        PyClipboardItem::new(0, text, "text".to_string())
+    } else if let Some(files) = files_val {
+        let content = serde_json::json!({ "files": files }).to_string();
+        PyClipboardItem::new(0, content, "file".to_string())
+    } else if let Some(image) = &stored_image {
+        PyClipboardItem::new(
+            0,
+            format!("[{}x{}]", image.width, image.height),
+            "image".to_string(),
+        )
+    } else if raw_image_fallback {
+        // raw_image_fallback：多图/EMF 等高层 API 无法解析的图片内容
+        // content 写入格式列表和总字节数，供前端直接显示
+        // 例：[PNG+CF_DIB 7.9 MB] 或 [PNG 1.2 MB]
+        let img_fmt_names: Vec<&str> = {
+            let mut names = Vec::new();
+            for (fid, fname, data) in &raw_formats {
+                if data.is_empty() { continue; }
+                if *fid == 17 { names.push("CF_DIBV5"); }
+                else if *fid == 8 { names.push("CF_DIB"); }
+                else if fname.eq_ignore_ascii_case("PNG") { names.push("PNG"); }
+            }
+            names.dedup();
+            names
+        };
+        let total_bytes: usize = raw_formats.iter()
+            .filter(|(fid, fname, _)| *fid == 8 || *fid == 17 || fname.eq_ignore_ascii_case("PNG"))
+            .map(|(_, _, d)| d.len())
+            .sum();
+        let size_str = if total_bytes >= 1024 * 1024 {
+            format!("{:.1} MB", total_bytes as f64 / 1024.0 / 1024.0)
+        } else if total_bytes > 0 {
+            format!("{:.0} KB", total_bytes as f64 / 1024.0)
+        } else {
+            "0 B".to_string()
+        };
+        let fmt_str = if img_fmt_names.is_empty() { "raw".to_string() }
+                      else { img_fmt_names.join("+") };
+        PyClipboardItem::new(
+            0,
+            format!("[{} {}]", fmt_str, size_str),
+            "image".to_string(),
+        )
+    } else {
+        return None;
+    };
+
+    main_item.html_content = html_content;
+    main_item.source_app = source_app;
+    if CAPTURE_FORMATS.load(Ordering::Relaxed) && !available.is_empty() {
+        main_item.formats = Some(available);
+    }
+    let image_dhash = stored_image.as_ref().map(|image| image.dhash);
+    if let Some(image) = stored_image {
+        main_item.image_id = Some(image.image_id);
+        main_item.thumbnail = image.thumbnail;
+        main_item.image_width = Some(image.width as i64);
+        main_item.image_height = Some(image.height as i64);
+        main_item.image_bytes = image.bytes.map(|b| b as i64);
+        main_item.image_format = Some("PNG".to_string());
+    }
+
+    Some(DetectedClipboard { item: main_item, raw_formats, image_dhash })
+}
+
+/// 图片编码为 PNG 写入图片存储（按内容哈希命名，已存在则跳过），并生成缩略图
+fn store_image(store: &ImageStore, image: &clipboard_rs::RustImageData) -> Option<StoredImage> {
+    use clipboard_rs::common::RustImage;
+    use image::codecs::png::PngEncoder;
+    use image::ImageEncoder;
+
+    let rgba = metrics::time(Phase::Decode, || image.to_rgba8()).ok()?;
+    // 按像素内容计算 ID，同一张图片跨版本/平台保持一致
+    let (image_id, dhash) = metrics::time(Phase::Hash, || {
+        (database::compute_image_id(&rgba), database::compute_image_dhash(&rgba))
+    });
+
+    let mut bytes = store.size(&image_id);
+    if bytes.is_none() {
+        let start = std::time::Instant::now();
+        let mut png_data = Vec::new();
+        let encoder = PngEncoder::new(&mut png_data);
+        encoder.write_image(
+            rgba.as_raw(),
+            rgba.width(),
+            rgba.height(),
+            image::ExtendedColorType::Rgba8,
+        ).ok()?;
+        match store.write(&image_id, &png_data) {
+            Ok(()) => {
+                bytes = Some(png_data.len() as u64);
+                tracing::debug!(%image_id, bytes = png_data.len(), "图片已保存");
+            }
+            Err(e) => tracing::warn!(%image_id, error = %e, "保存图片失败"),
+        }
+        metrics::record(Phase::Encode, start.elapsed());
+    }
+
+    Some(StoredImage {
+        image_id,
+        thumbnail: metrics::time(Phase::Thumbnail, || generate_thumbnail(&rgba, THUMBNAIL_SIZE.load(Ordering::Relaxed))),
+        width: rgba.width(),
+        height: rgba.height(),
+        bytes,
+        dhash,
+    })
+}
+
+/// 采集当前剪贴板为一条记录（图片写入 store），不写入数据库
+///
+/// 返回记录的 id 为 0；需要原始格式数据时改用 detect_clipboard_item
+fn capture_current_clipboard(store: &ImageStore) -> Option<PyClipboardItem> {
+    detect_clipboard_item(|img| store_image(store, img)).map(|detected| detected.item)
+}
+
+/// 计算图片 ID 和缩略图但不落盘（预览用，image_id 对应的文件可能尚不存在）
+fn preview_image(image: &clipboard_rs::RustImageData) -> Option<StoredImage> {
+    use clipboard_rs::common::RustImage;
+
+    let rgba = image.to_rgba8().ok()?;
+    Some(StoredImage {
+        image_id: database::compute_image_id(&rgba),
+        thumbnail: generate_thumbnail(&rgba, THUMBNAIL_SIZE.load(Ordering::Relaxed)),
+        width: rgba.width(),
+        height: rgba.height(),
+        bytes: None,
+        dhash: database::compute_image_dhash(&rgba),
+    })
+}
+
 /// 获取剪贴板文本
 #[pyfunction]
 fn get_clipboard_text() -> PyResult<Option<String>> {
@@ -110,6 +558,80 @@ fn set_clipboard_text(text: String) -> PyResult<()> {
         .map_err(|e| PyRuntimeError::new_err(format!("设置剪贴板失败: {}", e)))
 }
 
+/// 设置剪贴板文本，保留剪贴板上已有的 HTML / RTF / 图片 / 文件表示
+/// 
+/// 只替换文本表示，其余表示读出后与新文本一起重新写入
+/// （仅保留高层 API 能识别的格式，应用自定义格式会丢失）
+#[pyfunction]
+fn set_clipboard_text_keep_formats(text: String) -> PyResult<()> {
+    use clipboard_rs::{Clipboard, ClipboardContent, ClipboardContext};
+    
+    let ctx = ClipboardContext::new()
+        .map_err(|e| PyRuntimeError::new_err(format!("创建剪贴板上下文失败: {}", e)))?;
+    
+    let mut contents = vec![ClipboardContent::Text(text)];
+    if let Some(html) = ctx.get_html().ok().map(|h| parse_cf_html(&h)).filter(|h| !h.is_empty()) {
+        contents.push(ClipboardContent::Html(generate_cf_html(&html)));
+    }
+    if let Some(rtf) = ctx.get_rich_text().ok().filter(|r| !r.is_empty()) {
+        contents.push(ClipboardContent::Rtf(rtf));
+    }
+    if let Ok(image) = ctx.get_image() {
+        contents.push(ClipboardContent::Image(image));
+    }
+    if let Some(files) = ctx.get_files().ok().filter(|f| !f.is_empty()) {
+        contents.push(ClipboardContent::Files(files));
+    }
+    
+    ctx.set(contents)
+        .map_err(|e| PyRuntimeError::new_err(format!("设置剪贴板失败: {}", e)))
+}
+
+/// 一次性设置剪贴板的多种表示（同一次写入，粘贴目标按需选择格式）
+/// 
+/// Args:
+///     text: 纯文本
+///     html: HTML 片段（自动包装为 CF_HTML）
+///     image: PNG 图片字节
+///     files: 文件路径列表
+/// 
+/// Raises:
+///     RuntimeError: 未提供任何内容或写入失败
+#[pyfunction]
+#[pyo3(signature = (text=None, html=None, image=None, files=None))]
+fn set_clipboard(
+    text: Option<String>,
+    html: Option<String>,
+    image: Option<Vec<u8>>,
+    files: Option<Vec<String>>,
+) -> PyResult<()> {
+    use clipboard_rs::{Clipboard, ClipboardContent, ClipboardContext, common::RustImage};
+    
+    let mut contents = Vec::new();
+    if let Some(text) = text {
+        contents.push(ClipboardContent::Text(text));
+    }
+    if let Some(html) = html {
+        contents.push(ClipboardContent::Html(generate_cf_html(&html)));
+    }
+    if let Some(image_bytes) = image {
+        let rust_image = RustImage::from_bytes(&image_bytes)
+            .map_err(|e| PyRuntimeError::new_err(format!("解析图片失败: {}", e)))?;
+        contents.push(ClipboardContent::Image(rust_image));
+    }
+    if let Some(files) = files {
+        contents.push(ClipboardContent::Files(files));
+    }
+    if contents.is_empty() {
+        return Err(PyRuntimeError::new_err("至少需要提供一种剪贴板内容"));
+    }
+    
+    let ctx = ClipboardContext::new()
+        .map_err(|e| PyRuntimeError::new_err(format!("创建剪贴板上下文失败: {}", e)))?;
+    ctx.set(contents)
+        .map_err(|e| PyRuntimeError::new_err(format!("设置剪贴板失败: {}", e)))
+}
+
 /// 获取剪贴板图片（返回 PNG 字节）
 #[pyfunction]
 fn get_clipboard_image() -> PyResult<Option<Vec<u8>>> {
@@ -140,6 +662,30 @@ fn get_clipboard_image() -> PyResult<Option<Vec<u8>>> {
     }
 }
 
+/// 原生编码图片格式，按优先级排列（GIF 在前以保留动画；MIME 名称用于 macOS/Linux）
+const RAW_IMAGE_FORMATS: &[&str] = &["GIF", "image/gif", "PNG", "image/png", "JFIF", "image/jpeg"];
+
+/// 获取剪贴板图片的原生字节（不经过 RGBA 解码和 PNG 重新编码，GIF 动画得以保留）
+/// 
+/// Returns:
+///     Optional[Tuple[str, bytes]]: (格式名称, 原始字节)，剪贴板中没有已编码的图片格式时返回 None
+#[pyfunction]
+fn get_clipboard_image_raw() -> PyResult<Option<(String, Vec<u8>)>> {
+    use clipboard_rs::{Clipboard, ClipboardContext};
+    
+    let ctx = ClipboardContext::new()
+        .map_err(|e| PyRuntimeError::new_err(format!("创建剪贴板上下文失败: {}", e)))?;
+    
+    for name in RAW_IMAGE_FORMATS {
+        if let Ok(data) = ctx.get_buffer(name) {
+            if !data.is_empty() {
+                return Ok(Some((name.to_string(), data)));
+            }
+        }
+    }
+    Ok(None)
+}
+
 /// 设置剪贴板图片（从 PNG 字节）
 #[pyfunction]
 fn set_clipboard_image(image_bytes: Vec<u8>) -> PyResult<()> {
@@ -165,7 +711,7 @@ fn get_clipboard_html() -> PyResult<Option<String>> {
         .map_err(|e| PyRuntimeError::new_err(format!("创建剪贴板上下文失败: {}", e)))?;
     
     match ctx.get_html() {
-        Ok(html) => Ok(Some(html)),
+        Ok(html) => Ok(Some(parse_cf_html(&html))),
         Err(_) => Ok(None),
     }
 }
@@ -292,6 +838,34 @@ fn get_clipboard_owner() -> PyResult<Option<String>> {
     }
 }
 
+/// 获取剪贴板序列号（仅 Windows）
+/// 
+/// 剪贴板内容每次变化序列号都会增加，轮询时比较序列号即可判断是否变化，无需读取内容
+/// 
+/// Returns:
+///     Optional[int]: 当前序列号，不支持的平台返回 None
+#[pyfunction]
+fn get_clipboard_sequence() -> Option<u64> {
+    #[cfg(target_os = "windows")]
+    {
+        #[link(name = "user32")]
+        extern "system" {
+            fn GetClipboardSequenceNumber() -> u32;
+        }
+        
+        // 没有访问剪贴板的权限时返回 0
+        match unsafe { GetClipboardSequenceNumber() } {
+            0 => None,
+            seq => Some(seq as u64),
+        }
+    }
+    
+    #[cfg(not(target_os = "windows"))]
+    {
+        None
+    }
+}
+
 // ============== 剪贴板管理器 ==============
 
 /// 剪贴板历史管理器
@@ -301,6 +875,12 @@ fn get_clipboard_owner() -> PyResult<Option<String>> {
 /// 
 /// Args:
 ///     db_path: 数据库文件路径，默认存储在用户数据目录
+///     images_dir: 图片存储目录，默认为数据库所在目录下的 images 子目录
+///     busy_timeout_ms: 数据库被其他连接锁住时的最长等待毫秒数，默认 5000
+///     in_memory: 为 True 时数据库和图片都只保存在内存中，不写任何文件，管理器释放后数据随之丢失
+///         （用于测试或无痕会话，不能与 db_path / images_dir 同时指定）
+/// 
+/// 监听线程和修改操作共用一个写连接，查询使用独立的只读连接池，长时间写入时查询无需等待。
 /// 
 /// Example:
 ///     >>> manager = PyClipboardManager()
@@ -310,21 +890,43 @@ fn get_clipboard_owner() -> PyResult<Option<String>> {
 ///     ...     print(item.content)
 #[pyclass]
 pub struct PyClipboardManager {
+    /// 写连接：监听线程入库和所有修改操作
     db: Arc<Mutex<Database>>,
+    /// 只读连接池：列表、搜索、计数等纯查询，多个线程的查询并行执行，长写入期间也不必排队等 db 的锁
+    /// （内存数据库无法跨连接共享，此时只有 db 这一个连接）
+    reader: Arc<ReaderPool>,
     /// 数据库文件路径
     db_path: String,
     /// 历史记录数量限制，0 表示不限制
     history_limit: Arc<std::sync::atomic::AtomicI64>,
+    /// 存储字节数限制，0 表示不限制
+    storage_limit_bytes: Arc<std::sync::atomic::AtomicU64>,
 }
 
 /// 全局历史限制（供监听线程使用）
 static HISTORY_LIMIT: std::sync::atomic::AtomicI64 = std::sync::atomic::AtomicI64::new(0);
+/// 近似重复图片的 dHash 汉明距离阈值，0 表示不启用（供监听线程使用）
+static IMAGE_DEDUP_DISTANCE: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+/// 是否记录每条记录采集时的剪贴板格式列表（供监听线程使用）
+static CAPTURE_FORMATS: AtomicBool = AtomicBool::new(false);
+/// 剪贴板被清空时是否通知监听回调（供监听线程使用）
+static NOTIFY_CLEAR: AtomicBool = AtomicBool::new(false);
+/// 全局存储字节数限制（供监听线程使用）
+static STORAGE_LIMIT_BYTES: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+/// 图片记录缩略图的默认长边像素数
+pub(crate) const DEFAULT_THUMBNAIL_SIZE: u32 = 64;
+/// 图片记录缩略图的长边像素数（供监听线程使用）
+static THUMBNAIL_SIZE: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(DEFAULT_THUMBNAIL_SIZE);
 
 #[pymethods]
 impl PyClipboardManager {
     #[new]
-    #[pyo3(signature = (db_path=None))]
-    fn new(db_path: Option<String>) -> PyResult<Self> {
+    #[pyo3(signature = (db_path=None, images_dir=None, busy_timeout_ms=database::DEFAULT_BUSY_TIMEOUT_MS, in_memory=false))]
+    fn new(db_path: Option<String>, images_dir: Option<String>, busy_timeout_ms: u32, in_memory: bool) -> PyResult<Self> {
+        if in_memory && (db_path.is_some() || images_dir.is_some()) {
+            return Err(ClipboardError::InvalidArgument("in_memory 模式不能指定 db_path 或 images_dir".to_string()).into());
+        }
+        let db_path = if in_memory { Some(":memory:".to_string()) } else { db_path };
         let path = db_path.unwrap_or_else(|| {
             dirs::data_dir()
                 .unwrap_or_else(|| std::path::PathBuf::from("."))
@@ -335,21 +937,68 @@ impl PyClipboardManager {
         });
         
         // 确保目录存在
-        if let Some(parent) = std::path::Path::new(&path).parent() {
+        if let Some(parent) = std::path::Path::new(&path).parent().filter(|_| !in_memory) {
             std::fs::create_dir_all(parent)
                 .map_err(|e| PyRuntimeError::new_err(format!("创建目录失败: {}", e)))?;
         }
         
-        let db = Database::new(&path)
-            .map_err(|e| PyRuntimeError::new_err(e))?;
+        let mut db = Database::new(&path, busy_timeout_ms)
+            .map_err(PyErr::from)?;
+        
+        if let Some(dir) = images_dir {
+            db.set_images_dir(PathBuf::from(dir), false)
+                .map_err(PyErr::from)?;
+        }
+        
+        let db = Arc::new(Mutex::new(db));
+        let reader = if path.is_empty() || path == ":memory:" {
+            ReaderPool::shared(db.clone())
+        } else {
+            ReaderPool::open(&path, busy_timeout_ms, reader_pool::DEFAULT_READER_CONNECTIONS)
+                .map_err(PyErr::from)?
+        };
         
         Ok(Self {
-            db: Arc::new(Mutex::new(db)),
+            db,
+            reader: Arc::new(reader),
             db_path: path,
             history_limit: Arc::new(std::sync::atomic::AtomicI64::new(0)),
+            storage_limit_bytes: Arc::new(std::sync::atomic::AtomicU64::new(0)),
         })
     }
     
+    /// 按配置创建管理器（等价于构造后逐个调用对应的 set_* 方法）
+    /// 
+    /// Args:
+    ///     config: PyClipboardManagerConfig
+    /// 
+    /// Returns:
+    ///     PyClipboardManager: 管理器实例
+    /// 
+    /// Raises:
+    ///     ValueError: thumbnail_size 为 0、dedup_scope / dedup_strategy 无效，或 in_memory 时指定了 db_path / images_dir
+    ///     RuntimeError: 打开数据库或创建图片目录失败
+    #[staticmethod]
+    fn from_config(py: Python<'_>, config: &PyClipboardManagerConfig) -> PyResult<Self> {
+        let manager = Self::new(
+            config.db_path.clone(),
+            config.images_dir.clone(),
+            config.busy_timeout_ms,
+            config.in_memory,
+        )?;
+        manager.set_thumbnail_size(config.thumbnail_size)?;
+        manager.set_history_limit(py, config.history_limit);
+        manager.set_storage_limit_bytes(py, config.storage_limit_bytes);
+        manager.set_image_dedup_distance(config.image_dedup_distance);
+        manager.set_max_pinned(config.max_pinned);
+        manager.set_dedup_scope(&config.dedup_scope)?;
+        manager.set_dedup_strategy(&config.dedup_strategy, None)?;
+        manager.set_text_compression(config.text_compression);
+        manager.set_capture_formats(config.capture_formats);
+        manager.set_notify_clear(config.notify_clear);
+        Ok(manager)
+    }
+    
     /// 获取数据库文件路径
     #[getter]
     fn get_db_path(&self) -> String {
@@ -366,6 +1015,34 @@ impl PyClipboardManager {
         db.get_images_dir().to_string_lossy().to_string()
     }
     
+    /// 设置图片存储目录
+    /// 
+    /// Args:
+    ///     images_dir: 新的图片存储目录
+    ///     migrate: 是否把旧目录中的图片移动到新目录，默认 True
+    /// 
+    /// Returns:
+    ///     int: 迁移的图片数量
+    #[pyo3(signature = (images_dir, migrate=true))]
+    fn set_images_dir(&self, py: Python<'_>, images_dir: String, migrate: bool) -> PyResult<usize> {
+        let db = self.db.clone();
+        py.allow_threads(move || db.lock().set_images_dir(PathBuf::from(images_dir), migrate))
+            .map_err(PyErr::from)
+    }
+    
+    /// 一次性迁移：按像素内容重新计算已有图片的 ID 并重命名图片文件
+    /// 
+    /// 旧版本用 PNG 编码结果计算 ID，同一张图片可能得到不同 ID 而重复落盘；
+    /// 升级后调用一次即可，重复调用不会产生变化。
+    /// 
+    /// Returns:
+    ///     int: 更新了 ID 的图片数量
+    fn migrate_image_ids(&self, py: Python<'_>) -> PyResult<usize> {
+        let db = self.db.clone();
+        py.allow_threads(move || db.lock().migrate_image_ids())
+            .map_err(PyErr::from)
+    }
+    
     /// 设置历史记录数量限制
     /// 
     /// Args:
@@ -373,14 +1050,16 @@ impl PyClipboardManager {
     /// 
     /// 设置后，插入新记录时会自动清理超出限制的旧记录（保留置顶项）
     #[pyo3(name = "set_history_limit")]
-    fn set_history_limit(&self, limit: i64) {
+    fn set_history_limit(&self, py: Python<'_>, limit: i64) {
         self.history_limit.store(limit, Ordering::Relaxed);
         HISTORY_LIMIT.store(limit, Ordering::Relaxed);
         
-        // 立即清理一次
+        // 立即清理一次（释放 GIL，大表删除期间不阻塞其他 Python 线程）
         if limit > 0 {
-            let db = self.db.lock();
-            let _ = db.cleanup_old_items(limit);
+            let db = self.db.clone();
+            py.allow_threads(move || {
+                let _ = db.lock().cleanup_old_items(limit);
+            });
         }
     }
 
@@ -390,14 +1069,152 @@ impl PyClipboardManager {
         self.history_limit.load(Ordering::Relaxed)
     }
     
+    /// 设置存储字节数限制
+    /// 
+    /// Args:
+    ///     limit: 最大存储字节数（文本 + HTML 字节数 + 图片文件大小），0 表示不限制
+    /// 
+    /// 与 set_history_limit 可同时生效，任一超出都会清理最旧的记录（保留置顶项和分组内容）
+    #[pyo3(name = "set_storage_limit_bytes")]
+    fn set_storage_limit_bytes(&self, py: Python<'_>, limit: u64) {
+        self.storage_limit_bytes.store(limit, Ordering::Relaxed);
+        STORAGE_LIMIT_BYTES.store(limit, Ordering::Relaxed);
+        
+        if limit > 0 {
+            let db = self.db.clone();
+            py.allow_threads(move || {
+                let _ = db.lock().cleanup_by_bytes(limit);
+            });
+        }
+    }
+
+    /// 获取当前存储字节数限制
+    #[pyo3(name = "get_storage_limit_bytes")]
+    fn get_storage_limit_bytes(&self) -> u64 {
+        self.storage_limit_bytes.load(Ordering::Relaxed)
+    }
+    
+    /// 设置图片记录缩略图的长边像素数（只影响之后采集的记录）
+    /// 
+    /// Args:
+    ///     size: 长边像素数，默认 64
+    /// 
+    /// Raises:
+    ///     ValueError: size 为 0
+    fn set_thumbnail_size(&self, size: u32) -> PyResult<()> {
+        if size == 0 {
+            return Err(ClipboardError::InvalidArgument("缩略图尺寸必须大于 0".to_string()).into());
+        }
+        THUMBNAIL_SIZE.store(size, Ordering::Relaxed);
+        Ok(())
+    }
+    
+    /// 获取图片记录缩略图的长边像素数
+    fn get_thumbnail_size(&self) -> u32 {
+        THUMBNAIL_SIZE.load(Ordering::Relaxed)
+    }
+    
+    /// 设置近似重复图片去重阈值
+    /// 
+    /// Args:
+    ///     distance: dHash 汉明距离（0 ~ 64），新图片与最近一张图片的距离不超过该值时不保存；0 表示不启用
+    fn set_image_dedup_distance(&self, distance: u32) {
+        IMAGE_DEDUP_DISTANCE.store(distance, Ordering::Relaxed);
+    }
+    
+    /// 获取当前近似重复图片去重阈值
+    fn get_image_dedup_distance(&self) -> u32 {
+        IMAGE_DEDUP_DISTANCE.load(Ordering::Relaxed)
+    }
+    
+    /// 设置是否记录采集时的剪贴板格式列表（写入记录的 formats 字段，用于排查粘贴丢格式等问题）
+    /// 
+    /// Args:
+    ///     enabled: 是否记录，默认关闭
+    fn set_capture_formats(&self, enabled: bool) {
+        CAPTURE_FORMATS.store(enabled, Ordering::Relaxed);
+    }
+    
+    /// 获取是否记录采集时的剪贴板格式列表
+    fn get_capture_formats(&self) -> bool {
+        CAPTURE_FORMATS.load(Ordering::Relaxed)
+    }
+    
+    /// 设置剪贴板被清空时是否通知监听回调
+    /// 
+    /// 开启后，剪贴板变化但读取不到文本、文件或图片时，以 kind 为 "cleared" 的
+    /// PyClipboardEvent 调用 start_monitor 的回调（不写入历史，也不唤醒 wait_for_change）
+    /// 
+    /// Args:
+    ///     enabled: 是否通知，默认关闭
+    fn set_notify_clear(&self, enabled: bool) {
+        NOTIFY_CLEAR.store(enabled, Ordering::Relaxed);
+    }
+    
+    /// 获取剪贴板被清空时是否通知监听回调
+    fn get_notify_clear(&self) -> bool {
+        NOTIFY_CLEAR.load(Ordering::Relaxed)
+    }
+    
+    /// 开启日志输出（写到 stderr），用于排查监听线程和数据库操作的问题
+    /// 
+    /// 日志覆盖写入/查询/删除记录、重复内容检测、图片保存和超限清理等环节。
+    /// 整个进程只能初始化一次，重复调用返回 False 且不改变已有的级别
+    /// 
+    /// Args:
+    ///     level: 日志级别 "trace" / "debug" / "info" / "warn" / "error"，默认 "info"
+    /// 
+    /// Returns:
+    ///     bool: 本次是否完成初始化
+    /// 
+    /// Raises:
+    ///     ValueError: 无效的日志级别
+    #[pyo3(signature = (level="info"))]
+    fn enable_logging(&self, level: &str) -> PyResult<bool> {
+        let level: tracing::Level = level.parse()
+            .map_err(|_| pyo3::exceptions::PyValueError::new_err(format!("无效的日志级别: {}", level)))?;
+        Ok(tracing_subscriber::fmt()
+            .with_max_level(level)
+            .with_writer(std::io::stderr)
+            .try_init()
+            .is_ok())
+    }
+    
+    /// 获取监听各阶段的耗时统计（用于定位监听变慢的原因）
+    /// 
+    /// 阶段：read（读取并识别剪贴板，包含图片处理）、decode（图片转像素）、
+    /// hash（图片 ID 与感知哈希）、encode（PNG 编码落盘）、thumbnail（缩略图）、
+    /// insert（写入数据库与超限清理）、total（一次变化的总耗时，不含 Python 回调）
+    /// 
+    /// Returns:
+    ///     dict: {阶段名: {"count": 累计次数, "avg_ms": 最近 100 次平均毫秒, "max_ms": 最近 100 次最大毫秒}}
+    fn get_monitor_metrics(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let result = pyo3::types::PyDict::new_bound(py);
+        for (phase, count, avg_ms, max_ms) in metrics::snapshot() {
+            let stats = pyo3::types::PyDict::new_bound(py);
+            stats.set_item("count", count)?;
+            stats.set_item("avg_ms", avg_ms)?;
+            stats.set_item("max_ms", max_ms)?;
+            result.set_item(phase, stats)?;
+        }
+        Ok(result.into())
+    }
+    
+    /// 清空监听耗时统计
+    fn reset_monitor_metrics(&self) {
+        metrics::reset();
+    }
+    
     /// 启动剪贴板监听
     /// 
     /// Args:
-    ///     callback: 可选的回调函数，当剪贴板内容变化时调用
+    ///     callback: 可选的回调函数，当剪贴板内容变化时以 PyClipboardEvent 调用
+    ///         （开启 set_notify_clear 后，剪贴板被清空时也会以 "cleared" 事件调用）
     /// 
     /// Example:
-    ///     >>> def on_change(item):
-    ///     ...     print(f"New: {item.content}")
+    ///     >>> def on_change(event):
+    ///     ...     if event.kind == "new":
+    ///     ...         print(f"New: {event.item.content}")
     ///     >>> manager.start_monitor(callback=on_change)
     #[pyo3(signature = (callback=None))]
     fn start_monitor(&self, callback: Option<PyObject>) -> PyResult<()> {
@@ -414,338 +1231,80 @@ impl PyClipboardManager {
         
         let db = self.db.clone();
         
-        // 获取图片存储路径
-        let images_dir = {
-            let db_lock = db.lock();
-            db_lock.get_images_dir()
-        };
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<(), String>>();
         
-        thread::spawn(move || {
-            use clipboard_rs::common::RustImage;
-            use image::codecs::png::PngEncoder;
-            use image::ImageEncoder;
-            use sha2::{Sha256, Digest};
-            use base64::{Engine as _, engine::general_purpose};
-            
+        let handle = thread::spawn(move || {
             struct Handler {
                 db: Arc<Mutex<Database>>,
-                images_dir: PathBuf,
             }
             
-            // 生成缩略图 Base64
-            fn generate_thumbnail(rgba: &image::RgbaImage, max_size: u32) -> Option<String> {
-                use image::imageops::FilterType;
-                
-                let (w, h) = (rgba.width(), rgba.height());
-                let (new_w, new_h) = if w > h {
-                    (max_size, (max_size as f32 * h as f32 / w as f32) as u32)
-                } else {
-                    ((max_size as f32 * w as f32 / h as f32) as u32, max_size)
-                };
-                
-                let thumbnail = image::imageops::resize(rgba, new_w.max(1), new_h.max(1), FilterType::Triangle);
-                
-                let mut png_data = Vec::new();
-                let encoder = PngEncoder::new(&mut png_data);
-                if encoder.write_image(
-                    thumbnail.as_raw(),
-                    thumbnail.width(),
-                    thumbnail.height(),
-                    image::ExtendedColorType::Rgba8,
-                ).is_ok() {
-                    let base64_str = general_purpose::STANDARD.encode(&png_data);
-                    Some(format!("data:image/png;base64,{}", base64_str))
-                } else {
-                    None
-                }
-            }
-
-            // ── Ditto 风格：按白名单逐个取，不枚举全部格式 ─────────────────
-            // 策略：先用 IsClipboardFormatAvailable 轻量探测（不分配内存），
-            //       命中后才调用 GetClipboardData + GlobalLock 真正读取。
-            // 优势：Word/WPS 等程序会往剪贴板塞几十种私有格式（总计可达数十 MB），
-            //       先全枚举再筛选会把这些全读进内存再丢掉；按白名单取则完全跳过它们。
-            //
-            // 同时做一次轻量的"全格式探测"（只拿名称+ID，不读数据），
-            // 用于兜底判断剪贴板是否含有图片类数据（raw_image_fallback 逻辑）。
-            #[cfg(target_os = "windows")]
-            fn read_whitelisted_formats() -> (Vec<(u32, String, Vec<u8>)>, Vec<(u32, String)>) {
-                // 返回值：
-                //   .0  whitelisted_data  — 白名单格式的完整数据（存入 DB）
-                //   .1  all_format_names  — 剪贴板上所有格式的 (id, name)（仅用于兜底探测）
-                use std::ffi::OsString;
-                use std::os::windows::ffi::OsStringExt;
-
-                #[link(name = "user32")]
-                extern "system" {
-                    fn OpenClipboard(hwnd: *mut std::ffi::c_void) -> i32;
-                    fn CloseClipboard() -> i32;
-                    fn IsClipboardFormatAvailable(format: u32) -> i32;
-                    fn EnumClipboardFormats(format: u32) -> u32;
-                    fn GetClipboardData(format: u32) -> *mut std::ffi::c_void;
-                    fn GetClipboardFormatNameW(fmt: u32, buf: *mut u16, max: i32) -> i32;
-                    fn RegisterClipboardFormatW(lpszFormat: *const u16) -> u32;
-                }
-                #[link(name = "kernel32")]
-                extern "system" {
-                    fn GlobalLock(hmem: *mut std::ffi::c_void) -> *mut std::ffi::c_void;
-                    fn GlobalUnlock(hmem: *mut std::ffi::c_void) -> i32;
-                    fn GlobalSize(hmem: *mut std::ffi::c_void) -> usize;
-                }
-
-                // 把格式名称字符串转为 wide 用于 RegisterClipboardFormatW
-                fn to_wide(s: &str) -> Vec<u16> {
-                    s.encode_utf16().chain(std::iter::once(0)).collect()
-                }
-
-                // 标准格式名称
-                fn standard_name(id: u32) -> Option<&'static str> {
-                    match id {
-                        1  => Some("CF_TEXT"),
-                        7  => Some("CF_OEMTEXT"),
-                        8  => Some("CF_DIB"),
-                        13 => Some("CF_UNICODETEXT"),
-                        15 => Some("CF_HDROP"),
-                        16 => Some("CF_LOCALE"),
-                        17 => Some("CF_DIBV5"),
-                        _  => None,
-                    }
-                }
-
-                // 白名单定义：(format_id_or_0, name)
-                // format_id=0 表示需要用 RegisterClipboardFormatW 动态查询 ID
-                // format_id 已知的标准格式直接填写
-                struct WlEntry { id: u32, name: &'static str }
-                let whitelist: &[WlEntry] = &[
-                    WlEntry { id: 1,  name: "CF_TEXT" },
-                    WlEntry { id: 8,  name: "CF_DIB" },
-                    WlEntry { id: 13, name: "CF_UNICODETEXT" },
-                    WlEntry { id: 15, name: "CF_HDROP" },
-                    WlEntry { id: 16, name: "CF_LOCALE" },
-                    WlEntry { id: 17, name: "CF_DIBV5" },
-                    WlEntry { id: 0,  name: "PNG" },
-                    WlEntry { id: 0,  name: "HTML Format" },
-                    WlEntry { id: 0,  name: "Rich Text Format" },
-                ];
-
-                let mut data_result: Vec<(u32, String, Vec<u8>)> = Vec::new();
-                let mut all_names: Vec<(u32, String)> = Vec::new();
-
-                unsafe {
-                    // ── 阶段1：轻量探测 + 全格式枚举（仅取名称，不读数据）────
-                    // 目的：为 raw_image_fallback 收集全部格式名称列表
-                    if OpenClipboard(std::ptr::null_mut()) == 0 {
-                        return (data_result, all_names);
-                    }
-                    let mut fmt: u32 = 0;
-                    loop {
-                        fmt = EnumClipboardFormats(fmt);
-                        if fmt == 0 { break; }
-                        let name = if let Some(s) = standard_name(fmt) {
-                            s.to_string()
-                        } else {
-                            let mut buf = [0u16; 256];
-                            let len = GetClipboardFormatNameW(fmt, buf.as_mut_ptr(), 256);
-                            if len > 0 {
-                                OsString::from_wide(&buf[..len as usize]).to_string_lossy().into_owned()
-                            } else {
-                                format!("UNKNOWN_{}", fmt)
-                            }
-                        };
-                        all_names.push((fmt, name));
-                    }
-                    CloseClipboard();
-
-                    // ── 阶段2：按白名单逐个取数据（Ditto 风格）──────────────
-                    // IsClipboardFormatAvailable 不需要打开剪贴板，直接探测
-                    // 先收集命中的 (id, name) 列表，再一次性打开剪贴板读取
-                    let mut to_read: Vec<(u32, &'static str)> = Vec::new();
-                    for entry in whitelist {
-                        let fmt_id = if entry.id != 0 {
-                            entry.id
-                        } else {
-                            // 动态格式：用 RegisterClipboardFormatW 获取 ID（若未注册则返回 0）
-                            let wide = to_wide(entry.name);
-                            RegisterClipboardFormatW(wide.as_ptr())
-                        };
-                        if fmt_id == 0 { continue; }
-                        if IsClipboardFormatAvailable(fmt_id) != 0 {
-                            to_read.push((fmt_id, entry.name));
-                        }
-                    }
-
-                    if to_read.is_empty() {
-                        return (data_result, all_names);
-                    }
-
-                    // 一次打开剪贴板，读取所有命中的白名单格式
-                    if OpenClipboard(std::ptr::null_mut()) == 0 {
-                        return (data_result, all_names);
-                    }
-                    for (fmt_id, name) in &to_read {
-                        let hmem = GetClipboardData(*fmt_id);
-                        if hmem.is_null() { continue; }
-                        let ptr = GlobalLock(hmem);
-                        if ptr.is_null() { continue; }
-                        let size = GlobalSize(hmem);
-                        let data = if size > 0 && size <= 64 * 1024 * 1024 {
-                            std::slice::from_raw_parts(ptr as *const u8, size).to_vec()
-                        } else {
-                            GlobalUnlock(hmem);
-                            continue;
-                        };
-                        GlobalUnlock(hmem);
-                        data_result.push((*fmt_id, name.to_string(), data));
-                    }
-                    CloseClipboard();
-                }
-
-                (data_result, all_names)
-            }
-
-            #[cfg(not(target_os = "windows"))]
-            fn read_whitelisted_formats() -> (Vec<(u32, String, Vec<u8>)>, Vec<(u32, String)>) {
-                (Vec::new(), Vec::new())
-            }
-
             impl ClipboardHandler for Handler {
+                #[tracing::instrument(skip(self))]
                 fn on_clipboard_change(&mut self) {
                     if !IS_RUNNING.load(Ordering::Relaxed) {
                         return;
                     }
-                    
-                    // 检查是否需要跳过（paste_item 触发的变化）
-                    if SKIP_NEXT_CHANGE.compare_exchange(true, false, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+                    let started = std::time::Instant::now();
+
+                    // ── 第一~三步：读取剪贴板并识别为一条记录（与 peek_clipboard 共用）──
+                    let Some(DetectedClipboard { item: mut main_item, raw_formats, image_dhash }) =
+                        metrics::time(Phase::Read, || {
+                            // 每次从数据库读取图片目录，set_images_dir 对运行中的监听立即生效
+                            detect_clipboard_item(|img| store_image(&self.db.lock().image_store(), img))
+                        })
+                    else {
+                        // 文本、文件、图片都读取不到：剪贴板被清空（例如安全清除工具），只通知不入库
+                        if NOTIFY_CLEAR.load(Ordering::Relaxed) {
+                            tracing::debug!("剪贴板已清空");
+                            if let Some(callback) = CALLBACK.lock().as_ref() {
+                                Python::with_gil(|py| {
+                                    let _ = callback.call1(py, (PyClipboardEvent::cleared(),));
+                                });
+                            }
+                        }
                         return;
-                    }
-
-                    // ── 第一步：Ditto 风格按白名单读取格式数据 ────────────────
-                    // raw_formats  = 白名单格式的完整数据（直接存 DB，已经过滤好）
-                    // all_names    = 剪贴板上所有格式的 (id, name)（仅用于 fallback 探测）
-                    let (raw_formats, all_names) = read_whitelisted_formats();
-
-                    // ── 第二步：高层 API 解析主记录（用于 UI 展示）────────────
-                    use clipboard_rs::{Clipboard, ClipboardContext};
-                    let ctx = match ClipboardContext::new() {
-                        Ok(c) => c,
-                        Err(_) => return,
                     };
 
-                    let source_app = get_clipboard_owner().ok().flatten();
-                    let html_content = ctx.get_html().ok();
-
-                    let text_val  = ctx.get_text().ok().filter(|t| !t.trim().is_empty());
-                    let files_val = ctx.get_files().ok().filter(|f| !f.is_empty());
-                    let image_val = ctx.get_image().ok();
+                    // paste_item 自己写入的内容：按指纹精确跳过（不会误吞用户的真实复制）
+                    if suppress::consume(suppress::fingerprint(&main_item.content_type, &main_item.content)) {
+                        tracing::debug!("跳过 paste_item 自身写入的内容");
+                        return;
+                    }
 
-                    // 高层 API 全部失败时，检查白名单数据或全格式名称列表是否含图片类格式
-                    // 场景：Word 复制多张图片时 get_image() 返回 None，但 raw_formats 里有 PNG/DIB
-                    let raw_image_fallback = if text_val.is_none() && files_val.is_none() && image_val.is_none() {
-                        let has_image_data = raw_formats.iter().any(|(fid, fname, data)| {
-                            !data.is_empty() && (*fid == 8 || *fid == 17 || fname.eq_ignore_ascii_case("PNG"))
+                    // 文件记录：首张图片文件生成缩略图，否则按文件类型生成通用图标
+                    if main_item.content_type == "file" && main_item.thumbnail.is_none() {
+                        main_item.thumbnail = metrics::time(Phase::Thumbnail, || {
+                            file_thumbnail::file_thumbnail(&main_item.content)
                         });
-                        // 也检查 all_names，防止白名单中没有 PNG/DIB 但剪贴板里有其他图片格式
-                        let has_image_name = all_names.iter().any(|(fid, fname)| {
-                            *fid == 8 || *fid == 17 || fname.eq_ignore_ascii_case("PNG")
-                        });
-                        has_image_data || has_image_name
-                    } else {
-                        false
-                    };
-
-                    if text_val.is_none() && files_val.is_none() && image_val.is_none() && !raw_image_fallback {
-                        return;
                     }
 
-                    // ── 第三步：构造主记录 ────────────────────────────────────
-                    let mut main_item: PyClipboardItem;
-
-                    if let Some(text) = text_val {
-                        main_item = PyClipboardItem::new(0, text, "text".to_string());
-                        main_item.html_content = html_content;
-                        main_item.source_app = source_app;
-                    } else if let Some(files) = files_val {
-                        let content = serde_json::json!({ "files": files }).to_string();
-                        main_item = PyClipboardItem::new(0, content, "file".to_string());
-                        main_item.source_app = source_app;
-                    } else if image_val.is_some() {
-                        // 单张图片：落盘 PNG，生成缩略图
-                        let rust_image = image_val.unwrap();
-                        let rgba = match rust_image.to_rgba8() {
-                            Ok(r) => r,
-                            Err(_) => return,
-                        };
-                        let mut png_data = Vec::new();
-                        let encoder = PngEncoder::new(&mut png_data);
-                        if encoder.write_image(
-                            rgba.as_raw(),
-                            rgba.width(),
-                            rgba.height(),
-                            image::ExtendedColorType::Rgba8,
-                        ).is_err() {
+                    // ── 第四步：写入数据库 ────────────────────────────────────
+                    let db = self.db.lock();
+                    let insert_started = std::time::Instant::now();
+
+                    // 近似重复图片（例如只差几个像素的两次截图）：与最近一张图片比较 dHash，足够接近则不保存
+                    let dedup_distance = IMAGE_DEDUP_DISTANCE.load(Ordering::Relaxed);
+                    let image_dhash = image_dhash.filter(|_| main_item.content_type == "image");
+                    if let Some(dhash) = image_dhash.filter(|_| dedup_distance > 0) {
+                        let similar = db.latest_image_dhash().is_some_and(|(latest_id, latest)| {
+                            main_item.image_id.as_deref() != Some(latest_id.as_str())
+                                && (dhash ^ latest).count_ones() <= dedup_distance
+                        });
+                        if similar {
+                            tracing::debug!(dhash, "与最近一张图片近似重复，不保存");
+                            db.remove_unreferenced_images(main_item.image_id.take().into_iter().collect());
                             return;
                         }
-
-                        let mut hasher = Sha256::new();
-                        hasher.update(&png_data);
-                        let hash = format!("{:x}", hasher.finalize());
-                        let image_id = hash[..16].to_string();
-
-                        let image_path = self.images_dir.join(format!("{}.png", &image_id));
-                        if !image_path.exists() {
-                            let _ = std::fs::write(&image_path, &png_data);
-                        }
-
-                        let thumbnail = generate_thumbnail(&rgba, 64);
-
-                        main_item = PyClipboardItem::new(
-                            0,
-                            format!("[{}x{}]", rgba.width(), rgba.height()),
-                            "image".to_string(),
-                        );
-                        main_item.image_id = Some(image_id);
-                        main_item.thumbnail = thumbnail;
-                        main_item.source_app = source_app;
-                    } else {
-                        // raw_image_fallback：多图/EMF 等高层 API 无法解析的图片内容
-                        // content 写入格式列表和总字节数，供前端直接显示
-                        // 例：[PNG+CF_DIB 7.9 MB] 或 [PNG 1.2 MB]
-                        let img_fmt_names: Vec<&str> = {
-                            let mut names = Vec::new();
-                            for (fid, fname, data) in &raw_formats {
-                                if data.is_empty() { continue; }
-                                if *fid == 17 { names.push("CF_DIBV5"); }
-                                else if *fid == 8 { names.push("CF_DIB"); }
-                                else if fname.eq_ignore_ascii_case("PNG") { names.push("PNG"); }
-                            }
-                            names.dedup();
-                            names
-                        };
-                        let total_bytes: usize = raw_formats.iter()
-                            .filter(|(fid, fname, _)| *fid == 8 || *fid == 17 || fname.eq_ignore_ascii_case("PNG"))
-                            .map(|(_, _, d)| d.len())
-                            .sum();
-                        let size_str = if total_bytes >= 1024 * 1024 {
-                            format!("{:.1} MB", total_bytes as f64 / 1024.0 / 1024.0)
-                        } else if total_bytes > 0 {
-                            format!("{:.0} KB", total_bytes as f64 / 1024.0)
-                        } else {
-                            "0 B".to_string()
-                        };
-                        let fmt_str = if img_fmt_names.is_empty() { "raw".to_string() }
-                                      else { img_fmt_names.join("+") };
-                        main_item = PyClipboardItem::new(
-                            0,
-                            format!("[{} {}]", fmt_str, size_str),
-                            "image".to_string(),
-                        );
-                        main_item.source_app = source_app;
                     }
 
-                    // ── 第四步：写入数据库 ────────────────────────────────────
-                    let db = self.db.lock();
-                    if let Ok(id) = db.insert_item(&main_item) {
+                    let result = db.insert_item(&main_item)
+                        .inspect_err(|e| tracing::warn!(error = %e, "写入剪贴板记录失败"));
+                    if let Ok((id, inserted)) = result {
                         main_item.id = id;
+                        tracing::debug!(id, inserted, content_type = %main_item.content_type, "剪贴板记录已保存");
+                        if let (Some(dhash), true) = (image_dhash, inserted) {
+                            let _ = db.set_image_dhash(id, dhash);
+                        }
 
                         // 图片优化：
                         // CF_DIBV5(17) 是 CF_DIB(8) 的超集（含 alpha 通道），
@@ -795,113 +1354,622 @@ impl PyClipboardManager {
                         if limit > 0 {
                             let _ = db.cleanup_old_items(limit);
                         }
+                        let bytes_limit = STORAGE_LIMIT_BYTES.load(Ordering::Relaxed);
+                        if bytes_limit > 0 {
+                            let _ = db.cleanup_by_bytes(bytes_limit);
+                        }
+                        metrics::record(Phase::Insert, insert_started.elapsed());
+                        metrics::record(Phase::Total, started.elapsed());
 
                         if let Some(callback) = CALLBACK.lock().as_ref() {
                             Python::with_gil(|py| {
-                                let _ = callback.call1(py, (main_item.clone(),));
+                                let event = PyClipboardEvent::new(main_item.clone(), inserted);
+                                let _ = callback.call1(py, (event,));
                             });
                         }
+
+                        // 唤醒 wait_for_change：与回调共享同一次事件，不重复读取剪贴板
+                        for (_, tx) in CHANGE_WAITERS.lock().drain(..) {
+                            let _ = tx.send(main_item.clone());
+                        }
                     }
                 }
             }
             
-            let handler = Handler { db, images_dir };
-            if let Ok(mut watcher) = ClipboardWatcherContext::new() {
-                let _ = watcher.add_handler(handler).start_watch();
+            let handler = Handler { db };
+            // 监听器必须在本线程创建（Windows 下绑定到创建线程的消息循环），
+            // 创建结果通过通道回报给 start_monitor
+            let mut watcher = match ClipboardWatcherContext::new() {
+                Ok(watcher) => watcher,
+                Err(e) => {
+                    IS_RUNNING.store(false, Ordering::SeqCst);
+                    CHANGE_WAITERS.lock().clear();
+                    let _ = ready_tx.send(Err(e.to_string()));
+                    return;
+                }
+            };
+            *MONITOR_SHUTDOWN.lock() = Some(watcher.get_shutdown_channel());
+            let _ = ready_tx.send(Ok(()));
+            // 先登记停止通道再检查标志：若 stop_monitor 已先执行，这里直接退出
+            if IS_RUNNING.load(Ordering::SeqCst) {
+                watcher.add_handler(handler).start_watch();
             }
+            MONITOR_SHUTDOWN.lock().take();
             IS_RUNNING.store(false, Ordering::SeqCst);
+            // 丢弃发送端，让仍在等待的 wait_for_change 立即返回 None
+            CHANGE_WAITERS.lock().clear();
         });
         
+        // 等待监听器初始化完成，失败时把错误抛给调用方
+        let startup = ready_rx
+            .recv()
+            .unwrap_or_else(|_| Err("监听线程意外退出".to_string()));
+        if let Err(e) = startup {
+            let _ = handle.join();
+            IS_RUNNING.store(false, Ordering::SeqCst);
+            CALLBACK.lock().take();
+            return Err(PyRuntimeError::new_err(format!("启动剪贴板监听失败: {}", e)));
+        }
+        *MONITOR_THREAD.lock() = Some(handle);
+        
+        Ok(())
+    }
+    
+    /// 获取图片数据（通过 image_id）
+    #[pyo3(signature = (image_id))]
+    fn get_image_data(&self, image_id: String) -> PyResult<Option<Vec<u8>>> {
+        let store = self.db.lock().image_store();
+        match store.read(&image_id) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(PyRuntimeError::new_err(format!("读取图片失败: {}", e))),
+        }
+    }
+
+    /// 获取图片记录的缩略图（长边缩放到 max_size，按需生成并缓存到磁盘）
+    /// 
+    /// Args:
+    ///     id: 记录 ID
+    ///     max_size: 长边像素数，默认 200
+    /// 
+    /// Returns:
+    ///     Optional[bytes]: PNG 数据；原图缺失时返回记录中保存的小缩略图，无图片时返回 None
+    #[pyo3(signature = (id, max_size=200))]
+    fn get_image_thumbnail(&self, py: Python<'_>, id: i64, max_size: u32) -> PyResult<Option<Vec<u8>>> {
+        let db = self.db.clone();
+        py.allow_threads(move || db.lock().get_image_thumbnail(id, max_size))
+            .map_err(PyErr::from)
+    }
+
+    /// 把图片记录保存为文件（用于只接受文件拖放的程序）
+    /// 
+    /// Args:
+    ///     id: 记录 ID
+    ///     dest_path: 目标路径，按扩展名转换格式（.png / .jpg / .webp / .bmp 等），
+    ///         没有扩展名时保存为 .png
+    /// 
+    /// Returns:
+    ///     str: 最终写入的文件路径
+    /// 
+    /// Raises:
+    ///     KeyError: 记录不存在
+    ///     ValueError: 记录不是图片或目标格式不支持
+    ///     RuntimeError: 图片文件缺失或写入失败
+    fn save_item_image(&self, py: Python<'_>, id: i64, dest_path: String) -> PyResult<String> {
+        let db = self.db.clone();
+        py.allow_threads(move || db.lock().save_item_image(id, std::path::Path::new(&dest_path)))
+            .map(|path| path.to_string_lossy().into_owned())
+            .map_err(PyErr::from)
+    }
+
+    /// 获取某条记录保存的所有原始剪贴板格式（Ditto 风格）
+    /// 
+    /// Returns:
+    ///     List[Tuple[int, str, bytes]]: [(format_id, format_name, raw_data), ...]
+    fn get_raw_formats(&self, id: i64) -> PyResult<Vec<(u32, String, Vec<u8>)>> {
+        let db = self.reader.lock();
+        db.get_formats(id).map_err(PyErr::from)
+    }
+
+    /// 手动保存一批原始剪贴板格式数据（主要用于测试或外部调用）
+    ///
+    /// Args:
+    ///     event_id: 关联的 clipboard.id
+    ///     formats: List[Tuple[int, str, bytes]]，每项为 (format_id, format_name, raw_data)
+    fn insert_formats(&self, event_id: i64, formats: Vec<(u32, String, Vec<u8>)>) -> PyResult<()> {
+        let db = self.db.lock();
+        db.insert_formats(event_id, &formats).map_err(PyErr::from)
+    }
+    
+    /// 停止剪贴板监听
+    /// 
+    /// 主动结束监听循环并等待监听线程退出，返回时监听已确实停止
+    fn stop_monitor(&self, py: Python<'_>) -> PyResult<()> {
+        IS_RUNNING.store(false, Ordering::SeqCst);
+        if let Some(shutdown) = MONITOR_SHUTDOWN.lock().take() {
+            shutdown.stop();
+        }
+        
+        // 等待线程退出时释放 GIL：回调可能正在 with_gil 中执行
+        // 在回调内部调用 stop_monitor 时处于监听线程自身，不能 join 自己
+        let handle = MONITOR_THREAD.lock().take()
+            .filter(|h| h.thread().id() != thread::current().id());
+        if let Some(handle) = handle {
+            py.allow_threads(|| handle.join())
+                .map_err(|_| PyRuntimeError::new_err("监听线程异常退出"))?;
+        }
+        
+        *CALLBACK.lock() = None;
         Ok(())
     }
     
-    /// 获取图片数据（通过 image_id）
-    #[pyo3(signature = (image_id))]
-    fn get_image_data(&self, image_id: String) -> PyResult<Option<Vec<u8>>> {
+    /// 读取当前剪贴板内容用于预览，不写入数据库
+    /// 
+    /// 识别逻辑与监听完全相同（文本 → 文件 → 图片，附带 HTML 和来源应用），
+    /// 图片只计算 image_id 和缩略图，不落盘
+    /// 
+    /// Returns:
+    ///     Optional[PyClipboardItem]: id 为 0 的记录，剪贴板为空或无法识别时返回 None
+    fn peek_clipboard(&self, py: Python<'_>) -> Option<PyClipboardItem> {
+        py.allow_threads(|| detect_clipboard_item(preview_image).map(|detected| detected.item))
+    }
+    
+    /// 阻塞等待下一次剪贴板变化（等待期间释放 GIL）
+    ///
+    /// 监听已运行时复用监听线程的同一次事件（返回已写入历史的记录）；
+    /// 未运行时临时注册一个一次性监听，采集内容（图片会落盘）但不写入历史（返回记录的 id 为 0），
+    /// 返回前注销该监听
+    ///
+    /// Args:
+    ///     timeout_ms: 超时时间（毫秒），None 表示一直等待
+    ///
+    /// Returns:
+    ///     Optional[PyClipboardItem]: 变化后的内容，超时或监听停止时返回 None
+    #[pyo3(signature = (timeout_ms=None))]
+    fn wait_for_change(&self, py: Python<'_>, timeout_ms: Option<u64>) -> PyResult<Option<PyClipboardItem>> {
+        use std::sync::mpsc;
+
+        let (tx, rx) = mpsc::channel::<PyClipboardItem>();
+        let recv = move |rx: &mpsc::Receiver<PyClipboardItem>| match timeout_ms {
+            Some(ms) => rx.recv_timeout(std::time::Duration::from_millis(ms)).ok(),
+            None => rx.recv().ok(),
+        };
+
+        // 在等待者列表锁内检查监听状态：监听线程退出时先清标志再清列表，不会漏掉唤醒
+        let waiter_id = {
+            let mut waiters = CHANGE_WAITERS.lock();
+            if IS_RUNNING.load(Ordering::SeqCst) {
+                let id = NEXT_WAITER_ID.fetch_add(1, Ordering::Relaxed);
+                waiters.push((id, tx.clone()));
+                Some(id)
+            } else {
+                None
+            }
+        };
+
+        if let Some(id) = waiter_id {
+            drop(tx);
+            let item = py.allow_threads(move || recv(&rx));
+            if item.is_none() {
+                CHANGE_WAITERS.lock().retain(|(wid, _)| *wid != id);
+            }
+            return Ok(item);
+        }
+
+        use clipboard_rs::{ClipboardHandler, ClipboardWatcher, ClipboardWatcherContext};
+
+        struct OneShot {
+            tx: Option<mpsc::Sender<PyClipboardItem>>,
+            store: ImageStore,
+        }
+
+        impl ClipboardHandler for OneShot {
+            fn on_clipboard_change(&mut self) {
+                if let Some(item) = capture_current_clipboard(&self.store) {
+                    if let Some(tx) = self.tx.take() {
+                        let _ = tx.send(item);
+                    }
+                }
+            }
+        }
+
+        let store = self.db.lock().image_store();
+
+        // 与 start_monitor 相同：监听器必须在其运行的线程内创建
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<clipboard_rs::WatcherShutdown, String>>();
+        let handle = thread::spawn(move || {
+            let mut watcher = match ClipboardWatcherContext::new() {
+                Ok(watcher) => watcher,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e.to_string()));
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Ok(watcher.get_shutdown_channel()));
+            watcher.add_handler(OneShot { tx: Some(tx), store }).start_watch();
+        });
+
+        py.allow_threads(move || {
+            let shutdown = ready_rx
+                .recv()
+                .unwrap_or_else(|_| Err("监听线程意外退出".to_string()))
+                .map_err(|e| PyRuntimeError::new_err(format!("启动剪贴板监听失败: {}", e)));
+            let shutdown = match shutdown {
+                Ok(shutdown) => shutdown,
+                Err(e) => {
+                    let _ = handle.join();
+                    return Err(e);
+                }
+            };
+            let item = recv(&rx);
+            shutdown.stop();
+            handle
+                .join()
+                .map_err(|_| PyRuntimeError::new_err("监听线程异常退出"))?;
+            Ok(item)
+        })
+    }
+
+    /// 检查监听器是否运行中
+    /// 
+    /// Returns:
+    ///     bool: 是否正在监听
+    fn is_monitoring(&self) -> bool {
+        IS_RUNNING.load(Ordering::Relaxed)
+    }
+
+    /// 检查监听线程是否确实存活
+    ///
+    /// 监听线程 panic 后 is_monitoring 可能仍返回 True，
+    /// 两者不一致时可调用 force_reset_monitor 恢复
+    ///
+    /// Returns:
+    ///     bool: 监听线程是否仍在运行
+    fn monitor_health(&self) -> bool {
+        MONITOR_THREAD.lock().as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    /// 强制重置监听状态
+    ///
+    /// 清除运行标志、回调和等待者，并尝试通知残留的监听线程退出（不等待其结束），
+    /// 用于监听线程异常退出后 start_monitor 一直报"监听器已在运行"的情况
+    fn force_reset_monitor(&self) {
+        if let Some(shutdown) = MONITOR_SHUTDOWN.lock().take() {
+            shutdown.stop();
+        }
+        IS_RUNNING.store(false, Ordering::SeqCst);
+        MONITOR_THREAD.lock().take();
+        CALLBACK.lock().take();
+        CHANGE_WAITERS.lock().clear();
+    }
+
+    /// 创建监听作用域，配合 with 语句使用：进入时启动监听，退出时（包括异常）保证停止
+    /// 
+    /// Args:
+    ///     callback: 可选的回调函数，同 start_monitor
+    /// 
+    /// Example:
+    ///     >>> with manager.monitor_scope(on_change):
+    ///     ...     run_app()
+    #[pyo3(signature = (callback=None))]
+    fn monitor_scope(slf: Py<Self>, callback: Option<PyObject>) -> MonitorGuard {
+        MonitorGuard { manager: slf, callback }
+    }
+
+    /// 配合 with 语句使用：退出时（包括异常）停止监听并执行一次 WAL 检查点
+    ///
+    /// Example:
+    ///     >>> with PyClipboardManager() as manager:
+    ///     ...     manager.start_monitor(on_change)
+    ///     ...     run_app()
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// 停止监听（未运行时无操作）并把 WAL 写回主数据库文件，不吞掉 with 块中的异常
+    #[pyo3(signature = (_exc_type=None, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &self,
+        py: Python<'_>,
+        _exc_type: Option<PyObject>,
+        _exc_value: Option<PyObject>,
+        _traceback: Option<PyObject>,
+    ) -> PyResult<bool> {
+        let stopped = self.stop_monitor(py);
+        // 检查点失败（例如其他连接正在读）不影响退出，剩余 WAL 会在下次打开时写回
+        let db = self.db.clone();
+        if let Err(e) = py.allow_threads(move || db.lock().checkpoint_wal("PASSIVE")) {
+            tracing::warn!(error = %e, "退出时执行 WAL 检查点失败");
+        }
+        stopped.map(|()| false)
+    }
+
+    /// 查询剪贴板历史
+    /// 
+    /// Args:
+    ///     offset: 偏移量，默认 0
+    ///     limit: 每页数量，
+    ///     search: 搜索关键词
+    ///     content_type: 内容类型过滤 ("text", "file", "image", "all")
+    ///     search_fields: 关键词匹配的字段，可选 "content"、"html"、"title"、"files"
+    ///         （文件记录的路径）、"urls"（文本中的链接），任一字段匹配即可，默认 ["content"]
+    /// 
+    /// Returns:
+    ///     PyPaginatedResult: 分页结果
+    #[pyo3(signature = (offset=0, limit=50, search=None, content_type=None, search_fields=None))]
+    fn get_history(
+        &self,
+        py: Python<'_>,
+        offset: i64,
+        limit: i64,
+        search: Option<String>,
+        content_type: Option<String>,
+        search_fields: Option<Vec<String>>,
+    ) -> PyResult<PyPaginatedResult> {
+        let search_fields = search_fields.unwrap_or_else(|| vec!["content".to_string()]);
+        // 查询期间释放 GIL，避免大表查询冻结整个解释器
+        let db = self.reader.clone();
+        py.allow_threads(move || db.lock().query_items(offset, limit, search, content_type, &search_fields))
+            .map_err(PyErr::from)
+    }
+    
+    /// 获取历史记录数（不含回收站和收藏夹，与 get_history 一致）
+    /// 
+    /// Returns:
+    ///     int: 历史记录数
+    fn get_count(&self, py: Python<'_>) -> PyResult<i64> {
+        let db = self.reader.clone();
+        py.allow_threads(move || db.lock().get_count())
+            .map_err(PyErr::from)
+    }
+    
+    /// 手动执行 WAL 检查点，把 WAL 文件中的内容写回主数据库文件
+    /// 
+    /// Args:
+    ///     mode: "PASSIVE"（默认，不等待其他读写者）/ "FULL" / "RESTART" / "TRUNCATE"（完成后截断 WAL 文件）
+    /// 
+    /// Returns:
+    ///     tuple[bool, int, int]: (是否因其他连接占用未能完成, WAL 中的帧数, 已写回的帧数)
+    #[pyo3(signature = (mode="PASSIVE"))]
+    fn checkpoint_wal(&self, py: Python<'_>, mode: &str) -> PyResult<(bool, i64, i64)> {
+        let db = self.db.clone();
+        py.allow_threads(move || db.lock().checkpoint_wal(mode))
+            .map_err(PyErr::from)
+    }
+    
+    /// 压缩数据库（VACUUM + 截断 WAL），回收删除记录占用的磁盘空间
+    /// 
+    /// 压缩期间会独占数据库连接，建议在暂停监听时调用。
+    /// 
+    /// Returns:
+    ///     int: 回收的字节数
+    fn compact(&self, py: Python<'_>) -> PyResult<u64> {
+        let db = self.db.clone();
+        py.allow_threads(move || db.lock().compact())
+            .map_err(PyErr::from)
+    }
+    
+    /// 合并历史列表中相邻的重复记录，用于整理关闭去重等情况下产生的连续重复项
+    /// 
+    /// 连续几条内容完全相同（类型、内容 + HTML、图片都相同）的记录只保留最上面一条，
+    /// 粘贴次数累加、更新时间取最新；置顶、受保护、已加入分组或有标题的记录不参与合并
+    /// 
+    /// Returns:
+    ///     int: 删除的记录数
+    fn collapse_adjacent_duplicates(&self, py: Python<'_>) -> PyResult<i64> {
+        let db = self.db.clone();
+        py.allow_threads(move || db.lock().collapse_adjacent_duplicates())
+            .map_err(PyErr::from)
+    }
+    
+    /// 获取图片目录的磁盘占用
+    /// 
+    /// Returns:
+    ///     tuple[int, int]: (图片文件数, 总字节数)
+    fn get_images_disk_usage(&self) -> PyResult<(usize, u64)> {
+        let db = self.db.lock();
+        db.get_images_disk_usage()
+            .map_err(PyErr::from)
+    }
+    
+    /// 查找孤立图片（磁盘上存在但没有记录引用的图片文件）
+    /// 
+    /// Returns:
+    ///     List[str]: 孤立图片的 image_id 列表
+    fn find_orphan_images(&self) -> PyResult<Vec<String>> {
+        let db = self.db.lock();
+        db.find_orphan_images()
+            .map(|orphans| orphans.into_iter().map(|(id, _)| id).collect())
+            .map_err(PyErr::from)
+    }
+    
+    /// 删除孤立图片
+    /// 
+    /// Returns:
+    ///     tuple[int, int]: (删除的文件数, 释放的字节数)
+    fn cleanup_orphan_images(&self) -> PyResult<(usize, u64)> {
+        let db = self.db.lock();
+        db.cleanup_orphan_images()
+            .map_err(PyErr::from)
+    }
+    
+    /// 查找图片文件已丢失的记录
+    /// 
+    /// Returns:
+    ///     List[int]: 记录 ID 列表（UI 可显示损坏图片占位符）
+    fn find_items_with_missing_images(&self) -> PyResult<Vec<i64>> {
         let db = self.db.lock();
-        let image_path = db.get_images_dir().join(format!("{}.png", image_id));
-        
-        if image_path.exists() {
-            std::fs::read(&image_path)
-                .map(Some)
-                .map_err(|e| PyRuntimeError::new_err(format!("读取图片失败: {}", e)))
-        } else {
-            Ok(None)
-        }
+        db.find_items_with_missing_images()
+            .map_err(PyErr::from)
     }
-
-    /// 获取某条记录保存的所有原始剪贴板格式（Ditto 风格）
+    
+    /// 根据 ID 获取项
+    /// 
+    /// Args:
+    ///     id: 记录 ID
     /// 
     /// Returns:
-    ///     List[Tuple[int, str, bytes]]: [(format_id, format_name, raw_data), ...]
-    fn get_raw_formats(&self, id: i64) -> PyResult<Vec<(u32, String, Vec<u8>)>> {
-        let db = self.db.lock();
-        db.get_formats(id).map_err(|e| PyRuntimeError::new_err(e))
+    ///     PyClipboardItem: 剪贴板项
+    /// 
+    /// Raises:
+    ///     KeyError: 记录不存在
+    fn get_item(&self, id: i64) -> PyResult<PyClipboardItem> {
+        let db = self.reader.lock();
+        Ok(db.get_item_by_id(id)?.ok_or(ClipboardError::NotFound(id))?)
     }
-
-    /// 手动保存一批原始剪贴板格式数据（主要用于测试或外部调用）
-    ///
+    
+    /// 获取文件记录中每个文件的当前状态（是否存在、大小、是否文件夹、修改时间）
+    /// 
     /// Args:
-    ///     event_id: 关联的 clipboard.id
-    ///     formats: List[Tuple[int, str, bytes]]，每项为 (format_id, format_name, raw_data)
-    fn insert_formats(&self, event_id: i64, formats: Vec<(u32, String, Vec<u8>)>) -> PyResult<()> {
-        let db = self.db.lock();
-        db.insert_formats(event_id, &formats).map_err(|e| PyRuntimeError::new_err(e))
+    ///     id: 文件记录 ID
+    /// 
+    /// Returns:
+    ///     List[PyFileInfo]: 按复制时的顺序排列
+    /// 
+    /// Raises:
+    ///     KeyError: 记录不存在
+    ///     ValueError: 记录不是文件记录
+    fn get_item_files(&self, py: Python<'_>, id: i64) -> PyResult<Vec<PyFileInfo>> {
+        let item = self.get_item(id)?;
+        let files = Some(&item)
+            .filter(|item| item.content_type == "file")
+            .and_then(|item| serde_json::from_str::<serde_json::Value>(&item.content).ok())
+            .and_then(|json| {
+                json.get("files")?
+                    .as_array()
+                    .map(|files| files.iter().filter_map(|f| f.as_str().map(str::to_string)).collect::<Vec<_>>())
+            })
+            .ok_or_else(|| ClipboardError::InvalidArgument(format!("记录 {} 不是文件记录", id)))?;
+        // 网络路径上的 stat 可能较慢，期间释放 GIL
+        Ok(py.allow_threads(move || files.iter().map(|path| PyFileInfo::from_path(path)).collect()))
     }
     
-    /// 停止剪贴板监听
-    fn stop_monitor(&self) -> PyResult<()> {
-        IS_RUNNING.store(false, Ordering::SeqCst);
-        *CALLBACK.lock() = None;
-        Ok(())
+    /// 查找与指定图片记录相似的其他图片（按感知哈希 dHash 的汉明距离）
+    /// 
+    /// 只比较监听时记录了 dHash 的历史图片记录（不含回收站和收藏夹）
+    /// 
+    /// Args:
+    ///     id: 图片记录 ID
+    ///     max_distance: 最大汉明距离（0 ~ 64），默认 10
+    ///     limit: 返回数量限制，默认 20
+    /// 
+    /// Returns:
+    ///     List[Tuple[PyClipboardItem, int]]: (记录, 距离) 列表，最相似的在前
+    /// 
+    /// Raises:
+    ///     KeyError: 记录不存在
+    ///     ValueError: 记录没有图片哈希
+    #[pyo3(signature = (id, max_distance=10, limit=20))]
+    fn find_similar_images(&self, py: Python<'_>, id: i64, max_distance: u32, limit: usize) -> PyResult<Vec<(PyClipboardItem, u32)>> {
+        let db = self.reader.clone();
+        py.allow_threads(move || db.lock().find_similar_images(id, max_distance, limit))
+            .map_err(PyErr::from)
     }
     
-    /// 检查监听器是否运行中
+    /// 按相关度排序的搜索：常用（粘贴次数多）、较新、与查询更吻合的记录排在前面
+    /// 
+    /// 得分 = log2(1 + 粘贴次数) * 0.4 + 新近程度 * 0.4 + 匹配度 * 0.2，
+    /// 内容包含任一关键词（按空白拆分）的历史记录（不含回收站和收藏夹）参与打分
+    /// 
+    /// Args:
+    ///     query: 搜索关键词
+    ///     limit: 返回数量限制，默认 20
     /// 
     /// Returns:
-    ///     bool: 是否正在监听
-    fn is_monitoring(&self) -> bool {
-        IS_RUNNING.load(Ordering::Relaxed)
+    ///     List[Tuple[PyClipboardItem, float]]: (记录, 得分) 列表，得分高的在前
+    #[pyo3(signature = (query, limit=20))]
+    fn search_ranked(&self, py: Python<'_>, query: String, limit: usize) -> PyResult<Vec<(PyClipboardItem, f32)>> {
+        let db = self.reader.clone();
+        py.allow_threads(move || db.lock().search_ranked(&query, limit))
+            .map_err(PyErr::from)
     }
     
-    /// 查询剪贴板历史
+    /// 布尔条件搜索
+    /// 
+    /// 关键词之间默认为 AND，支持 OR、NOT、括号分组和双引号短语（运算符须大写），
+    /// OR 的优先级高于 AND：`python OR rust NOT pandas` 匹配含 python 或 rust、且不含 pandas 的记录。
+    /// 每个关键词匹配内容或 HTML 富文本，排序与 get_history 相同
     /// 
     /// Args:
+    ///     query: 搜索条件
     ///     offset: 偏移量，默认 0
-    ///     limit: 每页数量，
-    ///     search: 搜索关键词
-    ///     content_type: 内容类型过滤 ("text", "file", "image", "all")
+    ///     limit: 每页数量，默认 50
     /// 
     /// Returns:
     ///     PyPaginatedResult: 分页结果
-    #[pyo3(signature = (offset=0, limit=50, search=None, content_type=None))]
-    fn get_history(&self, offset: i64, limit: i64, search: Option<String>, content_type: Option<String>) -> PyResult<PyPaginatedResult> {
-        let db = self.db.lock();
-        db.query_items(offset, limit, search, content_type)
-            .map_err(|e| PyRuntimeError::new_err(e))
+    /// 
+    /// Raises:
+    ///     ValueError: 搜索条件为空或语法错误（括号、引号不匹配，运算符后缺少关键词等）
+    #[pyo3(signature = (query, offset=0, limit=50))]
+    fn search_boolean(&self, py: Python<'_>, query: String, offset: i64, limit: i64) -> PyResult<PyPaginatedResult> {
+        let db = self.reader.clone();
+        py.allow_threads(move || db.lock().search_boolean(&query, offset, limit))
+            .map_err(PyErr::from)
     }
     
-    /// 获取总记录数
+    /// 获取 ID 大于 last_id 的新记录（用于增量刷新，配合监听回调使用）
+    /// 
+    /// Args:
+    ///     last_id: 上次已加载的最大记录 ID
     /// 
     /// Returns:
-    ///     int: 总记录数
-    fn get_count(&self) -> PyResult<i64> {
-        let db = self.db.lock();
-        db.get_count()
-            .map_err(|e| PyRuntimeError::new_err(e))
+    ///     List[PyClipboardItem]: 新记录列表（新的在前）
+    fn get_items_since(&self, py: Python<'_>, last_id: i64) -> PyResult<Vec<PyClipboardItem>> {
+        let db = self.reader.clone();
+        py.allow_threads(move || db.lock().get_items_since(last_id))
+            .map_err(PyErr::from)
     }
     
-    /// 根据 ID 获取项
+    /// 遍历全部记录（按 id 升序），用于流式导出
+    /// 
+    /// 内部按 id 分批查询（keyset 分页），深翻页时不会变慢；
+    /// 遍历期间新增的记录也会被遍历到
     /// 
     /// Args:
-    ///     id: 记录 ID
+    ///     batch_size: 每批从数据库读取的记录数，默认 500
     /// 
     /// Returns:
-    ///     Optional[PyClipboardItem]: 剪贴板项，不存在则返回 None
-    fn get_item(&self, id: i64) -> PyResult<Option<PyClipboardItem>> {
-        let db = self.db.lock();
-        db.get_item_by_id(id)
-            .map_err(|e| PyRuntimeError::new_err(e))
+    ///     Iterator[PyClipboardItem]: 记录迭代器
+    /// 
+    /// Example:
+    ///     >>> for item in manager.iter_all():
+    ///     ...     writer.write(item.to_dict())
+    #[pyo3(signature = (batch_size=500))]
+    fn iter_all(&self, batch_size: i64) -> PyResult<ClipboardItemIter> {
+        if batch_size <= 0 {
+            return Err(PyRuntimeError::new_err("batch_size 必须大于 0"));
+        }
+        Ok(ClipboardItemIter {
+            db: self.reader.clone(),
+            batch_size,
+            last_id: 0,
+            buffer: std::collections::VecDeque::new(),
+            exhausted: false,
+        })
+    }
+    
+    /// 把全部记录（回收站除外）导出为单个 HTML 文件，浏览器直接打开即可浏览和搜索
+    /// 
+    /// 顶部为统计信息和搜索框，记录按日期分组（新的在前）；代码/JSON/CSV 文本显示为 <pre>，
+    /// 其他文本为 <blockquote>，图片以 base64 内嵌，文件记录列出路径。页面不引用任何外部资源
+    /// 
+    /// Args:
+    ///     path: 输出文件路径（目录不存在时自动创建，已存在的文件会被覆盖）
+    /// 
+    /// Returns:
+    ///     int: 导出的记录数
+    /// 
+    /// Raises:
+    ///     RuntimeError: 查询或写入文件失败
+    fn export_to_html(&self, py: Python<'_>, path: String) -> PyResult<i64> {
+        let images = self.db.lock().image_store();
+        let reader = self.reader.clone();
+        py.allow_threads(move || {
+            let items = html_export::load_items(&reader.lock())?;
+            html_export::write_html(std::path::Path::new(&path), &items, &images)?;
+            Ok::<_, ClipboardError>(items.len() as i64)
+        })
+        .map_err(PyErr::from)
     }
     
     /// 删除指定项
@@ -911,31 +1979,315 @@ impl PyClipboardManager {
     fn delete_item(&self, id: i64) -> PyResult<()> {
         let db = self.db.lock();
         db.delete_item(id)
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
+    }
+    
+    /// 把指定项移入回收站（可用 restore_item 撤销），不再出现在历史、分组和搜索结果中
+    /// 
+    /// Args:
+    ///     id: 要删除的记录 ID
+    /// 
+    /// Raises:
+    ///     KeyError: 记录不存在
+    fn soft_delete(&self, id: i64) -> PyResult<()> {
+        let db = self.db.lock();
+        db.soft_delete(id)
+            .map_err(PyErr::from)
+    }
+    
+    /// 分页查询回收站，最近删除的在前
+    /// 
+    /// Args:
+    ///     offset: 偏移量，默认 0
+    ///     limit: 每页数量，默认 50
+    /// 
+    /// Returns:
+    ///     PyPaginatedResult: 分页结果
+    #[pyo3(signature = (offset=0, limit=50))]
+    fn get_trash(&self, offset: i64, limit: i64) -> PyResult<PyPaginatedResult> {
+        let db = self.reader.lock();
+        db.get_trash(offset, limit)
+            .map_err(PyErr::from)
+    }
+    
+    /// 从回收站恢复指定项，恢复后排到历史最前
+    /// 
+    /// Args:
+    ///     id: 回收站中的记录 ID
+    /// 
+    /// Raises:
+    ///     KeyError: 记录不存在
+    ///     ValueError: 记录不在回收站中
+    fn restore_item(&self, id: i64) -> PyResult<()> {
+        let db = self.db.lock();
+        db.restore_item(id)
+            .map_err(PyErr::from)
+    }
+    
+    /// 清空回收站（彻底删除，无法恢复）
+    /// 
+    /// Returns:
+    ///     int: 删除的记录数
+    fn empty_trash(&self) -> PyResult<i64> {
+        let db = self.db.lock();
+        db.empty_trash()
+            .map_err(PyErr::from)
+    }
+    
+    /// 把指定项加入收藏夹
+    /// 
+    /// 收藏夹中的记录不出现在 get_history 中，不参与自动清理，clear_history 也不会删除；
+    /// 仍可通过 paste_item 粘贴
+    /// 
+    /// Args:
+    ///     id: 记录 ID
+    /// 
+    /// Raises:
+    ///     KeyError: 记录不存在
+    fn add_to_favorites(&self, id: i64) -> PyResult<()> {
+        let db = self.db.lock();
+        db.add_to_favorites(id)
+            .map_err(PyErr::from)
+    }
+    
+    /// 把指定项移出收藏夹，回到历史记录中
+    /// 
+    /// Args:
+    ///     id: 记录 ID
+    /// 
+    /// Raises:
+    ///     KeyError: 记录不存在
+    fn remove_from_favorites(&self, id: i64) -> PyResult<()> {
+        let db = self.db.lock();
+        db.remove_from_favorites(id)
+            .map_err(PyErr::from)
+    }
+    
+    /// 分页查询收藏夹（新的在前）
+    /// 
+    /// Args:
+    ///     offset: 偏移量，默认 0
+    ///     limit: 每页数量，默认 50
+    /// 
+    /// Returns:
+    ///     PyPaginatedResult: 分页结果
+    #[pyo3(signature = (offset=0, limit=50))]
+    fn get_favorites(&self, offset: i64, limit: i64) -> PyResult<PyPaginatedResult> {
+        let db = self.reader.lock();
+        db.get_favorites(offset, limit)
+            .map_err(PyErr::from)
     }
     
     /// 清空历史记录
     ///
     /// Args:
     ///     keep_grouped: True = 保留已加入分组的条目，只删历史区；False = 删除全部（默认）
+    ///         两种情况都保留收藏夹（add_to_favorites）中的记录
     #[pyo3(signature = (keep_grouped=false))]
     fn clear_history(&self, keep_grouped: bool) -> PyResult<()> {
         let db = self.db.lock();
         db.clear_all(keep_grouped)
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
     }
     
     /// 切换置顶状态
     /// 
+    /// 注意：基于当前状态取反，两次切换几乎同时发生时会互相抵消；
+    /// 复选框等需要确定结果的场景请用 set_pin
+    /// 
     /// Args:
     ///     id: 记录 ID
     /// 
     /// Returns:
     ///     bool: 新的置顶状态
+    /// 
+    /// Raises:
+    ///     KeyError: 记录不存在
+    ///     ValueError: 置顶数量已达 set_max_pinned 设置的上限
     fn toggle_pin(&self, id: i64) -> PyResult<bool> {
         let db = self.db.lock();
         db.toggle_pin(id)
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
+    }
+    
+    /// 设置置顶状态（幂等，重复设置同一状态不会改变置顶顺序）
+    /// 
+    /// Args:
+    ///     id: 记录 ID
+    ///     pinned: 是否置顶
+    /// 
+    /// Returns:
+    ///     bool: 最终的置顶状态
+    /// 
+    /// Raises:
+    ///     KeyError: 记录不存在
+    ///     ValueError: 置顶数量已达 set_max_pinned 设置的上限
+    fn set_pin(&self, id: i64, pinned: bool) -> PyResult<bool> {
+        let db = self.db.lock();
+        db.set_pin(id, pinned)
+            .map_err(PyErr::from)
+    }
+    
+    /// 设置保护状态
+    /// 
+    /// 受保护的记录不会被自动清理（条数上限 / 存储大小上限）删除，
+    /// 但不会像置顶那样排到列表顶部
+    /// 
+    /// Args:
+    ///     id: 记录 ID
+    ///     protected: 是否保护
+    /// 
+    /// Raises:
+    ///     KeyError: 记录不存在
+    fn set_protected(&self, id: i64, protected: bool) -> PyResult<()> {
+        let db = self.db.lock();
+        db.set_protected(id, protected)
+            .map_err(PyErr::from)
+    }
+    
+    /// 查询记录是否受保护
+    /// 
+    /// Raises:
+    ///     KeyError: 记录不存在
+    fn is_protected(&self, id: i64) -> PyResult<bool> {
+        let db = self.reader.lock();
+        db.is_protected(id)
+            .map_err(PyErr::from)
+    }
+    
+    /// 设置最大置顶数量
+    /// 
+    /// Args:
+    ///     limit: 最大置顶数量，0 表示不限制
+    /// 
+    /// 达到上限后 toggle_pin 置顶新项会抛出异常（取消置顶不受影响）
+    fn set_max_pinned(&self, limit: u32) {
+        self.db.lock().set_max_pinned(limit);
+    }
+    
+    /// 获取最大置顶数量（0 表示不限制）
+    fn get_max_pinned(&self) -> u32 {
+        self.db.lock().get_max_pinned()
+    }
+    
+    /// 设置重复内容的判定范围（只影响之后的记录）
+    /// 
+    /// 文本/文件按 content + html_content 判定重复，图片按 image_id 判定重复；
+    /// "per_app" 时还要求来源应用相同，不同应用复制的相同内容各自保留一条记录。
+    /// set_image_dedup_distance 的近似图片去重始终与最近一张图片比较，不区分来源应用
+    /// 
+    /// Args:
+    ///     scope: "global"（默认，不区分来源应用）或 "per_app"
+    /// 
+    /// Raises:
+    ///     ValueError: scope 不是上述值
+    fn set_dedup_scope(&self, scope: &str) -> PyResult<()> {
+        let scope = database::DedupScope::parse(scope)?;
+        self.db.lock().set_dedup_scope(scope);
+        Ok(())
+    }
+    
+    /// 获取重复内容的判定范围（"global" / "per_app"）
+    fn get_dedup_scope(&self) -> &'static str {
+        self.db.lock().get_dedup_scope().name()
+    }
+    
+    /// 设置重复内容的判定方式（只影响之后的记录，图片始终按 image_id 判定）
+    /// 
+    /// Args:
+    ///     strategy: "exact"（默认，哈希相同后再逐字比较内容和 HTML）、
+    ///         "content_hash"（只比较内容 + HTML 的 SHA256 哈希）或 "disabled"（不去重，相同内容每次都新增记录）
+    ///     **params: 预留给以后的去重方式，目前的三种都不接受额外参数
+    /// 
+    /// Raises:
+    ///     ValueError: strategy 不是上述值，或传入了额外参数
+    #[pyo3(signature = (strategy, **params))]
+    fn set_dedup_strategy(&self, strategy: &str, params: Option<&Bound<'_, pyo3::types::PyDict>>) -> PyResult<()> {
+        let strategy = database::DeduplicationStrategy::parse(strategy)?;
+        if let Some(params) = params.filter(|p| !p.is_empty()) {
+            let names: Vec<String> = params.keys().iter().map(|k| k.to_string()).collect();
+            return Err(ClipboardError::InvalidArgument(format!(
+                "去重方式 {} 不支持参数: {}", strategy.name(), names.join(", ")
+            )).into());
+        }
+        self.db.lock().set_dedup_strategy(strategy);
+        Ok(())
+    }
+    
+    /// 获取重复内容的判定方式（"exact" / "content_hash" / "disabled"）
+    fn get_dedup_strategy(&self) -> &'static str {
+        self.db.lock().get_dedup_strategy().name()
+    }
+    
+    /// 设置是否压缩存储大段文本（只影响之后新增或修改的记录，默认关闭）
+    /// 
+    /// 开启后超过 16KB 的文本记录用 zstd 压缩存储，读取时自动解压，返回的内容与原文相同。
+    /// 代价是搜索：数据库中只保留这类记录的前 1000 个字符，
+    /// 关键词只出现在后面部分时 get_history / get_history_after 的 search 匹配不到。
+    /// 
+    /// Args:
+    ///     enabled: True 开启压缩
+    fn set_text_compression(&self, enabled: bool) {
+        self.db.lock().set_text_compression(enabled);
+    }
+    
+    /// 获取是否压缩存储大段文本
+    fn get_text_compression(&self) -> bool {
+        self.db.lock().get_text_compression()
+    }
+    
+    /// 移动置顶项到指定位置（置顶区独立排序）
+    /// 
+    /// Args:
+    ///     id: 要移动的置顶项 ID
+    ///     before_id: 它上方的置顶项 ID（None = 移到置顶区最前）
+    ///     after_id: 它下方的置顶项 ID（None = 移到置顶区最后）
+    #[pyo3(signature = (id, before_id=None, after_id=None))]
+    fn move_pinned_between(&self, id: i64, before_id: Option<i64>, after_id: Option<i64>) -> PyResult<()> {
+        let db = self.db.lock();
+        db.move_pinned_between(id, before_id, after_id)
+            .map_err(PyErr::from)
+    }
+    
+    /// 按游标查询剪贴板历史（keyset 分页）
+    /// 
+    /// 排序与 get_history 相同，但按上一页末尾的位置继续读取，
+    /// 翻到很深的位置也不会像 offset 那样越来越慢；需要跳页时仍用 get_history
+    /// 
+    /// Args:
+    ///     cursor: 上一次返回的游标，None 表示从第一页开始
+    ///     limit: 每页数量，默认 50
+    ///     search: 搜索关键词
+    ///     content_type: 内容类型过滤 ("text", "file", "image", "all")
+    ///     search_fields: 关键词匹配的字段，同 get_history，默认 ["content"]
+    /// 
+    /// Returns:
+    ///     (List[PyClipboardItem], Optional[str]): 当前页记录和下一页游标，没有更多数据时游标为 None
+    /// 
+    /// Example:
+    ///     >>> items, cursor = manager.get_history_after()
+    ///     >>> while cursor:
+    ///     ...     more, cursor = manager.get_history_after(cursor)
+    #[pyo3(signature = (cursor=None, limit=50, search=None, content_type=None, search_fields=None))]
+    fn get_history_after(
+        &self,
+        py: Python<'_>,
+        cursor: Option<String>,
+        limit: i64,
+        search: Option<String>,
+        content_type: Option<String>,
+        search_fields: Option<Vec<String>>,
+    ) -> PyResult<(Vec<PyClipboardItem>, Option<String>)> {
+        let cursor = cursor.as_deref()
+            .map(database::HistoryCursor::parse)
+            .transpose()
+            .map_err(PyErr::from)?;
+        let search_fields = search_fields.unwrap_or_else(|| vec!["content".to_string()]);
+        let db = self.reader.clone();
+        let (items, next) = py
+            .allow_threads(move || db.lock().query_items_after(cursor, limit, search, content_type, &search_fields))
+            .map_err(PyErr::from)?;
+        Ok((items, next.map(|c| c.encode())))
     }
     
     /// 搜索内容
@@ -943,12 +2295,13 @@ impl PyClipboardManager {
     /// Args:
     ///     keyword: 搜索关键词
     ///     limit: 返回数量限制，默认 50
+    ///     search_fields: 关键词匹配的字段，同 get_history，默认 ["content"]
     /// 
     /// Returns:
     ///     List[PyClipboardItem]: 匹配的记录列表
-    #[pyo3(signature = (keyword, limit=50))]
-    fn search(&self, keyword: String, limit: i64) -> PyResult<Vec<PyClipboardItem>> {
-        let result = self.get_history(0, limit, Some(keyword), None)?;
+    #[pyo3(signature = (keyword, limit=50, search_fields=None))]
+    fn search(&self, py: Python<'_>, keyword: String, limit: i64, search_fields: Option<Vec<String>>) -> PyResult<Vec<PyClipboardItem>> {
+        let result = self.get_history(py, 0, limit, Some(keyword), None, search_fields)?;
         Ok(result.items)
     }
     
@@ -967,7 +2320,30 @@ impl PyClipboardManager {
         item.title = title;
         let db = self.db.lock();
         db.insert_item(&item)
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map(|(id, _)| id)
+            .map_err(PyErr::from)
+    }
+    
+    /// 批量添加内容到历史（单个事务，比逐条 add_item 快得多，且全部成功或全部回滚）
+    /// 
+    /// Args:
+    ///     items: List[Tuple[str, Optional[str], Optional[str]]]，每项为 (content, content_type, title)，
+    ///            content_type 为 None 时默认 "text"
+    /// 
+    /// Returns:
+    ///     List[int]: 按输入顺序排列的记录 ID
+    fn add_items(&self, py: Python<'_>, items: Vec<(String, Option<String>, Option<String>)>) -> PyResult<Vec<i64>> {
+        let items: Vec<PyClipboardItem> = items
+            .into_iter()
+            .map(|(content, content_type, title)| {
+                let mut item = PyClipboardItem::new(0, content, content_type.unwrap_or_else(|| "text".to_string()));
+                item.title = title;
+                item
+            })
+            .collect();
+        let db = self.db.clone();
+        py.allow_threads(move || db.lock().insert_items(&items))
+            .map_err(PyErr::from)
     }
     
     /// 更新内容项
@@ -980,7 +2356,7 @@ impl PyClipboardManager {
     fn update_item(&self, id: i64, content: String, title: Option<String>) -> PyResult<()> {
         let db = self.db.lock();
         db.update_item(id, title.as_deref(), &content)
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
     }
     
     /// 移动剪贴板内容到指定位置（拖拽排序）
@@ -1003,7 +2379,7 @@ impl PyClipboardManager {
     fn move_item_between(&self, id: i64, before_id: Option<i64>, after_id: Option<i64>) -> PyResult<()> {
         let db = self.db.lock();
         db.move_item_between(id, before_id, after_id)
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
     }
     
     // ==================== 分组功能 ====================
@@ -1017,11 +2393,14 @@ impl PyClipboardManager {
     /// 
     /// Returns:
     ///     int: 新分组的 ID
+    /// 
+    /// Raises:
+    ///     RuntimeError: 同名分组已存在
     #[pyo3(signature = (name, color=None, icon=None))]
     fn create_group(&self, name: String, color: Option<String>, icon: Option<String>) -> PyResult<i64> {
         let db = self.db.lock();
         db.create_group(&name, color.as_deref(), icon.as_deref())
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
     }
     
     /// 获取所有分组
@@ -1029,9 +2408,9 @@ impl PyClipboardManager {
     /// Returns:
     ///     List[PyGroup]: 分组列表
     fn get_groups(&self) -> PyResult<Vec<PyGroup>> {
-        let db = self.db.lock();
+        let db = self.reader.lock();
         db.get_groups()
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
     }
     
     /// 删除分组
@@ -1041,7 +2420,7 @@ impl PyClipboardManager {
     fn delete_group(&self, id: i64) -> PyResult<()> {
         let db = self.db.lock();
         db.delete_group(id)
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
     }
     
     /// 重命名分组
@@ -1052,7 +2431,7 @@ impl PyClipboardManager {
     fn rename_group(&self, id: i64, name: String) -> PyResult<()> {
         let db = self.db.lock();
         db.rename_group(id, &name)
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
     }
     
     /// 更新分组
@@ -1066,7 +2445,7 @@ impl PyClipboardManager {
     fn update_group(&self, id: i64, name: String, color: Option<String>, icon: Option<String>) -> PyResult<()> {
         let db = self.db.lock();
         db.update_group(id, &name, color.as_deref(), icon.as_deref())
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
     }
     
     /// 将项目移动到分组
@@ -1078,7 +2457,7 @@ impl PyClipboardManager {
     fn move_to_group(&self, item_id: i64, group_id: Option<i64>) -> PyResult<()> {
         let db = self.db.lock();
         db.move_to_group(item_id, group_id)
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
     }
     
     /// 移动分组到指定位置（拖拽排序）
@@ -1095,7 +2474,7 @@ impl PyClipboardManager {
     fn move_group_between(&self, id: i64, before_id: Option<i64>, after_id: Option<i64>) -> PyResult<()> {
         let db = self.db.lock();
         db.move_group_between(id, before_id, after_id)
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
     }
     
     /// 按分组查询
@@ -1109,9 +2488,9 @@ impl PyClipboardManager {
     ///     PyPaginatedResult: 分页结果
     #[pyo3(signature = (group_id=None, offset=0, limit=50))]
     fn get_by_group(&self, group_id: Option<i64>, offset: i64, limit: i64) -> PyResult<PyPaginatedResult> {
-        let db = self.db.lock();
+        let db = self.reader.lock();
         db.query_by_group(group_id, offset, limit)
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
     }
     
     /// 增加粘贴次数（当用户粘贴某项时调用）
@@ -1124,7 +2503,56 @@ impl PyClipboardManager {
     fn increment_paste_count(&self, id: i64) -> PyResult<i64> {
         let db = self.db.lock();
         db.increment_paste_count(id)
-            .map_err(|e| PyRuntimeError::new_err(e))
+            .map_err(PyErr::from)
+    }
+    
+    /// 以纯文本粘贴：只写入去除格式后的文本
+    /// 
+    /// 文本记录统一换行符并去除零宽字符；只有 HTML 的记录提取可见文字；
+    /// 文件记录每行一个路径。图片等没有文本表示的记录不写入剪贴板。
+    /// 
+    /// Args:
+    ///     id: 剪贴板项 ID
+    ///     move_to_top: 粘贴后是否移到最前（默认 true）
+    /// 
+    /// Returns:
+    ///     bool: 是否成功（记录不存在或没有文本表示时为 False）
+    #[pyo3(signature = (id, move_to_top=true))]
+    fn paste_item_plain(&self, id: i64, move_to_top: bool) -> PyResult<bool> {
+        use clipboard_rs::{Clipboard, ClipboardContext};
+        
+        let db = self.db.lock();
+        let Some(item) = db.get_item_by_id(id).map_err(PyErr::from)? else {
+            return Ok(false);
+        };
+        
+        let text = match item.content_type.as_str() {
+            "text" if !item.content.trim().is_empty() => plain_text::normalize_text(&item.content),
+            "file" => serde_json::from_str::<serde_json::Value>(&item.content)
+                .ok()
+                .and_then(|json| {
+                    let files = json.get("files")?.as_array()?;
+                    let paths: Vec<&str> = files.iter().filter_map(|f| f.as_str()).collect();
+                    Some(plain_text::normalize_text(&paths.join("\n")))
+                })
+                .unwrap_or_default(),
+            _ => item.html_content.as_deref().map(plain_text::html_to_text).unwrap_or_default(),
+        };
+        if text.trim().is_empty() {
+            return Ok(false);
+        }
+        
+        // 监听线程会把这次写入识别为文本记录，按相同指纹登记自写入
+        suppress::register(suppress::fingerprint("text", &text));
+        
+        let ctx = ClipboardContext::new()
+            .map_err(|e| PyRuntimeError::new_err(format!("创建剪贴板上下文失败: {}", e)))?;
+        ctx.set_text(text)
+            .map_err(|e| PyRuntimeError::new_err(format!("设置剪贴板失败: {}", e)))?;
+        
+        let _ = db.increment_paste_count(id);
+        if move_to_top { let _ = db.move_item_to_top(id); }
+        Ok(true)
     }
     
     /// 将项目内容设置到剪贴板（用于粘贴）
@@ -1139,14 +2567,13 @@ impl PyClipboardManager {
     fn paste_item(&self, id: i64, with_html: bool, move_to_top: bool) -> PyResult<bool> {
         use clipboard_rs::{Clipboard, ClipboardContext, ClipboardContent, common::RustImage};
         
-        // 设置跳过标志，防止自己触发监听
-        SKIP_NEXT_CHANGE.store(true, Ordering::SeqCst);
-        
         let db = self.db.lock();
         let item = db.get_item_by_id(id)
-            .map_err(|e| PyRuntimeError::new_err(e))?;
+            .map_err(PyErr::from)?;
         
         if let Some(item) = item {
+            // 登记本次写入的内容指纹，防止自己触发监听
+            suppress::register(suppress::fingerprint(&item.content_type, &item.content));
 
             // ── 优先路径：用原始格式数据完整还原（Ditto 风格）────────────────
             let raw_formats = db.get_formats(id).unwrap_or_default();
@@ -1260,12 +2687,12 @@ impl PyClipboardManager {
                 }
                 "image" => {
                     if let Some(image_id) = item.image_id {
-                        let image_path = db.get_images_dir().join(format!("{}.png", image_id));
-                        if image_path.exists() {
-                            let image_bytes = std::fs::read(&image_path)
+                        let store = db.image_store();
+                        if store.contains(&image_id) {
+                            // 直接从文件流式解码，不在内存中同时保留整份 PNG 字节和解码后的像素
+                            let image = store.open_image(&image_id)
                                 .map_err(|e| PyRuntimeError::new_err(format!("读取图片失败: {}", e)))?;
-                            let rust_image = RustImage::from_bytes(&image_bytes)
-                                .map_err(|e| PyRuntimeError::new_err(format!("解析图片失败: {}", e)))?;
+                            let rust_image = RustImage::from_dynamic_image(image);
                             ctx.set_image(rust_image)
                                 .map_err(|e| PyRuntimeError::new_err(format!("设置剪贴板图片失败: {}", e)))?;
                         }
@@ -1296,3 +2723,87 @@ impl PyClipboardManager {
         }
     }
 }
+
+/// 监听作用域（由 PyClipboardManager.monitor_scope 创建）
+/// 
+/// __enter__ 时调用 start_monitor，__exit__ 时调用 stop_monitor，异常不会被吞掉
+#[pyclass]
+pub struct MonitorGuard {
+    manager: Py<PyClipboardManager>,
+    callback: Option<PyObject>,
+}
+
+#[pymethods]
+impl MonitorGuard {
+    fn __enter__(slf: PyRef<'_, Self>) -> PyResult<PyRef<'_, Self>> {
+        let py = slf.py();
+        let callback = slf.callback.as_ref().map(|cb| cb.clone_ref(py));
+        slf.manager.borrow(py).start_monitor(callback)?;
+        Ok(slf)
+    }
+    
+    #[pyo3(signature = (_exc_type=None, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &self,
+        py: Python<'_>,
+        _exc_type: Option<PyObject>,
+        _exc_value: Option<PyObject>,
+        _traceback: Option<PyObject>,
+    ) -> PyResult<bool> {
+        self.manager.borrow(py).stop_monitor(py)?;
+        Ok(false)
+    }
+}
+
+/// 全部记录的迭代器（由 PyClipboardManager.iter_all 创建）
+/// 
+/// 缓冲区取空时再按 id 读取下一批，读到不足一批即结束
+#[pyclass]
+pub struct ClipboardItemIter {
+    db: Arc<ReaderPool>,
+    batch_size: i64,
+    /// 已读取的最大 id
+    last_id: i64,
+    buffer: std::collections::VecDeque<PyClipboardItem>,
+    exhausted: bool,
+}
+
+#[pymethods]
+impl ClipboardItemIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+    
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<PyClipboardItem>> {
+        if self.buffer.is_empty() && !self.exhausted {
+            let db = self.db.clone();
+            let (last_id, batch_size) = (self.last_id, self.batch_size);
+            let batch = py.allow_threads(move || db.lock().get_items_after(last_id, batch_size))
+                .map_err(PyErr::from)?;
+            self.exhausted = (batch.len() as i64) < batch_size;
+            if let Some(last) = batch.last() {
+                self.last_id = last.id;
+            }
+            self.buffer.extend(batch);
+        }
+        Ok(self.buffer.pop_front())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cf_html_round_trip() {
+        let fragment = "<b>粗体</b> and <i>italic</i>";
+        assert_eq!(parse_cf_html(&generate_cf_html(fragment)), fragment);
+
+        // 偏移错误时按片段标记截取
+        let broken = "Version:0.9\r\nStartHTML:abc\r\n<html><body><!--StartFragment--><p>x</p><!--EndFragment--></body></html>";
+        assert_eq!(parse_cf_html(broken), "<p>x</p>");
+
+        // 非 CF_HTML 原样返回
+        assert_eq!(parse_cf_html("<p>plain</p>"), "<p>plain</p>");
+    }
+}
@@ -0,0 +1,150 @@
+//! 监听耗时统计：记录每次剪贴板变化处理中各阶段的耗时，
+//! 用于定位大图片等场景下监听变慢的瓶颈（解码 / 哈希 / PNG 编码 / 缩略图 / 入库）。
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// 每个阶段保留最近多少次耗时用于计算平均值/最大值
+const METRICS_WINDOW: usize = 100;
+
+/// 处理阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// 读取剪贴板并识别为一条记录（包含下面的图片处理阶段）
+    Read,
+    /// 剪贴板图片转为 RGBA 像素
+    Decode,
+    /// 计算图片 ID 和感知哈希
+    Hash,
+    /// PNG 编码并写入文件（图片已存在时跳过）
+    Encode,
+    /// 生成缩略图
+    Thumbnail,
+    /// 写入数据库（记录、原始格式数据和超限清理）
+    Insert,
+    /// 一次变化的总耗时（不含 Python 回调）
+    Total,
+}
+
+impl Phase {
+    const ALL: [Phase; 7] = [
+        Phase::Read,
+        Phase::Decode,
+        Phase::Hash,
+        Phase::Encode,
+        Phase::Thumbnail,
+        Phase::Insert,
+        Phase::Total,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Read => "read",
+            Phase::Decode => "decode",
+            Phase::Hash => "hash",
+            Phase::Encode => "encode",
+            Phase::Thumbnail => "thumbnail",
+            Phase::Insert => "insert",
+            Phase::Total => "total",
+        }
+    }
+}
+
+/// 单个阶段的统计
+#[derive(Debug, Default)]
+struct PhaseStats {
+    /// 累计次数
+    count: u64,
+    /// 最近 METRICS_WINDOW 次的耗时
+    recent: VecDeque<Duration>,
+}
+
+impl PhaseStats {
+    fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        if self.recent.len() == METRICS_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(elapsed);
+    }
+}
+
+/// 某阶段的统计快照：(累计次数, 最近平均毫秒, 最近最大毫秒)
+pub type PhaseSummary = (&'static str, u64, f64, f64);
+
+static METRICS: Lazy<Mutex<Vec<PhaseStats>>> =
+    Lazy::new(|| Mutex::new(Phase::ALL.iter().map(|_| PhaseStats::default()).collect()));
+
+/// 记录一次阶段耗时
+pub fn record(phase: Phase, elapsed: Duration) {
+    record_in(&mut METRICS.lock(), phase, elapsed);
+}
+
+/// 执行 f 并把耗时记到 phase 上
+pub fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    record(phase, start.elapsed());
+    result
+}
+
+/// 所有阶段的统计快照（按处理顺序）
+pub fn snapshot() -> Vec<PhaseSummary> {
+    snapshot_of(&METRICS.lock())
+}
+
+/// 清空统计
+pub fn reset() {
+    METRICS.lock().iter_mut().for_each(|stats| *stats = PhaseStats::default());
+}
+
+fn record_in(stats: &mut [PhaseStats], phase: Phase, elapsed: Duration) {
+    let idx = Phase::ALL.iter().position(|p| *p == phase).unwrap_or(0);
+    stats[idx].record(elapsed);
+}
+
+fn snapshot_of(stats: &[PhaseStats]) -> Vec<PhaseSummary> {
+    Phase::ALL
+        .iter()
+        .zip(stats)
+        .map(|(phase, stats)| {
+            let millis = |d: &Duration| d.as_secs_f64() * 1000.0;
+            let avg = if stats.recent.is_empty() {
+                0.0
+            } else {
+                stats.recent.iter().map(millis).sum::<f64>() / stats.recent.len() as f64
+            };
+            let max = stats.recent.iter().map(millis).fold(0.0, f64::max);
+            (phase.name(), stats.count, avg, max)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolling_window_keeps_recent_samples() {
+        let mut stats: Vec<PhaseStats> = Phase::ALL.iter().map(|_| PhaseStats::default()).collect();
+        record_in(&mut stats, Phase::Encode, Duration::from_millis(500));
+        for _ in 0..METRICS_WINDOW {
+            record_in(&mut stats, Phase::Encode, Duration::from_millis(10));
+        }
+        record_in(&mut stats, Phase::Insert, Duration::from_millis(4));
+
+        let summary = snapshot_of(&stats);
+        let encode = summary.iter().find(|s| s.0 == "encode").unwrap();
+        // 最早的 500ms 已滑出窗口，但仍计入累计次数
+        assert_eq!(encode.1, METRICS_WINDOW as u64 + 1);
+        assert!((encode.2 - 10.0).abs() < 1e-9);
+        assert!((encode.3 - 10.0).abs() < 1e-9);
+
+        let insert = summary.iter().find(|s| s.0 == "insert").unwrap();
+        assert_eq!((insert.1, insert.3), (1, 4.0));
+        let decode = summary.iter().find(|s| s.0 == "decode").unwrap();
+        assert_eq!((decode.1, decode.2, decode.3), (0, 0.0, 0.0));
+    }
+}
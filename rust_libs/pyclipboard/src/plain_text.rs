@@ -0,0 +1,159 @@
+//! 纯文本粘贴：从任意记录导出不带格式的文本
+//! （统一换行符、去除零宽字符，HTML 只保留可见文字）
+
+/// 当前平台的换行符
+#[cfg(target_os = "windows")]
+const LINE_ENDING: &str = "\r\n";
+#[cfg(not(target_os = "windows"))]
+const LINE_ENDING: &str = "\n";
+
+/// 零宽字符：复制网页内容时常混入，粘贴到代码/表单中会造成隐蔽错误
+const ZERO_WIDTH_CHARS: [char; 5] = ['\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}'];
+
+/// 块级标签：结束（或自闭合）时换行
+const BLOCK_TAGS: [&str; 14] = [
+    "br", "p", "div", "li", "tr", "h1", "h2", "h3", "h4", "h5", "h6", "blockquote", "pre", "table",
+];
+
+/// 统一换行符并去除零宽字符
+pub fn normalize_text(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .chars()
+        .filter(|c| !ZERO_WIDTH_CHARS.contains(c))
+        .collect::<String>()
+        .replace('\n', LINE_ENDING)
+}
+
+/// 提取 HTML 的可见文字：去掉标签、script/style 内容和注释，块级标签处换行，解码常见实体
+pub fn html_to_text(html: &str) -> String {
+    // CF_HTML 带有 "Version:0.9 StartHTML:..." 头部，从第一个标签开始解析
+    let html = match html.find('<') {
+        Some(start) if html.starts_with("Version:") => &html[start..],
+        _ => html,
+    };
+    let mut out = String::new();
+    let mut rest = html;
+
+    while let Some(lt) = rest.find('<') {
+        push_text(&mut out, &rest[..lt]);
+        rest = &rest[lt..];
+
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+            continue;
+        }
+
+        let Some(gt) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = rest[1..gt].trim();
+        rest = &rest[gt + 1..];
+
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        // script/style 的内容不可见，整体跳过
+        if !tag.starts_with('/') && (name == "script" || name == "style") {
+            let close = format!("</{}", name);
+            rest = match rest.to_ascii_lowercase().find(&close) {
+                Some(end) => rest[end..].find('>').map_or("", |gt| &rest[end + gt + 1..]),
+                None => "",
+            };
+            continue;
+        }
+
+        let is_close_or_self = tag.starts_with('/') || tag.ends_with('/') || name == "br";
+        if is_close_or_self && BLOCK_TAGS.contains(&name.as_str()) {
+            out.push('\n');
+        }
+    }
+    push_text(&mut out, rest);
+
+    // 行内空白折叠为单个空格，去掉首尾空行和连续空行
+    let mut lines: Vec<String> = Vec::new();
+    for line in out.split('\n') {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() && lines.last().is_none_or(|l: &String| l.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    normalize_text(&lines.join("\n"))
+}
+
+/// 追加一段标签之间的文本（解码实体；源码中的换行不代表可见换行）
+fn push_text(out: &mut String, text: &str) {
+    let text = text.replace(['\r', '\n'], " ");
+    out.push_str(&decode_entities(&text));
+}
+
+/// 解码常见 HTML 实体（命名实体只处理常用的几个，数字实体全部支持）
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').filter(|&semi| semi <= 10).and_then(|semi| {
+            let entity = &rest[1..semi];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, semi))
+        });
+        match decoded {
+            Some((c, semi)) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_strips_zero_width_and_unifies_line_endings() {
+        let text = normalize_text("a\u{200B}b\r\nc\rd\u{FEFF}");
+        assert_eq!(text, format!("ab{0}c{0}d", LINE_ENDING));
+    }
+
+    #[test]
+    fn html_keeps_only_visible_text() {
+        let html = "<html><head><style>p { color: red; }</style></head><body>\
+                    <!-- StartFragment --><p>Hello&nbsp;<b>world</b> &amp; co</p>\
+                    <script>alert(1)</script><div>line&#x32;<br/>line&#51;</div></body></html>";
+        assert_eq!(
+            html_to_text(html),
+            format!("Hello world & co{0}line2{0}line3", LINE_ENDING)
+        );
+    }
+}
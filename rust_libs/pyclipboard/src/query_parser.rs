@@ -0,0 +1,283 @@
+//! 布尔搜索语法（search_boolean）：解析为表达式树后生成参数化的 SQLite WHERE 子句
+//!
+//! - 空格分隔的关键词之间默认为 AND，也可以显式写 AND
+//! - OR 的优先级高于 AND（与常见搜索引擎一致）：`python OR rust NOT pandas` 即 (python 或 rust) 且不含 pandas
+//! - NOT 排除后面的关键词或括号分组，双引号包围的短语按整体匹配
+//! - AND / OR / NOT 只识别大写，小写按普通关键词处理
+//!
+//! 每个关键词匹配 content 或 html_content（LIKE，不区分 ASCII 大小写）；
+//! 关键词只作为参数传入，生成的 SQL 只由固定片段组成，并在返回前校验引用的列
+
+use crate::error::ClipboardError;
+
+/// 括号的最大嵌套层数，避免恶意输入导致递归过深
+const MAX_DEPTH: usize = 32;
+
+/// 生成的 WHERE 子句允许出现的标识符（列名、函数名和关键字）
+const ALLOWED_IDENTIFIERS: [&str; 8] = ["content", "html_content", "COALESCE", "LIKE", "ESCAPE", "AND", "OR", "NOT"];
+
+/// 查询表达式
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// 关键词或短语（子串匹配）
+    Term(String),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+fn invalid(msg: impl Into<String>) -> ClipboardError {
+    ClipboardError::InvalidArgument(msg.into())
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, ClipboardError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '"' => {
+                chars.next();
+                let mut phrase = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => phrase.push(c),
+                        None => return Err(invalid("搜索条件中的引号没有闭合")),
+                    }
+                }
+                if phrase.trim().is_empty() {
+                    return Err(invalid("搜索条件中有空的引号短语"));
+                }
+                tokens.push(Token::Phrase(phrase));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// 递归下降解析：and := or ([AND] or)*，or := unary (OR unary)*，unary := NOT unary | primary
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ClipboardError> {
+        let mut operands = vec![self.parse_or()?];
+        loop {
+            match self.peek() {
+                None | Some(Token::RParen) => break,
+                Some(Token::And) => {
+                    self.pos += 1;
+                }
+                _ => {}
+            }
+            operands.push(self.parse_or()?);
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { Expr::And(operands) })
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ClipboardError> {
+        let mut operands = vec![self.parse_unary()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            operands.push(self.parse_unary()?);
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { Expr::Or(operands) })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ClipboardError> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ClipboardError> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(Expr::Term(word)),
+            Some(Token::Phrase(phrase)) => Ok(Expr::Term(phrase)),
+            Some(Token::LParen) => {
+                self.depth += 1;
+                if self.depth > MAX_DEPTH {
+                    return Err(invalid(format!("搜索条件的括号嵌套超过 {} 层", MAX_DEPTH)));
+                }
+                let expr = self.parse_and()?;
+                if self.next() != Some(Token::RParen) {
+                    return Err(invalid("搜索条件中的括号没有闭合"));
+                }
+                self.depth -= 1;
+                Ok(expr)
+            }
+            Some(Token::RParen) => Err(invalid("搜索条件中有多余的右括号或空括号")),
+            Some(_) | None => Err(invalid("搜索条件中的 AND / OR / NOT 后缺少关键词")),
+        }
+    }
+}
+
+/// 解析查询字符串
+pub fn parse(query: &str) -> Result<Expr, ClipboardError> {
+    let tokens = tokenize(query)?;
+    if tokens.is_empty() {
+        return Err(invalid("搜索条件不能为空"));
+    }
+    let mut parser = Parser { tokens, pos: 0, depth: 0 };
+    let expr = parser.parse_and()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(invalid("搜索条件中有多余的右括号"));
+    }
+    Ok(expr)
+}
+
+/// 转义 LIKE 的通配符（配合 ESCAPE '\'）
+fn like_pattern(term: &str) -> String {
+    let mut pattern = String::from("%");
+    for c in term.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+impl Expr {
+    fn to_sql(&self, params: &mut Vec<String>) -> String {
+        match self {
+            Expr::Term(term) => {
+                let pattern = like_pattern(term);
+                params.push(pattern.clone());
+                params.push(pattern);
+                // html_content 为 NULL 时按空串处理，否则 NOT 的结果也是 NULL，整行被过滤掉
+                "(content LIKE ? ESCAPE '\\' OR COALESCE(html_content, '') LIKE ? ESCAPE '\\')".to_string()
+            }
+            Expr::Not(inner) => format!("(NOT {})", inner.to_sql(params)),
+            Expr::And(operands) => Self::join(operands, " AND ", params),
+            Expr::Or(operands) => Self::join(operands, " OR ", params),
+        }
+    }
+
+    fn join(operands: &[Expr], op: &str, params: &mut Vec<String>) -> String {
+        let parts: Vec<String> = operands.iter().map(|e| e.to_sql(params)).collect();
+        format!("({})", parts.join(op))
+    }
+}
+
+/// 校验 SQL 片段中（字符串字面量以外）出现的标识符都在允许列表中
+fn validate_sql(sql: &str) -> Result<(), ClipboardError> {
+    let mut in_literal = false;
+    let mut ident = String::new();
+    for c in sql.chars().chain(std::iter::once(' ')) {
+        if in_literal {
+            in_literal = c != '\'';
+            continue;
+        }
+        if c.is_ascii_alphanumeric() || c == '_' {
+            ident.push(c);
+            continue;
+        }
+        if !ident.is_empty() && !ALLOWED_IDENTIFIERS.contains(&ident.as_str()) {
+            return Err(invalid(format!("搜索条件引用了不允许的标识符: {}", ident)));
+        }
+        ident.clear();
+        in_literal = c == '\'';
+    }
+    Ok(())
+}
+
+/// 把查询字符串转换为 WHERE 子句（不含 WHERE 关键字）及其参数
+pub fn where_clause(query: &str) -> Result<(String, Vec<String>), ClipboardError> {
+    let expr = parse(query)?;
+    let mut params = Vec::new();
+    let sql = expr.to_sql(&mut params);
+    validate_sql(&sql)?;
+    Ok((sql, params))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(t: &str) -> Expr {
+        Expr::Term(t.to_string())
+    }
+
+    #[test]
+    fn parses_operators_and_phrases() {
+        assert_eq!(
+            parse("python OR rust NOT pandas").unwrap(),
+            Expr::And(vec![Expr::Or(vec![term("python"), term("rust")]), Expr::Not(Box::new(term("pandas")))])
+        );
+        assert_eq!(
+            parse("\"import os\" AND (a OR b)").unwrap(),
+            Expr::And(vec![term("import os"), Expr::Or(vec![term("a"), term("b")])])
+        );
+        assert_eq!(parse("and or").unwrap(), Expr::And(vec![term("and"), term("or")]));
+
+        let too_deep = "(".repeat(MAX_DEPTH + 1) + "a";
+        for bad in ["", "  ", "a OR", "NOT", "(a", "a)", "()", "\"open", too_deep.as_str()] {
+            assert!(matches!(parse(bad), Err(ClipboardError::InvalidArgument(_))), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn generates_parameterized_sql() {
+        let (sql, params) = where_clause("50%_off' OR 1=1").unwrap();
+        assert!(!sql.contains("50") && !sql.contains("1=1"));
+        assert_eq!(params, ["%50\\%\\_off'%", "%50\\%\\_off'%", "%1=1%", "%1=1%"]);
+
+        assert!(validate_sql("(content LIKE ? ESCAPE '\\')").is_ok());
+        assert!(validate_sql("(content LIKE ? OR title LIKE ?)").is_err());
+    }
+}
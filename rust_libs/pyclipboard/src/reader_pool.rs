@@ -0,0 +1,75 @@
+//! 只读连接池：多个 Python 线程同时查询时各自使用一个读连接并行执行。
+//!
+//! rusqlite 的 Connection 不是 Sync，同一个连接无法被 RwLock 的多个读者同时使用，
+//! 因此并发读只能靠多个连接实现；WAL 模式下这些连接之间、以及与写连接之间都不互相阻塞。
+
+use crate::database::Database;
+use crate::error::ClipboardError;
+use parking_lot::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// 默认的读连接数量
+pub const DEFAULT_READER_CONNECTIONS: usize = 4;
+
+pub struct ReaderPool {
+    conns: Vec<Arc<Mutex<Database>>>,
+    /// 全部连接都忙时轮流排队，避免都挤在第一个连接上
+    next: AtomicUsize,
+}
+
+impl ReaderPool {
+    /// 为数据库文件打开 size 个只读连接（至少 1 个）
+    pub fn open(db_path: &str, busy_timeout_ms: u32, size: usize) -> Result<Self, ClipboardError> {
+        let conns = (0..size.max(1))
+            .map(|_| Database::open_reader(db_path, busy_timeout_ms).map(|db| Arc::new(Mutex::new(db))))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { conns, next: AtomicUsize::new(0) })
+    }
+
+    /// 直接复用已有连接（内存数据库无法跨连接共享）
+    pub fn shared(db: Arc<Mutex<Database>>) -> Self {
+        Self { conns: vec![db], next: AtomicUsize::new(0) }
+    }
+
+    /// 取一个空闲的连接；全部在用时等待其中一个
+    pub fn lock(&self) -> MutexGuard<'_, Database> {
+        if let Some(guard) = self.conns.iter().find_map(|conn| conn.try_lock()) {
+            return guard;
+        }
+        let idx = self.next.fetch_add(1, Ordering::Relaxed) % self.conns.len();
+        self.conns[idx].lock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_readers_use_separate_connections() {
+        let dir = std::env::temp_dir().join(format!("pyclipboard_reader_pool_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("clipboard.db").to_string_lossy().into_owned();
+        let writer = Database::new(&path, 5000).unwrap();
+        writer
+            .insert_item(&crate::types::PyClipboardItem::new(0, "x".into(), "text".into()))
+            .unwrap();
+
+        let pool = Arc::new(ReaderPool::open(&path, 5000, 2).unwrap());
+        // 一个查询占用连接期间，另一个线程的查询不需要等待
+        let first = pool.lock();
+        let other = {
+            let pool = pool.clone();
+            std::thread::spawn(move || pool.lock().get_count().unwrap())
+        };
+        assert_eq!(other.join().unwrap(), 1);
+        assert_eq!(first.get_count().unwrap(), 1);
+        drop(first);
+
+        // 内存数据库只有一个共享连接
+        let shared = ReaderPool::shared(Arc::new(Mutex::new(Database::new(":memory:", 0).unwrap())));
+        assert_eq!(shared.lock().get_count().unwrap(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,84 @@
+//! 自写入抑制：记录 paste_item 写入剪贴板的内容指纹，
+//! 监听线程只跳过内容匹配的那一次变化，而不是盲目跳过"下一次"变化。
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// 指纹有效期：超过该时间仍未被监听线程消费则丢弃（例如系统合并了通知）
+const SELF_WRITE_TTL: Duration = Duration::from_secs(5);
+
+static PENDING: Lazy<Mutex<Vec<(u64, Instant)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// 计算剪贴板内容指纹（与监听线程构造的主记录 content_type + content 一致）
+pub fn fingerprint(content_type: &str, content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content_type.hash(&mut hasher);
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 登记一次自写入
+pub fn register(fp: u64) {
+    register_at(&mut PENDING.lock(), fp, Instant::now());
+}
+
+/// 若该指纹是待消费的自写入则移除并返回 true
+pub fn consume(fp: u64) -> bool {
+    consume_at(&mut PENDING.lock(), fp, Instant::now())
+}
+
+fn register_at(pending: &mut Vec<(u64, Instant)>, fp: u64, now: Instant) {
+    pending.retain(|(_, at)| now.duration_since(*at) < SELF_WRITE_TTL);
+    pending.push((fp, now));
+}
+
+fn consume_at(pending: &mut Vec<(u64, Instant)>, fp: u64, now: Instant) -> bool {
+    pending.retain(|(_, at)| now.duration_since(*at) < SELF_WRITE_TTL);
+    match pending.iter().position(|(p, _)| *p == fp) {
+        Some(idx) => {
+            pending.remove(idx);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn real_copy_between_two_pastes_is_not_swallowed() {
+        let mut pending = Vec::new();
+        let now = Instant::now();
+
+        let paste_a = fingerprint("text", "pasted A");
+        let paste_b = fingerprint("text", "pasted B");
+        let user_copy = fingerprint("text", "user copy");
+
+        register_at(&mut pending, paste_a, now);
+        register_at(&mut pending, paste_b, now);
+
+        // 通知顺序被打乱：用户的真实复制先到达，不能被跳过
+        assert!(!consume_at(&mut pending, user_copy, now));
+        assert!(consume_at(&mut pending, paste_b, now));
+        assert!(consume_at(&mut pending, paste_a, now));
+
+        // 每次自写入只抑制一次
+        assert!(!consume_at(&mut pending, paste_a, now));
+    }
+
+    #[test]
+    fn expired_self_write_is_dropped() {
+        let mut pending = Vec::new();
+        let start = Instant::now();
+        let fp = fingerprint("text", "stale");
+
+        register_at(&mut pending, fp, start);
+        assert!(!consume_at(&mut pending, fp, start + SELF_WRITE_TTL));
+        assert!(pending.is_empty());
+    }
+}
@@ -16,6 +16,14 @@ use serde::{Deserialize, Serialize};
 ///     char_count: 字符数
 ///     created_at: 创建时间戳
 ///     updated_at: 更新时间戳
+///     image_width: 原图宽度（仅图片记录）
+///     image_height: 原图高度（仅图片记录）
+///     image_bytes: 图片文件字节数（仅图片记录）
+///     image_format: 图片文件格式，如 "PNG"（仅图片记录）
+///     formats: 采集时剪贴板上的格式列表（仅开启 set_capture_formats 时记录）
+///     subtype: 文本子类型 "url" / "email" / "json" / "code" / "csv"（仅文本记录，入库时自动识别）
+/// 
+/// 相等和哈希只看 id，可放入 set 或作为 dict 的键
 #[pyclass]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PyClipboardItem {
@@ -45,6 +53,18 @@ pub struct PyClipboardItem {
     pub created_at: i64,
     #[pyo3(get, set)]
     pub updated_at: i64,
+    #[pyo3(get, set)]
+    pub image_width: Option<i64>,
+    #[pyo3(get, set)]
+    pub image_height: Option<i64>,
+    #[pyo3(get, set)]
+    pub image_bytes: Option<i64>,
+    #[pyo3(get, set)]
+    pub image_format: Option<String>,
+    #[pyo3(get, set)]
+    pub formats: Option<Vec<String>>,
+    #[pyo3(get, set)]
+    pub subtype: Option<String>,
 }
 
 #[pymethods]
@@ -67,6 +87,12 @@ impl PyClipboardItem {
             char_count: None,
             created_at: now,
             updated_at: now,
+            image_width: None,
+            image_height: None,
+            image_bytes: None,
+            image_format: None,
+            formats: None,
+            subtype: None,
         }
     }
     
@@ -83,13 +109,101 @@ impl PyClipboardItem {
         self.content.clone()
     }
     
+    /// 按 id 判断相等（同一条记录的不同快照视为相等）
+    fn __eq__(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+    
+    /// 按 id 计算哈希，可放入 set 或作为 dict 的键
+    fn __hash__(&self) -> u64 {
+        self.id as u64
+    }
+    
+    /// 行数（非文本记录返回 0）
+    fn line_count(&self) -> usize {
+        self.text().map_or(0, |t| t.lines().count())
+    }
+    
+    /// UTF-8 字节长度（非文本记录返回 0）
+    fn byte_len(&self) -> usize {
+        self.text().map_or(0, str::len)
+    }
+    
+    /// 单词数：按空白分隔计数，CJK 字符每个字计为一个词（非文本记录返回 0）
+    fn word_count(&self) -> usize {
+        self.text().map_or(0, |t| {
+            t.split_whitespace()
+                .map(|word| {
+                    let cjk = word.chars().filter(|&c| is_cjk(c)).count();
+                    // 去掉 CJK 字符后若还剩其他字符，按一个词计
+                    let has_other = word.chars().any(|c| !is_cjk(c));
+                    cjk + has_other as usize
+                })
+                .sum()
+        })
+    }
+    
+    /// 按当前内容重新识别文本子类型（不修改 subtype 属性）
+    /// 
+    /// Returns:
+    ///     Optional[str]: "url" / "email" / "json" / "code" / "csv"，普通文字或非文本记录返回 None
+    fn detect_subtype(&self) -> Option<&'static str> {
+        self.text().and_then(crate::heuristics::detect_content_subtype)
+    }
+    
+    /// 提取内容中的所有 http(s) 链接（按出现顺序）
+    /// 
+    /// Returns:
+    ///     list[str]: 链接列表，非文本记录返回空列表
+    fn extract_urls(&self) -> Vec<String> {
+        self.text().map(crate::heuristics::extract_urls).unwrap_or_default()
+    }
+    
+    /// 提取内容中的邮箱地址和电话号码（按出现顺序，去重）
+    /// 
+    /// Returns:
+    ///     dict: {"emails": list[str], "phones": list[str]}，非文本记录两项都为空列表
+    fn extract_contacts(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let text = self.text().unwrap_or("");
+        let dict = pyo3::types::PyDict::new_bound(py);
+        dict.set_item("emails", crate::heuristics::extract_emails(text))?;
+        dict.set_item("phones", crate::heuristics::extract_phones(text))?;
+        Ok(dict.into())
+    }
+    
+    /// 列表展示用的单行预览：换行与连续空白折叠为一个空格，超出 max_chars 时以 "…" 结尾
+    /// 
+    /// Args:
+    ///     max_chars: 最大字符数，默认 100
+    /// 
+    /// Returns:
+    ///     str: 预览文本（非文本记录返回空字符串）
+    #[pyo3(signature = (max_chars=100))]
+    fn preview(&self, max_chars: usize) -> String {
+        let Some(text) = self.text() else {
+            return String::new();
+        };
+        let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if collapsed.chars().count() <= max_chars {
+            return collapsed;
+        }
+        let mut preview: String = collapsed.chars().take(max_chars.saturating_sub(1)).collect();
+        preview.push('…');
+        preview
+    }
+    
     /// 转换为 Python 字典
     /// 
+    /// Args:
+    ///     include_stats: 是否附带 line_count/byte_len/word_count/preview，默认 False
+    /// 
     /// Returns:
     ///     dict: 包含所有属性的字典
-    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+    #[pyo3(signature = (include_stats=false))]
+    fn to_dict(&self, py: Python<'_>, include_stats: bool) -> PyResult<PyObject> {
         let dict = pyo3::types::PyDict::new_bound(py);
         dict.set_item("id", self.id)?;
+        dict.set_item("title", &self.title)?;
         dict.set_item("content", &self.content)?;
         dict.set_item("html_content", &self.html_content)?;
         dict.set_item("content_type", &self.content_type)?;
@@ -101,10 +215,89 @@ impl PyClipboardItem {
         dict.set_item("char_count", self.char_count)?;
         dict.set_item("created_at", self.created_at)?;
         dict.set_item("updated_at", self.updated_at)?;
+        dict.set_item("image_width", self.image_width)?;
+        dict.set_item("image_height", self.image_height)?;
+        dict.set_item("image_bytes", self.image_bytes)?;
+        dict.set_item("image_format", &self.image_format)?;
+        dict.set_item("formats", &self.formats)?;
+        dict.set_item("subtype", &self.subtype)?;
+        if include_stats {
+            dict.set_item("line_count", self.line_count())?;
+            dict.set_item("byte_len", self.byte_len())?;
+            dict.set_item("word_count", self.word_count())?;
+            dict.set_item("preview", self.preview(100))?;
+        }
         Ok(dict.into())
     }
 }
 
+impl PyClipboardItem {
+    /// 文本内容（仅 content_type 为 "text" 时有效）
+    fn text(&self) -> Option<&str> {
+        (self.content_type == "text").then_some(self.content.as_str())
+    }
+}
+
+/// 是否为 CJK 表意文字或假名/谚文（这些语言不用空格分词）
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'   // 平假名、片假名
+        | '\u{3400}'..='\u{4DBF}' // CJK 扩展 A
+        | '\u{4E00}'..='\u{9FFF}' // CJK 统一表意文字
+        | '\u{AC00}'..='\u{D7AF}' // 谚文音节
+        | '\u{F900}'..='\u{FAFF}' // CJK 兼容表意文字
+    )
+}
+
+/// 监听回调事件
+/// 
+/// Attributes:
+///     kind: 事件类型 ("new" 新增记录, "duplicate_bumped" 重复内容被移到最前,
+///         "cleared" 剪贴板被清空，仅开启 set_notify_clear 时产生)
+///     item: 对应的剪贴板项（"cleared" 事件为 id 0、内容为空、类型为 "empty" 的占位项，不入库）
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct PyClipboardEvent {
+    #[pyo3(get)]
+    pub kind: String,
+    #[pyo3(get)]
+    pub item: PyClipboardItem,
+}
+
+impl PyClipboardEvent {
+    pub fn new(item: PyClipboardItem, inserted: bool) -> Self {
+        let kind = if inserted { "new" } else { "duplicate_bumped" };
+        Self { kind: kind.to_string(), item }
+    }
+
+    /// 剪贴板被清空（读取不到文本、文件或图片）
+    pub fn cleared() -> Self {
+        Self {
+            kind: "cleared".to_string(),
+            item: PyClipboardItem::new(0, String::new(), "empty".to_string()),
+        }
+    }
+}
+
+#[pymethods]
+impl PyClipboardEvent {
+    /// 是否为重复内容（已存在的记录被移到最前）
+    #[getter]
+    fn is_duplicate(&self) -> bool {
+        self.kind == "duplicate_bumped"
+    }
+    
+    /// 是否为剪贴板被清空的通知
+    #[getter]
+    fn is_cleared(&self) -> bool {
+        self.kind == "cleared"
+    }
+    
+    fn __repr__(&self) -> String {
+        format!("ClipboardEvent(kind='{}', item_id={})", self.kind, self.item.id)
+    }
+}
+
 /// 查询参数
 /// 
 /// Attributes:
@@ -138,6 +331,169 @@ impl PyQueryParams {
     }
 }
 
+/// 文件记录中单个文件的当前状态（读取时实时获取，文件被移动或删除后 exists 为 False）
+/// 
+/// Attributes:
+///     path: 采集时记录的路径
+///     exists: 文件或文件夹当前是否存在
+///     size: 文件字节数（文件夹或不存在时为 None）
+///     is_dir: 是否为文件夹
+///     modified: 最后修改时间戳（秒，无法获取时为 None）
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct PyFileInfo {
+    #[pyo3(get)]
+    pub path: String,
+    #[pyo3(get)]
+    pub exists: bool,
+    #[pyo3(get)]
+    pub size: Option<u64>,
+    #[pyo3(get)]
+    pub is_dir: bool,
+    #[pyo3(get)]
+    pub modified: Option<i64>,
+}
+
+impl PyFileInfo {
+    pub fn from_path(path: &str) -> Self {
+        let meta = std::fs::metadata(path).ok();
+        let is_dir = meta.as_ref().is_some_and(|m| m.is_dir());
+        Self {
+            path: path.to_string(),
+            exists: meta.is_some(),
+            size: meta.as_ref().filter(|m| m.is_file()).map(|m| m.len()),
+            is_dir,
+            modified: meta
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64),
+        }
+    }
+}
+
+#[pymethods]
+impl PyFileInfo {
+    fn __repr__(&self) -> String {
+        format!("FileInfo(path='{}', exists={}, size={:?})", self.path, self.exists, self.size)
+    }
+}
+
+/// 剪贴板管理器配置，配合 PyClipboardManager.from_config 一次性完成初始化
+/// 
+/// Attributes:
+///     db_path: 数据库文件路径，None 时使用用户数据目录下的 pyclipboard/clipboard.db
+///     images_dir: 图片存储目录，None 时使用数据库旁的 images 子目录
+///     busy_timeout_ms: 其他连接持有锁时的最长等待毫秒数，默认 5000
+///     in_memory: 数据库和图片只保存在内存中，默认 False
+///     history_limit: 历史记录数量限制，默认 0（不限制）
+///     storage_limit_bytes: 存储字节数限制，默认 0（不限制）
+///     thumbnail_size: 图片记录缩略图的长边像素数，默认 64
+///     image_dedup_distance: 近似重复图片去重阈值（dHash 汉明距离），默认 0（不启用）
+///     max_pinned: 最大置顶数量，默认 0（不限制）
+///     dedup_scope: 重复内容的判定范围，"global"（默认）或 "per_app"
+///     dedup_strategy: 重复内容的判定方式，"exact"（默认）、"content_hash" 或 "disabled"
+///     text_compression: 是否压缩存储大段文本，默认 False
+///     capture_formats: 是否记录采集时的剪贴板格式列表，默认 False
+///     notify_clear: 剪贴板被清空时是否以 "cleared" 事件调用监听回调，默认 False
+/// 
+/// Example:
+///     >>> config = PyClipboardManagerConfig(history_limit=500, thumbnail_size=128)
+///     >>> manager = PyClipboardManager.from_config(config)
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct PyClipboardManagerConfig {
+    #[pyo3(get, set)]
+    pub db_path: Option<String>,
+    #[pyo3(get, set)]
+    pub images_dir: Option<String>,
+    #[pyo3(get, set)]
+    pub busy_timeout_ms: u32,
+    #[pyo3(get, set)]
+    pub in_memory: bool,
+    #[pyo3(get, set)]
+    pub history_limit: i64,
+    #[pyo3(get, set)]
+    pub storage_limit_bytes: u64,
+    #[pyo3(get, set)]
+    pub thumbnail_size: u32,
+    #[pyo3(get, set)]
+    pub image_dedup_distance: u32,
+    #[pyo3(get, set)]
+    pub max_pinned: u32,
+    #[pyo3(get, set)]
+    pub dedup_scope: String,
+    #[pyo3(get, set)]
+    pub dedup_strategy: String,
+    #[pyo3(get, set)]
+    pub text_compression: bool,
+    #[pyo3(get, set)]
+    pub capture_formats: bool,
+    #[pyo3(get, set)]
+    pub notify_clear: bool,
+}
+
+#[pymethods]
+impl PyClipboardManagerConfig {
+    #[new]
+    #[pyo3(signature = (
+        db_path=None,
+        images_dir=None,
+        busy_timeout_ms=crate::database::DEFAULT_BUSY_TIMEOUT_MS,
+        in_memory=false,
+        history_limit=0,
+        storage_limit_bytes=0,
+        thumbnail_size=crate::DEFAULT_THUMBNAIL_SIZE,
+        image_dedup_distance=0,
+        max_pinned=0,
+        dedup_scope="global".to_string(),
+        dedup_strategy="exact".to_string(),
+        text_compression=false,
+        capture_formats=false,
+        notify_clear=false,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        db_path: Option<String>,
+        images_dir: Option<String>,
+        busy_timeout_ms: u32,
+        in_memory: bool,
+        history_limit: i64,
+        storage_limit_bytes: u64,
+        thumbnail_size: u32,
+        image_dedup_distance: u32,
+        max_pinned: u32,
+        dedup_scope: String,
+        dedup_strategy: String,
+        text_compression: bool,
+        capture_formats: bool,
+        notify_clear: bool,
+    ) -> Self {
+        Self {
+            db_path,
+            images_dir,
+            busy_timeout_ms,
+            in_memory,
+            history_limit,
+            storage_limit_bytes,
+            thumbnail_size,
+            image_dedup_distance,
+            max_pinned,
+            dedup_scope,
+            dedup_strategy,
+            text_compression,
+            capture_formats,
+            notify_clear,
+        }
+    }
+    
+    fn __repr__(&self) -> String {
+        format!(
+            "ClipboardManagerConfig(db_path={:?}, history_limit={}, storage_limit_bytes={}, thumbnail_size={})",
+            self.db_path, self.history_limit, self.storage_limit_bytes, self.thumbnail_size
+        )
+    }
+}
+
 /// 分页查询结果
 /// 
 /// 支持迭代和索引访问。
@@ -206,6 +562,28 @@ impl PyPaginatedResult {
         let iter = PyPaginatedResultIter { items, index: 0 };
         Py::new(slf.py(), iter)
     }
+    
+    /// 转换为 Python 字典，可直接 json.dumps
+    /// 
+    /// Returns:
+    ///     dict: {total_count, offset, limit, has_more, items}，items 为各项 to_dict() 的列表
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let dict = pyo3::types::PyDict::new_bound(py);
+        dict.set_item("total_count", self.total_count)?;
+        dict.set_item("offset", self.offset)?;
+        dict.set_item("limit", self.limit)?;
+        dict.set_item("has_more", self.has_more)?;
+        dict.set_item("items", self.to_list(py)?)?;
+        Ok(dict.into())
+    }
+    
+    /// 当前页各项的 to_dict() 列表
+    /// 
+    /// Returns:
+    ///     list[dict]: 每项一个字典
+    fn to_list(&self, py: Python<'_>) -> PyResult<Vec<PyObject>> {
+        self.items.iter().map(|item| item.to_dict(py, false)).collect()
+    }
 }
 
 #[pyclass]
@@ -276,7 +654,82 @@ impl PyGroup {
         format!("Group(id={}, name='{}')", self.id, self.name)
     }
     
+    /// 转换为 Python 字典
+    /// 
+    /// Returns:
+    ///     dict: 包含所有属性的字典
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let dict = pyo3::types::PyDict::new_bound(py);
+        dict.set_item("id", self.id)?;
+        dict.set_item("name", &self.name)?;
+        dict.set_item("color", &self.color)?;
+        dict.set_item("icon", &self.icon)?;
+        dict.set_item("item_order", self.item_order)?;
+        dict.set_item("created_at", self.created_at)?;
+        Ok(dict.into())
+    }
+    
     fn __str__(&self) -> String {
         self.name.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_stats_and_preview() {
+        let item = PyClipboardItem::new(1, "hello  world\n你好abc\nlast".to_string(), "text".to_string());
+        assert_eq!(item.line_count(), 3);
+        assert_eq!(item.byte_len(), "hello  world\n你好abc\nlast".len());
+        // hello, world, 你, 好, abc, last
+        assert_eq!(item.word_count(), 6);
+        assert_eq!(item.preview(100), "hello world 你好abc last");
+        assert_eq!(item.preview(8), "hello w…");
+
+        let image = PyClipboardItem::new(2, "[10x10]".to_string(), "image".to_string());
+        assert_eq!((image.line_count(), image.byte_len(), image.word_count()), (0, 0, 0));
+        assert_eq!(image.preview(100), "");
+    }
+
+    #[test]
+    fn file_info_reflects_current_state() {
+        let dir = std::env::temp_dir().join(format!("pyclipboard_file_info_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("a.txt");
+        std::fs::write(&file, "hello").unwrap();
+
+        let info = PyFileInfo::from_path(file.to_str().unwrap());
+        assert!(info.exists && !info.is_dir);
+        assert_eq!(info.size, Some(5));
+        assert!(info.modified.is_some());
+
+        let folder = PyFileInfo::from_path(dir.to_str().unwrap());
+        assert!(folder.exists && folder.is_dir && folder.size.is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+        let gone = PyFileInfo::from_path(file.to_str().unwrap());
+        assert!(!gone.exists && gone.size.is_none() && gone.modified.is_none());
+    }
+
+    #[test]
+    fn items_compare_by_id() {
+        let item = PyClipboardItem::new(7, "before".to_string(), "text".to_string());
+        let mut edited = item.clone();
+        edited.content = "after".to_string();
+        assert!(item.__eq__(&edited) && item.__hash__() == edited.__hash__());
+        assert!(!item.__eq__(&PyClipboardItem::new(8, "before".to_string(), "text".to_string())));
+    }
+
+    #[test]
+    fn event_kinds() {
+        let item = PyClipboardItem::new(3, "x".to_string(), "text".to_string());
+        assert_eq!(PyClipboardEvent::new(item.clone(), true).kind, "new");
+        assert!(PyClipboardEvent::new(item, false).is_duplicate());
+
+        let cleared = PyClipboardEvent::cleared();
+        assert!(cleared.is_cleared() && !cleared.is_duplicate());
+        assert_eq!((cleared.item.id, cleared.item.content_type.as_str()), (0, "empty"));
+    }
+}
@@ -0,0 +1,24 @@
+//! 二维码 / 条码识别（rxing，与 OCR 引擎无关）
+
+/// 识别图片中的所有二维码和条码，返回解码出的文本（按识别顺序，去重）
+///
+/// 图片中没有可识别的码时返回空列表；图片本身无法解码时返回错误
+pub fn detect_qr_codes(image_data: &[u8]) -> Result<Vec<String>, String> {
+    let gray = image::load_from_memory(image_data)
+        .map_err(|e| format!("图像解码失败: {}", e))?
+        .to_luma8();
+    let (width, height) = gray.dimensions();
+
+    // 找不到码时 rxing 返回 NotFound 错误，这里统一视为空结果
+    let results = rxing::helpers::detect_multiple_in_luma(gray.into_raw(), width, height)
+        .unwrap_or_default();
+
+    let mut texts: Vec<String> = Vec::new();
+    for result in results {
+        let text = result.getText().to_string();
+        if !text.is_empty() && !texts.contains(&text) {
+            texts.push(text);
+        }
+    }
+    Ok(texts)
+}
@@ -0,0 +1,232 @@
+//! 版面分析：把物理行合并为段落，并按阅读顺序排序
+
+use serde::{Deserialize, Serialize};
+
+use crate::{BoundingBox, OcrLine};
+
+/// 相邻两行的垂直间距不超过「行高 × 该系数」时视为同一段落
+const PARAGRAPH_GAP_FACTOR: f32 = 0.8;
+/// 相邻两行左边缘偏差不超过「行高 × 该系数」时视为左对齐
+const LEFT_ALIGN_FACTOR: f32 = 1.5;
+/// 同一行内相邻单词的间距小于「字高 × 该系数」时视为同一单元格（如 "New York"）
+const CELL_WORD_GAP_FACTOR: f32 = 1.0;
+
+/// 段落：由若干相邻且左对齐的行组成
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrParagraph {
+    /// 段落文本（行与行之间按需插入空格）
+    pub text: String,
+    /// 段落整体的边界框
+    pub bounds: BoundingBox,
+    /// 段落包含的行（自上而下）
+    pub lines: Vec<OcrLine>,
+}
+
+/// 把行合并为段落，并按阅读顺序（先按栏从左到右，栏内自上而下）排序
+pub fn group_into_paragraphs(lines: &[OcrLine]) -> Vec<OcrParagraph> {
+    let mut sorted: Vec<&OcrLine> = lines.iter().filter(|l| !l.text.is_empty()).collect();
+    sorted.sort_by(|a, b| a.bounds.y.total_cmp(&b.bounds.y));
+
+    // 1️⃣ 逐行尝试接到已有段落的末尾
+    let mut groups: Vec<Vec<&OcrLine>> = Vec::new();
+    for line in sorted {
+        let target = groups.iter_mut().rev().find(|group| {
+            let last = group.last().unwrap();
+            let height = last.bounds.height.max(line.bounds.height).max(1.0);
+            let gap = line.bounds.y - (last.bounds.y + last.bounds.height);
+            let left_delta = (line.bounds.x - last.bounds.x).abs();
+
+            gap >= -height * 0.5
+                && gap <= height * PARAGRAPH_GAP_FACTOR
+                && left_delta <= height * LEFT_ALIGN_FACTOR
+        });
+
+        match target {
+            Some(group) => group.push(line),
+            None => groups.push(vec![line]),
+        }
+    }
+
+    let mut paragraphs: Vec<OcrParagraph> = groups
+        .into_iter()
+        .map(|group| {
+            let mut text = String::new();
+            for line in &group {
+                if needs_space_between(&text, &line.text) {
+                    text.push(' ');
+                }
+                text.push_str(&line.text);
+            }
+            OcrParagraph {
+                text,
+                bounds: union_bounds(group.iter().map(|l| &l.bounds)),
+                lines: group.into_iter().cloned().collect(),
+            }
+        })
+        .collect();
+
+    sort_reading_order(&mut paragraphs);
+    paragraphs
+}
+
+/// 按阅读顺序把段落拼成文本，段落之间空一行
+pub fn reading_order_text(paragraphs: &[OcrParagraph]) -> String {
+    paragraphs
+        .iter()
+        .map(|p| p.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// 把行按阅读顺序排序（规则与段落相同：先按栏从左到右，栏内自上而下）
+pub fn sort_lines_reading_order(lines: &mut [OcrLine]) {
+    sort_by_columns(lines, |l| &l.bounds);
+}
+
+/// 阅读顺序：水平投影有重叠的段落归为同一栏，栏从左到右，栏内自上而下
+fn sort_reading_order(paragraphs: &mut [OcrParagraph]) {
+    sort_by_columns(paragraphs, |p| &p.bounds);
+}
+
+/// 按水平投影把元素聚成栏，栏从左到右，栏内自上而下
+fn sort_by_columns<T>(items: &mut [T], bounds_of: impl Fn(&T) -> &BoundingBox) {
+    // 合并水平区间得到栏
+    let mut columns: Vec<(f32, f32)> = Vec::new();
+    let mut ranges: Vec<(f32, f32)> = items
+        .iter()
+        .map(|item| {
+            let b = bounds_of(item);
+            (b.x, b.x + b.width)
+        })
+        .collect();
+    ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (left, right) in ranges {
+        match columns.last_mut() {
+            Some(col) if left < col.1 => col.1 = col.1.max(right),
+            _ => columns.push((left, right)),
+        }
+    }
+
+    let column_of = |b: &BoundingBox| {
+        columns
+            .iter()
+            .position(|&(left, right)| b.x >= left && b.x < right.max(left + 1.0))
+            .unwrap_or(0)
+    };
+
+    items.sort_by(|a, b| {
+        let (a, b) = (bounds_of(a), bounds_of(b));
+        column_of(a)
+            .cmp(&column_of(b))
+            .then(a.y.total_cmp(&b.y))
+    });
+}
+
+/// 表格中的一个文字片段（同一单元格内相邻单词合并而成）
+struct CellFragment {
+    bounds: BoundingBox,
+    text: String,
+}
+
+/// 把单词还原为表格
+///
+/// `lines` 为每个 OCR 行的 (边界框, 文字) 列表。同一行内间距很小的相邻单词先合并为一个片段；
+/// 片段按左边缘聚成列（与列首片段的 x 相差不超过 column_tolerance），
+/// 按上边缘聚成行（与行首片段的 y 相差不超过 column_tolerance / 2）。
+/// 结果按行自上而下、列从左到右排列，空单元格为空字符串；不足两列时返回空表
+pub fn extract_table(lines: &[Vec<(BoundingBox, &str)>], column_tolerance: f32) -> Vec<Vec<String>> {
+    let mut fragments: Vec<CellFragment> = Vec::new();
+    for words in lines {
+        let mut current: Option<CellFragment> = None;
+        for (bounds, text) in words {
+            if let Some(fragment) = current.as_mut() {
+                let gap = bounds.x - (fragment.bounds.x + fragment.bounds.width);
+                let height = fragment.bounds.height.max(bounds.height);
+                if gap <= height * CELL_WORD_GAP_FACTOR {
+                    if needs_space_between(&fragment.text, text) {
+                        fragment.text.push(' ');
+                    }
+                    fragment.text.push_str(text);
+                    fragment.bounds = union_bounds([&fragment.bounds, bounds].into_iter());
+                    continue;
+                }
+            }
+            fragments.extend(current.replace(CellFragment { bounds: bounds.clone(), text: text.to_string() }));
+        }
+        fragments.extend(current);
+    }
+
+    let columns = cluster_starts(fragments.iter().map(|f| f.bounds.x), column_tolerance);
+    if columns.len() < 2 {
+        return Vec::new();
+    }
+    let rows = cluster_starts(fragments.iter().map(|f| f.bounds.y), column_tolerance / 2.0);
+
+    let mut table = vec![vec![String::new(); columns.len()]; rows.len()];
+    // 同一单元格的多个片段按从上到下、从左到右拼接
+    fragments.sort_by(|a, b| a.bounds.y.total_cmp(&b.bounds.y).then(a.bounds.x.total_cmp(&b.bounds.x)));
+    for fragment in &fragments {
+        let row = bucket_of(&rows, fragment.bounds.y);
+        let column = bucket_of(&columns, fragment.bounds.x);
+        let cell = &mut table[row][column];
+        if !cell.is_empty() {
+            cell.push(' ');
+        }
+        cell.push_str(&fragment.text);
+    }
+    table
+}
+
+/// 一维聚类：排序后与当前簇起点相差超过 tolerance 时开启新簇，返回各簇起点（升序）
+fn cluster_starts(values: impl Iterator<Item = f32>, tolerance: f32) -> Vec<f32> {
+    let mut values: Vec<f32> = values.collect();
+    values.sort_by(f32::total_cmp);
+    let mut starts: Vec<f32> = Vec::new();
+    for v in values {
+        if starts.last().is_none_or(|&start| v - start > tolerance) {
+            starts.push(v);
+        }
+    }
+    starts
+}
+
+/// 值所属的簇：起点不大于该值的最后一个簇
+fn bucket_of(starts: &[f32], value: f32) -> usize {
+    starts.partition_point(|&start| start <= value).saturating_sub(1)
+}
+
+/// 两段拉丁文字拼接时需要空格，中日韩文字直接相连
+pub(crate) fn needs_space_between(prev: &str, next: &str) -> bool {
+    match (prev.chars().last(), next.chars().next()) {
+        (Some(a), Some(b)) => {
+            (a.is_ascii_alphanumeric() || a.is_ascii_punctuation()) && b.is_ascii_alphanumeric()
+        }
+        _ => false,
+    }
+}
+
+/// 计算多个边界框的外接矩形
+pub(crate) fn union_bounds<'a>(boxes: impl Iterator<Item = &'a BoundingBox>) -> BoundingBox {
+    let mut min_x = f32::MAX;
+    let mut min_y = f32::MAX;
+    let mut max_x = f32::MIN;
+    let mut max_y = f32::MIN;
+
+    for b in boxes {
+        min_x = min_x.min(b.x);
+        min_y = min_y.min(b.y);
+        max_x = max_x.max(b.x + b.width);
+        max_y = max_y.max(b.y + b.height);
+    }
+
+    if min_x > max_x {
+        return BoundingBox { x: 0.0, y: 0.0, width: 0.0, height: 0.0 };
+    }
+
+    BoundingBox {
+        x: min_x,
+        y: min_y,
+        width: max_x - min_x,
+        height: max_y - min_y,
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::sync::Mutex;
 use windows::{
     core::HSTRING,
     Globalization::Language,
@@ -16,6 +17,17 @@ pub use python::*;
 pub mod oneocr;
 mod oneocr_python;
 
+// OCR 前的图像预处理
+pub mod preprocess;
+pub use preprocess::PreprocessOptions;
+
+// 版面分析（段落 / 阅读顺序）
+pub mod layout;
+pub use layout::OcrParagraph;
+
+// 二维码 / 条码识别
+pub mod barcode;
+
 /// OCR 识别的文字行
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OcrLine {
@@ -25,6 +37,9 @@ pub struct OcrLine {
     pub bounds: BoundingBox,
     /// 该行包含的单词列表
     pub words: Vec<OcrWord>,
+    /// 行置信度（0.0~1.0），引擎不提供时为 None
+    #[serde(default)]
+    pub confidence: Option<f64>,
 }
 
 impl OcrLine {
@@ -72,6 +87,9 @@ pub struct OcrWord {
     pub text: String,
     /// 文字区域的边界框
     pub bounds: BoundingBox,
+    /// 单词置信度（0.0~1.0），引擎不提供时为 None
+    #[serde(default)]
+    pub confidence: Option<f64>,
 }
 
 /// 文字区域的边界框信息
@@ -96,31 +114,327 @@ pub struct OcrRecognitionResult {
     pub text: String,
     /// 文本角度（旋转角度）
     pub text_angle: Option<f64>,
+    /// 按阅读顺序排列的段落
+    #[serde(default)]
+    pub paragraphs: Vec<OcrParagraph>,
+}
+
+impl OcrRecognitionResult {
+    /// 按阅读顺序拼接的段落文本，段落之间空一行
+    pub fn reading_order_text(&self) -> String {
+        layout::reading_order_text(&self.paragraphs)
+    }
+
+    /// 把 `lines` 从引擎原始顺序改为阅读顺序（多栏版面不再交错），并据此重建 `text`
+    pub fn sort_lines_reading_order(&mut self) {
+        layout::sort_lines_reading_order(&mut self.lines);
+        self.text = self
+            .lines
+            .iter()
+            .map(|l| l.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string();
+    }
 }
 
 /// 从图片文件执行 OCR 识别
 /// 
 /// # 参数
 /// - `image_path` - 图片文件路径
-/// - `language` - 语言代码（如 "zh-Hans-CN", "en-US"），None 使用系统默认语言
+/// - `language` - 语言代码（如 "zh-Hans-CN", "en-US"），None 使用系统默认语言，
+///   `"auto"` 自动选择识别效果最好的已安装语言
 pub fn recognize_from_file(image_path: &str, language: Option<&str>) -> Result<OcrRecognitionResult, String> {
     let file_path = Path::new(image_path);
     if !file_path.exists() {
         return Err(format!("文件不存在: {}", image_path));
     }
     
-    recognize_internal(image_path, language)
-        .map_err(|e| format!("OCR 识别失败: {}", e))
+    recognize_with_language(language, |lang| {
+        recognize_internal(image_path, lang)
+            .map_err(|e| format!("OCR 识别失败: {}", e))
+    })
 }
 
 /// 从字节数组执行 OCR 识别
 /// 
 /// # 参数
 /// - `image_data` - 图片字节数据（支持 PNG、JPG、BMP 等格式）
-/// - `language` - 语言代码（如 "zh-Hans-CN", "en-US"），None 使用系统默认语言
+/// - `language` - 语言代码（如 "zh-Hans-CN", "en-US"），None 使用系统默认语言，
+///   `"auto"` 自动选择识别效果最好的已安装语言
 pub fn recognize_from_bytes(image_data: &[u8], language: Option<&str>) -> Result<OcrRecognitionResult, String> {
-    recognize_from_bytes_internal(image_data, language)
-        .map_err(|e| format!("OCR 识别失败: {}", e))
+    recognize_with_language(language, |lang| {
+        recognize_from_bytes_internal(image_data, lang)
+            .map_err(|e| format!("OCR 识别失败: {}", e))
+    })
+}
+
+/// 自动选择语言的标记值
+pub const AUTO_LANGUAGE: &str = "auto";
+
+/// 自动模式选中的语言，本进程内后续的自动识别直接复用
+static AUTO_SELECTED_LANGUAGE: Mutex<Option<String>> = Mutex::new(None);
+
+/// 按语言参数执行识别：普通语言先检查语言包，`"auto"` 交给自动选择
+fn recognize_with_language<F>(language: Option<&str>, recognize: F) -> Result<OcrRecognitionResult, String>
+where
+    F: Fn(Option<&str>) -> Result<OcrRecognitionResult, String>,
+{
+    match language {
+        Some(lang) if lang.eq_ignore_ascii_case(AUTO_LANGUAGE) => recognize_auto_language(recognize),
+        _ => {
+            ensure_language_available(language)?;
+            recognize(language)
+        }
+    }
+}
+
+/// 自动选择语言后识别
+///
+/// 已有缓存的语言时直接使用；否则依次用每个已安装的语言包识别，
+/// 取识别字符最多的结果（Windows.Media.Ocr 不提供置信度，只按字符数评分），
+/// 并缓存该语言。未识别出任何文字时不缓存，下次仍会重新选择。
+fn recognize_auto_language<F>(recognize: F) -> Result<OcrRecognitionResult, String>
+where
+    F: Fn(Option<&str>) -> Result<OcrRecognitionResult, String>,
+{
+    let cached = AUTO_SELECTED_LANGUAGE.lock().ok().and_then(|guard| guard.clone());
+    if let Some(lang) = cached {
+        return recognize(Some(&lang));
+    }
+
+    let mut best: Option<(usize, String, OcrRecognitionResult)> = None;
+    for lang in get_available_languages()? {
+        let result = recognize(Some(&lang))?;
+        let score = readability_score(&result);
+        if best.as_ref().is_none_or(|(best_score, _, _)| score > *best_score) {
+            best = Some((score, lang, result));
+        }
+    }
+
+    let (score, lang, result) = best.ok_or_else(|| "未安装任何 OCR 语言包".to_string())?;
+    if score > 0 {
+        if let Ok(mut guard) = AUTO_SELECTED_LANGUAGE.lock() {
+            *guard = Some(lang);
+        }
+    }
+    Ok(result)
+}
+
+/// 清除自动模式缓存的语言，下次 `"auto"` 识别时重新选择
+pub fn reset_auto_language() {
+    if let Ok(mut guard) = AUTO_SELECTED_LANGUAGE.lock() {
+        *guard = None;
+    }
+}
+
+/// 自动模式当前缓存的语言，尚未选择时返回 None
+pub fn auto_selected_language() -> Option<String> {
+    AUTO_SELECTED_LANGUAGE.lock().ok().and_then(|guard| guard.clone())
+}
+
+/// 从字节数组执行 OCR 识别，识别前先对图像做预处理
+/// 
+/// # 参数
+/// - `image_data` - 图片字节数据（支持 PNG、JPG、BMP 等格式）
+/// - `language` - 语言代码（如 "zh-Hans-CN", "en-US"），None 使用系统默认语言
+/// - `options` - 预处理选项（放大 / 灰度 / 二值化）
+/// 
+/// 返回的边界框已缩放回原图分辨率
+pub fn recognize_from_bytes_with_options(
+    image_data: &[u8],
+    language: Option<&str>,
+    options: &PreprocessOptions,
+) -> Result<OcrRecognitionResult, String> {
+    if options.is_noop() {
+        return recognize_from_bytes(image_data, language);
+    }
+
+    let processed = preprocess::preprocess_image(image_data, options)?;
+    let mut result = recognize_from_bytes(&processed, language)?;
+
+    if (options.upscale - 1.0).abs() >= f32::EPSILON {
+        scale_result_bounds(&mut result, 1.0 / options.upscale);
+    }
+
+    Ok(result)
+}
+
+/// 文本角度超过该值（度）时认为图片被旋转，需要尝试其他方向
+const AUTO_ROTATE_ANGLE_THRESHOLD: f64 = 10.0;
+
+/// 识别结果的可读性评分：识别出的非空白字符数
+fn readability_score(result: &OcrRecognitionResult) -> usize {
+    result.text.chars().filter(|c| !c.is_whitespace()).count()
+}
+
+/// 自动纠正旋转后执行 OCR 识别
+/// 
+/// 先按原图识别，若 `text_angle` 明显偏离 0 或未识别出任何文字，
+/// 再依次尝试顺时针旋转 90 / 180 / 270 度，返回识别字符最多的结果。
+/// 
+/// # 参数
+/// - `image_data` - 图片字节数据（支持 PNG、JPG、BMP 等格式）
+/// - `language` - 语言代码（如 "zh-Hans-CN", "en-US"），None 使用系统默认语言
+/// 
+/// 返回 (识别结果, 实际应用的顺时针旋转角度)，边界框为旋转后图像的坐标
+pub fn recognize_auto_rotate(
+    image_data: &[u8],
+    language: Option<&str>,
+) -> Result<(OcrRecognitionResult, u32), String> {
+    let original = recognize_from_bytes(image_data, language)?;
+
+    let angle_off = original
+        .text_angle
+        .map(|a| a.abs() > AUTO_ROTATE_ANGLE_THRESHOLD)
+        .unwrap_or(false);
+    if !angle_off && readability_score(&original) > 0 {
+        return Ok((original, 0));
+    }
+
+    let mut best_score = readability_score(&original);
+    let mut best = (original, 0);
+
+    for degrees in [90, 180, 270] {
+        let rotated = preprocess::rotate_image(image_data, degrees)?;
+        let result = recognize_from_bytes(&rotated, language)?;
+        let score = readability_score(&result);
+        if score > best_score {
+            best_score = score;
+            best = (result, degrees);
+        }
+    }
+
+    Ok(best)
+}
+
+/// 只识别图片中的矩形区域
+/// 
+/// # 参数
+/// - `image_data` - 图片字节数据（支持 PNG、JPG、BMP 等格式）
+/// - `x`, `y`, `width`, `height` - 区域在原图中的位置和尺寸，超出图片的部分会被截掉
+/// - `language` - 语言代码（如 "zh-Hans-CN", "en-US"），None 使用系统默认语言
+/// 
+/// 返回的边界框相对于区域左上角，加上 (x, y) 即为原图坐标
+pub fn recognize_region(
+    image_data: &[u8],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    language: Option<&str>,
+) -> Result<OcrRecognitionResult, String> {
+    let cropped = preprocess::crop_image(image_data, x, y, width, height)?;
+    recognize_from_bytes(&cropped, language)
+}
+
+/// 分块识别大图
+/// 
+/// Windows.Media.Ocr 对图片尺寸有上限（OcrEngine.MaxImageDimension），超大的图片
+/// 切成 tile_size × tile_size、相邻重叠 overlap 像素的块逐块识别，再按块的位置合并结果。
+/// overlap 应不小于一行文字的高度，避免文字被块边界切断后两边都识别不出来
+pub fn recognize_tiled(
+    image_data: &[u8],
+    tile_size: u32,
+    overlap: u32,
+    language: Option<&str>,
+) -> Result<OcrRecognitionResult, String> {
+    let tiles = preprocess::split_tiles(image_data, tile_size, overlap)?;
+    let mut parts = Vec::with_capacity(tiles.len());
+    for tile in tiles {
+        let result = recognize_from_bytes(&tile.data, language)
+            .map_err(|e| format!("分块 ({}, {}) {}", tile.x, tile.y, e))?;
+        parts.push((result, (tile.x as f32, tile.y as f32)));
+    }
+    Ok(merge_results(parts))
+}
+
+/// 分块识别时，两个单词的重叠面积超过较小者的该比例即视为同一个词
+const TILE_DUPLICATE_OVERLAP: f32 = 0.5;
+
+/// 合并分块识别的结果
+/// 
+/// 每块结果按对应的 (x, y) 偏移平移到原图坐标后合并；相邻块重叠区域里重复识别的单词
+/// （与已保留单词的重叠面积超过较小者的一半）只保留先出现的一份，单词全被去掉的行整体丢弃。
+/// 合并结果的行保持输入顺序，段落按合并后的行重新划分
+pub fn merge_results(parts: Vec<(OcrRecognitionResult, (f32, f32))>) -> OcrRecognitionResult {
+    let text_angle = parts.iter().find_map(|(result, _)| result.text_angle);
+    let mut kept: Vec<BoundingBox> = Vec::new();
+    let mut lines: Vec<OcrLine> = Vec::new();
+
+    for (result, (dx, dy)) in parts {
+        for mut line in result.lines {
+            let word_count = line.words.len();
+            line.words.retain_mut(|word| {
+                word.bounds.x += dx;
+                word.bounds.y += dy;
+                if kept.iter().any(|other| overlap_ratio(&word.bounds, other) > TILE_DUPLICATE_OVERLAP) {
+                    return false;
+                }
+                kept.push(word.bounds.clone());
+                true
+            });
+            line.bounds.x += dx;
+            line.bounds.y += dy;
+
+            if line.words.is_empty() && word_count > 0 {
+                continue;
+            }
+            if line.words.len() < word_count {
+                // 去掉了部分单词：按剩余单词重建文本和边界框
+                let mut text = String::new();
+                for word in &line.words {
+                    if layout::needs_space_between(&text, &word.text) {
+                        text.push(' ');
+                    }
+                    text.push_str(&word.text);
+                }
+                line.text = text;
+                line.bounds = layout::union_bounds(line.words.iter().map(|w| &w.bounds));
+            }
+            lines.push(line);
+        }
+    }
+
+    let text = lines
+        .iter()
+        .map(|l| l.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string();
+    let paragraphs = layout::group_into_paragraphs(&lines);
+    OcrRecognitionResult { lines, text, text_angle, paragraphs }
+}
+
+/// 两个边界框的交集面积占较小者面积的比例
+fn overlap_ratio(a: &BoundingBox, b: &BoundingBox) -> f32 {
+    let width = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+    let height = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+    let smaller = (a.width * a.height).min(b.width * b.height);
+    if width <= 0.0 || height <= 0.0 || smaller <= 0.0 {
+        return 0.0;
+    }
+    width * height / smaller
+}
+
+/// 按比例缩放结果中所有边界框
+fn scale_result_bounds(result: &mut OcrRecognitionResult, factor: f32) {
+    fn scale(bounds: &mut BoundingBox, factor: f32) {
+        bounds.x *= factor;
+        bounds.y *= factor;
+        bounds.width *= factor;
+        bounds.height *= factor;
+    }
+
+    for line in &mut result.lines {
+        scale(&mut line.bounds, factor);
+        for word in &mut line.words {
+            scale(&mut word.bounds, factor);
+        }
+    }
+    result.paragraphs = layout::group_into_paragraphs(&result.lines);
 }
 
 fn recognize_from_bytes_internal(image_data: &[u8], language: Option<&str>) -> windows::core::Result<OcrRecognitionResult> {
@@ -206,9 +520,11 @@ fn convert_ocr_result(win_result: &WinOcrResult) -> windows::core::Result<OcrRec
             max_x = max_x.max(rect.X + rect.Width);
             max_y = max_y.max(rect.Y + rect.Height);
 
+            // Windows.Media.Ocr 不提供置信度，保持 None 而不是伪造数值
             words.push(OcrWord {
                 text: word_text,
                 bounds: word_bounds,
+                confidence: None,
             });
         }
 
@@ -236,6 +552,7 @@ fn convert_ocr_result(win_result: &WinOcrResult) -> windows::core::Result<OcrRec
             text: line_text,
             bounds: line_bounds,
             words,
+            confidence: None,
         });
     }
 
@@ -243,10 +560,13 @@ fn convert_ocr_result(win_result: &WinOcrResult) -> windows::core::Result<OcrRec
         .ok()
         .and_then(|a| a.Value().ok());
 
+    let paragraphs = layout::group_into_paragraphs(&lines);
+
     Ok(OcrRecognitionResult {
         lines,
         text: full_text.trim().to_string(),
         text_angle,
+        paragraphs,
     })
 }
 
@@ -269,3 +589,38 @@ fn get_available_languages_internal() -> windows::core::Result<Vec<String>> {
     
     Ok(result)
 }
+
+/// 检查指定语言的 OCR 语言包是否已安装
+/// 
+/// 语言代码大小写不敏感；只给出主语言（如 "en"）时，匹配任意 "en-*" 语言包
+pub fn is_language_available(language: &str) -> bool {
+    get_available_languages()
+        .map(|langs| language_matches(&langs, language))
+        .unwrap_or(false)
+}
+
+fn language_matches(available: &[String], language: &str) -> bool {
+    let wanted = language.to_ascii_lowercase();
+    available.iter().any(|tag| {
+        let tag = tag.to_ascii_lowercase();
+        tag == wanted || tag.starts_with(&format!("{}-", wanted))
+    })
+}
+
+/// 识别前检查语言包，未安装时返回明确的错误信息
+fn ensure_language_available(language: Option<&str>) -> Result<(), String> {
+    let Some(lang) = language else {
+        return Ok(());
+    };
+
+    let available = get_available_languages()?;
+    if language_matches(&available, lang) {
+        Ok(())
+    } else {
+        Err(format!(
+            "language '{}' not installed; available: {}",
+            lang,
+            available.join(", ")
+        ))
+    }
+}
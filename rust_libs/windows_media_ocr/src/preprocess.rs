@@ -0,0 +1,424 @@
+//! OCR 前的图像预处理
+//!
+//! 截图中的小号 UI 文字、低对比度文字在 Windows OCR 下识别率较低，
+//! 在交给引擎之前先做放大 / 对比度增强 / 锐化 / 灰度化 / 二值化可以明显改善效果。
+
+use image::{imageops::FilterType, DynamicImage, GrayImage, ImageFormat, Rgba, RgbaImage};
+use std::io::Cursor;
+
+/// 预处理选项
+#[derive(Debug, Clone, Copy)]
+pub struct PreprocessOptions {
+    /// 放大倍数（Lanczos3 插值），1.0 表示不缩放
+    pub upscale: f32,
+    /// 是否转为灰度图
+    pub grayscale: bool,
+    /// 是否使用 Otsu 阈值二值化（隐含灰度化）
+    pub binarize: bool,
+    /// 是否做 CLAHE 局部直方图均衡化增强对比度（隐含灰度化）
+    pub enhance_contrast: bool,
+    /// 是否做 USM 锐化
+    pub sharpen: bool,
+}
+
+impl Default for PreprocessOptions {
+    fn default() -> Self {
+        Self {
+            upscale: 1.0,
+            grayscale: false,
+            binarize: false,
+            enhance_contrast: false,
+            sharpen: false,
+        }
+    }
+}
+
+impl PreprocessOptions {
+    /// 是否不需要任何预处理
+    pub fn is_noop(&self) -> bool {
+        (self.upscale - 1.0).abs() < f32::EPSILON
+            && !self.grayscale
+            && !self.binarize
+            && !self.enhance_contrast
+            && !self.sharpen
+    }
+
+    /// 按名称开启一种预处理：'enhance_contrast' / 'grayscale' / 'binarize' / 'sharpen'
+    pub fn apply_preset(&mut self, name: &str) -> Result<(), String> {
+        match name {
+            "enhance_contrast" => self.enhance_contrast = true,
+            "grayscale" => self.grayscale = true,
+            "binarize" => self.binarize = true,
+            "sharpen" => self.sharpen = true,
+            other => {
+                return Err(format!(
+                    "未知的预处理方式 '{}'，可选: enhance_contrast, grayscale, binarize, sharpen",
+                    other
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// USM 锐化的高斯模糊半径
+const SHARPEN_SIGMA: f32 = 1.0;
+/// USM 锐化的差值阈值（低于此值的细节不增强，避免放大噪点）
+const SHARPEN_THRESHOLD: i32 = 2;
+/// CLAHE 每个方向的分块数
+const CLAHE_TILES: u32 = 8;
+/// CLAHE 对比度限制：直方图每个灰度级的计数上限为平均值的倍数
+const CLAHE_CLIP_LIMIT: f32 = 2.0;
+/// 倾斜校正搜索的最大角度（度），在 ±该值范围内按 DESKEW_STEP 逐个尝试
+const DESKEW_MAX_ANGLE: i32 = 10;
+/// 倾斜校正搜索的角度步长（度）
+const DESKEW_STEP: f32 = 1.0;
+/// 估算倾斜角时先把图片长边缩到该尺寸以内（只影响估算速度，校正仍在原图上进行）
+const DESKEW_ANALYSIS_SIZE: u32 = 1000;
+
+/// 对图片字节执行预处理，返回重新编码后的 PNG 字节
+pub fn preprocess_image(image_data: &[u8], options: &PreprocessOptions) -> Result<Vec<u8>, String> {
+    if !options.upscale.is_finite() || options.upscale <= 0.0 {
+        return Err(format!("无效的放大倍数: {}", options.upscale));
+    }
+
+    let mut img = image::load_from_memory(image_data)
+        .map_err(|e| format!("图像解码失败: {}", e))?;
+
+    if (options.upscale - 1.0).abs() >= f32::EPSILON {
+        let width = ((img.width() as f32 * options.upscale).round() as u32).max(1);
+        let height = ((img.height() as f32 * options.upscale).round() as u32).max(1);
+        img = img.resize_exact(width, height, FilterType::Lanczos3);
+    }
+
+    if options.enhance_contrast {
+        img = DynamicImage::ImageLuma8(clahe(&img.to_luma8()));
+    }
+
+    if options.sharpen {
+        img = img.unsharpen(SHARPEN_SIGMA, SHARPEN_THRESHOLD);
+    }
+
+    if options.binarize {
+        let mut gray = img.to_luma8();
+        let threshold = otsu_threshold(&gray);
+        for p in gray.pixels_mut() {
+            p.0[0] = if p.0[0] > threshold { 255 } else { 0 };
+        }
+        img = DynamicImage::ImageLuma8(gray);
+    } else if options.grayscale {
+        img = DynamicImage::ImageLuma8(img.to_luma8());
+    }
+
+    let mut buf = Vec::new();
+    img.write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)
+        .map_err(|e| format!("图像编码失败: {}", e))?;
+    Ok(buf)
+}
+
+/// 将图片按顺时针旋转指定角度（仅支持 0 / 90 / 180 / 270），返回 PNG 字节
+pub fn rotate_image(image_data: &[u8], degrees: u32) -> Result<Vec<u8>, String> {
+    let img = image::load_from_memory(image_data)
+        .map_err(|e| format!("图像解码失败: {}", e))?;
+
+    let rotated = match degrees % 360 {
+        0 => img,
+        90 => img.rotate90(),
+        180 => img.rotate180(),
+        270 => img.rotate270(),
+        other => return Err(format!("不支持的旋转角度: {}", other)),
+    };
+
+    let mut buf = Vec::new();
+    rotated
+        .write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)
+        .map_err(|e| format!("图像编码失败: {}", e))?;
+    Ok(buf)
+}
+
+/// 校正文字倾斜（扫描件、斜拍的照片），返回 PNG 字节
+///
+/// 在 ±DESKEW_MAX_ANGLE 度范围内逐个角度计算文字像素的水平投影，
+/// 文字行与水平方向对齐时各行的投影差异最大（行内密集、行间空白），取投影方差最大的角度，
+/// 再把图片反向旋转该角度（画布尺寸不变，露出的角落用左上角像素的颜色填充）。
+/// 没有检测到倾斜时原样返回输入数据
+pub fn deskew_image(image_data: &[u8]) -> Result<Vec<u8>, String> {
+    let img = image::load_from_memory(image_data)
+        .map_err(|e| format!("图像解码失败: {}", e))?;
+
+    let angle = estimate_skew_angle(&img.thumbnail(DESKEW_ANALYSIS_SIZE, DESKEW_ANALYSIS_SIZE).to_luma8());
+    if angle == 0.0 {
+        return Ok(image_data.to_vec());
+    }
+
+    let rotated = rotate_by_angle(&img.to_rgba8(), angle);
+    let mut buf = Vec::new();
+    rotated
+        .write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)
+        .map_err(|e| format!("图像编码失败: {}", e))?;
+    Ok(buf)
+}
+
+/// 估算文字的倾斜角度（度，顺时针为正）
+///
+/// Otsu 阈值分出的少数一侧视为文字像素；对每个候选角度把文字像素投影到旋转后的行上，
+/// 返回行投影方差最大的角度，方差相同时取绝对值更小的角度
+fn estimate_skew_angle(gray: &GrayImage) -> f32 {
+    let threshold = otsu_threshold(gray);
+    let dark = gray.pixels().filter(|p| p.0[0] <= threshold).count();
+    let text_is_dark = dark * 2 <= gray.pixels().len();
+    let points: Vec<(f32, f32)> = gray
+        .enumerate_pixels()
+        .filter(|(_, _, p)| (p.0[0] <= threshold) == text_is_dark)
+        .map(|(x, y, _)| (x as f32, y as f32))
+        .collect();
+    if points.is_empty() {
+        return 0.0;
+    }
+
+    let (width, height) = (gray.width() as f32, gray.height() as f32);
+    let rows = (width + height).ceil() as usize + 1;
+    let mut best = (0.0f32, f64::MIN);
+    for step in 0..=DESKEW_MAX_ANGLE * 2 {
+        // 从 0 度向两侧交替尝试，方差相同时保留更小的角度
+        let offset = (step + 1) / 2 * if step % 2 == 0 { 1 } else { -1 };
+        let angle = offset as f32 * DESKEW_STEP;
+        let (sin, cos) = angle.to_radians().sin_cos();
+
+        let mut profile = vec![0u32; rows];
+        for &(x, y) in &points {
+            // 旋转后所在的行，加上 width 保证非负
+            let row = (y * cos - x * sin + width).round() as usize;
+            profile[row.min(rows - 1)] += 1;
+        }
+        let mean = points.len() as f64 / rows as f64;
+        let variance = profile.iter().map(|&c| (c as f64 - mean).powi(2)).sum::<f64>() / rows as f64;
+        if variance > best.1 {
+            best = (angle, variance);
+        }
+    }
+    best.0
+}
+
+/// 把图片逆时针旋转 angle 度（抵消顺时针 angle 度的倾斜），画布尺寸不变，双线性插值
+fn rotate_by_angle(img: &RgbaImage, angle: f32) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    let fill = img.get_pixel(0, 0).to_owned();
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        // 输出像素对应到倾斜原图中的位置
+        let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+        let sx = dx * cos - dy * sin + cx - 0.5;
+        let sy = dx * sin + dy * cos + cy - 0.5;
+        if sx < 0.0 || sy < 0.0 || sx > (width - 1) as f32 || sy > (height - 1) as f32 {
+            return fill;
+        }
+
+        let (x0, y0) = (sx.floor() as u32, sy.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+        let (fx, fy) = (sx - x0 as f32, sy - y0 as f32);
+        let (p00, p10) = (img.get_pixel(x0, y0).0, img.get_pixel(x1, y0).0);
+        let (p01, p11) = (img.get_pixel(x0, y1).0, img.get_pixel(x1, y1).0);
+        let mut out = [0u8; 4];
+        for c in 0..4 {
+            let top = p00[c] as f32 * (1.0 - fx) + p10[c] as f32 * fx;
+            let bottom = p01[c] as f32 * (1.0 - fx) + p11[c] as f32 * fx;
+            out[c] = (top * (1.0 - fy) + bottom * fy).round() as u8;
+        }
+        Rgba(out)
+    })
+}
+
+/// 裁剪出图片中的矩形区域，返回 PNG 字节
+///
+/// 超出图片范围的部分会被截掉；截完后区域为空时返回错误
+pub fn crop_image(image_data: &[u8], x: u32, y: u32, width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let img = image::load_from_memory(image_data)
+        .map_err(|e| format!("图像解码失败: {}", e))?;
+
+    let width = width.min(img.width().saturating_sub(x));
+    let height = height.min(img.height().saturating_sub(y));
+    if width == 0 || height == 0 {
+        return Err(format!(
+            "裁剪区域超出图片范围: ({}, {}) 图片尺寸 {}x{}",
+            x, y, img.width(), img.height()
+        ));
+    }
+
+    let cropped = image::imageops::crop_imm(&img, x, y, width, height).to_image();
+    let mut buf = Vec::new();
+    cropped
+        .write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)
+        .map_err(|e| format!("图像编码失败: {}", e))?;
+    Ok(buf)
+}
+
+/// 分块识别的一块图片
+pub struct Tile {
+    /// 块左上角在原图中的 X 坐标
+    pub x: u32,
+    /// 块左上角在原图中的 Y 坐标
+    pub y: u32,
+    /// 块的 PNG 字节
+    pub data: Vec<u8>,
+}
+
+/// 把图片切成 tile_size × tile_size 的块（相邻块重叠 overlap 像素）
+///
+/// 最后一行/列的块贴着图片边缘放置，不会超出图片；图片小于块大小时只有一块
+pub fn split_tiles(image_data: &[u8], tile_size: u32, overlap: u32) -> Result<Vec<Tile>, String> {
+    if tile_size == 0 || overlap >= tile_size {
+        return Err(format!("无效的分块参数: tile_size={}, overlap={}", tile_size, overlap));
+    }
+    let img = image::load_from_memory(image_data)
+        .map_err(|e| format!("图像解码失败: {}", e))?;
+
+    let (width, height) = (img.width(), img.height());
+    let mut tiles = Vec::new();
+    for y in tile_origins(height, tile_size, overlap) {
+        for x in tile_origins(width, tile_size, overlap) {
+            let tile = image::imageops::crop_imm(&img, x, y, tile_size.min(width), tile_size.min(height)).to_image();
+            let mut data = Vec::new();
+            tile.write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+                .map_err(|e| format!("图像编码失败: {}", e))?;
+            tiles.push(Tile { x, y, data });
+        }
+    }
+    Ok(tiles)
+}
+
+/// 一个方向上各块的起点：步长 tile_size - overlap，最后一块对齐到末端
+fn tile_origins(length: u32, tile_size: u32, overlap: u32) -> Vec<u32> {
+    if length <= tile_size {
+        return vec![0];
+    }
+    let last = length - tile_size;
+    let mut origins: Vec<u32> = (0..last).step_by((tile_size - overlap) as usize).collect();
+    origins.push(last);
+    origins
+}
+
+/// CLAHE（限制对比度的自适应直方图均衡化）
+///
+/// 图像划分为 CLAHE_TILES × CLAHE_TILES 块，每块的直方图按 CLAHE_CLIP_LIMIT 截断、
+/// 多出的计数平均分配后生成映射表，像素值在相邻四块的映射结果之间双线性插值
+fn clahe(gray: &GrayImage) -> GrayImage {
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return gray.clone();
+    }
+
+    let tiles_x = CLAHE_TILES.min(width);
+    let tiles_y = CLAHE_TILES.min(height);
+    let tile_w = width.div_ceil(tiles_x);
+    let tile_h = height.div_ceil(tiles_y);
+
+    // 每块的灰度映射表
+    let mut luts = vec![[0u8; 256]; (tiles_x * tiles_y) as usize];
+    for ty in 0..tiles_y {
+        for tx in 0..tiles_x {
+            let x0 = tx * tile_w;
+            let y0 = ty * tile_h;
+            let x1 = (x0 + tile_w).min(width);
+            let y1 = (y0 + tile_h).min(height);
+
+            let mut histogram = [0u32; 256];
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    histogram[gray.get_pixel(x, y).0[0] as usize] += 1;
+                }
+            }
+
+            let count = (x1 - x0) * (y1 - y0);
+            let clip = ((CLAHE_CLIP_LIMIT * count as f32 / 256.0) as u32).max(1);
+            let mut excess = 0u32;
+            for bin in histogram.iter_mut() {
+                if *bin > clip {
+                    excess += *bin - clip;
+                    *bin = clip;
+                }
+            }
+            let bonus = excess / 256;
+            for bin in histogram.iter_mut() {
+                *bin += bonus;
+            }
+
+            let total: u32 = histogram.iter().sum();
+            let lut = &mut luts[(ty * tiles_x + tx) as usize];
+            let mut cdf = 0u32;
+            for (v, &bin) in histogram.iter().enumerate() {
+                cdf += bin;
+                lut[v] = ((cdf as f32 / total.max(1) as f32) * 255.0).round() as u8;
+            }
+        }
+    }
+
+    // 像素所在位置相对块中心的坐标：(左/上块序号, 右/下块序号, 插值权重)
+    let locate = |pos: u32, tile: u32, tiles: u32| {
+        let f = (pos as f32 + 0.5) / tile as f32 - 0.5;
+        let lo = f.floor().clamp(0.0, (tiles - 1) as f32) as u32;
+        let hi = (lo + 1).min(tiles - 1);
+        let t = (f - lo as f32).clamp(0.0, 1.0);
+        (lo, hi, t)
+    };
+
+    GrayImage::from_fn(width, height, |x, y| {
+        let v = gray.get_pixel(x, y).0[0] as usize;
+        let (x_lo, x_hi, tx) = locate(x, tile_w, tiles_x);
+        let (y_lo, y_hi, ty) = locate(y, tile_h, tiles_y);
+        let map = |bx: u32, by: u32| luts[(by * tiles_x + bx) as usize][v] as f32;
+
+        let top = map(x_lo, y_lo) * (1.0 - tx) + map(x_hi, y_lo) * tx;
+        let bottom = map(x_lo, y_hi) * (1.0 - tx) + map(x_hi, y_hi) * tx;
+        image::Luma([(top * (1.0 - ty) + bottom * ty).round() as u8])
+    })
+}
+
+/// Otsu 法计算全局二值化阈值（最大化类间方差）
+fn otsu_threshold(gray: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for p in gray.pixels() {
+        histogram[p.0[0] as usize] += 1;
+    }
+
+    let total = gray.pixels().len() as f64;
+    if total == 0.0 {
+        return 127;
+    }
+
+    let sum_all: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| i as f64 * c as f64)
+        .sum();
+
+    let mut sum_bg = 0.0;
+    let mut weight_bg = 0.0;
+    let mut best_variance = 0.0;
+    let mut threshold = 127u8;
+
+    for (t, &count) in histogram.iter().enumerate() {
+        weight_bg += count as f64;
+        if weight_bg == 0.0 {
+            continue;
+        }
+        let weight_fg = total - weight_bg;
+        if weight_fg == 0.0 {
+            break;
+        }
+
+        sum_bg += t as f64 * count as f64;
+        let mean_bg = sum_bg / weight_bg;
+        let mean_fg = (sum_all - sum_bg) / weight_fg;
+
+        let variance = weight_bg * weight_fg * (mean_bg - mean_fg).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            threshold = t as u8;
+        }
+    }
+
+    threshold
+}
@@ -1,9 +1,10 @@
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
+use pyo3::types::{PyBytes, PyDict, PyType};
+use serde::{Deserialize, Serialize};
 
 /// Python 版本的边界框
 #[pyclass]
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PyBoundingBox {
     #[pyo3(get)]
     pub x: f32,
@@ -30,28 +31,73 @@ impl PyBoundingBox {
         dict.set_item("height", self.height)?;
         Ok(dict.into())
     }
+    
+    /// 同时包含两个边界框的最小边界框
+    fn union(&self, other: &PyBoundingBox) -> PyBoundingBox {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        PyBoundingBox {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+    
+    /// 与另一个边界框的交集
+    /// 
+    /// Returns:
+    ///     Optional[BoundingBox]: 两者不重叠（交集面积为 0）时返回 None
+    fn intersection(&self, other: &PyBoundingBox) -> Option<PyBoundingBox> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        (right > x && bottom > y).then_some(PyBoundingBox {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        })
+    }
+    
+    /// 点是否在边界框内（含左/上边缘，不含右/下边缘）
+    fn contains_point(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
 }
 
 /// Python 版本的单词
 #[pyclass]
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PyOcrWord {
     #[pyo3(get)]
     pub text: String,
     #[pyo3(get)]
     pub bounds: PyBoundingBox,
+    /// 置信度（0.0~1.0），引擎不提供时为 None
+    #[pyo3(get)]
+    pub confidence: Option<f64>,
 }
 
 #[pymethods]
 impl PyOcrWord {
     fn __repr__(&self) -> String {
-        format!("OcrWord(text='{}', bounds={})", self.text, self.bounds.__repr__())
+        format!("OcrWord(text='{}', bounds={}, confidence={:?})", self.text, self.bounds.__repr__(), self.confidence)
+    }
+    
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Py<PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("text", &self.text)?;
+        dict.set_item("bounds", self.bounds.to_dict(py)?)?;
+        dict.set_item("confidence", self.confidence)?;
+        Ok(dict.into())
     }
 }
 
 /// Python 版本的行
 #[pyclass]
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PyOcrLine {
     #[pyo3(get)]
     pub text: String,
@@ -59,18 +105,65 @@ pub struct PyOcrLine {
     pub bounds: PyBoundingBox,
     #[pyo3(get)]
     pub words: Vec<PyOcrWord>,
+    /// 置信度（0.0~1.0），引擎不提供时为 None
+    #[pyo3(get)]
+    pub confidence: Option<f64>,
 }
 
 #[pymethods]
 impl PyOcrLine {
     fn __repr__(&self) -> String {
-        format!("OcrLine(text='{}', words={})", self.text, self.words.len())
+        format!("OcrLine(text='{}', words={}, confidence={:?})", self.text, self.words.len(), self.confidence)
+    }
+    
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Py<PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("text", &self.text)?;
+        dict.set_item("bounds", self.bounds.to_dict(py)?)?;
+        dict.set_item("confidence", self.confidence)?;
+        
+        let words_list = self.words.iter()
+            .map(|word| word.to_dict(py))
+            .collect::<PyResult<Vec<_>>>()?;
+        dict.set_item("words", words_list)?;
+        Ok(dict.into())
+    }
+}
+
+/// Python 版本的段落
+#[pyclass]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PyOcrParagraph {
+    #[pyo3(get)]
+    pub text: String,
+    #[pyo3(get)]
+    pub bounds: PyBoundingBox,
+    #[pyo3(get)]
+    pub lines: Vec<PyOcrLine>,
+}
+
+#[pymethods]
+impl PyOcrParagraph {
+    fn __repr__(&self) -> String {
+        format!("OcrParagraph(text='{}', lines={})", self.text, self.lines.len())
+    }
+    
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Py<PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("text", &self.text)?;
+        dict.set_item("bounds", self.bounds.to_dict(py)?)?;
+        
+        let lines_list = self.lines.iter()
+            .map(|line| line.to_dict(py))
+            .collect::<PyResult<Vec<_>>>()?;
+        dict.set_item("lines", lines_list)?;
+        Ok(dict.into())
     }
 }
 
 /// Python 版本的 OCR 结果
 #[pyclass]
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PyOcrResult {
     #[pyo3(get)]
     pub text: String,
@@ -78,12 +171,209 @@ pub struct PyOcrResult {
     pub lines: Vec<PyOcrLine>,
     #[pyo3(get)]
     pub text_angle: Option<f64>,
+    /// 按阅读顺序排列的段落
+    #[pyo3(get)]
+    pub paragraphs: Vec<PyOcrParagraph>,
+    /// 识别区域在原图中的偏移（recognize_region 以外均为 0），边界框加上偏移即为原图坐标
+    #[pyo3(get)]
+    #[serde(default)]
+    pub offset_x: f32,
+    #[pyo3(get)]
+    #[serde(default)]
+    pub offset_y: f32,
 }
 
 #[pymethods]
 impl PyOcrResult {
     fn __repr__(&self) -> String {
-        format!("OcrResult(lines={}, text_angle={:?})", self.lines.len(), self.text_angle)
+        format!("OcrResult(lines={}, paragraphs={}, text_angle={:?}, offset=({}, {}))",
+            self.lines.len(), self.paragraphs.len(), self.text_angle, self.offset_x, self.offset_y)
+    }
+    
+    /// 按阅读顺序拼接的段落文本，段落之间空一行
+    #[getter]
+    fn reading_order_text(&self) -> String {
+        self.paragraphs.iter()
+            .map(|p| p.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+    
+    /// 按位置重新排列行：自上而下，同一水平行内（垂直中心落在该行范围内）从左到右
+    /// 
+    /// Returns:
+    ///     OcrResult: 新的结果，text 按排序后的行重新拼接
+    fn sort_lines_by_position(&self) -> PyOcrResult {
+        let mut lines = self.lines.clone();
+        lines.sort_by(|a, b| a.bounds.y.total_cmp(&b.bounds.y));
+        
+        // 垂直中心落在当前行范围内的归为同一水平行，行内按 x 排序
+        let mut rows: Vec<Vec<PyOcrLine>> = Vec::new();
+        for line in lines {
+            let center = line.bounds.y + line.bounds.height / 2.0;
+            match rows.last_mut() {
+                Some(row) if row.iter().any(|l| center >= l.bounds.y && center <= l.bounds.y + l.bounds.height) => {
+                    row.push(line);
+                }
+                _ => rows.push(vec![line]),
+            }
+        }
+        for row in &mut rows {
+            row.sort_by(|a, b| a.bounds.x.total_cmp(&b.bounds.x));
+        }
+        
+        self.with_lines(rows.into_iter().flatten().collect())
+    }
+    
+    /// 只按 y 坐标重新排列行（比 sort_lines_by_position 更快，同一水平行内的顺序不保证）
+    /// 
+    /// Returns:
+    ///     OcrResult: 新的结果，text 按排序后的行重新拼接
+    fn normalize_line_order(&self) -> PyOcrResult {
+        let mut lines = self.lines.clone();
+        lines.sort_by(|a, b| a.bounds.y.total_cmp(&b.bounds.y));
+        self.with_lines(lines)
+    }
+    
+    /// 按垂直间距把行分块（用于段落切分）
+    /// 
+    /// 行按 y 排序后依次比较：与上一行底边（y + height）的间距超过 vertical_gap 时开始新的一块
+    /// 
+    /// Args:
+    ///     vertical_gap: 允许的最大行间距（像素）
+    /// 
+    /// Returns:
+    ///     List[List[OcrLine]]: 自上而下的各块，块内的行自上而下
+    fn group_into_blocks(&self, vertical_gap: f32) -> Vec<Vec<PyOcrLine>> {
+        let mut lines = self.lines.clone();
+        lines.sort_by(|a, b| a.bounds.y.total_cmp(&b.bounds.y));
+        
+        let mut blocks: Vec<Vec<PyOcrLine>> = Vec::new();
+        for line in lines {
+            match blocks.last_mut() {
+                Some(block) if block.last().is_some_and(|prev| {
+                    line.bounds.y - (prev.bounds.y + prev.bounds.height) <= vertical_gap
+                }) => block.push(line),
+                _ => blocks.push(vec![line]),
+            }
+        }
+        blocks
+    }
+    
+    /// 与矩形区域重叠的单词（坐标与单词边界框相同，recognize_region 的结果不含偏移）
+    /// 
+    /// Args:
+    ///     x: 区域左上角 x 坐标
+    ///     y: 区域左上角 y 坐标
+    ///     w: 区域宽度
+    ///     h: 区域高度
+    /// 
+    /// Returns:
+    ///     List[OcrWord]: 按行顺序排列的单词
+    fn words_in_region(&self, x: f32, y: f32, w: f32, h: f32) -> Vec<PyOcrWord> {
+        let region = PyBoundingBox { x, y, width: w, height: h };
+        self.lines.iter()
+            .flat_map(|line| &line.words)
+            .filter(|word| word.bounds.intersection(&region).is_some())
+            .cloned()
+            .collect()
+    }
+    
+    /// 矩形区域内的文字：命中的单词按行自上而下、行内从左到右拼接，行之间换行
+    /// 
+    /// Args:
+    ///     x: 区域左上角 x 坐标
+    ///     y: 区域左上角 y 坐标
+    ///     w: 区域宽度
+    ///     h: 区域高度
+    /// 
+    /// Returns:
+    ///     str: 区域内的文字，没有命中时为空字符串
+    fn text_in_region(&self, x: f32, y: f32, w: f32, h: f32) -> String {
+        let region = PyBoundingBox { x, y, width: w, height: h };
+        let mut lines: Vec<(f32, Vec<&PyOcrWord>)> = self.lines.iter()
+            .map(|line| {
+                let mut words: Vec<&PyOcrWord> = line.words.iter()
+                    .filter(|word| word.bounds.intersection(&region).is_some())
+                    .collect();
+                words.sort_by(|a, b| a.bounds.x.total_cmp(&b.bounds.x));
+                (line.bounds.y, words)
+            })
+            .filter(|(_, words)| !words.is_empty())
+            .collect();
+        lines.sort_by(|a, b| a.0.total_cmp(&b.0));
+        
+        lines.iter()
+            .map(|(_, words)| {
+                let mut text = String::new();
+                for word in words {
+                    if crate::layout::needs_space_between(&text, &word.text) {
+                        text.push(' ');
+                    }
+                    text.push_str(&word.text);
+                }
+                text
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+    
+    /// 查找包含关键词的单词（不区分大小写），用于在截图上高亮
+    /// 
+    /// Args:
+    ///     keyword: 关键词
+    /// 
+    /// Returns:
+    ///     List[BoundingBox]: 命中单词的边界框，关键词为空时返回空列表
+    fn highlight_keyword(&self, keyword: &str) -> Vec<PyBoundingBox> {
+        let keyword = keyword.trim().to_lowercase();
+        if keyword.is_empty() {
+            return Vec::new();
+        }
+        self.lines.iter()
+            .flat_map(|line| &line.words)
+            .filter(|word| word.text.to_lowercase().contains(&keyword))
+            .map(|word| word.bounds.clone())
+            .collect()
+    }
+    
+    /// 把识别结果还原为表格
+    /// 
+    /// 同一行内间距很小的相邻单词视为同一单元格；左边缘相差不超过 column_tolerance 的单元格归为同一列，
+    /// 上边缘相差不超过 column_tolerance / 2 的归为同一行
+    /// 
+    /// Args:
+    ///     column_tolerance: 列对齐容差（像素）
+    /// 
+    /// Returns:
+    ///     List[List[str]]: 按行、列排列的单元格文字，空单元格为 ""；不足两列时返回空列表
+    fn extract_table(&self, column_tolerance: f32) -> PyResult<Vec<Vec<String>>> {
+        if !column_tolerance.is_finite() || column_tolerance < 0.0 {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("无效的列容差: {}", column_tolerance)));
+        }
+        let lines: Vec<Vec<(crate::BoundingBox, &str)>> = self.lines.iter()
+            .map(|line| line.words.iter()
+                .map(|word| (to_crate_bounds(&word.bounds), word.text.as_str()))
+                .collect())
+            .collect();
+        Ok(crate::layout::extract_table(&lines, column_tolerance))
+    }
+    
+    /// 序列化为 JSON 字符串（直接在 Rust 中完成，比 to_dict 再 json.dumps 快）
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(self)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("序列化失败: {}", e)))
+    }
+    
+    /// 从 to_json 生成的 JSON 字符串还原结果
+    /// 
+    /// Raises:
+    ///     ValueError: JSON 格式不正确
+    #[classmethod]
+    fn from_json(_cls: &Bound<'_, PyType>, s: &str) -> PyResult<Self> {
+        serde_json::from_str(s)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("解析 JSON 失败: {}", e)))
     }
     
     /// 转换为字典格式
@@ -91,51 +381,104 @@ impl PyOcrResult {
         let dict = PyDict::new_bound(py);
         dict.set_item("text", &self.text)?;
         dict.set_item("text_angle", self.text_angle)?;
+        dict.set_item("offset_x", self.offset_x)?;
+        dict.set_item("offset_y", self.offset_y)?;
         
-        let lines_list: Vec<_> = self.lines.iter().map(|line| {
-            let line_dict = PyDict::new_bound(py);
-            line_dict.set_item("text", &line.text).unwrap();
-            line_dict.set_item("bounds", line.bounds.to_dict(py).unwrap()).unwrap();
-            
-            let words_list: Vec<_> = line.words.iter().map(|word| {
-                let word_dict = PyDict::new_bound(py);
-                word_dict.set_item("text", &word.text).unwrap();
-                word_dict.set_item("bounds", word.bounds.to_dict(py).unwrap()).unwrap();
-                word_dict
-            }).collect();
-            line_dict.set_item("words", words_list).unwrap();
-            line_dict
-        }).collect();
-        
+        let lines_list = self.lines.iter()
+            .map(|line| line.to_dict(py))
+            .collect::<PyResult<Vec<_>>>()?;
         dict.set_item("lines", lines_list)?;
+        
+        let paragraphs_list = self.paragraphs.iter()
+            .map(|p| p.to_dict(py))
+            .collect::<PyResult<Vec<_>>>()?;
+        dict.set_item("paragraphs", paragraphs_list)?;
+        dict.set_item("reading_order_text", self.reading_order_text())?;
         Ok(dict.into())
     }
 }
 
-/// 将内部结果转换为 Python 结果
-fn convert_result(result: crate::OcrRecognitionResult) -> PyOcrResult {
-    let lines = result.lines.into_iter().map(|line| {
-        let words = line.words.into_iter().map(|word| {
-            PyOcrWord {
-                text: word.text,
-                bounds: PyBoundingBox {
-                    x: word.bounds.x,
-                    y: word.bounds.y,
-                    width: word.bounds.width,
-                    height: word.bounds.height,
-                },
-            }
-        }).collect();
-        
-        PyOcrLine {
-            text: line.text,
-            bounds: PyBoundingBox {
-                x: line.bounds.x,
-                y: line.bounds.y,
-                width: line.bounds.width,
-                height: line.bounds.height,
-            },
-            words,
+impl PyOcrResult {
+    /// 替换行并按新顺序重新拼接 text，其余字段保持不变
+    fn with_lines(&self, lines: Vec<PyOcrLine>) -> PyOcrResult {
+        let text = lines.iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string();
+        PyOcrResult {
+            text,
+            lines,
+            text_angle: self.text_angle,
+            paragraphs: self.paragraphs.clone(),
+            offset_x: self.offset_x,
+            offset_y: self.offset_y,
+        }
+    }
+}
+
+fn to_crate_bounds(bounds: &PyBoundingBox) -> crate::BoundingBox {
+    crate::BoundingBox {
+        x: bounds.x,
+        y: bounds.y,
+        width: bounds.width,
+        height: bounds.height,
+    }
+}
+
+fn to_crate_line(line: &PyOcrLine) -> crate::OcrLine {
+    crate::OcrLine {
+        text: line.text.clone(),
+        bounds: to_crate_bounds(&line.bounds),
+        words: line.words.iter().map(|word| crate::OcrWord {
+            text: word.text.clone(),
+            bounds: to_crate_bounds(&word.bounds),
+            confidence: word.confidence,
+        }).collect(),
+        confidence: line.confidence,
+    }
+}
+
+fn convert_bounds(bounds: crate::BoundingBox) -> PyBoundingBox {
+    PyBoundingBox {
+        x: bounds.x,
+        y: bounds.y,
+        width: bounds.width,
+        height: bounds.height,
+    }
+}
+
+fn convert_line(line: crate::OcrLine) -> PyOcrLine {
+    let words = line.words.into_iter().map(|word| {
+        PyOcrWord {
+            text: word.text,
+            bounds: convert_bounds(word.bounds),
+            confidence: word.confidence,
+        }
+    }).collect();
+    
+    PyOcrLine {
+        text: line.text,
+        bounds: convert_bounds(line.bounds),
+        words,
+        confidence: line.confidence,
+    }
+}
+
+/// 将内部结果转换为 Python 结果，默认先把行排成阅读顺序
+fn convert_result(mut result: crate::OcrRecognitionResult, preserve_engine_order: bool) -> PyOcrResult {
+    if !preserve_engine_order {
+        result.sort_lines_reading_order();
+    }
+    
+    let lines = result.lines.into_iter().map(convert_line).collect();
+    
+    let paragraphs = result.paragraphs.into_iter().map(|p| {
+        PyOcrParagraph {
+            text: p.text,
+            bounds: convert_bounds(p.bounds),
+            lines: p.lines.into_iter().map(convert_line).collect(),
         }
     }).collect();
     
@@ -143,6 +486,9 @@ fn convert_result(result: crate::OcrRecognitionResult) -> PyOcrResult {
         text: result.text,
         lines,
         text_angle: result.text_angle,
+        paragraphs,
+        offset_x: 0.0,
+        offset_y: 0.0,
     }
 }
 
@@ -150,15 +496,22 @@ fn convert_result(result: crate::OcrRecognitionResult) -> PyOcrResult {
 /// 
 /// Args:
 ///     image_path: 图片文件路径
-///     language: 语言代码，如 "zh-Hans-CN", "en-US"，默认使用系统语言
+///     language: 语言代码，如 "zh-Hans-CN", "en-US"，默认使用系统语言；
+///         传 "auto" 时依次尝试已安装的语言，取识别字符最多的结果并缓存该语言
+///     preserve_engine_order: 为 True 时 lines/text 保持引擎原始顺序，
+///         默认 False 按阅读顺序排列（多栏按栏从左到右，栏内自上而下）
 /// 
 /// Returns:
 ///     OcrResult 对象，包含识别结果
 #[pyfunction]
-#[pyo3(signature = (image_path, language=None))]
-pub fn recognize_from_file(image_path: &str, language: Option<&str>) -> PyResult<PyOcrResult> {
+#[pyo3(signature = (image_path, language=None, preserve_engine_order=false))]
+pub fn recognize_from_file(
+    image_path: &str,
+    language: Option<&str>,
+    preserve_engine_order: bool,
+) -> PyResult<PyOcrResult> {
     crate::recognize_from_file(image_path, language)
-        .map(convert_result)
+        .map(|result| convert_result(result, preserve_engine_order))
         .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e))
 }
 
@@ -166,15 +519,260 @@ pub fn recognize_from_file(image_path: &str, language: Option<&str>) -> PyResult
 /// 
 /// Args:
 ///     image_data: 图片字节数据 (bytes)
-///     language: 语言代码，如 "zh-Hans-CN", "en-US"，默认使用系统语言
+///     language: 语言代码，如 "zh-Hans-CN", "en-US"，默认使用系统语言；
+///         传 "auto" 时依次尝试已安装的语言，取识别字符最多的结果并缓存该语言
+///     upscale: 识别前的放大倍数（Lanczos 插值），默认 1.0 不缩放
+///     grayscale: 识别前是否转为灰度图，默认 False
+///     binarize: 识别前是否做 Otsu 二值化，默认 False
+///     preprocess: 额外开启一种预处理，可选 "enhance_contrast"（CLAHE 对比度增强）、
+///         "grayscale"、"binarize"、"sharpen"（USM 锐化），默认 None
+///     preserve_engine_order: 为 True 时 lines/text 保持引擎原始顺序，
+///         默认 False 按阅读顺序排列（多栏按栏从左到右，栏内自上而下）
 /// 
 /// Returns:
-///     OcrResult 对象，包含识别结果
+///     OcrResult 对象，包含识别结果（边界框为原图坐标）
 #[pyfunction]
-#[pyo3(signature = (image_data, language=None))]
-pub fn recognize_from_bytes(image_data: &[u8], language: Option<&str>) -> PyResult<PyOcrResult> {
-    crate::recognize_from_bytes(image_data, language)
-        .map(convert_result)
+#[pyo3(signature = (image_data, language=None, upscale=1.0, grayscale=false, binarize=false, preserve_engine_order=false, preprocess=None))]
+pub fn recognize_from_bytes(
+    image_data: &[u8],
+    language: Option<&str>,
+    upscale: f32,
+    grayscale: bool,
+    binarize: bool,
+    preserve_engine_order: bool,
+    preprocess: Option<&str>,
+) -> PyResult<PyOcrResult> {
+    let mut options = crate::PreprocessOptions {
+        upscale,
+        grayscale,
+        binarize,
+        ..Default::default()
+    };
+    if let Some(name) = preprocess {
+        options.apply_preset(name)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e))?;
+    }
+    crate::recognize_from_bytes_with_options(image_data, language, &options)
+        .map(|result| convert_result(result, preserve_engine_order))
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e))
+}
+
+/// 只识别图片中的矩形区域
+/// 
+/// Args:
+///     image_data: 图片字节数据 (bytes)
+///     x: 区域左上角 x 坐标
+///     y: 区域左上角 y 坐标
+///     w: 区域宽度，超出图片的部分会被截掉
+///     h: 区域高度，超出图片的部分会被截掉
+///     language: 语言代码，如 "zh-Hans-CN", "en-US"，默认使用系统语言；
+///         传 "auto" 时依次尝试已安装的语言，取识别字符最多的结果并缓存该语言
+///     preserve_engine_order: 为 True 时 lines/text 保持引擎原始顺序，
+///         默认 False 按阅读顺序排列（多栏按栏从左到右，栏内自上而下）
+/// 
+/// Returns:
+///     OcrResult 对象，边界框相对于区域左上角，加上 offset_x/offset_y 即为原图坐标
+#[pyfunction]
+#[pyo3(signature = (image_data, x, y, w, h, language=None, preserve_engine_order=false))]
+pub fn recognize_region(
+    image_data: &[u8],
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    language: Option<&str>,
+    preserve_engine_order: bool,
+) -> PyResult<PyOcrResult> {
+    crate::recognize_region(image_data, x, y, w, h, language)
+        .map(|result| PyOcrResult {
+            offset_x: x as f32,
+            offset_y: y as f32,
+            ..convert_result(result, preserve_engine_order)
+        })
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e))
+}
+
+/// 批量识别多张图片的文字
+/// 
+/// 逐张顺序识别（Windows OCR 不适合多线程并发调用），单张失败不影响其余图片
+/// 
+/// Args:
+///     images: 图片字节数据列表
+///     language: 语言代码，如 "zh-Hans-CN", "en-US"，默认使用系统语言；
+///         传 "auto" 时依次尝试已安装的语言，取识别字符最多的结果并缓存该语言
+///     preserve_engine_order: 为 True 时 lines/text 保持引擎原始顺序，
+///         默认 False 按阅读顺序排列（多栏按栏从左到右，栏内自上而下）
+/// 
+/// Returns:
+///     List[Optional[OcrResult]]，与输入顺序一致，识别失败的位置为 None
+#[pyfunction]
+#[pyo3(signature = (images, language=None, preserve_engine_order=false))]
+pub fn batch_recognize_from_bytes(
+    images: Vec<Vec<u8>>,
+    language: Option<&str>,
+    preserve_engine_order: bool,
+) -> Vec<Option<PyOcrResult>> {
+    images.iter()
+        .map(|image_data| {
+            crate::recognize_from_bytes(image_data, language)
+                .ok()
+                .map(|result| convert_result(result, preserve_engine_order))
+        })
+        .collect()
+}
+
+/// 在后台线程识别文字，完成后调用 callback
+/// 
+/// 识别期间不持有 GIL，每次调用使用独立线程。callback 在后台线程中执行，
+/// asyncio 应用需通过 loop.call_soon_threadsafe 转回事件循环
+/// 
+/// Args:
+///     image_data: 图片字节数据 (bytes)
+///     callback: 完成回调 fn(result: Optional[OcrResult])，识别失败时传入 None
+///     language: 语言代码，如 "zh-Hans-CN", "en-US"，默认使用系统语言；
+///         传 "auto" 时依次尝试已安装的语言，取识别字符最多的结果并缓存该语言
+///     preserve_engine_order: 为 True 时 lines/text 保持引擎原始顺序，
+///         默认 False 按阅读顺序排列（多栏按栏从左到右，栏内自上而下）
+#[pyfunction]
+#[pyo3(signature = (image_data, callback, language=None, preserve_engine_order=false))]
+pub fn recognize_async(
+    image_data: Vec<u8>,
+    callback: PyObject,
+    language: Option<String>,
+    preserve_engine_order: bool,
+) -> PyResult<()> {
+    std::thread::Builder::new()
+        .name("ocr-recognize-async".to_string())
+        .spawn(move || {
+            let result = crate::recognize_from_bytes(&image_data, language.as_deref())
+                .ok()
+                .map(|result| convert_result(result, preserve_engine_order));
+            Python::with_gil(|py| {
+                if let Err(e) = callback.call1(py, (result,)) {
+                    e.print(py);
+                }
+            });
+        })
+        .map(|_| ())
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("启动识别线程失败: {}", e)))
+}
+
+/// 自动纠正旋转后识别文字
+/// 
+/// 先按原图识别，若文本角度明显偏离或未识别出文字，则尝试 90/180/270 度旋转，
+/// 返回识别字符最多的结果
+/// 
+/// Args:
+///     image_data: 图片字节数据 (bytes)
+///     language: 语言代码，如 "zh-Hans-CN", "en-US"，默认使用系统语言；
+///         传 "auto" 时依次尝试已安装的语言，取识别字符最多的结果并缓存该语言
+///     preserve_engine_order: 为 True 时 lines/text 保持引擎原始顺序，
+///         默认 False 按阅读顺序排列（多栏按栏从左到右，栏内自上而下）
+/// 
+/// Returns:
+///     (OcrResult, int) 元组，第二项为实际应用的顺时针旋转角度（0/90/180/270）
+#[pyfunction]
+#[pyo3(signature = (image_data, language=None, preserve_engine_order=false))]
+pub fn recognize_auto_rotate(
+    image_data: &[u8],
+    language: Option<&str>,
+    preserve_engine_order: bool,
+) -> PyResult<(PyOcrResult, u32)> {
+    crate::recognize_auto_rotate(image_data, language)
+        .map(|(result, rotation)| (convert_result(result, preserve_engine_order), rotation))
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e))
+}
+
+/// 分块识别大图
+/// 
+/// 图片切成 tile_size × tile_size 的块（相邻块重叠 overlap 像素）逐块识别，
+/// 再用 merge_ocr_results 的规则合并，适合超出 OCR 引擎尺寸上限的长图/大图
+/// 
+/// Args:
+///     image_data: 图片字节数据 (bytes)
+///     tile_size: 块边长（像素）
+///     overlap: 相邻块的重叠像素，需小于 tile_size，建议不小于一行文字的高度
+///     language: 语言代码，如 "zh-Hans-CN", "en-US"，默认使用系统语言；
+///         传 "auto" 时依次尝试已安装的语言，取识别字符最多的结果并缓存该语言
+/// 
+/// Returns:
+///     OcrResult 对象，边界框为原图坐标，行按阅读顺序排列
+#[pyfunction]
+#[pyo3(signature = (image_data, tile_size=2000, overlap=100, language=None))]
+pub fn recognize_tiled(
+    py: Python<'_>,
+    image_data: &[u8],
+    tile_size: u32,
+    overlap: u32,
+    language: Option<&str>,
+) -> PyResult<PyOcrResult> {
+    py.allow_threads(|| crate::recognize_tiled(image_data, tile_size, overlap, language))
+        .map(|result| convert_result(result, false))
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e))
+}
+
+/// 校正图片中文字的倾斜（扫描件、斜拍的文档），识别前调用可提高识别率
+/// 
+/// 在 ±10 度范围内按 1 度步长搜索使文字行最水平的角度，并反向旋转图片
+/// 
+/// Args:
+///     image_data: 图片字节数据 (bytes)
+/// 
+/// Returns:
+///     bytes: 校正后的 PNG 数据；没有检测到倾斜时原样返回输入数据
+#[pyfunction]
+pub fn deskew_image<'py>(py: Python<'py>, image_data: &[u8]) -> PyResult<Bound<'py, PyBytes>> {
+    let data = py
+        .allow_threads(|| crate::preprocess::deskew_image(image_data))
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e))?;
+    Ok(PyBytes::new_bound(py, &data))
+}
+
+/// 合并分块识别的结果（大图切块识别后还原为一份结果）
+/// 
+/// 每块结果的坐标加上对应偏移后合并，相邻块重叠区域中重复识别的单词
+/// （与已保留单词的重叠面积超过较小者的一半）只保留一份，合并后的行按阅读顺序排列
+/// 
+/// Args:
+///     results: 各块的识别结果
+///     offsets: 各块左上角在原图中的 (x, y) 坐标，与 results 一一对应
+/// 
+/// Returns:
+///     OcrResult 对象，边界框为原图坐标
+/// 
+/// Raises:
+///     ValueError: results 与 offsets 数量不一致
+#[pyfunction]
+pub fn merge_ocr_results(results: Vec<PyOcrResult>, offsets: Vec<(f32, f32)>) -> PyResult<PyOcrResult> {
+    if results.len() != offsets.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "results 与 offsets 数量不一致: {} != {}", results.len(), offsets.len())));
+    }
+    let parts = results.iter().zip(offsets)
+        .map(|(result, (dx, dy))| {
+            let tile = crate::OcrRecognitionResult {
+                lines: result.lines.iter().map(to_crate_line).collect(),
+                text: result.text.clone(),
+                text_angle: result.text_angle,
+                paragraphs: Vec::new(),
+            };
+            // 块本身来自 recognize_region 时，先加上它自带的偏移
+            (tile, (dx + result.offset_x, dy + result.offset_y))
+        })
+        .collect();
+    Ok(convert_result(crate::merge_results(parts), false))
+}
+
+/// 识别图片中的二维码和条码
+/// 
+/// Args:
+///     image_data: 图片字节数据 (bytes)
+/// 
+/// Returns:
+///     List[str]: 解码出的文本（去重），没有可识别的码时为空列表
+#[pyfunction]
+pub fn detect_qr_codes(py: Python<'_>, image_data: &[u8]) -> PyResult<Vec<String>> {
+    py.allow_threads(|| crate::barcode::detect_qr_codes(image_data))
         .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e))
 }
 
@@ -185,6 +783,33 @@ pub fn get_available_languages() -> PyResult<Vec<String>> {
         .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e))
 }
 
+/// 获取 "auto" 模式当前缓存的语言
+/// 
+/// Returns:
+///     Optional[str]: 尚未自动选择过语言时返回 None
+#[pyfunction]
+pub fn auto_selected_language() -> Option<String> {
+    crate::auto_selected_language()
+}
+
+/// 清除 "auto" 模式缓存的语言，下次识别时重新选择
+#[pyfunction]
+pub fn reset_auto_language() {
+    crate::reset_auto_language()
+}
+
+/// 检查指定语言的 OCR 语言包是否已安装
+/// 
+/// Args:
+///     language: 语言代码，如 "zh-Hans-CN", "en-US"，也可只给主语言如 "en"
+/// 
+/// Returns:
+///     bool: 已安装返回 True
+#[pyfunction]
+pub fn is_language_available(language: &str) -> bool {
+    crate::is_language_available(language)
+}
+
 /// windows_media_ocr - Windows OCR Python 库
 /// 
 /// 使用 Windows.Media.Ocr API 进行文字识别
@@ -194,10 +819,22 @@ pub fn windows_media_ocr(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyBoundingBox>()?;
     m.add_class::<PyOcrWord>()?;
     m.add_class::<PyOcrLine>()?;
+    m.add_class::<PyOcrParagraph>()?;
     m.add_class::<PyOcrResult>()?;
     m.add_function(wrap_pyfunction!(recognize_from_file, m)?)?;
     m.add_function(wrap_pyfunction!(recognize_from_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(recognize_region, m)?)?;
+    m.add_function(wrap_pyfunction!(batch_recognize_from_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(recognize_async, m)?)?;
+    m.add_function(wrap_pyfunction!(recognize_auto_rotate, m)?)?;
+    m.add_function(wrap_pyfunction!(recognize_tiled, m)?)?;
+    m.add_function(wrap_pyfunction!(deskew_image, m)?)?;
+    m.add_function(wrap_pyfunction!(merge_ocr_results, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_qr_codes, m)?)?;
     m.add_function(wrap_pyfunction!(get_available_languages, m)?)?;
+    m.add_function(wrap_pyfunction!(is_language_available, m)?)?;
+    m.add_function(wrap_pyfunction!(auto_selected_language, m)?)?;
+    m.add_function(wrap_pyfunction!(reset_auto_language, m)?)?;
 
     // 注册 oneocr.dll 高精度引擎函数
     crate::oneocr_python::register_oneocr_functions(m)?;